@@ -0,0 +1,87 @@
+//! Rotor encode/reconstruct throughput and relay planning cost
+//!
+//! Run with `cargo bench --bench rotor`.
+
+use alpenglow::rotor::{Rotor, RotorConfig};
+use alpenglow::types::*;
+use ed25519_dalek::SigningKey;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const BLOCK_SIZES: [usize; 4] = [64 << 10, 512 << 10, 2 << 20, 8 << 20];
+const VALIDATOR_COUNTS: [u64; 3] = [100, 400, 1000];
+
+fn validator_set(count: u64) -> ValidatorSet {
+    ValidatorSet::with_stakes((0..count).map(|i| StakeWeight(1000 + i * 10)))
+}
+
+/// Block whose serialized size is roughly `size` bytes
+fn block_of_size(size: usize) -> Block {
+    let transaction_count = size.div_ceil(1024);
+    let payer = SigningKey::from_bytes(&[1u8; 32]);
+    // 120 bytes of payer, nonce, signature and length prefixes per transaction
+    let transaction = |i: usize| Transaction::new(&payer, i as u64, vec![i as u8; 1024 - 120]);
+    let transactions = (0..transaction_count).map(transaction).collect();
+    Block::new(Slot(1), None, ValidatorId::Index(0), transactions, 1000)
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotor_encode");
+    group.sample_size(10);
+    let rotor = Rotor::new(validator_set(100));
+
+    for size in BLOCK_SIZES {
+        let block = block_of_size(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &block, |b, block| {
+            b.iter(|| rotor.encode_block(black_box(block)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn reconstruct(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotor_reconstruct");
+    group.sample_size(10);
+
+    for size in BLOCK_SIZES {
+        let block = block_of_size(size);
+        let shreds = Rotor::new(validator_set(100)).encode_block(&block).unwrap();
+        // Worst case for the decoder: only coding shreds arrive
+        let coding: Vec<_> = shreds.into_iter().filter(|s| s.is_coding()).collect();
+
+        group.throughput(Throughput::Bytes(size as u64));
+        for workers in [1, 0] {
+            let config = RotorConfig {
+                decode_workers: workers,
+                ..RotorConfig::default()
+            };
+            let label = if workers == 1 { "serial" } else { "parallel" };
+            group.bench_with_input(BenchmarkId::new(label, size), &coding, |b, coding| {
+                b.iter(|| {
+                    let mut rotor = Rotor::with_config(validator_set(1), config);
+                    let (blocks, _) = rotor.receive_shreds(coding.clone());
+                    assert_eq!(blocks.len(), 1);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn broadcast_plan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotor_broadcast_plan");
+    group.sample_size(10);
+    let block = block_of_size(64 << 10);
+
+    for count in VALIDATOR_COUNTS {
+        let rotor = Rotor::new(validator_set(count));
+        let shreds = rotor.encode_block(&block).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(count), &shreds, |b, shreds| {
+            b.iter(|| rotor.broadcast_plan(black_box(shreds)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, reconstruct, broadcast_plan);
+criterion_main!(benches);
@@ -1,6 +1,7 @@
 //! Quick demonstration without heavy dependencies
 
 use alpenglow::{ConsensusEngine, types::*};
+use ed25519_dalek::SigningKey;
 
 fn main() {
     println!("=== Alpenglow Consensus Quick Demo ===\n");
@@ -9,36 +10,36 @@ fn main() {
     let mut validator_set = ValidatorSet::new();
     for i in 0..5 {
         validator_set.add_validator(ValidatorConfig {
-            id: ValidatorId(i),
+            id: ValidatorId::Index(i),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
     }
     println!("✓ Created 5 validators with 100 stake each");
     println!("  Total stake: {}\n", validator_set.total_stake().as_u64());
 
     // Create consensus engine
     let config = alpenglow::consensus::ConsensusConfig::default();
-    let mut engine = ConsensusEngine::new(ValidatorId(0), validator_set.clone(), config);
+    let mut engine = ConsensusEngine::new(ValidatorId::Index(0), validator_set.clone(), config);
 
     println!("✓ Consensus engine initialized");
     println!("  Leader: {}\n", engine.is_leader());
 
     // Create a block
-    let mut block = Block {
-        id: BlockId::new([0u8; 32]),
-        slot: Slot(0),
-        parent: None,
-        leader: ValidatorId(0),
-        transactions: vec![vec![1, 2, 3], vec![4, 5, 6]],
-        timestamp: 1000,
-    };
-    block.id = block.compute_id();
+    let payer = SigningKey::from_bytes(&[1u8; 32]);
+    let transactions = vec![
+        Transaction::new(&payer, 0, vec![1, 2, 3]),
+        Transaction::new(&payer, 1, vec![4, 5, 6]),
+    ];
+    let block = Block::new(Slot(0), None, ValidatorId::Index(0), transactions, 1000);
 
     println!("✓ Block created");
     println!("  Block ID: {}", block.id);
-    println!("  Transactions: {}\n", block.transactions.len());
+    println!("  Transactions: {}\n", block.body.transactions.len());
 
     // Propose block
     match engine.propose_block(block.clone()) {
@@ -1,6 +1,7 @@
 //! Simple demonstration of Alpenglow consensus
 
 use alpenglow::{ConsensusEngine, types::*};
+use ed25519_dalek::SigningKey;
 
 fn main() {
     println!("╔══════════════════════════════════════════════════════════╗");
@@ -12,11 +13,14 @@ fn main() {
     let mut validator_set = ValidatorSet::new();
     for i in 0..5 {
         validator_set.add_validator(ValidatorConfig {
-            id: ValidatorId(i),
+            id: ValidatorId::Index(i),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
         println!("   ✓ Validator {} added with stake 100", i);
     }
     println!("   Total stake: {}\n", validator_set.total_stake().as_u64());
@@ -33,7 +37,8 @@ fn main() {
     let config = alpenglow::consensus::ConsensusConfig::default();
     let mut engines: Vec<_> = (0..5)
         .map(|i| {
-            let engine = ConsensusEngine::new(ValidatorId(i), validator_set.clone(), config.clone());
+            let engine =
+                ConsensusEngine::new(ValidatorId::Index(i), validator_set.clone(), config.clone());
             println!("   ✓ Engine {} initialized (Leader: {})", i, engine.is_leader());
             engine
         })
@@ -42,21 +47,15 @@ fn main() {
 
     // Create a block
     println!("📦 Leader (Validator 0) proposing block...");
-    let mut block = Block {
-        id: BlockId::new([0u8; 32]),
-        slot: Slot(0),
-        parent: None,
-        leader: ValidatorId(0),
-        transactions: vec![
-            vec![1, 2, 3, 4],  // Simulated transaction data
-            vec![5, 6, 7, 8],
-        ],
-        timestamp: 1000,
-    };
-    block.id = block.compute_id();
+    let payer = SigningKey::from_bytes(&[1u8; 32]);
+    let transactions = vec![
+        Transaction::new(&payer, 0, vec![1, 2, 3, 4]),
+        Transaction::new(&payer, 1, vec![5, 6, 7, 8]),
+    ];
+    let block = Block::new(Slot(0), None, ValidatorId::Index(0), transactions, 1000);
     println!("   Block ID: {}", block.id);
-    println!("   Slot: {}", block.slot);
-    println!("   Transactions: {}\n", block.transactions.len());
+    println!("   Slot: {}", block.header.slot);
+    println!("   Transactions: {}\n", block.body.transactions.len());
 
     // Leader proposes block and creates shreds
     println!("🔀 Encoding block into shreds (erasure coding)...");
@@ -13,11 +13,14 @@ fn main() {
     let mut validator_set = ValidatorSet::new();
     for i in 0..5 {
         validator_set.add_validator(ValidatorConfig {
-            id: ValidatorId(i),
+            id: ValidatorId::Index(i),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
     }
 
     println!("🎯 Quorum Thresholds:");
@@ -43,10 +46,10 @@ fn main() {
     println!("🗳️  Round 1 voting:");
     for i in 0..4 {
         let vote = Vote {
-            validator: ValidatorId(i),
+            validator: ValidatorId::Index(i),
             block_id,
             slot,
-            round: VoteRound::Round1,
+            kind: VoteKind::Notar,
             signature: vec![],
         };
 
@@ -81,10 +84,10 @@ fn main() {
     println!("🗳️  Round 1 voting:");
     for i in 0..3 {
         let vote = Vote {
-            validator: ValidatorId(i),
+            validator: ValidatorId::Index(i),
             block_id: block_id2,
             slot,
-            round: VoteRound::Round1,
+            kind: VoteKind::Notar,
             signature: vec![],
         };
 
@@ -106,10 +109,10 @@ fn main() {
     println!("🗳️  Round 2 voting:");
     for i in 0..3 {
         let vote = Vote {
-            validator: ValidatorId(i),
+            validator: ValidatorId::Index(i),
             block_id: block_id2,
             slot,
-            round: VoteRound::Round2,
+            kind: VoteKind::Final,
             signature: vec![],
         };
 
@@ -137,10 +140,10 @@ fn main() {
     let block_id3 = BlockId::new([3u8; 32]);
 
     let vote1 = Vote {
-        validator: ValidatorId(0),
+        validator: ValidatorId::Index(0),
         block_id: block_id3,
         slot,
-        round: VoteRound::Round1,
+        kind: VoteKind::Notar,
         signature: vec![],
     };
 
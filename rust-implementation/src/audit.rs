@@ -0,0 +1,262 @@
+//! Safety audit log
+//!
+//! Every finalization certificate the engine accepts or is shown with a
+//! valid quorum goes into an `AuditLog`. Entries form a hash chain: each one
+//! commits to the one before it, so editing, dropping or reordering entries
+//! breaks the chain and is caught when the log is reopened. Two certificates
+//! for one slot naming different blocks mean the protocol's safety was
+//! violated; the log reports that instead of letting it pass, and the engine
+//! halts.
+//!
+//! When opened with a path, entries are appended to the file as they are
+//! recorded.
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("Audit log I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Audit log serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+
+    #[error("Audit log entry {0} does not chain to the one before it")]
+    Tampered(u64),
+}
+
+/// Two certificates finalizing different blocks in one slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyViolation {
+    pub slot: Slot,
+    /// Block finalized first
+    pub first: BlockId,
+    /// Block the contradicting certificate finalizes
+    pub second: BlockId,
+}
+
+/// One recorded certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub slot: Slot,
+    pub block_id: BlockId,
+    pub round: VoteRound,
+    pub voters: Vec<ValidatorId>,
+    pub stake: StakeWeight,
+    /// Hash of the previous entry; zero for the first
+    pub previous: [u8; 32],
+    /// Hash over this entry's fields, `previous` included
+    pub hash: [u8; 32],
+}
+
+impl AuditEntry {
+    fn new(sequence: u64, cert: &FinalizationCertificate, previous: [u8; 32]) -> Self {
+        let mut voters: Vec<_> = cert.votes.iter().map(|vote| vote.validator).collect();
+        voters.sort();
+        let mut entry = Self {
+            sequence,
+            slot: cert.slot,
+            block_id: cert.block_id,
+            round: cert.round,
+            voters,
+            stake: cert.total_stake,
+            previous,
+            hash: [0u8; 32],
+        };
+        entry.hash = entry.compute_hash();
+        entry
+    }
+
+    fn compute_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.previous);
+        hasher.update(self.sequence.to_le_bytes());
+        hasher.update(bincode::serialize(&(self.slot, self.block_id, self.round)).unwrap());
+        hasher.update(bincode::serialize(&(&self.voters, self.stake)).unwrap());
+        hasher.finalize().into()
+    }
+}
+
+/// Check that `entries` form an unbroken hash chain
+pub fn verify_chain(entries: &[AuditEntry]) -> Result<(), AuditError> {
+    let mut previous = [0u8; 32];
+    for (sequence, entry) in (0u64..).zip(entries) {
+        if entry.sequence != sequence
+            || entry.previous != previous
+            || entry.hash != entry.compute_hash()
+        {
+            return Err(AuditError::Tampered(sequence));
+        }
+        previous = entry.hash;
+    }
+    Ok(())
+}
+
+/// Hash-chained record of every certificate seen
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    /// Backing file, `None` for an in-memory log
+    path: Option<PathBuf>,
+    entries: Vec<AuditEntry>,
+    /// First block certified in each slot
+    by_slot: HashMap<Slot, BlockId>,
+    violations: Vec<SafetyViolation>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a persistent log, loading and checking existing entries
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = Vec::new();
+        if path.exists() {
+            let len = fs::metadata(&path)?.len();
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut read = 0;
+            while read < len {
+                let entry: AuditEntry = bincode::deserialize_from(&mut reader)?;
+                read += bincode::serialized_size(&entry)?;
+                entries.push(entry);
+            }
+        }
+        verify_chain(&entries)?;
+
+        let mut log = Self { path: Some(path), ..Self::default() };
+        for entry in entries {
+            log.check(entry.slot, entry.block_id);
+            log.entries.push(entry);
+        }
+        Ok(log)
+    }
+
+    /// Record a certificate; the violation if it contradicts an earlier one
+    ///
+    /// A certificate for a block already recorded in its slot is skipped.
+    pub fn record(
+        &mut self,
+        cert: &FinalizationCertificate,
+    ) -> Result<Option<SafetyViolation>, AuditError> {
+        if self.by_slot.get(&cert.slot) == Some(&cert.block_id) {
+            return Ok(None);
+        }
+        let previous = self.entries.last().map_or([0u8; 32], |entry| entry.hash);
+        let entry = AuditEntry::new(self.entries.len() as u64, cert, previous);
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(&bincode::serialize(&entry)?)?;
+            file.sync_data()?;
+        }
+        self.entries.push(entry);
+        Ok(self.check(cert.slot, cert.block_id))
+    }
+
+    fn check(&mut self, slot: Slot, block_id: BlockId) -> Option<SafetyViolation> {
+        let first = *self.by_slot.entry(slot).or_insert(block_id);
+        if first == block_id {
+            return None;
+        }
+        let violation = SafetyViolation { slot, first, second: block_id };
+        tracing::error!(
+            "SAFETY VIOLATION in slot {}: {} and {} both finalized",
+            slot,
+            first,
+            block_id
+        );
+        self.violations.push(violation.clone());
+        Some(violation)
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Contradictions found so far, including ones loaded from disk
+    pub fn violations(&self) -> &[SafetyViolation] {
+        &self.violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusEvent, EngineStatus};
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_contradicting_certificate_halts_engine_and_log_is_tamper_evident() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let cert = |block: u8| {
+            let block_id = BlockId::new([block; 32]);
+            FinalizationCertificate {
+                block_id,
+                slot: Slot(0),
+                round: VoteRound::Round1,
+                votes: (0..4)
+                    .map(|i| {
+                        let mut vote = Vote {
+                            validator: ValidatorId::Index(i),
+                            block_id,
+                            slot: Slot(0),
+                            kind: VoteKind::Notar,
+                            signature: vec![],
+                        };
+                        vote.sign(&keys[i as usize], &[0u8; 32]);
+                        vote
+                    })
+                    .collect(),
+                total_stake: StakeWeight(400),
+            }
+        };
+
+        let path = std::env::temp_dir()
+            .join(format!("alpenglow-audit-{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+        engine.set_audit_log(AuditLog::open(&path).unwrap());
+        let events = engine.subscribe();
+
+        assert!(engine.process_certificate(cert(1)).unwrap());
+        assert!(engine.process_certificate(cert(2)).is_err());
+        let violation = events
+            .try_iter()
+            .find_map(|event| match event {
+                ConsensusEvent::SafetyViolation(violation) => Some(violation),
+                _ => None,
+            })
+            .unwrap();
+        let expected = SafetyViolation {
+            slot: Slot(0),
+            first: BlockId::new([1; 32]),
+            second: BlockId::new([2; 32]),
+        };
+        assert_eq!(violation, expected);
+        assert_eq!(engine.status(), EngineStatus::Halted);
+        assert!(engine.resume().is_err());
+
+        // Both certificates are on disk and the violation is found again
+        let reopened = AuditLog::open(&path).unwrap();
+        assert_eq!(reopened.entries().len(), 2);
+        assert_eq!(reopened.violations(), &[expected]);
+
+        // Rewriting history breaks the chain
+        let mut entries = reopened.entries().to_vec();
+        entries[0].block_id = BlockId::new([2; 32]);
+        assert!(matches!(verify_chain(&entries), Err(AuditError::Tampered(0))));
+        entries.remove(0);
+        assert!(matches!(verify_chain(&entries), Err(AuditError::Tampered(0))));
+        let _ = fs::remove_file(&path);
+    }
+}
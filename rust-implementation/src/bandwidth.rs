@@ -0,0 +1,159 @@
+//! Bandwidth accounting for relay strategies
+//!
+//! A research aid: charges every validator for the shreds it sends while a
+//! block propagates through Rotor's relay trees, then compares each node's
+//! egress against a per-node budget. Nothing is sent; the trees are only
+//! walked.
+
+use crate::rotor::{Rotor, Shred};
+use crate::types::*;
+use crate::wire;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Egress each validator may spend on one block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressBudget {
+    /// Bytes per second a validator may send
+    pub default_bytes_per_sec: u64,
+    /// Budgets of validators that differ from the default
+    pub per_validator: HashMap<ValidatorId, u64>,
+    /// Time within which a block's shreds must go out
+    pub slot_duration: Duration,
+}
+
+impl EgressBudget {
+    pub fn uniform(bytes_per_sec: u64, slot_duration: Duration) -> Self {
+        Self {
+            default_bytes_per_sec: bytes_per_sec,
+            per_validator: HashMap::new(),
+            slot_duration,
+        }
+    }
+
+    pub fn bytes_per_sec(&self, validator: &ValidatorId) -> u64 {
+        self.per_validator
+            .get(validator)
+            .copied()
+            .unwrap_or(self.default_bytes_per_sec)
+    }
+}
+
+/// A validator whose egress exceeds its budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetOverrun {
+    pub validator: ValidatorId,
+    pub required_bytes_per_sec: u64,
+    pub budget_bytes_per_sec: u64,
+}
+
+/// Egress charged to each validator for one block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BandwidthReport {
+    /// Bytes sent per validator, leader included; silent validators are omitted
+    pub egress_bytes: BTreeMap<ValidatorId, u64>,
+    pub overruns: Vec<BudgetOverrun>,
+}
+
+impl BandwidthReport {
+    /// Whether every validator stays within its budget
+    pub fn fits(&self) -> bool {
+        self.overruns.is_empty()
+    }
+
+    /// Validator sending the most, with its byte count
+    pub fn busiest(&self) -> Option<(ValidatorId, u64)> {
+        self.egress_bytes
+            .iter()
+            .max_by_key(|(_, bytes)| **bytes)
+            .map(|(id, bytes)| (*id, *bytes))
+    }
+}
+
+/// Charge every validator for propagating `shreds` with `rotor`'s relay
+/// strategy and check the result against `budget`
+///
+/// The leader pays for each shred's root layer and every relay for its
+/// children, at the shred's wire size.
+pub fn account_block(rotor: &Rotor, shreds: &[Shred], budget: &EgressBudget) -> BandwidthReport {
+    let fanout = rotor.config().fanout;
+    let mut egress_bytes: BTreeMap<ValidatorId, u64> = BTreeMap::new();
+
+    for shred in shreds {
+        let size = wire::encode_shred(shred).map_or(shred.data.len(), |packet| packet.len()) as u64;
+        let seed = Rotor::shred_seed(shred.slot, shred.position());
+        let tree = rotor.relay_tree(&seed, fanout, shred.leader);
+
+        let mut charge = |validator: ValidatorId, peers: usize| {
+            if peers > 0 {
+                *egress_bytes.entry(validator).or_default() += size * peers as u64;
+            }
+        };
+        charge(shred.leader, tree.root_layer().len());
+        for layer in tree.layers() {
+            for relay in layer {
+                charge(*relay, tree.children(relay).len());
+            }
+        }
+    }
+
+    let seconds = budget.slot_duration.as_secs_f64();
+    let overruns = egress_bytes
+        .iter()
+        .filter_map(|(validator, bytes)| {
+            let required_bytes_per_sec = (*bytes as f64 / seconds).ceil() as u64;
+            let budget_bytes_per_sec = budget.bytes_per_sec(validator);
+            (required_bytes_per_sec > budget_bytes_per_sec).then_some(BudgetOverrun {
+                validator: *validator,
+                required_bytes_per_sec,
+                budget_bytes_per_sec,
+            })
+        })
+        .collect();
+
+    BandwidthReport {
+        egress_bytes,
+        overruns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotor::RotorConfig;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_fanout_shifts_load_off_the_leader() {
+        let vset = StakeDistribution::Equal(100).validator_set(20, 0);
+        let payer = SigningKey::from_bytes(&[1u8; 32]);
+        let transaction = Transaction::new(&payer, 0, vec![1u8; 10_000]);
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![transaction], 1000);
+        let rotor = |fanout| {
+            let config = RotorConfig {
+                fanout,
+                ..RotorConfig::default()
+            };
+            Rotor::with_config(vset.clone(), config)
+        };
+
+        let wide = rotor(200);
+        let narrow = rotor(2);
+        let shreds = wide.encode_block(&block).unwrap();
+        let budget = EgressBudget::uniform(500_000, Duration::from_millis(400));
+
+        // With a huge fan-out the leader sends every shred to all 19 peers
+        let report = account_block(&wide, &shreds, &budget);
+        assert_eq!(report.egress_bytes.len(), 1);
+        assert_eq!(report.busiest().unwrap().0, ValidatorId::Index(0));
+        assert_eq!(report.overruns[0].validator, ValidatorId::Index(0));
+
+        // A narrow tree spreads the same total over the relays
+        let spread = account_block(&narrow, &shreds, &budget);
+        assert!(spread.fits(), "{:?}", spread.overruns);
+        let total = |r: &BandwidthReport| r.egress_bytes.values().sum::<u64>();
+        assert_eq!(total(&spread), total(&report));
+        assert!(spread.egress_bytes.len() > 10);
+    }
+}
@@ -0,0 +1,64 @@
+//! Alpenglow command-line tools
+//!
+//! Usage:
+//!   alpenglow-cli genesis verify <file> [--expect-hash <hex>]
+
+use alpenglow::genesis::Genesis;
+use std::process::ExitCode;
+
+fn usage() -> ExitCode {
+    eprintln!("Usage: alpenglow-cli genesis verify <file> [--expect-hash <hex>]");
+    ExitCode::from(2)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["genesis", "verify", file] => verify_genesis(file, None),
+        ["genesis", "verify", file, "--expect-hash", hash] => verify_genesis(file, Some(hash)),
+        _ => usage(),
+    }
+}
+
+fn verify_genesis(path: &str, expected_hash: Option<&str>) -> ExitCode {
+    let genesis = match Genesis::load(path) {
+        Ok(genesis) => genesis,
+        Err(e) => {
+            eprintln!("✗ Failed to read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = genesis.verify();
+    let hash = hex::encode(report.hash);
+
+    println!("Chain ID:     {}", genesis.chain_id);
+    println!("Validators:   {}", genesis.validators.len());
+    match report.total_stake {
+        Some(stake) => println!("Total stake:  {}", stake.as_u64()),
+        None => println!("Total stake:  overflow"),
+    }
+    println!("Genesis hash: {}", hash);
+
+    let mut ok = report.is_ok();
+    for problem in &report.problems {
+        println!("✗ {}", problem);
+    }
+
+    if let Some(expected) = expected_hash {
+        if expected.eq_ignore_ascii_case(&hash) {
+            println!("✓ Matches peer genesis hash");
+        } else {
+            println!("✗ Peer genesis hash {} does not match", expected);
+            ok = false;
+        }
+    }
+
+    if ok {
+        println!("✓ Genesis is valid");
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,218 @@
+//! Tree of reconstructed blocks that are not finalized yet
+//!
+//! A leader may build on a block that is only notarized, or on one that
+//! ends up skipped, so pending blocks form a tree rooted at the latest
+//! finalized block. The tree records which blocks got notarized, exposes the
+//! notarized branch, and drops every branch that doesn't descend from a
+//! newly finalized block.
+
+use crate::types::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+struct Node {
+    slot: Slot,
+    parent: Option<BlockId>,
+    children: Vec<BlockId>,
+    notarized: bool,
+}
+
+/// Pending blocks keyed by ID, linked to their parents
+#[derive(Debug, Clone, Default)]
+pub struct BlockTree {
+    /// Latest finalized block; always present in `nodes` once set
+    root: Option<BlockId>,
+    nodes: HashMap<BlockId, Node>,
+}
+
+impl BlockTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a reconstructed block
+    ///
+    /// Its parent needn't be known yet; the block is linked once the parent
+    /// arrives. Returns false for duplicates and blocks no newer than the
+    /// finalized root.
+    pub fn insert(&mut self, block: &Block) -> bool {
+        let stale = self.root_slot().is_some_and(|root| block.header.slot <= root);
+        if stale || self.nodes.contains_key(&block.id) {
+            return false;
+        }
+
+        let children = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.parent == Some(block.id))
+            .map(|(id, _)| *id)
+            .collect();
+        if let Some(parent) = block.header.parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            parent.children.push(block.id);
+        }
+        self.nodes.insert(
+            block.id,
+            Node {
+                slot: block.header.slot,
+                parent: block.header.parent,
+                children,
+                notarized: false,
+            },
+        );
+        true
+    }
+
+    /// Record that a block got notarized; false if it isn't in the tree
+    pub fn mark_notarized(&mut self, block_id: &BlockId) -> bool {
+        match self.nodes.get_mut(block_id) {
+            Some(node) => {
+                node.notarized = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make `block_id` the finalized root and discard what doesn't descend from it
+    ///
+    /// Blocks whose ancestry is still unknown are kept while newer than the
+    /// root, since their parents may yet arrive. Returns the discarded blocks,
+    /// ordered by ID.
+    pub fn finalize(&mut self, block_id: BlockId, slot: Slot) -> Vec<BlockId> {
+        if self.root_slot().is_some_and(|root| slot < root) {
+            return Vec::new();
+        }
+        let root = self.nodes.entry(block_id).or_insert(Node {
+            slot,
+            parent: None,
+            children: Vec::new(),
+            notarized: true,
+        });
+        root.notarized = true;
+
+        let mut discarded: Vec<BlockId> = self
+            .nodes
+            .keys()
+            .filter(|id| !self.survives(id, &block_id, slot))
+            .copied()
+            .collect();
+        discarded.sort();
+        for id in &discarded {
+            self.nodes.remove(id);
+        }
+        self.root = Some(block_id);
+        discarded
+    }
+
+    /// Whether a block stays when `root` at `root_slot` is finalized
+    fn survives(&self, id: &BlockId, root: &BlockId, root_slot: Slot) -> bool {
+        let mut current = *id;
+        loop {
+            if current == *root {
+                return true;
+            }
+            let node = &self.nodes[&current];
+            match node.parent.filter(|parent| self.nodes.contains_key(parent)) {
+                Some(parent) => current = parent,
+                // Top of a detached branch
+                None => return node.slot > root_slot && node.parent.is_some(),
+            }
+        }
+    }
+
+    /// Blocks from the root down the notarized chain, root first
+    ///
+    /// If several children of a block are notarized, the newest is followed.
+    pub fn notarized_branch(&self) -> Vec<BlockId> {
+        let Some(mut current) = self.root else {
+            return Vec::new();
+        };
+        let mut branch = vec![current];
+        while let Some(next) = self.nodes[&current]
+            .children
+            .iter()
+            .filter(|child| self.nodes[*child].notarized)
+            .max_by_key(|child| self.nodes[*child].slot)
+        {
+            branch.push(*next);
+            current = *next;
+        }
+        branch
+    }
+
+    pub fn root(&self) -> Option<BlockId> {
+        self.root
+    }
+
+    fn root_slot(&self) -> Option<Slot> {
+        self.root.map(|root| self.nodes[&root].slot)
+    }
+
+    pub fn contains(&self, block_id: &BlockId) -> bool {
+        self.nodes.contains_key(block_id)
+    }
+
+    pub fn is_notarized(&self, block_id: &BlockId) -> bool {
+        self.nodes.get(block_id).is_some_and(|node| node.notarized)
+    }
+
+    pub fn children(&self, block_id: &BlockId) -> &[BlockId] {
+        self.nodes.get(block_id).map_or(&[], |node| &node.children)
+    }
+
+    pub fn parent(&self, block_id: &BlockId) -> Option<BlockId> {
+        self.nodes.get(block_id).and_then(|node| node.parent)
+    }
+
+    /// Number of blocks held, the root included
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: u8, slot: u64, parent: Option<u8>) -> Block {
+        let parent = parent.map(|p| BlockId::new([p; 32]));
+        let mut block = Block::new(Slot(slot), parent, ValidatorId::Index(0), vec![], 1000 + slot);
+        block.id = BlockId::new([id; 32]);
+        block
+    }
+
+    #[test]
+    fn test_forks_resolve_on_finalization() {
+        let mut tree = BlockTree::new();
+        let id = |id: u8| BlockId::new([id; 32]);
+
+        // 1 <- 2 <- 4 and 1 <- 3; 6's parent 5 hasn't arrived yet
+        assert!(tree.insert(&block(1, 1, None)));
+        assert!(tree.insert(&block(2, 2, Some(1))));
+        assert!(tree.insert(&block(3, 3, Some(1))));
+        assert!(tree.insert(&block(6, 6, Some(5))));
+        assert!(tree.insert(&block(4, 4, Some(2))));
+        assert!(!tree.insert(&block(4, 4, Some(2))));
+        assert_eq!(tree.children(&id(1)).len(), 2);
+
+        tree.finalize(id(1), Slot(1));
+        tree.mark_notarized(&id(2));
+        tree.mark_notarized(&id(4));
+        assert_eq!(tree.notarized_branch(), vec![id(1), id(2), id(4)]);
+
+        // Finalizing 2 drops the competing branch and the old root
+        assert_eq!(tree.finalize(id(2), Slot(2)), vec![id(1), id(3)]);
+        assert_eq!(tree.root(), Some(id(2)));
+        assert!(tree.contains(&id(6)));
+
+        // The missing parent arrives and links the detached block
+        assert!(tree.insert(&block(5, 5, Some(4))));
+        assert_eq!(tree.children(&id(5)), &[id(6)]);
+        assert_eq!(tree.finalize(id(6), Slot(6)), vec![id(2), id(4), id(5)]);
+        assert!(!tree.insert(&block(7, 3, Some(2))));
+    }
+}
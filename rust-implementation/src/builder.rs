@@ -0,0 +1,199 @@
+//! Validated engine construction
+//!
+//! `ConsensusEngine::new` accepts any configuration, including ones the
+//! engine can never make progress with. `ConsensusEngine::builder()` checks
+//! the validator set and configuration first and names every problem found:
+//! our ID missing from the set, zero stake, misordered timeouts, quorum
+//! thresholds outside the protocol's safety bounds.
+
+use crate::consensus::{ConsensusConfig, ConsensusEngine};
+use crate::params::{self, ParamsError};
+use crate::types::*;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("No validator ID given")]
+    MissingValidatorId,
+
+    #[error("No validator set given")]
+    MissingValidatorSet,
+
+    #[error("Validator {0} is not in the validator set")]
+    UnknownValidator(ValidatorId),
+
+    #[error("Validator {0} has zero stake")]
+    ZeroStake(ValidatorId),
+
+    #[error("Validator set has no stake")]
+    NoStake,
+
+    #[error("Unsafe protocol parameters: {0}")]
+    UnsafeParams(#[from] ParamsError),
+
+    #[error("Pipeline depth must be at least 1")]
+    ZeroPipelineDepth,
+
+    #[error("Epochs must be at least one slot long")]
+    ZeroEpochLength,
+
+    #[error("Blocks need at least one data shred")]
+    NoDataShreds,
+
+    #[error("Leader windows must be at least one slot long")]
+    ZeroLeaderWindow,
+
+    #[error("`{0}` can't change on a running engine")]
+    Immutable(&'static str),
+}
+
+/// Builder returned by `ConsensusEngine::builder`
+#[derive(Debug, Clone, Default)]
+pub struct ConsensusEngineBuilder {
+    validator_id: Option<ValidatorId>,
+    validator_set: Option<ValidatorSet>,
+    config: ConsensusConfig,
+}
+
+impl ConsensusEngineBuilder {
+    pub fn validator_id(mut self, validator_id: ValidatorId) -> Self {
+        self.validator_id = Some(validator_id);
+        self
+    }
+
+    pub fn validator_set(mut self, validator_set: ValidatorSet) -> Self {
+        self.validator_set = Some(validator_set);
+        self
+    }
+
+    /// Replace the whole configuration; defaults otherwise
+    pub fn config(mut self, config: ConsensusConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Every problem with the settings, empty if `build` would succeed
+    pub fn check(&self) -> Vec<ConfigError> {
+        let mut problems = Vec::new();
+        match (&self.validator_id, &self.validator_set) {
+            (None, _) => problems.push(ConfigError::MissingValidatorId),
+            (Some(id), Some(vset))
+                if !self.config.observer && vset.get_validator(id).is_none() =>
+            {
+                problems.push(ConfigError::UnknownValidator(*id));
+            }
+            _ => {}
+        }
+        match &self.validator_set {
+            None => problems.push(ConfigError::MissingValidatorSet),
+            Some(vset) => {
+                for validator in vset.sorted_validators() {
+                    if validator.stake.as_u64() == 0 {
+                        problems.push(ConfigError::ZeroStake(validator.id));
+                    }
+                }
+                if vset.total_stake().as_u64() == 0 {
+                    problems.push(ConfigError::NoStake);
+                }
+            }
+        }
+
+        problems.extend(check_config(&self.config));
+        problems
+    }
+
+    /// Build the engine, or fail with the first problem `check` finds
+    pub fn build(self) -> Result<ConsensusEngine, ConfigError> {
+        if let Some(problem) = self.check().into_iter().next() {
+            return Err(problem);
+        }
+        match (self.validator_id, self.validator_set) {
+            (Some(id), Some(vset)) => Ok(ConsensusEngine::new(id, vset, self.config)),
+            _ => unreachable!("`check` reports a missing ID or set"),
+        }
+    }
+}
+
+/// Problems with a configuration on its own, whatever the validator set
+pub(crate) fn check_config(config: &ConsensusConfig) -> Vec<ConfigError> {
+    let mut problems = Vec::new();
+    problems.extend(config.params.check().into_iter().map(ConfigError::from));
+    for timeouts in config.timing.all_timeouts() {
+        problems.extend(params::check_timeouts(timeouts).map(ConfigError::from));
+    }
+
+    if config.pipeline_depth == 0 {
+        problems.push(ConfigError::ZeroPipelineDepth);
+    }
+    if config.epoch_schedule.slots_per_epoch == 0 {
+        problems.push(ConfigError::ZeroEpochLength);
+    }
+    if config.rotor.data_shreds == 0 {
+        problems.push(ConfigError::NoDataShreds);
+    }
+    if config.leader_window == 0 {
+        problems.push(ConfigError::ZeroLeaderWindow);
+    }
+    problems
+}
+
+impl ConsensusEngine {
+    /// Start building an engine whose configuration is checked up front
+    pub fn builder() -> ConsensusEngineBuilder {
+        ConsensusEngineBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stake::StakeDistribution;
+    use crate::timing::{RoundTimeouts, TimingConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn test_builder_rejects_unusable_configuration() {
+        let engine = ConsensusEngine::builder()
+            .validator_id(ValidatorId::Index(2))
+            .validator_set(StakeDistribution::Equal(100).validator_set(3, 0))
+            .build()
+            .unwrap();
+        assert_eq!(engine.current_slot(), Slot(0));
+
+        assert_eq!(
+            ConsensusEngine::builder().validator_id(ValidatorId::Index(0)).build().err(),
+            Some(ConfigError::MissingValidatorSet)
+        );
+        assert_eq!(
+            ConsensusEngine::builder()
+                .validator_id(ValidatorId::Index(7))
+                .validator_set(StakeDistribution::Equal(100).validator_set(2, 0))
+                .build()
+                .err(),
+            Some(ConfigError::UnknownValidator(ValidatorId::Index(7)))
+        );
+
+        let builder = ConsensusEngine::builder()
+            .validator_id(ValidatorId::Index(0))
+            .validator_set(ValidatorSet::with_stakes([StakeWeight(0); 2]))
+            .config(ConsensusConfig {
+                timing: TimingConfig::with_timeouts(RoundTimeouts::new(
+                    Duration::from_millis(200),
+                    Duration::from_millis(100),
+                )),
+                pipeline_depth: 0,
+                ..ConsensusConfig::default()
+            });
+        assert_eq!(
+            builder.check(),
+            vec![
+                ConfigError::ZeroStake(ValidatorId::Index(0)),
+                ConfigError::ZeroStake(ValidatorId::Index(1)),
+                ConfigError::NoStake,
+                ConfigError::UnsafeParams(ParamsError::TimeoutOrder),
+                ConfigError::ZeroPipelineDepth,
+            ]
+        );
+        assert!(builder.build().is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Injectable time source
+//!
+//! The engine reads the time for round deadlines, rebroadcast intervals and
+//! latency metrics through a `Clock`, and so do the `SlotClock`, block
+//! timestamps and the verifier's timestamp check, which take the wall time
+//! from it. Nodes use `SystemClock`; tests and simulations use a
+//! `ManualClock` and advance it explicitly, so timeout behavior is
+//! deterministic and needs no sleeping.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Calendar time, for slot boundaries and block timestamps
+    fn wall_time(&self) -> SystemTime;
+
+    /// `wall_time` in milliseconds since the Unix epoch; 0 before it
+    fn unix_millis(&self) -> u64 {
+        self.wall_time()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+/// The operating system's monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to; clones share the same time
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<(Instant, SystemTime)>>,
+}
+
+impl ManualClock {
+    /// Clock stopped at the current system time
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// Clock stopped at wall time `wall_time`
+    pub fn starting_at(wall_time: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new((Instant::now(), wall_time))),
+        }
+    }
+
+    /// Move both the monotonic and the wall time forward
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += by;
+        now.1 += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.lock().unwrap().0
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.now.lock().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::stake::StakeDistribution;
+    use crate::timer::Timeout;
+    use crate::timing::{RoundTimeouts, TimingConfig};
+    use crate::types::*;
+
+    #[test]
+    fn test_manual_clock_drives_round_deadlines() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let config = ConsensusConfig {
+            timing: TimingConfig::with_timeouts(RoundTimeouts::new(
+                Duration::from_secs(10),
+                Duration::from_secs(20),
+            )),
+            ..ConsensusConfig::default()
+        };
+        let clock = ManualClock::new();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        engine.set_clock(Arc::new(clock.clone()));
+        engine.start_round1_timer();
+        assert_eq!(engine.next_deadline(), Some(clock.now() + Duration::from_secs(10)));
+
+        clock.advance(Duration::from_secs(9));
+        assert!(engine.fire_timers().is_empty());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(engine.fire_timers(), vec![Timeout::Round1(Slot(0))]);
+        assert_eq!(engine.current_round(), VoteRound::Round2);
+
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(engine.fire_timers(), vec![Timeout::Round2(Slot(0))]);
+    }
+}
@@ -0,0 +1,124 @@
+//! Pluggable serialization of wire types
+//!
+//! Messages are encoded with bincode unless another `Codec` is chosen. With
+//! the `borsh` feature every wire type (blocks, transactions, votes,
+//! certificates, shreds and message envelopes) also derives the borsh
+//! traits, and `BorshCodec` encodes them the way Solana tooling expects.
+//! Block payloads inside shreds stay bincode: Rotor parses their layout
+//! while streaming reconstruction.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CodecError {
+    #[error("bincode error: {0}")]
+    Bincode(#[from] bincode::Error),
+
+    #[cfg(feature = "borsh")]
+    #[error("borsh error: {0}")]
+    Borsh(#[from] borsh::io::Error),
+}
+
+/// Encoding of values of type `T` as bytes
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decode exactly one value; trailing bytes are an error
+    fn decode(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// bincode with its default (fixed-width little-endian) options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl<T: Serialize + DeserializeOwned> Codec<T> for BincodeCodec {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        use bincode::Options as _;
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .reject_trailing_bytes();
+        Ok(options.deserialize(bytes)?)
+    }
+}
+
+/// Borsh, as used by Solana programs and tooling
+#[cfg(feature = "borsh")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BorshCodec;
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshSerialize + borsh::BorshDeserialize> Codec<T> for BorshCodec {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        Ok(borsh::to_vec(value)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        Ok(borsh::from_slice(bytes)?)
+    }
+}
+
+/// Borsh encoding of `Bytes` fields, as a length-prefixed byte vector
+#[cfg(feature = "borsh")]
+pub(crate) mod borsh_bytes {
+    use borsh::io::{Read, Result, Write};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use bytes::Bytes;
+
+    pub fn serialize<W: Write>(bytes: &Bytes, writer: &mut W) -> Result<()> {
+        bytes.as_ref().serialize(writer)
+    }
+
+    pub fn deserialize<R: Read>(reader: &mut R) -> Result<Bytes> {
+        Vec::<u8>::deserialize_reader(reader).map(Bytes::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{ConsensusMessage, Envelope};
+    use crate::types::*;
+
+    #[test]
+    fn test_envelope_round_trips_through_each_codec() {
+        let vote = Vote {
+            validator: ValidatorId::Index(3),
+            block_id: BlockId::new([5u8; 32]),
+            slot: Slot(9),
+            kind: VoteKind::Notar,
+            signature: vec![1; 64],
+        };
+        let envelope =
+            Envelope::new([7u8; 32], ValidatorId::Index(3), ConsensusMessage::Vote(vote));
+
+        let bytes = envelope.encode_with(&BincodeCodec).unwrap();
+        assert_eq!(bytes, envelope.encode().unwrap());
+        let decoded = Envelope::decode_with(&BincodeCodec, &bytes).unwrap();
+        assert!(matches!(decoded.message, ConsensusMessage::Vote(v) if v.slot == Slot(9)));
+        let mut padded = bytes;
+        padded.push(0);
+        assert!(Envelope::decode_with(&BincodeCodec, &padded).is_err());
+
+        #[cfg(feature = "borsh")]
+        {
+            let bytes = envelope.encode_with(&BorshCodec).unwrap();
+            let decoded = Envelope::decode_with(&BorshCodec, &bytes).unwrap();
+            assert_eq!(decoded.sender, ValidatorId::Index(3));
+            let signed = |v: &Vote| v.signature.len() == 64;
+            assert!(matches!(decoded.message, ConsensusMessage::Vote(v) if signed(&v)));
+
+            let block = Block::new(Slot(1), None, ValidatorId::Index(0), vec![], 1000);
+            let rotor = crate::rotor::Rotor::new(ValidatorSet::new());
+            let shred = rotor.encode_block(&block).unwrap().remove(0);
+            let bytes = BorshCodec.encode(&ConsensusMessage::Shred(shred.clone())).unwrap();
+            let decoded: ConsensusMessage = BorshCodec.decode(&bytes).unwrap();
+            assert!(matches!(decoded, ConsensusMessage::Shred(s) if s.data == shred.data));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Block payload compression
+//!
+//! Rotor may compress a serialized block before cutting it into FEC sets.
+//! The algorithm travels in every shred header so receivers can reverse it;
+//! decompression is bounded by the block size limit.
+
+use crate::rotor::RotorError;
+use serde::{Deserialize, Serialize};
+
+/// zstd level used for block payloads
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compression applied to a block payload before shredding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, RotorError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            Compression::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)
+                .map_err(|_| RotorError::CompressionFailed),
+        }
+    }
+
+    /// Reverse `compress`, failing if the output would exceed `max_size`
+    pub fn decompress(self, data: &[u8], max_size: usize) -> Result<Vec<u8>, RotorError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => {
+                // Check the declared size before allocating for it
+                let (size, _) = lz4_flex::block::uncompressed_size(data)
+                    .map_err(|_| RotorError::CompressionFailed)?;
+                if size > max_size {
+                    return Err(RotorError::BlockTooLarge { size, max: max_size });
+                }
+                lz4_flex::decompress_size_prepended(data)
+                    .map_err(|_| RotorError::CompressionFailed)
+            }
+            Compression::Zstd => zstd::bulk::decompress(data, max_size)
+                .map_err(|_| RotorError::CompressionFailed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_bound() {
+        let data: Vec<u8> = (0..4096u32).map(|i| (i % 7) as u8).collect();
+
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let packed = compression.compress(&data).unwrap();
+            if compression != Compression::None {
+                assert!(packed.len() < data.len() / 4);
+            }
+            assert_eq!(compression.decompress(&packed, data.len()).unwrap(), data);
+
+            // Payloads inflating past the limit are refused
+            if compression != Compression::None {
+                assert!(compression.decompress(&packed, data.len() - 1).is_err());
+            }
+        }
+
+        assert!(Compression::Zstd.decompress(&[1, 2, 3], 100).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Protocol conformance suite
+//!
+//! Black-box scenarios (propose, vote, finalize, skip, equivocation
+//! evidence, forged votes) a node implementation can be scored against. A
+//! node is driven through the `ConformanceTarget` trait. Every validator in
+//! the suite has a key, and proposals and votes are signed for
+//! `SUITE_CHAIN_ID`, so a target must check signatures to pass.
+//!
+//! `EngineTarget` runs the suite against this crate's own engine in
+//! process; the crate ships no adapter for remote nodes.
+
+use crate::consensus::{ConsensusConfig, ConsensusEngine};
+use crate::stake::StakeDistribution;
+use crate::types::*;
+use ed25519_dalek::SigningKey;
+
+/// Number of validators every scenario runs with (100 stake each)
+pub const SUITE_VALIDATORS: u64 = 5;
+
+/// Chain ID the suite signs for, the default `ConsensusConfig` one
+pub const SUITE_CHAIN_ID: [u8; 32] = [0u8; 32];
+
+/// A node under test
+pub trait ConformanceTarget {
+    /// Restart the node at slot 0 as validator 0, which leads, with
+    /// `validators`
+    fn reset(&mut self, validators: &ValidatorSet);
+
+    /// Submit a block proposal as the leader; returns whether it was accepted
+    fn propose(&mut self, block: Block) -> bool;
+
+    /// Deliver a vote; returns whether it was accepted
+    fn submit_vote(&mut self, vote: Vote) -> bool;
+
+    /// Block finalized in `slot`, if any
+    fn finalized_block(&self, slot: Slot) -> Option<BlockId>;
+
+    fn is_skipped(&self, slot: Slot) -> bool;
+
+    /// Pieces of double-vote evidence held against `validator`
+    fn evidence_count(&self, validator: ValidatorId) -> usize;
+}
+
+/// Outcome of one scenario
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioResult {
+    pub name: &'static str,
+    /// `None` on pass, otherwise why the scenario failed
+    pub failure: Option<String>,
+}
+
+/// Outcome of a full suite run
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub results: Vec<ScenarioResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.failure.is_none()).count()
+    }
+
+    /// Fraction of scenarios passed, in percent
+    pub fn score_pct(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        self.passed() as f64 * 100.0 / self.results.len() as f64
+    }
+
+    pub fn is_conformant(&self) -> bool {
+        self.passed() == self.results.len()
+    }
+}
+
+type Scenario = fn(&mut dyn ConformanceTarget, &Suite) -> Result<(), String>;
+
+const SCENARIOS: [(&str, Scenario); 7] = [
+    ("propose", propose),
+    ("fast_finalization", fast_finalization),
+    ("no_finalization_below_quorum", no_finalization_below_quorum),
+    ("skip", skip),
+    ("equivocation_evidence", equivocation_evidence),
+    ("unknown_validator_rejected", unknown_validator_rejected),
+    ("forged_vote_rejected", forged_vote_rejected),
+];
+
+/// Run every scenario against `target`, resetting it before each one
+pub fn run_suite(target: &mut dyn ConformanceTarget) -> ConformanceReport {
+    let (validators, keys) = StakeDistribution::Equal(100).keyed_validator_set(SUITE_VALIDATORS, 0);
+    let suite = Suite { keys };
+    let results = SCENARIOS
+        .iter()
+        .map(|(name, scenario)| {
+            target.reset(&validators);
+            ScenarioResult {
+                name,
+                failure: scenario(target, &suite).err(),
+            }
+        })
+        .collect();
+
+    ConformanceReport { results }
+}
+
+/// Keys of the suite's validators, in ID order
+struct Suite {
+    keys: Vec<SigningKey>,
+}
+
+impl Suite {
+    /// A block from validator 0, signed by it
+    fn block(&self, slot: u64, tag: u8) -> Block {
+        let transaction = Transaction::new(&SigningKey::from_bytes(&[tag; 32]), 0, vec![tag]);
+        let timestamp = 1000 + slot * 10 + tag as u64;
+        let mut block =
+            Block::new(Slot(slot), None, ValidatorId::Index(0), vec![transaction], timestamp);
+        block.header.sign(&self.keys[0], &SUITE_CHAIN_ID);
+        block
+    }
+
+    /// A vote from `validator`, signed with its own key
+    fn vote(&self, validator: u64, block: &Block, kind: VoteKind) -> Vote {
+        signed_vote(validator, &self.keys[validator as usize], block, kind)
+    }
+}
+
+fn signed_vote(validator: u64, key: &SigningKey, block: &Block, kind: VoteKind) -> Vote {
+    let mut vote = Vote {
+        validator: ValidatorId::Index(validator),
+        block_id: block.id,
+        slot: block.header.slot,
+        kind,
+        signature: vec![],
+    };
+    vote.sign(key, &SUITE_CHAIN_ID);
+    vote
+}
+
+fn expect(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_string())
+    }
+}
+
+fn propose(target: &mut dyn ConformanceTarget, suite: &Suite) -> Result<(), String> {
+    expect(!target.propose(suite.block(7, 0)), "accepted a proposal for a future slot")?;
+    expect(target.propose(suite.block(0, 0)), "rejected the leader's proposal")
+}
+
+fn fast_finalization(target: &mut dyn ConformanceTarget, suite: &Suite) -> Result<(), String> {
+    let block = suite.block(0, 0);
+    for v in 0..4 {
+        let vote = suite.vote(v, &block, VoteKind::Notar);
+        expect(target.submit_vote(vote), "rejected a valid vote")?;
+    }
+    expect(
+        target.finalized_block(Slot(0)) == Some(block.id),
+        "80% notar votes did not finalize",
+    )
+}
+
+fn no_finalization_below_quorum(
+    target: &mut dyn ConformanceTarget,
+    suite: &Suite,
+) -> Result<(), String> {
+    let block = suite.block(0, 0);
+    for v in 0..3 {
+        target.submit_vote(suite.vote(v, &block, VoteKind::Notar));
+    }
+    expect(
+        target.finalized_block(Slot(0)).is_none(),
+        "finalized with 60% notar votes in round 1",
+    )
+}
+
+fn skip(target: &mut dyn ConformanceTarget, suite: &Suite) -> Result<(), String> {
+    let block = suite.block(0, 0);
+    for v in 0..2 {
+        target.submit_vote(suite.vote(v, &block, VoteKind::Skip));
+    }
+    expect(!target.is_skipped(Slot(0)), "skipped with 40% skip votes")?;
+
+    target.submit_vote(suite.vote(2, &block, VoteKind::Skip));
+    expect(target.is_skipped(Slot(0)), "60% skip votes did not skip the slot")
+}
+
+fn equivocation_evidence(target: &mut dyn ConformanceTarget, suite: &Suite) -> Result<(), String> {
+    let (a, b) = (suite.block(0, 1), suite.block(0, 2));
+    let first = suite.vote(3, &a, VoteKind::Notar);
+    expect(target.submit_vote(first), "rejected the first vote")?;
+    expect(
+        !target.submit_vote(suite.vote(3, &b, VoteKind::Notar)),
+        "accepted a conflicting vote",
+    )?;
+    expect(
+        target.evidence_count(ValidatorId::Index(3)) == 1,
+        "no evidence recorded for the double vote",
+    )
+}
+
+fn unknown_validator_rejected(
+    target: &mut dyn ConformanceTarget,
+    suite: &Suite,
+) -> Result<(), String> {
+    let block = suite.block(0, 0);
+    let outsider = SigningKey::from_bytes(&[0xee; 32]);
+    expect(
+        !target.submit_vote(signed_vote(SUITE_VALIDATORS + 10, &outsider, &block, VoteKind::Notar)),
+        "accepted a vote from outside the validator set",
+    )
+}
+
+fn forged_vote_rejected(target: &mut dyn ConformanceTarget, suite: &Suite) -> Result<(), String> {
+    let block = suite.block(0, 0);
+    let mut unsigned = suite.vote(2, &block, VoteKind::Notar);
+    unsigned.signature.clear();
+    expect(!target.submit_vote(unsigned), "accepted an unsigned vote")?;
+    let forged = signed_vote(2, &suite.keys[3], &block, VoteKind::Notar);
+    expect(!target.submit_vote(forged), "accepted a vote signed with another key")?;
+
+    // Validator 2 can still vote once the forgeries are dropped
+    let genuine = suite.vote(2, &block, VoteKind::Notar);
+    expect(target.submit_vote(genuine), "rejected the genuine vote after a forgery")
+}
+
+/// Runs the suite against an in-process `ConsensusEngine` as validator 0
+#[derive(Default)]
+pub struct EngineTarget {
+    engine: Option<ConsensusEngine>,
+}
+
+impl EngineTarget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn engine(&self) -> &ConsensusEngine {
+        self.engine.as_ref().expect("reset before use")
+    }
+
+    fn engine_mut(&mut self) -> &mut ConsensusEngine {
+        self.engine.as_mut().expect("reset before use")
+    }
+}
+
+impl ConformanceTarget for EngineTarget {
+    fn reset(&mut self, validators: &ValidatorSet) {
+        let config = ConsensusConfig {
+            chain_id: SUITE_CHAIN_ID,
+            ..ConsensusConfig::default()
+        };
+        self.engine = Some(ConsensusEngine::new(ValidatorId::Index(0), validators.clone(), config));
+    }
+
+    fn propose(&mut self, block: Block) -> bool {
+        self.engine_mut().propose_block(block).is_ok()
+    }
+
+    fn submit_vote(&mut self, vote: Vote) -> bool {
+        self.engine_mut().process_vote(vote).is_ok()
+    }
+
+    fn finalized_block(&self, slot: Slot) -> Option<BlockId> {
+        self.engine()
+            .finalized_blocks()
+            .iter()
+            .find(|cert| cert.slot == slot)
+            .map(|cert| cert.block_id)
+    }
+
+    fn is_skipped(&self, slot: Slot) -> bool {
+        self.engine().is_skipped(slot)
+    }
+
+    fn evidence_count(&self, validator: ValidatorId) -> usize {
+        self.engine().evidence(&validator).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_is_conformant() {
+        let report = run_suite(&mut EngineTarget::new());
+        assert!(report.is_conformant(), "{:?}", report.results);
+        assert_eq!(report.score_pct(), 100.0);
+    }
+
+    /// A node that accepts everything and never finalizes
+    struct Permissive;
+
+    impl ConformanceTarget for Permissive {
+        fn reset(&mut self, _validators: &ValidatorSet) {}
+        fn propose(&mut self, _block: Block) -> bool {
+            true
+        }
+        fn submit_vote(&mut self, _vote: Vote) -> bool {
+            true
+        }
+        fn finalized_block(&self, _slot: Slot) -> Option<BlockId> {
+            None
+        }
+        fn is_skipped(&self, _slot: Slot) -> bool {
+            false
+        }
+        fn evidence_count(&self, _validator: ValidatorId) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn test_nonconformant_node_is_scored() {
+        let report = run_suite(&mut Permissive);
+        assert!(!report.is_conformant());
+        assert_eq!(report.passed(), 1);
+        let failed: Vec<_> = report
+            .results
+            .iter()
+            .filter(|r| r.failure.is_some())
+            .map(|r| r.name)
+            .collect();
+        assert!(failed.contains(&"equivocation_evidence"));
+        assert!(failed.contains(&"forged_vote_rejected"));
+    }
+}
@@ -1,11 +1,42 @@
 //! Main consensus engine integrating Votor and Rotor
 
-use crate::rotor::{Rotor, Shred};
+use crate::audit::{AuditLog, SafetyViolation};
+use crate::block_tree::BlockTree;
+use crate::builder::{self, ConfigError};
+use crate::clock::{Clock, SystemClock};
+use crate::epoch::{EpochError, EpochSchedule, EpochValidatorSets};
+use crate::executor::Executor;
+use crate::ingress::IngressCounters;
+use crate::integrity::{self, Checkpoint, InvariantViolation};
+use crate::ledger::MisbehaviorLedger;
+use crate::message::{ConsensusMessage, Dispatched};
+use crate::metrics::{EngineMetrics, MetricsRecorder};
+use crate::params::ProtocolParams;
+use crate::proof::FinalityProof;
+use crate::rotor::{Rotor, RotorConfig, RotorError, RotorEvent, Shred, ShredIndex};
+use crate::shred_store::ShredStore;
+use crate::signer::{Signer, SignerError};
+use crate::snapshot::{SignedSnapshot, Snapshot, SnapshotError};
+use crate::slashing::{Evidence, SlashingManager, SlashingRules, ValidatorSlashed};
+use crate::startup::{StartupConfig, StartupPhase, StartupState, StartupStatus};
+use crate::storage::{EngineState, Storage, StorageError};
+use crate::sync::{self, SyncError, SyncRequest, SyncResponse, MAX_SYNC_SLOTS};
+use crate::timer::{Timeout, TimerService};
+use crate::timing::{RoundTimeouts, TimingConfig};
 use crate::types::*;
+use crate::verifier::{BlockContext, BlockVerifier, VerifyError};
 use crate::votor::Votor;
+use crate::watchdog::{StandstillReport, Watchdog};
+use ed25519_dalek::VerifyingKey;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Most votes held back for blocks we don't have yet
+pub const MAX_EARLY_VOTES: usize = 4096;
+
 #[derive(Error, Debug)]
 pub enum ConsensusError {
     #[error("Votor error: {0}")]
@@ -17,8 +48,145 @@ pub enum ConsensusError {
     #[error("Not the leader for slot {0}")]
     NotLeader(Slot),
 
+    #[error("Observers don't propose blocks")]
+    Observer,
+
     #[error("Invalid slot: expected {expected}, got {got}")]
     InvalidSlot { expected: Slot, got: Slot },
+
+    #[error("Node is not active yet (phase {0:?})")]
+    NotActive(StartupPhase),
+
+    #[error("Refusing to sign a conflicting vote for slot {0}")]
+    WouldDoubleSign(Slot),
+
+    #[error("Leader {leader} proposed two blocks for slot {slot}")]
+    LeaderEquivocation { slot: Slot, leader: ValidatorId },
+
+    #[error("Certificate for slot {slot} is too old (current slot {current})")]
+    CertificateTooOld { slot: Slot, current: Slot },
+
+    #[error("Certificate for slot {slot} is too far ahead (current slot {current})")]
+    CertificateTooNew { slot: Slot, current: Slot },
+
+    #[error("No finalization certificate for slot {0}")]
+    NotFinalized(Slot),
+
+    #[error("Block body for slot {0} is unavailable")]
+    BlockBodyUnavailable(Slot),
+
+    #[error("Block body for slot {0} does not match its certificate")]
+    BlockBodyMismatch(Slot),
+
+    #[error("Shred store error: {0}")]
+    Store(#[from] crate::shred_store::StoreError),
+
+    #[error("Block for slot {slot} is led by {got}, expected {expected}")]
+    WrongLeader { slot: Slot, expected: ValidatorId, got: ValidatorId },
+
+    #[error("Block for slot {slot} has parent {got:?}, expected {expected:?}")]
+    InvalidParent { slot: Slot, expected: Option<BlockId>, got: Option<BlockId> },
+
+    #[error("Slot {0} is neither skipped nor has a notarized block yet")]
+    ParentNotReady(Slot),
+
+    #[error("Signer key is not the public key registered for {0}")]
+    SignerKeyMismatch(ValidatorId),
+
+    #[error("Epoch error: {0}")]
+    Epoch(#[from] EpochError),
+
+    #[error("Sync error: {0}")]
+    Sync(#[from] SyncError),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+
+    #[error("Signing failed: {0}")]
+    Signing(#[from] SignerError),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(InvariantViolation),
+
+    #[error("Engine is not running ({0:?})")]
+    NotRunning(EngineStatus),
+
+    #[error("Block failed verification: {0}")]
+    InvalidBlock(#[from] VerifyError),
+
+    #[error("Snapshot rejected: {0}")]
+    Snapshot(#[from] SnapshotError),
+
+    #[error("Validator {0} is banned for misbehavior")]
+    Banned(ValidatorId),
+
+    #[error("Vote by {validator} for slot {slot} is unsigned or badly signed")]
+    InvalidVoteSignature { validator: ValidatorId, slot: Slot },
+}
+
+/// Consensus progress reported to subscribers
+#[derive(Debug, Clone)]
+pub enum ConsensusEvent {
+    FinalizedBlock(FinalizationCertificate),
+    SkippedSlot(Slot),
+    /// Entered `round` of `slot`; round 1 at every slot start
+    RoundAdvanced { slot: Slot, round: VoteRound },
+    LeaderChanged { slot: Slot, leader: ValidatorId },
+    /// A validator signed conflicting votes; only sent for evidence its
+    /// key verifies
+    EvidenceDetected(DoubleVoteEvidence),
+    /// A leader proposed two blocks for one slot
+    LeaderEquivocated(EquivocationEvidence),
+    /// Evidence cost a validator stake, from the next epoch on
+    ValidatorSlashed(ValidatorSlashed),
+    /// No slot was decided for `standstill_timeouts` timeouts
+    Standstill(StandstillReport),
+    /// Two blocks were finalized in one slot; the engine halted
+    SafetyViolation(SafetyViolation),
+}
+
+/// How settled a block is, from least to most certain
+///
+/// Applications pick the level they wait for, trading latency for
+/// certainty; the variants are ordered so `status >= level` tests for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfirmationStatus {
+    /// Neither the block nor votes for it have been seen
+    Unknown,
+    /// The block was reconstructed but isn't notarized yet
+    Received,
+    /// Notarized, with the percentage of total stake that voted for it
+    Notarized(u8),
+    /// Finalized through round 1 votes (fast path)
+    FastFinalized,
+    /// Finalized through round 2 votes (fallback path)
+    FallbackFinalized,
+}
+
+/// Whether the engine takes part in consensus
+///
+/// A paused engine keeps following the chain from incoming messages but
+/// signs nothing and lets no deadline fire until resumed. A shut down engine
+/// stays that way, and so does one halted by a safety violation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineStatus {
+    Running,
+    Paused,
+    ShutDown,
+    Halted,
+}
+
+/// Source of block bodies for certificate-first consumers (peers or an archive)
+pub trait BlockSource {
+    /// Fetch the body of the block with the given ID for a slot
+    fn fetch_block(&mut self, slot: Slot, block_id: &BlockId) -> Option<Block>;
+}
+
+/// In-memory archive keyed by slot
+impl BlockSource for HashMap<Slot, Block> {
+    fn fetch_block(&mut self, slot: Slot, _block_id: &BlockId) -> Option<Block> {
+        self.get(&slot).cloned()
+    }
 }
 
 /// Main consensus engine state
@@ -26,9 +194,15 @@ pub struct ConsensusEngine {
     /// Our validator ID
     validator_id: ValidatorId,
 
-    /// Validator set
+    /// Validator set of the current epoch
     validator_set: ValidatorSet,
 
+    /// Validator sets of every epoch, including scheduled ones
+    epochs: EpochValidatorSets,
+
+    /// Reconstructed blocks not finalized yet, by parent
+    block_tree: BlockTree,
+
     /// Votor for vote management
     votor: Votor,
 
@@ -38,24 +212,193 @@ pub struct ConsensusEngine {
     /// Current leader
     current_leader: ValidatorId,
 
-    /// Round 1 start time
-    round1_start: Option<Instant>,
+    /// Round deadlines of the current slot
+    timers: TimerService,
+
+    /// Verified block bodies fetched on demand, per finalized slot
+    fetched_bodies: HashMap<Slot, Block>,
+
+    /// Startup phase gating when we may first sign
+    startup: StartupState,
+
+    /// Running, paused for maintenance, or shut down
+    status: EngineStatus,
+
+    /// Blocks we have signed votes for, per (slot, kind); never rolled back
+    signed_votes: HashMap<(Slot, VoteKind), BlockId>,
+
+    /// Last state that passed the integrity check
+    checkpoint: Option<Checkpoint>,
+
+    /// Outcomes of gossip certificate admission
+    gossip_counters: GossipCounters,
+
+    /// Votes we cast that still have to be sent to peers
+    outgoing_votes: Vec<Vote>,
+
+    /// Shreds we relay, with the peer each copy goes to
+    outgoing_shreds: Vec<(ValidatorId, Shred)>,
+
+    /// Our votes in slots not finalized or skipped yet, for rebroadcast
+    cast_votes: BTreeMap<Slot, Vec<Vote>>,
+
+    /// When our votes were last rebroadcast
+    last_rebroadcast: Option<Instant>,
+
+    /// Event subscribers; dropped once their receiver is gone
+    subscribers: Vec<Sender<ConsensusEvent>>,
+
+    /// Time source for deadlines, intervals and latencies
+    clock: Arc<dyn Clock>,
+
+    /// Finalization paths, skips, timeouts and block latencies
+    metrics: MetricsRecorder,
+
+    /// Ingress queue outcomes, as last reported by the run loop
+    ingress_counters: IngressCounters,
+
+    /// Leaders caught proposing two blocks for a slot
+    equivocations: Vec<EquivocationEvidence>,
+
+    /// Penalizes detected misbehavior; no slashing without one
+    slashing: Option<SlashingManager>,
+
+    /// Verified misbehavior and the validators banned for it; no bans
+    /// without one
+    misbehavior: Option<MisbehaviorLedger>,
+
+    /// Timeouts since a slot was last decided
+    watchdog: Watchdog,
+
+    /// Signs our votes and proposals; unsigned without one
+    signer: Option<Arc<dyn Signer>>,
+
+    /// Application finalized blocks are applied to
+    executor: Option<Box<dyn Executor>>,
+
+    /// Last block applied to the executor
+    last_executed: Option<(Slot, BlockId)>,
+
+    /// Checks reconstructed blocks before we vote for them
+    verifier: BlockVerifier,
+
+    /// Every certificate seen, checked for contradictions
+    audit: AuditLog,
+
+    /// Votes for blocks of later slots that arrived before the block
+    early_votes: Vec<Vote>,
 
     /// Configuration
     config: ConsensusConfig,
+
+    /// Configuration taking effect at the next slot boundary
+    pending_config: Option<ConsensusConfig>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ConsensusConfig {
-    pub round1_timeout: Duration,
-    pub round2_timeout: Duration,
+    /// Quorum thresholds and fault tolerance of the network; see
+    /// `from_params` for its timeouts and retention window
+    pub params: ProtocolParams,
+
+    /// Slot length and round timeouts, per epoch
+    pub timing: TimingConfig,
+
+    /// Startup checks before the node may sign; `None` starts active
+    pub startup: Option<StartupConfig>,
+
+    /// Slot window for certificates accepted via gossip
+    pub certificate_window: CertificateWindow,
+
+    /// Data/coding shred counts for proposed blocks
+    pub rotor: RotorConfig,
+
+    /// Slots behind the current one whose archived blocks and stored shreds
+    /// are kept to serve repairs; `None` keeps them forever
+    pub archive_retention_slots: Option<u64>,
+
+    /// Epoch length; the validator set only changes at epoch boundaries
+    pub epoch_schedule: EpochSchedule,
+
+    /// How often our votes in undecided slots are sent again
+    pub vote_rebroadcast_interval: Duration,
+
+    /// Most undecided slots in flight at once; 1 runs one slot at a time
+    pub pipeline_depth: u64,
+
+    /// Follow consensus without ever signing, e.g. for read replicas; the
+    /// node needn't be in the validator set
+    pub observer: bool,
+
+    /// Timeouts without a decided slot before the engine reports a
+    /// standstill and steps up rebroadcast and repair; 0 never does
+    pub standstill_timeouts: u32,
+
+    /// Consecutive slots each leader holds before the next one takes over
+    pub leader_window: u64,
+
+    /// Chain our signatures are bound to: the hash of its genesis
+    pub chain_id: [u8; 32],
+}
+
+/// Slots around the current slot within which gossiped certificates are processed
+///
+/// Certificates outside the window are only accepted through the sync path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CertificateWindow {
+    pub max_slots_behind: u64,
+    pub max_slots_ahead: u64,
+}
+
+impl Default for CertificateWindow {
+    fn default() -> Self {
+        Self {
+            max_slots_behind: 64,
+            max_slots_ahead: 8,
+        }
+    }
+}
+
+/// Counters for certificates received via gossip
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GossipCounters {
+    pub accepted: u64,
+    pub rejected_too_old: u64,
+    pub rejected_too_new: u64,
 }
 
 impl Default for ConsensusConfig {
     fn default() -> Self {
         Self {
-            round1_timeout: Duration::from_millis(crate::ROUND1_TIMEOUT_MS),
-            round2_timeout: Duration::from_millis(crate::ROUND2_TIMEOUT_MS),
+            params: ProtocolParams::DEFAULT,
+            timing: TimingConfig::default(),
+            startup: None,
+            certificate_window: CertificateWindow::default(),
+            rotor: RotorConfig::default(),
+            archive_retention_slots: Some(4096),
+            epoch_schedule: EpochSchedule::default(),
+            vote_rebroadcast_interval: Duration::from_millis(50),
+            pipeline_depth: 1,
+            observer: false,
+            standstill_timeouts: 4,
+            leader_window: 1,
+            chain_id: [0u8; 32],
+        }
+    }
+}
+
+impl ConsensusConfig {
+    /// Defaults with the quorums, round timeouts and shred retention of `params`
+    pub fn from_params(params: ProtocolParams) -> Self {
+        let rotor = RotorConfig {
+            retention_slots: Some(params.retention_slots),
+            ..RotorConfig::default()
+        };
+        Self {
+            params,
+            timing: TimingConfig::with_timeouts(params.round_timeouts()),
+            rotor,
+            ..Self::default()
         }
     }
 }
@@ -66,52 +409,232 @@ impl ConsensusEngine {
         validator_set: ValidatorSet,
         config: ConsensusConfig,
     ) -> Self {
-        let votor = Votor::new(validator_set.clone());
-        let rotor = Rotor::new(validator_set.clone());
+        let votor = Votor::new(validator_set.clone()).with_params(config.params);
+        let rotor =
+            Rotor::with_config(validator_set.clone(), config.rotor).with_params(&config.params);
 
-        // Determine initial leader (simplified: validator 0)
-        let current_leader = ValidatorId(0);
+        // Slot 0 is led by the validator with the lowest ID
+        let current_leader = validator_set.iter().next().map_or(ValidatorId::Index(0), |v| v.id);
 
-        Self {
+        let startup = match &config.startup {
+            Some(startup_config) => StartupState::new(startup_config.clone()),
+            None => StartupState::active(),
+        };
+
+        let mut engine = Self {
             validator_id,
+            epochs: EpochValidatorSets::new(config.epoch_schedule, validator_set.clone()),
+            block_tree: BlockTree::new(),
             validator_set,
             votor,
             rotor,
             current_leader,
-            round1_start: None,
+            timers: TimerService::new(),
+            fetched_bodies: HashMap::new(),
+            startup,
+            status: EngineStatus::Running,
+            signed_votes: HashMap::new(),
+            checkpoint: None,
+            gossip_counters: GossipCounters::default(),
+            outgoing_votes: Vec::new(),
+            outgoing_shreds: Vec::new(),
+            cast_votes: BTreeMap::new(),
+            last_rebroadcast: None,
+            subscribers: Vec::new(),
+            clock: Arc::new(SystemClock),
+            metrics: MetricsRecorder::default(),
+            ingress_counters: IngressCounters::default(),
+            equivocations: Vec::new(),
+            slashing: None,
+            misbehavior: None,
+            watchdog: Watchdog::new(config.standstill_timeouts),
+            signer: None,
+            executor: None,
+            last_executed: None,
             config,
-        }
+            verifier: BlockVerifier::default(),
+            audit: AuditLog::new(),
+            early_votes: Vec::new(),
+            pending_config: None,
+        };
+        engine.update_leader_schedule();
+        engine
     }
 
     /// Start a new slot as leader
     pub fn propose_block(&mut self, block: Block) -> Result<Vec<Shred>, ConsensusError> {
+        self.check_proposal(&block)?;
+        let shreds = self.rotor.encode_block(&block)?;
+        Ok(self.store_proposal(&block, shreds))
+    }
+
+    /// Like `propose_block`, with the block header and every shred signed
+    /// by the signer
+    ///
+    /// Without a signer the block stays unsigned. Nothing is stored if any
+    /// signature fails, so the proposal can be retried.
+    pub async fn propose_block_signed(
+        &mut self,
+        mut block: Block,
+    ) -> Result<Vec<Shred>, ConsensusError> {
+        self.check_proposal(&block)?;
+        let signer = self.signer.clone();
+        if let Some(signer) = &signer {
+            let message = block.header.signing_bytes(&self.config.chain_id);
+            block.header.signature = signer.sign(message).await?;
+        }
+        let mut shreds = self.rotor.encode_block(&block)?;
+        if let Some(signer) = signer {
+            for shred in &mut shreds {
+                shred.signature = signer.sign(shred.signing_bytes()).await?;
+            }
+        }
+        Ok(self.store_proposal(&block, shreds))
+    }
+
+    fn check_proposal(&self, block: &Block) -> Result<(), ConsensusError> {
+        if self.config.observer {
+            return Err(ConsensusError::Observer);
+        }
+        if self.status != EngineStatus::Running {
+            return Err(ConsensusError::NotRunning(self.status));
+        }
         if self.current_leader != self.validator_id {
-            return Err(ConsensusError::NotLeader(block.slot));
+            return Err(ConsensusError::NotLeader(block.header.slot));
         }
 
-        if block.slot != self.votor.current_slot() {
+        if !self.startup.may_sign() {
+            return Err(ConsensusError::NotActive(self.startup.phase()));
+        }
+
+        if block.header.slot != self.votor.current_slot() {
             return Err(ConsensusError::InvalidSlot {
                 expected: self.votor.current_slot(),
-                got: block.slot,
+                got: block.header.slot,
             });
         }
+        Ok(())
+    }
+
+    /// Keep our own copy of the proposal's shreds for retransmission
+    fn store_proposal(&mut self, block: &Block, shreds: Vec<Shred>) -> Vec<Shred> {
+        let now = self.clock.now();
+        self.metrics.block_started(block.id, block.header.slot, now);
+        self.rotor.receive_shreds(shreds.clone());
+        self.metrics.block_reconstructed(&block.id, now);
+
+        // Start the round deadlines from the proposal
+        let timeouts = self.timeouts_at(block.header.slot);
+        self.timers.start_slot(block.header.slot, now, timeouts);
+
+        // The caller routes the shreds according to `broadcast_plan`
+        shreds
+    }
+
+    /// Shreds to resend to validators that haven't voted in the current slot
+    ///
+    /// Only while round 1 runs, since afterwards the slot proceeds through
+    /// the fallback path anyway. Validators with more stake come first.
+    pub fn plan_retransmissions(&mut self) -> Vec<(ValidatorId, Vec<Shred>)> {
+        let slot = self.votor.current_slot();
+        let round1_expired = self.timers.round1_expired(slot, self.clock.now());
+        let round1 = self.votor.current_round() == VoteRound::Round1 && !round1_expired;
+        if !round1 && !self.watchdog.is_stalled() {
+            return Vec::new();
+        }
 
-        // Encode block into shreds
-        let shreds = self.rotor.encode_block(&block)?;
+        let voters = self.votor.voters(slot);
+        let mut plan = Vec::new();
+        for block_id in self.rotor.blocks_in_slot(slot) {
+            for validator in self.validator_set.sorted_validators() {
+                if validator.id != self.validator_id && !voters.contains(&validator.id) {
+                    self.rotor.note_missing_vote(block_id, validator.id);
+                }
+            }
+            plan.extend(self.rotor.retransmission_plan(&block_id, self.validator_id));
+        }
+        plan
+    }
+
+    /// Persist received shreds to `store` and resume the blocks it holds
+    ///
+    /// Shreds stored before a restart are replayed, so partially received
+    /// blocks continue reconstructing and only their missing shreds need to
+    /// arrive again. Blocks the store already completes are kept but not
+    /// voted on. Returns the number of blocks still incomplete.
+    pub fn resume_from_store(
+        &mut self,
+        store: Box<dyn ShredStore>,
+    ) -> Result<usize, ConsensusError> {
+        self.rotor.set_store(store);
+        let completed = self.rotor.restore()?;
+        let incomplete = self.rotor.incomplete_blocks().len();
+        tracing::info!(
+            "Resumed {} incomplete blocks ({} complete) from the shred store",
+            incomplete,
+            completed.len()
+        );
+        Ok(incomplete)
+    }
+
+    /// Receive consensus events from now on
+    pub fn subscribe(&mut self) -> Receiver<ConsensusEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
 
-        // Start round 1 timer
-        self.round1_start = Some(Instant::now());
+    fn emit(&mut self, event: ConsensusEvent) {
+        self.subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Subscribe to Rotor's block reconstruction events
+    pub fn subscribe_rotor_events(&mut self) -> Receiver<RotorEvent> {
+        self.rotor.subscribe()
+    }
 
-        // In a real implementation, broadcast shreds to relays
-        // For now, just return them for manual distribution
+    /// Relays each proposed shred should be sent to
+    pub fn broadcast_plan(&self, shreds: &[Shred]) -> Vec<(ValidatorId, Vec<ShredIndex>)> {
+        self.rotor.broadcast_plan(shreds)
+    }
 
-        Ok(shreds)
+    /// Peers we forward `shred` to: our children in its relay tree
+    pub fn relay_targets(&self, shred: &Shred) -> Vec<ValidatorId> {
+        let fanout = self.config.rotor.fanout;
+        self.rotor
+            .forwarding_plan(std::slice::from_ref(shred), fanout, self.validator_id)
+            .into_iter()
+            .map(|(to, _)| to)
+            .collect()
     }
 
     /// Receive a shred from the network
+    ///
+    /// A shred we hadn't stored yet is queued for our relay targets once
+    /// Rotor accepts it (see `take_outgoing_shreds`). Shreds of past slots
+    /// are repair traffic and aren't relayed.
     pub fn receive_shred(&mut self, shred: Shred) -> Result<(), ConsensusError> {
+        let slot = shred.slot;
+        let (block_id, position) = (shred.block_id, shred.position());
+        self.metrics.block_started(block_id, slot, self.clock.now());
+        let fresh = slot >= self.current_slot() && !self.rotor.has_shred(&block_id, position);
+        let targets = if fresh { self.relay_targets(&shred) } else { Vec::new() };
+        let relayed = (!targets.is_empty()).then(|| shred.clone());
+
         // Try to reconstruct block
-        if let Some(block) = self.rotor.receive_shred(shred)? {
+        let reconstructed = self.rotor.receive_shred(shred);
+        if let Some(shred) = relayed.filter(|_| self.rotor.has_shred(&block_id, position)) {
+            let copies = targets.into_iter().map(|to| (to, shred.clone()));
+            self.outgoing_shreds.extend(copies);
+        }
+        if let Some(block) = reconstructed? {
+            self.metrics.block_reconstructed(&block.id, self.clock.now());
+            self.verify_block(&block, slot)?;
+            self.block_tree.insert(&block);
+            self.release_early_votes(|vote| vote.block_id == block.id);
+            // A finalized block may have been waiting for this body
+            self.execute_finalized();
+            self.check_equivocation(&block)?;
             // Block reconstructed, cast our vote if we're honest
             self.vote_for_block(block)?;
         }
@@ -119,179 +642,2792 @@ impl ConsensusEngine {
         Ok(())
     }
 
+    /// Handle any protocol message `from` a peer
+    ///
+    /// Messages from a banned peer, and votes by a banned validator, are
+    /// refused. Rebroadcasts of votes we already counted are ignored and
+    /// gossiped certificates must pass `admit_gossip_certificate`. Repair
+    /// requests are answered in `replies`; our own votes and relayed shreds
+    /// are still collected through `take_outgoing_votes` and
+    /// `take_outgoing_shreds`.
+    pub fn dispatch(
+        &mut self,
+        from: ValidatorId,
+        message: ConsensusMessage,
+    ) -> Result<Dispatched, ConsensusError> {
+        if self.is_banned(&from) {
+            return Err(ConsensusError::Banned(from));
+        }
+        let mut dispatched = Dispatched::default();
+        match message {
+            // Our vote on the reconstructed block may complete a certificate
+            // when the other votes arrived first
+            ConsensusMessage::Shred(shred) => {
+                let before = self.latest_finalized().map(|cert| cert.block_id);
+                self.receive_shred(shred)?;
+                let latest = self.latest_finalized();
+                dispatched.finalized = latest.filter(|cert| Some(cert.block_id) != before).cloned();
+                dispatched.assembled = dispatched.finalized.is_some();
+            }
+            ConsensusMessage::Vote(vote) if self.is_banned(&vote.validator) => {
+                return Err(ConsensusError::Banned(vote.validator));
+            }
+            ConsensusMessage::Vote(vote) if self.has_vote(&vote) => {}
+            ConsensusMessage::Vote(vote) => {
+                dispatched.finalized = self.process_vote(vote)?;
+                dispatched.assembled = dispatched.finalized.is_some();
+            }
+            ConsensusMessage::Certificate(cert) => {
+                self.admit_gossip_certificate(&cert)?;
+                if self.process_certificate(cert.clone())? {
+                    dispatched.finalized = Some(cert);
+                }
+            }
+            ConsensusMessage::RepairRequest(request) => {
+                let shreds = self.rotor.serve_repair(&request);
+                let block_id = request.block_id;
+                tracing::debug!("Serving {} shreds of {} to {}", shreds.len(), block_id, from);
+                dispatched.replies = shreds.into_iter().map(ConsensusMessage::Shred).collect();
+                if dispatched.replies.is_empty() && !request.missing_indices.is_empty() {
+                    let error = ConsensusError::from(RotorError::NotArchived(block_id));
+                    dispatched.replies.push(ConsensusMessage::Error((&error).into()));
+                }
+            }
+            ConsensusMessage::Error(response) => {
+                let (code, detail) = (response.code, &response.detail);
+                tracing::debug!("{} answered with error {}: {}", from, code, detail);
+                dispatched.error = Some(response);
+            }
+        }
+        Ok(dispatched)
+    }
+
+    /// Run the verifier on a block reconstructed from shreds of `slot`
+    fn verify_block(&self, block: &Block, slot: Slot) -> Result<(), VerifyError> {
+        let parent = block.header.parent.and_then(|parent| self.rotor.get_block(&parent));
+        let now = self.clock.unix_millis();
+        let leader = self.leader_of(slot);
+        let context = BlockContext {
+            slot,
+            leader,
+            leader_key: self.rotor.leader_key(&leader),
+            chain_id: self.config.chain_id,
+            epoch: self.config.epoch_schedule.epoch_of(slot),
+            parent_height: parent.map(|parent| parent.header.height),
+            parent_timestamp: parent.map(|parent| parent.header.timestamp),
+            block_time: self.config.params.block_time,
+            now,
+        };
+        self.verifier.verify(block, &context).inspect_err(|e| {
+            tracing::warn!("Not voting for {} in slot {}: {}", block.id, slot, e);
+        })
+    }
+
+    /// Replace the verifier reconstructed blocks must pass before we vote
+    pub fn set_block_verifier(&mut self, verifier: BlockVerifier) {
+        self.verifier = verifier;
+    }
+
+    /// Record evidence if `block`'s leader already signed another block
+    /// for its slot
+    ///
+    /// We then vote for neither: a vote already cast for the first block
+    /// stands, but no further notar or final vote is signed in the slot, and
+    /// its round 1 timeout votes to skip it if we can. Only headers that
+    /// verify against the leader's key count, so nothing is reported for a
+    /// leader without one.
+    fn check_equivocation(&mut self, block: &Block) -> Result<(), ConsensusError> {
+        let (slot, leader) = (block.header.slot, block.header.leader);
+        let Some(key) = self.rotor.leader_key(&leader) else {
+            return Ok(());
+        };
+        let chain_id = self.config.chain_id;
+        let evidence = self
+            .rotor
+            .blocks_in_slot(slot)
+            .into_iter()
+            .filter(|id| *id != block.id)
+            .filter_map(|id| self.rotor.get_block(&id))
+            .map(|other| EquivocationEvidence {
+                leader,
+                slot,
+                first: other.header.clone(),
+                second: block.header.clone(),
+            })
+            .find(|evidence| evidence.verify(&key, &chain_id));
+        let Some(evidence) = evidence else {
+            return Ok(());
+        };
+        if !self.equivocations.contains(&evidence) {
+            tracing::warn!("Leader {} equivocated in slot {}", leader, slot);
+            self.equivocations.push(evidence.clone());
+            let reported = Evidence::LeaderEquivocation(evidence.clone());
+            if self.verify_evidence(&reported) {
+                self.emit(ConsensusEvent::LeaderEquivocated(evidence));
+                self.report(reported);
+            }
+        }
+        Err(ConsensusError::LeaderEquivocation { slot, leader })
+    }
+
+    /// Whether a leader proposed two blocks for `slot`
+    pub fn is_equivocated(&self, slot: Slot) -> bool {
+        self.equivocations.iter().any(|evidence| evidence.slot == slot)
+    }
+
+    /// Evidence of every leader equivocation seen
+    pub fn equivocations(&self) -> &[EquivocationEvidence] {
+        &self.equivocations
+    }
+
     /// Cast a vote for a block
     fn vote_for_block(&mut self, block: Block) -> Result<(), ConsensusError> {
-        // Don't vote if we're Byzantine or offline
-        if let Some(config) = self.validator_set.get_validator(&self.validator_id) {
-            if config.is_byzantine || config.is_offline {
-                return Ok(());
+        if !self.may_vote() {
+            return Ok(());
+        }
+        let skipped = [VoteKind::Skip, VoteKind::SkipFallback]
+            .iter()
+            .any(|kind| self.signed_votes.contains_key(&(block.header.slot, *kind)));
+        if skipped {
+            return Err(ConsensusError::WouldDoubleSign(block.header.slot));
+        }
+        if self.is_equivocated(block.header.slot) {
+            return Ok(());
+        }
+
+        let kind = match self.votor.round_of(block.header.slot) {
+            VoteRound::Round1 => VoteKind::Notar,
+            VoteRound::Round2 => VoteKind::Final,
+        };
+
+        // Double-sign protection survives rollbacks
+        match self.signed_votes.get(&(block.header.slot, kind)) {
+            Some(signed) if *signed != block.id => {
+                return Err(ConsensusError::WouldDoubleSign(block.header.slot));
             }
+            Some(_) => return Ok(()),
+            None => {}
         }
+        self.check_block_linkage(&block)?;
+        self.signed_votes.insert((block.header.slot, kind), block.id);
 
         let vote = Vote {
             validator: self.validator_id,
             block_id: block.id,
-            slot: block.slot,
-            round: self.votor.current_round(),
+            slot: block.header.slot,
+            kind,
             signature: vec![], // Simplified: no actual signature
         };
 
-        // Process our own vote
-        self.process_vote(vote)?;
+        self.cast_vote(vote)
+    }
 
+    /// Count our own vote and queue it for peers
+    fn cast_vote(&mut self, vote: Vote) -> Result<(), ConsensusError> {
+        self.outgoing_votes.push(vote.clone());
+        self.cast_votes.entry(vote.slot).or_default().push(vote.clone());
+        self.count_vote(vote)?;
         Ok(())
     }
 
-    /// Process a vote from any validator
-    pub fn process_vote(&mut self, vote: Vote) -> Result<Option<FinalizationCertificate>, ConsensusError> {
-        let cert = self.votor.process_vote(vote)?;
-
-        if let Some(ref certificate) = cert {
-            tracing::info!(
-                "Block {} finalized in slot {} via {:?}",
-                certificate.block_id,
-                certificate.slot,
-                certificate.round
-            );
+    /// Whether we sign votes at all: not an observer, honest, online and
+    /// past startup
+    fn may_vote(&self) -> bool {
+        if self.config.observer || self.status != EngineStatus::Running {
+            return false;
         }
 
-        Ok(cert)
-    }
-
-    /// Check if round 1 timeout has expired
-    pub fn check_round1_timeout(&mut self) -> bool {
-        if let Some(start) = self.round1_start {
-            if start.elapsed() >= self.config.round1_timeout {
-                self.advance_to_round2();
-                return true;
+        // Don't vote if we're Byzantine or offline
+        if let Some(config) = self.validator_set.get_validator(&self.validator_id) {
+            if config.is_byzantine || config.is_offline {
+                return false;
             }
         }
-        false
-    }
 
-    /// Advance to round 2
-    fn advance_to_round2(&mut self) {
-        tracing::info!("Advancing to round 2 for slot {}", self.votor.current_slot());
-        self.votor.advance_to_round2();
+        // Never sign before startup completes
+        if !self.startup.may_sign() {
+            tracing::debug!("Suppressing vote during {:?}", self.startup.phase());
+            return false;
+        }
+        true
     }
 
-    /// Move to the next slot
-    pub fn next_slot(&mut self) {
-        self.votor.next_slot();
-        self.round1_start = None;
-
-        // Rotate leader (simplified: round-robin)
-        let next_leader_idx = (self.current_leader.0 + 1) % self.validator_set.len() as u64;
-        self.current_leader = ValidatorId(next_leader_idx);
+    /// Check a block's leader against the schedule and its parent against
+    /// `parent_for_slot`
+    fn check_block_linkage(&self, block: &Block) -> Result<(), ConsensusError> {
+        let expected = self.leader_of(block.header.slot);
+        if block.header.leader != expected {
+            return Err(ConsensusError::WrongLeader {
+                slot: block.header.slot,
+                expected,
+                got: block.header.leader,
+            });
+        }
 
-        tracing::info!(
-            "Advanced to slot {}, leader is {}",
-            self.votor.current_slot(),
-            self.current_leader
-        );
+        let expected_parent = self.parent_for_slot(block.header.slot)?;
+        if block.header.parent != expected_parent {
+            return Err(ConsensusError::InvalidParent {
+                slot: block.header.slot,
+                expected: expected_parent,
+                got: block.header.parent,
+            });
+        }
+        Ok(())
     }
 
-    /// Check if we are the current leader
-    pub fn is_leader(&self) -> bool {
-        self.current_leader == self.validator_id
+    /// Parent a block in `slot` must build on
+    ///
+    /// Slots before `slot` are walked back past skipped ones; the first
+    /// remaining slot must have a finalized or notarized block, which is the
+    /// parent. With every earlier slot skipped (or none at all) there is no
+    /// parent.
+    pub fn parent_for_slot(&self, slot: Slot) -> Result<Option<BlockId>, ConsensusError> {
+        for previous in (0..slot.0).rev().map(Slot) {
+            if self.votor.is_skipped(previous) {
+                continue;
+            }
+            let certified = self
+                .votor
+                .finalized_blocks()
+                .iter()
+                .find(|cert| cert.slot == previous)
+                .map(|cert| cert.block_id)
+                .or_else(|| self.votor.notarized_block(previous));
+            return certified
+                .map(Some)
+                .ok_or(ConsensusError::ParentNotReady(previous));
+        }
+        Ok(None)
     }
 
-    /// Get current slot
-    pub fn current_slot(&self) -> Slot {
-        self.votor.current_slot()
+    /// Scheduled leader of a slot
+    ///
+    /// The validators of the slot's epoch take turns in ID order, each
+    /// leading for `leader_window` consecutive slots. A skipped slot doesn't end its
+    /// leader's window: the leader's next block builds on the last certified
+    /// block instead.
+    pub fn leader_of(&self, slot: Slot) -> ValidatorId {
+        self.epochs.leader_of(slot, self.config.leader_window)
     }
 
-    /// Get finalized blocks
-    pub fn finalized_blocks(&self) -> &[FinalizationCertificate] {
-        self.votor.finalized_blocks()
+    /// First slot of the leader window `slot` falls in
+    pub fn window_start(&self, slot: Slot) -> Slot {
+        let window = self.config.leader_window.max(1);
+        Slot(slot.0 - slot.0 % window)
     }
 
-    /// Check if a block is finalized
-    pub fn is_finalized(&self, block_id: &BlockId) -> bool {
-        self.votor.is_finalized(block_id)
+    /// Process a vote from any validator
+    ///
+    /// A skip vote that completes the current slot's skip certificate moves
+    /// the engine to the next slot. A vote for a block of a later slot that
+    /// we haven't reconstructed yet is held back until the block arrives or
+    /// we reach its slot, and counted then.
+    ///
+    /// Once validators have keys, the vote must carry its voter's signature.
+    pub fn process_vote(&mut self, vote: Vote) -> Result<Option<FinalizationCertificate>, ConsensusError> {
+        self.check_vote_signature(&vote)?;
+        self.count_vote(vote)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn create_test_validator_set(count: usize) -> ValidatorSet {
-        let mut vset = ValidatorSet::new();
-        for i in 0..count {
-            vset.add_validator(ValidatorConfig {
-                id: ValidatorId(i as u64),
-                stake: StakeWeight(100),
-                is_byzantine: false,
-                is_offline: false,
+    /// Check a peer's vote against its voter's key in the set of its slot
+    ///
+    /// Sets without any key accept unsigned votes; votes by validators
+    /// outside the set are left for Votor to reject.
+    fn check_vote_signature(&self, vote: &Vote) -> Result<(), ConsensusError> {
+        let validator_set = self.epochs.for_slot(vote.slot);
+        let Some(validator) = validator_set.get_validator(&vote.validator) else {
+            return Ok(());
+        };
+        let valid = match validator.pubkey {
+            Some(key) => vote.verify(&key, &self.config.chain_id),
+            None => validator_set.iter().all(|validator| validator.pubkey.is_none()),
+        };
+        if !valid {
+            tracing::debug!("Dropping badly signed vote of {} for {}", vote.validator, vote.slot);
+            return Err(ConsensusError::InvalidVoteSignature {
+                validator: vote.validator,
+                slot: vote.slot,
             });
         }
-        vset
+        Ok(())
     }
 
-    fn create_test_block(slot: u64, leader: ValidatorId) -> Block {
-        let mut block = Block {
-            id: BlockId::new([0u8; 32]),
-            slot: Slot(slot),
-            parent: None,
-            leader,
-            transactions: vec![],
-            timestamp: 1000 + slot,
+    /// Count a vote whose signature, if any is needed, was checked
+    fn count_vote(
+        &mut self,
+        vote: Vote,
+    ) -> Result<Option<FinalizationCertificate>, ConsensusError> {
+        let (block_id, slot, is_skip) = (vote.block_id, vote.slot, vote.kind.is_skip());
+        let early =
+            !is_skip && slot > self.votor.current_slot() && !self.rotor.has_block(&block_id);
+        if early && self.early_votes.len() < MAX_EARLY_VOTES {
+            if !self.early_votes.contains(&vote) {
+                self.early_votes.push(vote);
+            }
+            return Ok(None);
+        }
+        let validator = vote.validator;
+        let evidence_count = self.votor.evidence(&validator).len();
+        let was_skipped = self.votor.is_skipped(slot);
+
+        let cert = match self.votor.process_vote(vote) {
+            Ok(cert) => cert,
+            Err(e) => {
+                if let Some(double_vote) = self.votor.evidence(&validator).get(evidence_count) {
+                    let double_vote = double_vote.clone();
+                    let evidence = Evidence::DoubleVote(double_vote.clone());
+                    if self.verify_evidence(&evidence) {
+                        self.emit(ConsensusEvent::EvidenceDetected(double_vote));
+                        self.report(evidence);
+                    }
+                }
+                return Err(e.into());
+            }
         };
-        block.id = block.compute_id();
-        block
+        if is_skip {
+            if !was_skipped && self.votor.is_skipped(slot) {
+                self.cast_votes.remove(&slot);
+                self.timers.cancel(slot);
+                self.metrics.skipped(slot);
+                self.note_progress();
+                self.emit(ConsensusEvent::SkippedSlot(slot));
+                if slot == self.votor.current_slot() {
+                    tracing::info!("Slot {} skipped", slot);
+                    self.next_slot();
+                }
+            }
+            return Ok(None);
+        }
+        if self.votor.is_notarized(&block_id) {
+            self.block_tree.mark_notarized(&block_id);
+        }
+
+        if let Some(ref certificate) = cert {
+            self.on_finalized(certificate);
+        }
+
+        Ok(cert)
     }
 
-    #[test]
-    fn test_consensus_engine_creation() {
-        let vset = create_test_validator_set(5);
-        let config = ConsensusConfig::default();
-        let engine = ConsensusEngine::new(ValidatorId(0), vset, config);
+    /// Finalize a block from a certificate assembled elsewhere
+    ///
+    /// The certificate is checked against the validator set of its slot,
+    /// down to every vote's signature, and against what we already
+    /// finalized, so a node that missed the votes (e.g. while partitioned)
+    /// still learns of the finalization. Returns false if the block was
+    /// already finalized.
+    pub fn process_certificate(
+        &mut self,
+        cert: FinalizationCertificate,
+    ) -> Result<bool, ConsensusError> {
+        if self.votor.is_finalized(&cert.block_id) {
+            return Ok(false);
+        }
+        let valid = integrity::verify_certificates_with(
+            |slot| self.epochs.for_slot(slot),
+            &self.config.params,
+            &self.config.chain_id,
+            std::slice::from_ref(&cert),
+        );
+        if self.votor.finalized_blocks().iter().any(|known| known.slot == cert.slot) {
+            // With a valid quorum behind it, safety is already broken
+            if valid.is_ok() {
+                self.audit(&cert);
+            }
+            return Err(ConsensusError::InvalidCertificate(
+                InvariantViolation::ConflictingFinalization(cert.slot),
+            ));
+        }
+        valid.map_err(ConsensusError::InvalidCertificate)?;
 
-        assert_eq!(engine.current_slot(), Slot(0));
-        assert!(engine.is_leader());
+        self.votor.import_certificate(cert.clone());
+        self.on_finalized(&cert);
+        Ok(true)
+    }
+
+    /// Drop archived blocks and stored shreds older than the archive window
+    fn prune_archive(&mut self, slot: Slot) {
+        let Some(retention) = self.config.archive_retention_slots else {
+            return;
+        };
+        let horizon = Slot(slot.0.saturating_sub(retention));
+        let archived = self.rotor.prune_archive_before(horizon);
+        match self.rotor.prune_store_before(horizon) {
+            Ok(stored) if archived + stored > 0 => {
+                tracing::debug!("Pruned {} archived and {} stored blocks", archived, stored);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to prune the shred store: {}", e),
+        }
+    }
+
+    /// Bookkeeping once a block is finalized, however we learned of it
+    fn on_finalized(&mut self, cert: &FinalizationCertificate) {
+        self.audit(cert);
+        // Earlier slots are settled by the finalization too
+        self.cast_votes = self.cast_votes.split_off(&Slot(cert.slot.0 + 1));
+        self.timers.cancel_before(Slot(cert.slot.0 + 1));
+        let orphaned = self.block_tree.finalize(cert.block_id, cert.slot);
+        if !orphaned.is_empty() {
+            tracing::debug!("Discarded {} orphaned blocks", orphaned.len());
+        }
+        // Keep the block servable to repairing peers after its shreds are pruned
+        self.rotor.archive_block(&cert.block_id);
+        tracing::info!(
+            "Block {} finalized in slot {} via {:?}",
+            cert.block_id,
+            cert.slot,
+            cert.round
+        );
+        self.metrics.finalized(cert, self.clock.now());
+        self.note_progress();
+        self.emit(ConsensusEvent::FinalizedBlock(cert.clone()));
+        self.execute_finalized();
+    }
+
+    /// Record a certificate in the audit log, halting on a contradiction
+    fn audit(&mut self, cert: &FinalizationCertificate) {
+        match self.audit.record(cert) {
+            Ok(None) => {}
+            Ok(Some(violation)) => {
+                self.status = EngineStatus::Halted;
+                self.timers.cancel_all();
+                self.outgoing_votes.clear();
+                self.emit(ConsensusEvent::SafetyViolation(violation));
+            }
+            Err(e) => tracing::error!("Failed to write the audit log: {}", e),
+        }
+    }
+
+    /// Audit certificates into `log` from now on, starting with the ones
+    /// already finalized
+    pub fn set_audit_log(&mut self, log: AuditLog) {
+        self.audit = log;
+        for cert in self.votor.finalized_blocks().to_vec() {
+            self.audit(&cert);
+        }
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// Apply finalized blocks to the executor from now on
+    pub fn set_executor(&mut self, executor: Box<dyn Executor>) {
+        self.executor = Some(executor);
+        self.execute_finalized();
+    }
+
+    /// Slot of the last block applied to the executor
+    pub fn last_executed(&self) -> Option<Slot> {
+        self.last_executed.map(|(slot, _)| slot)
+    }
+
+    /// Apply the finalized chain up to the latest finalized block
+    ///
+    /// Walks back from the latest finalized block to the last one applied
+    /// and applies the blocks in between oldest first. Stops without
+    /// applying anything if a body on the way is still missing.
+    fn execute_finalized(&mut self) {
+        let Some(head) = self.latest_finalized().map(|cert| cert.block_id) else {
+            return;
+        };
+        if self.executor.is_none() || self.last_executed.is_some_and(|(_, id)| id == head) {
+            return;
+        }
+
+        let mut chain = Vec::new();
+        let mut next = Some(head);
+        while let Some(block_id) = next {
+            let Some(block) = self.local_block(&block_id) else {
+                tracing::debug!("Execution waits for the body of block {}", block_id);
+                return;
+            };
+            // Rotor only checks the ID a body claims
+            if block.compute_id() != block_id {
+                tracing::warn!("Not executing a body that doesn't hash to block {}", block_id);
+                return;
+            }
+            if self.last_executed.is_some_and(|(slot, _)| block.header.slot <= slot) {
+                break;
+            }
+            next = block.header.parent;
+            chain.push(block.clone());
+        }
+
+        if let Some(executor) = self.executor.as_mut() {
+            for block in chain.iter().rev() {
+                executor.apply(block);
+            }
+        }
+        if let Some(block) = chain.first() {
+            self.last_executed = Some((block.header.slot, block.id));
+        }
+    }
+
+    /// Votes cast since the last call, to broadcast to peers
+    pub fn take_outgoing_votes(&mut self) -> Vec<Vote> {
+        // Held back until resumed, so nothing gets signed while paused
+        if self.status != EngineStatus::Running {
+            return Vec::new();
+        }
+        std::mem::take(&mut self.outgoing_votes)
+    }
+
+    /// Shreds received since the last call that we relay, with their
+    /// destination
+    pub fn take_outgoing_shreds(&mut self) -> Vec<(ValidatorId, Shred)> {
+        std::mem::take(&mut self.outgoing_shreds)
+    }
+
+    /// Our votes in slots not decided yet, once per rebroadcast interval
+    ///
+    /// Peers that missed a vote (lost packets, late joiners) get it again
+    /// until the slot is finalized or skipped, so our stake still counts.
+    /// Returns nothing between intervals.
+    pub fn rebroadcast_votes(&mut self) -> Vec<Vote> {
+        let now = self.clock.now();
+        let interval = self.config.vote_rebroadcast_interval;
+        let due = self.watchdog.is_stalled()
+            || self.last_rebroadcast.is_none_or(|last| now.duration_since(last) >= interval);
+        if !due || self.cast_votes.is_empty() || self.status != EngineStatus::Running {
+            return Vec::new();
+        }
+        self.last_rebroadcast = Some(now);
+        self.cast_votes.values().flatten().cloned().collect()
+    }
+
+    /// Sign our votes and proposals with `signer` from now on
+    ///
+    /// Fails if the validator set has a different public key on record for
+    /// us, as peers would reject everything it signs.
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) -> Result<(), ConsensusError> {
+        let registered = self
+            .validator_set
+            .get_validator(&self.validator_id)
+            .and_then(|config| config.pubkey);
+        if registered.is_some_and(|key| key != signer.public_key()) {
+            return Err(ConsensusError::SignerKeyMismatch(self.validator_id));
+        }
+        self.signer = Some(signer);
+        Ok(())
+    }
+
+    /// Sign votes from `take_outgoing_votes` or `rebroadcast_votes`
+    ///
+    /// Votes already signed pass through, and without a signer votes stay
+    /// unsigned. Signatures are kept for rebroadcasts; a vote that failed
+    /// is signed again when it is next rebroadcast.
+    pub async fn sign_votes(&mut self, votes: Vec<Vote>) -> Vec<Result<Vote, SignerError>> {
+        if self.status != EngineStatus::Running {
+            let refused = || SignerError::Refused(format!("engine is {:?}", self.status));
+            return votes
+                .into_iter()
+                .map(|vote| if vote.signature.is_empty() { Err(refused()) } else { Ok(vote) })
+                .collect();
+        }
+        let Some(signer) = self.signer.clone() else {
+            return votes.into_iter().map(Ok).collect();
+        };
+        let mut signed = Vec::with_capacity(votes.len());
+        for mut vote in votes {
+            if vote.signature.is_empty() {
+                match signer.sign(vote.signing_bytes(&self.config.chain_id)).await {
+                    Ok(signature) => vote.signature = signature,
+                    Err(e) => {
+                        signed.push(Err(e));
+                        continue;
+                    }
+                }
+                let cast = self.cast_votes.get_mut(&vote.slot).into_iter().flatten();
+                for cast in cast.filter(|cast| cast.kind == vote.kind) {
+                    cast.signature = vote.signature.clone();
+                }
+                self.votor.attach_signature(&vote);
+            }
+            signed.push(Ok(vote));
+        }
+        signed
+    }
+
+    /// Whether exactly this vote was already counted or held back, e.g. a
+    /// rebroadcast
+    pub fn has_vote(&self, vote: &Vote) -> bool {
+        self.votor.has_vote(vote) || self.early_votes.contains(vote)
+    }
+
+    /// Votes held back until their block or slot arrives
+    pub fn early_votes(&self) -> &[Vote] {
+        &self.early_votes
+    }
+
+    /// Count the held back votes matching `ready`
+    fn release_early_votes(&mut self, ready: impl Fn(&Vote) -> bool) {
+        let (ready, waiting) = std::mem::take(&mut self.early_votes).into_iter().partition(ready);
+        self.early_votes = waiting;
+        for vote in ready {
+            let (validator, slot) = (vote.validator, vote.slot);
+            if let Err(e) = self.count_vote(vote) {
+                tracing::debug!("Dropped early vote of {} for slot {}: {}", validator, slot, e);
+            }
+        }
+    }
+
+    /// Start the current slot's round deadlines unless already running
+    ///
+    /// `propose_block` starts them for the leader; other nodes start them
+    /// when they enter the slot.
+    pub fn start_round1_timer(&mut self) {
+        let slot = self.votor.current_slot();
+        let stopped = matches!(self.status, EngineStatus::ShutDown | EngineStatus::Halted);
+        if !stopped && !self.timers.is_running(slot) {
+            self.timers.start_slot(slot, self.clock.now(), self.timeouts_at(slot));
+        }
+    }
+
+    /// Round timeouts of the epoch `slot` belongs to
+    fn timeouts_at(&self, slot: Slot) -> RoundTimeouts {
+        let epoch = self.config.epoch_schedule.epoch_of(slot);
+        self.config.timing.timeouts_for(epoch)
+    }
+
+    /// Earliest pending round deadline, for callers to sleep until
+    ///
+    /// None while paused, when no deadline fires.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.status != EngineStatus::Running {
+            return None;
+        }
+        self.timers.next_deadline()
+    }
+
+    /// Act on the round deadlines that have passed
+    ///
+    /// Round 1 ending starts round 2 and, if the slot's block never arrived,
+    /// casts a skip vote. Round 2 ending casts a skip vote (skip-fallback if
+    /// we already voted for a block), so the slot can still be skipped.
+    /// Earlier slots still in flight in the pipeline time out the same way.
+    /// Deadlines of finalized or skipped slots are dropped. Returns the
+    /// timeouts acted on.
+    pub fn fire_timers(&mut self) -> Vec<Timeout> {
+        let mut fired = Vec::new();
+        if self.status != EngineStatus::Running {
+            return fired;
+        }
+        for timeout in self.timers.expired(self.clock.now()) {
+            let slot = timeout.slot();
+            if slot > self.votor.current_slot() || self.is_slot_decided(slot) {
+                continue;
+            }
+            match timeout {
+                Timeout::Round1(slot) => self.on_round1_timeout(slot),
+                Timeout::Round2(slot) => self.on_round2_timeout(slot),
+            }
+            fired.push(timeout);
+        }
+        fired
+    }
+
+    /// Whether `slot` has a finalized block or a skip certificate
+    pub(crate) fn is_slot_decided(&self, slot: Slot) -> bool {
+        self.votor.is_skipped(slot)
+            || self.votor.finalized_blocks().iter().any(|cert| cert.slot == slot)
+    }
+
+    /// Configuration the engine runs with
+    pub fn config(&self) -> &ConsensusConfig {
+        &self.config
+    }
+
+    /// Read the time from `clock` from now on, e.g. a `ManualClock` in tests
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// The engine's time source, to share with a `SlotClock` or producer
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Snapshot of finalization, skip and timeout counts and block latencies
+    pub fn metrics(&self) -> EngineMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Messages the run loop queued and dropped on ingress
+    pub fn ingress_counters(&self) -> IngressCounters {
+        self.ingress_counters
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn set_ingress_counters(&mut self, counters: IngressCounters) {
+        self.ingress_counters = counters;
+    }
+
+    /// Whether we only follow consensus and never sign
+    pub fn is_observer(&self) -> bool {
+        self.config.observer
+    }
+
+    pub fn status(&self) -> EngineStatus {
+        self.status
+    }
+
+    /// Stop signing for a maintenance window
+    ///
+    /// Incoming shreds, votes and certificates are still processed, so the
+    /// engine keeps up with the chain. Votes already decided on are held
+    /// back unsigned and round deadlines wait until `resume`.
+    pub fn pause(&mut self) -> Result<(), ConsensusError> {
+        if matches!(self.status, EngineStatus::ShutDown | EngineStatus::Halted) {
+            return Err(ConsensusError::NotRunning(self.status));
+        }
+        tracing::info!("Pausing at slot {}", self.votor.current_slot());
+        self.status = EngineStatus::Paused;
+        Ok(())
+    }
+
+    /// Take part in consensus again after `pause`
+    ///
+    /// Deadlines that passed during the pause fire on the next
+    /// `fire_timers`.
+    pub fn resume(&mut self) -> Result<(), ConsensusError> {
+        if matches!(self.status, EngineStatus::ShutDown | EngineStatus::Halted) {
+            return Err(ConsensusError::NotRunning(self.status));
+        }
+        tracing::info!("Resuming at slot {}", self.votor.current_slot());
+        self.status = EngineStatus::Running;
+        Ok(())
+    }
+
+    /// Stop for good: cancel every deadline and persist the state to `storage`
+    ///
+    /// Every vote we decided on is in the persisted state, sent or not, so a
+    /// restart never signs a conflicting one. Votes not yet sent are dropped.
+    pub fn shutdown(&mut self, storage: &dyn Storage) -> Result<(), ConsensusError> {
+        tracing::info!("Shutting down at slot {}", self.votor.current_slot());
+        self.status = EngineStatus::ShutDown;
+        self.timers.cancel_all();
+        self.outgoing_votes.clear();
+        self.outgoing_shreds.clear();
+        self.persist(storage)
+    }
+
+    /// Fire due round deadlines; whether round 1 of the current slot timed out
+    pub fn check_round1_timeout(&mut self) -> bool {
+        self.fire_timers()
+            .iter()
+            .any(|timeout| matches!(timeout, Timeout::Round1(_)))
+    }
+
+    /// Round 1 of `slot` ran out: move it to round 2, and vote to skip it
+    /// if its block never arrived
+    pub(crate) fn on_round1_timeout(&mut self, slot: Slot) {
+        self.metrics.timed_out(Timeout::Round1(slot));
+        self.check_standstill();
+        self.advance_to_round2(slot);
+        if self.rotor.blocks_in_slot(slot).is_empty() || self.is_equivocated(slot) {
+            self.cast_skip_vote(slot, VoteKind::Skip);
+        }
+    }
+
+    /// Count a timeout, reporting a standstill once too many passed
+    fn check_standstill(&mut self) {
+        if !self.watchdog.timed_out() {
+            return;
+        }
+        let mut votes_seen = 0;
+        let mut heard = HashSet::new();
+        for slot in self.in_flight_slots() {
+            let mut voters = self.votor.voters(slot);
+            voters.extend(self.votor.skip_voters(slot));
+            votes_seen += voters.len();
+            heard.extend(voters);
+        }
+        let report = StandstillReport {
+            current_slot: self.votor.current_slot(),
+            last_finalized: self.latest_finalized().map(|cert| cert.slot),
+            timeouts: self.watchdog.timeouts(),
+            votes_seen,
+            stake_heard: self.validator_set.calculate_stake(&heard),
+            total_stake: self.validator_set.total_stake(),
+        };
+        tracing::warn!("Standstill: {:?}", report);
+        self.emit(ConsensusEvent::Standstill(report));
+    }
+
+    fn note_progress(&mut self) {
+        if self.watchdog.progress() {
+            tracing::info!("Standstill over at slot {}", self.votor.current_slot());
+        }
+    }
+
+    /// Whether the engine is in standstill, rebroadcasting and repairing
+    /// eagerly
+    pub fn is_stalled(&self) -> bool {
+        self.watchdog.is_stalled()
+    }
+
+    /// Round 2 ran out too: vote to skip the slot, as a skip-fallback vote
+    /// if we already voted for a block in it
+    fn on_round2_timeout(&mut self, slot: Slot) {
+        self.metrics.timed_out(Timeout::Round2(slot));
+        self.check_standstill();
+        let voted = [VoteKind::Notar, VoteKind::Final]
+            .iter()
+            .any(|kind| self.signed_votes.contains_key(&(slot, *kind)));
+        let kind = if voted { VoteKind::SkipFallback } else { VoteKind::Skip };
+        self.cast_skip_vote(slot, kind);
+    }
+
+    /// Advance `slot` to round 2
+    fn advance_to_round2(&mut self, slot: Slot) {
+        tracing::info!("Advancing to round 2 for slot {}", slot);
+        self.votor.start_round2(slot);
+        self.emit(ConsensusEvent::RoundAdvanced { slot, round: VoteRound::Round2 });
+    }
+
+    /// Vote to skip `slot` with a skip or skip-fallback vote
+    ///
+    /// At most one skip vote of either kind is signed per slot, and a plain
+    /// skip vote never after voting for a block.
+    fn cast_skip_vote(&mut self, slot: Slot, kind: VoteKind) {
+        if !self.may_vote() {
+            return;
+        }
+        let conflicting: &[VoteKind] = match kind {
+            VoteKind::Skip => &[VoteKind::Notar, VoteKind::Final, VoteKind::Skip],
+            _ => &[VoteKind::Skip, VoteKind::SkipFallback],
+        };
+        if conflicting
+            .iter()
+            .any(|kind| self.signed_votes.contains_key(&(slot, *kind)))
+        {
+            return;
+        }
+        // Skip votes aren't for a block; they carry the zero ID
+        let block_id = BlockId::new([0u8; 32]);
+        self.signed_votes.insert((slot, kind), block_id);
+
+        let vote = Vote {
+            validator: self.validator_id,
+            block_id,
+            slot,
+            kind,
+            signature: vec![],
+        };
+        tracing::info!("Voting to skip slot {} ({:?})", slot, kind);
+        if let Err(e) = self.cast_vote(vote) {
+            tracing::warn!("Failed to record our skip vote: {}", e);
+        }
+    }
+
+    /// Schedule the validator set for a future epoch
+    ///
+    /// The set replaces the current one in Votor and Rotor together when
+    /// the epoch's first slot begins.
+    pub fn schedule_validator_set(
+        &mut self,
+        epoch: Epoch,
+        validator_set: ValidatorSet,
+    ) -> Result<(), ConsensusError> {
+        let current = self.votor.current_slot();
+        self.epochs.schedule_set(epoch, validator_set, current)?;
+        self.update_leader_schedule();
+        Ok(())
+    }
+
+    /// Have Rotor check every shred against the leader our epochs schedule
+    /// for its slot
+    fn update_leader_schedule(&mut self) {
+        self.rotor.set_leader_schedule(self.epochs.clone(), self.config.leader_window);
+    }
+
+    /// Slash validators for the misbehavior we detect from now on
+    ///
+    /// Penalties are taken from the current validator set and the result is
+    /// scheduled for the next epoch, replacing any set scheduled for it.
+    pub fn enable_slashing(&mut self, rules: SlashingRules) {
+        let (validator_set, chain_id) = (self.validator_set.clone(), self.config.chain_id);
+        self.slashing = Some(SlashingManager::new(rules, validator_set, chain_id));
+    }
+
+    pub fn slashing(&self) -> Option<&SlashingManager> {
+        self.slashing.as_ref()
+    }
+
+    /// Record verified misbehavior in `ledger` from now on, and refuse
+    /// messages from the validators it bans
+    pub fn set_misbehavior_ledger(&mut self, ledger: MisbehaviorLedger) {
+        self.misbehavior = Some(ledger);
+    }
+
+    pub fn misbehavior_ledger(&self) -> Option<&MisbehaviorLedger> {
+        self.misbehavior.as_ref()
+    }
+
+    /// Whether the misbehavior ledger bans `validator`
+    pub fn is_banned(&self, validator: &ValidatorId) -> bool {
+        self.misbehavior.as_ref().is_some_and(|ledger| ledger.is_banned(validator))
+    }
+
+    /// Whether the offender's key on record verifies `evidence`
+    fn verify_evidence(&self, evidence: &Evidence) -> bool {
+        let offender = evidence.offender();
+        let key = self.validator_set.get_validator(&offender).and_then(|v| v.pubkey);
+        let verified = key.is_some_and(|key| evidence.verify(&key, &self.config.chain_id));
+        if !verified {
+            tracing::warn!("Not acting on unverified evidence against {}", offender);
+        }
+        verified
+    }
+
+    /// Record evidence that passed `verify_evidence` in the misbehavior
+    /// ledger and hand it to the slashing manager
+    fn report(&mut self, evidence: Evidence) {
+        let offender = evidence.offender();
+        if let Some(ledger) = &mut self.misbehavior {
+            if let Err(e) = ledger.record(evidence.record()) {
+                tracing::warn!("Failed to record misbehavior of {}: {}", offender, e);
+            }
+        }
+        self.slash(evidence);
+    }
+
+    fn slash(&mut self, evidence: Evidence) {
+        let Some(manager) = &mut self.slashing else {
+            return;
+        };
+        let slashed = match manager.slash(evidence) {
+            Ok(slashed) => slashed,
+            Err(e) => {
+                tracing::warn!("Not slashing: {}", e);
+                return;
+            }
+        };
+        let validator_set = manager.validator_set().clone();
+        let next_epoch = self.epochs.schedule().epoch_of(self.votor.current_slot()).next();
+        if let Err(e) = self.schedule_validator_set(next_epoch, validator_set) {
+            tracing::warn!("Failed to schedule the slashed validator set: {}", e);
+        }
+        self.emit(ConsensusEvent::ValidatorSlashed(slashed));
+    }
+
+    /// Replace the configuration from the next slot boundary on
+    ///
+    /// Round timeouts apply to slots started after the boundary; slots in
+    /// flight keep their deadlines. `params`, `epoch_schedule`, `rotor`,
+    /// `observer` and `leader_window` are fixed for the engine's lifetime, and
+    /// `startup` is only read at construction. A second call before the
+    /// boundary replaces the first.
+    pub fn reconfigure(&mut self, config: ConsensusConfig) -> Result<(), ConfigError> {
+        if config.params != self.config.params {
+            return Err(ConfigError::Immutable("params"));
+        }
+        if config.epoch_schedule != self.config.epoch_schedule {
+            return Err(ConfigError::Immutable("epoch_schedule"));
+        }
+        if config.rotor != self.config.rotor {
+            return Err(ConfigError::Immutable("rotor"));
+        }
+        if config.observer != self.config.observer {
+            return Err(ConfigError::Immutable("observer"));
+        }
+        if config.leader_window != self.config.leader_window {
+            return Err(ConfigError::Immutable("leader_window"));
+        }
+        if config.timing.slot_duration != self.config.timing.slot_duration {
+            return Err(ConfigError::Immutable("timing.slot_duration"));
+        }
+        if config.chain_id != self.config.chain_id {
+            return Err(ConfigError::Immutable("chain_id"));
+        }
+        if let Some(problem) = builder::check_config(&config).into_iter().next() {
+            return Err(problem);
+        }
+        self.pending_config = Some(config);
+        Ok(())
+    }
+
+    /// Configuration waiting for the next slot boundary
+    pub fn pending_config(&self) -> Option<&ConsensusConfig> {
+        self.pending_config.as_ref()
+    }
+
+    /// Validator sets per epoch
+    pub fn epochs(&self) -> &EpochValidatorSets {
+        &self.epochs
+    }
+
+    /// Move to the next slot
+    pub fn next_slot(&mut self) {
+        // Earlier undecided slots keep their deadlines while in flight
+        self.votor.next_slot();
+
+        let slot = self.votor.current_slot();
+        self.rotor.set_current_slot(slot);
+        if let Some(config) = self.pending_config.take() {
+            tracing::info!("Slot {} starts with the new configuration", slot);
+            self.watchdog.set_threshold(config.standstill_timeouts);
+            self.config = config;
+        }
+        if let Some(validator_set) = self.epochs.starting_at(slot).cloned() {
+            tracing::info!(
+                "{} begins with {} validators",
+                self.epochs.schedule().epoch_of(slot),
+                validator_set.len()
+            );
+            self.votor.begin_epoch(validator_set.clone(), slot);
+            self.rotor.set_validator_set(validator_set.clone());
+            self.validator_set = validator_set;
+        }
+        self.prune_archive(slot);
+
+        self.startup.update(self.votor.current_slot());
+        self.run_integrity_check();
+        self.emit(ConsensusEvent::RoundAdvanced { slot, round: VoteRound::Round1 });
+
+        // Rotate leader at window boundaries
+        let leader = self.leader_of(slot);
+        if leader != self.current_leader {
+            self.current_leader = leader;
+            self.emit(ConsensusEvent::LeaderChanged { slot, leader });
+        }
+
+        tracing::info!(
+            "Advanced to slot {}, leader is {}",
+            self.votor.current_slot(),
+            self.current_leader
+        );
+
+        // Held back votes count once we reach their slot, block or not
+        self.release_early_votes(|vote| vote.slot <= slot);
+    }
+
+    /// Check whether a gossiped certificate falls inside the configured slot window
+    pub fn admit_gossip_certificate(
+        &mut self,
+        cert: &FinalizationCertificate,
+    ) -> Result<(), ConsensusError> {
+        let current = self.votor.current_slot();
+        let window = self.config.certificate_window;
+
+        if cert.slot.0.saturating_add(window.max_slots_behind) < current.0 {
+            self.gossip_counters.rejected_too_old += 1;
+            return Err(ConsensusError::CertificateTooOld { slot: cert.slot, current });
+        }
+        if cert.slot.0 > current.0.saturating_add(window.max_slots_ahead) {
+            self.gossip_counters.rejected_too_new += 1;
+            return Err(ConsensusError::CertificateTooNew { slot: cert.slot, current });
+        }
+
+        self.gossip_counters.accepted += 1;
+        Ok(())
+    }
+
+    /// Gossip certificate admission counters
+    pub fn gossip_counters(&self) -> GossipCounters {
+        self.gossip_counters
+    }
+
+    /// Check local state for corruption
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        integrity::check_certificates_with(
+            |slot| self.epochs.for_slot(slot),
+            &self.config.params,
+            self.votor.finalized_blocks(),
+        )
+    }
+
+    /// Watchdog step: checkpoint a consistent state, or roll back a corrupt one
+    ///
+    /// Runs automatically at every slot boundary. Returns the violation that
+    /// triggered a rollback, if any.
+    pub fn run_integrity_check(&mut self) -> Option<InvariantViolation> {
+        match self.check_invariants() {
+            Ok(()) => {
+                self.checkpoint = Some(Checkpoint {
+                    slot: self.votor.current_slot(),
+                    leader: self.current_leader,
+                    finalized: self.votor.finalized_blocks().to_vec(),
+                });
+                None
+            }
+            Err(violation) => {
+                tracing::error!("Local state corrupted ({}), rolling back", violation);
+                self.rollback_to_checkpoint();
+                Some(violation)
+            }
+        }
+    }
+
+    /// Snapshot of our finalized state for a joining node to bootstrap from
+    ///
+    /// None until a block is finalized.
+    pub fn export_snapshot(&self) -> Option<Snapshot> {
+        let mut certificates = self.votor.finalized_blocks().to_vec();
+        certificates.sort_by_key(|cert| cert.slot);
+        let tip = certificates.last()?.slot;
+        Some(Snapshot {
+            validators: self.epochs.for_slot(tip).entries(),
+            certificates,
+            epoch: self.epochs.schedule().epoch_of(tip),
+            state_hash: self.executor.as_ref().and_then(|executor| executor.state_hash()),
+        })
+    }
+
+    /// Verify a snapshot signed by one of the `trusted` validators and
+    /// continue from the slot after its tip; that slot
+    ///
+    /// The snapshot's validator set applies from then on, and its tip counts
+    /// as executed: the application must restore the state matching
+    /// `state_hash` itself.
+    pub fn import_snapshot(
+        &mut self,
+        signed: &SignedSnapshot,
+        trusted: &HashMap<ValidatorId, VerifyingKey>,
+    ) -> Result<Slot, ConsensusError> {
+        let snapshot = signed.verify(trusted, &self.config.params)?;
+        let tip = snapshot.tip().ok_or(SnapshotError::Empty)?;
+        if let Some(finalized) = self.latest_finalized().map(|cert| cert.slot) {
+            if tip.slot <= finalized {
+                return Err(SnapshotError::Stale { tip: tip.slot, finalized }.into());
+            }
+        }
+
+        let slot = tip.slot.next();
+        let validator_set = snapshot.validator_set()?;
+        self.epochs = EpochValidatorSets::new(self.config.epoch_schedule, validator_set);
+        self.reset_to(slot, self.current_leader, snapshot.certificates.clone());
+        self.current_leader = self.leader_of(slot);
+        self.last_executed = Some((tip.slot, tip.block_id));
+        self.startup.update(slot);
+        tracing::info!(
+            "Bootstrapped from a snapshot by {} at slot {} with {} finalized blocks",
+            signed.signer,
+            tip.slot,
+            snapshot.certificates.len()
+        );
+        Ok(slot)
+    }
+
+    /// Restore the last verified checkpoint and re-sync forward
+    ///
+    /// Our record of signed votes is kept, so re-syncing can never make us
+    /// sign a conflicting vote for a slot we already voted in.
+    pub fn rollback_to_checkpoint(&mut self) {
+        let checkpoint = self.checkpoint.clone().unwrap_or(Checkpoint {
+            slot: Slot(0),
+            leader: ValidatorId::Index(0),
+            finalized: Vec::new(),
+        });
+
+        self.reset_to(checkpoint.slot, checkpoint.leader, checkpoint.finalized);
+        self.startup.resync();
+    }
+
+    /// Rebuild Votor, Rotor and the block tree at `slot` with only the
+    /// `finalized` certificates known; signed votes, and Rotor's store,
+    /// archive, keys and subscribers are kept
+    fn reset_to(
+        &mut self,
+        slot: Slot,
+        leader: ValidatorId,
+        finalized: Vec<FinalizationCertificate>,
+    ) {
+        let (epoch_start, validator_set) = self.epochs.active(slot);
+        self.validator_set = validator_set.clone();
+        self.votor = Votor::from_checkpoint(self.validator_set.clone(), slot, finalized)
+            .with_params(self.config.params);
+        self.votor.begin_epoch(self.validator_set.clone(), epoch_start);
+        self.rotor.reset(self.validator_set.clone());
+        self.rotor.set_current_slot(slot);
+        self.update_leader_schedule();
+        self.current_leader = leader;
+        self.timers = TimerService::new();
+        self.fetched_bodies.clear();
+        self.block_tree = BlockTree::new();
+        if let Some(latest) = self.latest_finalized() {
+            let (block_id, slot) = (latest.block_id, latest.slot);
+            self.block_tree.finalize(block_id, slot);
+        }
+    }
+
+    /// State to persist for crash recovery
+    pub(crate) fn engine_state(&self) -> EngineState {
+        let mut signed_votes: Vec<_> = self
+            .signed_votes
+            .iter()
+            .map(|((slot, kind), block_id)| (*slot, *kind, *block_id))
+            .collect();
+        signed_votes.sort_by_key(|(slot, kind, _)| (*slot, *kind as u8));
+        EngineState {
+            slot: self.votor.current_slot(),
+            leader: self.current_leader,
+            signed_votes,
+            finalized: self.votor.finalized_blocks().to_vec(),
+        }
+    }
+
+    /// Resume from persisted state, as after a restart
+    pub(crate) fn restore_state(&mut self, state: EngineState) {
+        self.reset_to(state.slot, state.leader, state.finalized);
+        self.signed_votes.extend(
+            state
+                .signed_votes
+                .into_iter()
+                .map(|(slot, kind, block_id)| ((slot, kind), block_id)),
+        );
+    }
+
+    /// Last verified checkpoint
+    pub fn checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Startup status (phase and the conditions it depends on)
+    pub fn startup_status(&self) -> StartupStatus {
+        self.startup.status()
+    }
+
+    /// Record that the voting-safety file has been loaded
+    pub fn mark_voting_safety_loaded(&mut self) -> StartupPhase {
+        self.startup.mark_safety_file_loaded();
+        self.startup.update(self.votor.current_slot())
+    }
+
+    /// Record the highest slot observed on the network
+    pub fn observe_network_slot(&mut self, slot: Slot) -> StartupPhase {
+        self.startup.observe_network_slot(slot);
+        self.startup.update(self.votor.current_slot())
+    }
+
+    /// Record the measured clock drift from network time
+    pub fn observe_clock_drift(&mut self, drift_ms: u64) -> StartupPhase {
+        self.startup.observe_clock_drift(drift_ms);
+        self.startup.update(self.votor.current_slot())
+    }
+
+    /// Check if we are the current leader
+    pub fn is_leader(&self) -> bool {
+        self.current_leader == self.validator_id
+    }
+
+    /// Leader of the current slot
+    pub fn current_leader(&self) -> ValidatorId {
+        self.current_leader
+    }
+
+    /// Get current slot
+    pub fn current_round(&self) -> VoteRound {
+        self.votor.current_round()
+    }
+
+    pub fn current_slot(&self) -> Slot {
+        self.votor.current_slot()
+    }
+
+    /// Get finalized blocks
+    pub fn finalized_blocks(&self) -> &[FinalizationCertificate] {
+        self.votor.finalized_blocks()
+    }
+
+    /// Pending blocks and the branch that got notarized
+    pub fn block_tree(&self) -> &BlockTree {
+        &self.block_tree
+    }
+
+    /// Slots after the latest finalized one, up to the current slot, that
+    /// are neither finalized nor skipped yet
+    pub fn in_flight_slots(&self) -> Vec<Slot> {
+        let first = self.latest_finalized().map_or(0, |cert| cert.slot.0 + 1);
+        (first..=self.votor.current_slot().0)
+            .map(Slot)
+            .filter(|slot| !self.is_slot_decided(*slot))
+            .collect()
+    }
+
+    /// Whether the next slot may start before the current one is decided
+    ///
+    /// The current slot needs a notarized block, so the next leader has a
+    /// parent to build on, and fewer than `pipeline_depth` slots may be in
+    /// flight. A decided current slot can always be left.
+    pub fn can_advance_pipeline(&self) -> bool {
+        let current = self.votor.current_slot();
+        if self.is_slot_decided(current) {
+            return true;
+        }
+        self.votor.notarized_block(current).is_some()
+            && (self.in_flight_slots().len() as u64) < self.config.pipeline_depth
+    }
+
+    /// Start the next slot if the pipeline allows it; whether it did
+    ///
+    /// With `pipeline_depth` above 1, slot N+1's block is proposed and
+    /// disseminated while slot N's finalization votes still aggregate.
+    pub fn advance_pipeline(&mut self) -> bool {
+        if !self.can_advance_pipeline() {
+            return false;
+        }
+        self.next_slot();
+        true
+    }
+
+    /// Certificate of the highest finalized slot
+    pub fn latest_finalized(&self) -> Option<&FinalizationCertificate> {
+        self.votor.finalized_blocks().iter().max_by_key(|cert| cert.slot)
+    }
+
+    /// Proof for light clients that a block in `slot` was finalized
+    pub fn finality_proof(&self, slot: Slot) -> Option<FinalityProof> {
+        let cert = self.votor.finalized_blocks().iter().find(|cert| cert.slot == slot)?;
+        let epoch = self.epochs.schedule().epoch_of(slot);
+        Some(FinalityProof::new(cert.clone(), epoch, self.epochs.for_slot(slot)))
+    }
+
+    /// Check if a block is finalized
+    pub fn is_finalized(&self, block_id: &BlockId) -> bool {
+        self.votor.is_finalized(block_id)
+    }
+
+    /// How far a block has progressed towards finality
+    pub fn confirmation_status(&self, block_id: &BlockId) -> ConfirmationStatus {
+        let finalized = self
+            .votor
+            .finalized_blocks()
+            .iter()
+            .find(|cert| cert.block_id == *block_id);
+        if let Some(cert) = finalized {
+            return match cert.round {
+                VoteRound::Round1 => ConfirmationStatus::FastFinalized,
+                VoteRound::Round2 => ConfirmationStatus::FallbackFinalized,
+            };
+        }
+        if self.votor.is_notarized(block_id) {
+            let stake = self.votor.notarization_stake(block_id).as_u64() as u128;
+            let total = self.validator_set.total_stake().as_u64().max(1) as u128;
+            return ConfirmationStatus::Notarized((stake * 100 / total).min(100) as u8);
+        }
+        let received = self.rotor.has_block(block_id)
+            || self.rotor.archived_block(block_id).is_some()
+            || self.block_tree.contains(block_id);
+        if received {
+            ConfirmationStatus::Received
+        } else {
+            ConfirmationStatus::Unknown
+        }
+    }
+
+    /// Check if a slot was skipped
+    pub fn is_skipped(&self, slot: Slot) -> bool {
+        self.votor.is_skipped(slot)
+    }
+
+    /// Double-vote evidence collected against a validator
+    pub fn evidence(&self, validator: &ValidatorId) -> &[DoubleVoteEvidence] {
+        self.votor.evidence(validator)
+    }
+
+    /// Get the body of a finalized block, fetching it on demand
+    ///
+    /// The body is looked up locally first, then requested from `source`.
+    /// Fetched bodies are verified against the block ID committed in the
+    /// slot's finalization certificate and cached.
+    pub fn fetch_block_body(
+        &mut self,
+        slot: Slot,
+        source: &mut dyn BlockSource,
+    ) -> Result<&Block, ConsensusError> {
+        let block_id = self
+            .votor
+            .finalized_blocks()
+            .iter()
+            .find(|cert| cert.slot == slot)
+            .map(|cert| cert.block_id)
+            .ok_or(ConsensusError::NotFinalized(slot))?;
+
+        if !self.fetched_bodies.contains_key(&slot) {
+            let block = match self.rotor.get_block(&block_id) {
+                Some(block) => block.clone(),
+                None => source
+                    .fetch_block(slot, &block_id)
+                    .ok_or(ConsensusError::BlockBodyUnavailable(slot))?,
+            };
+
+            if block.header.slot != slot || block.id != block_id || block.compute_id() != block_id {
+                return Err(ConsensusError::BlockBodyMismatch(slot));
+            }
+
+            self.fetched_bodies.insert(slot, block);
+        }
+
+        Ok(&self.fetched_bodies[&slot])
+    }
+
+    /// Start catching up if `network_slot` is further ahead than the startup lag
+    ///
+    /// Drops back to `Syncing`, so nothing is signed until the missed slots
+    /// are applied, and returns the range to request from a peer: every slot
+    /// after our latest finalized one. Large gaps are fetched in several
+    /// rounds of at most `MAX_SYNC_SLOTS` slots.
+    pub fn sync_request(&mut self, network_slot: Slot) -> Option<SyncRequest> {
+        self.startup.observe_network_slot(network_slot);
+        if !self.startup.is_behind(self.votor.current_slot()) {
+            return None;
+        }
+        self.startup.resync();
+        let from = self.latest_finalized().map_or(Slot(0), |cert| Slot(cert.slot.0 + 1));
+        let to = Slot(network_slot.0.min(from.0.saturating_add(MAX_SYNC_SLOTS - 1)));
+        Some(SyncRequest { from, to })
+    }
+
+    /// Answer a peer's sync request with what we have finalized in its range
+    ///
+    /// The response stops before the first certified block whose body (or
+    /// whose link to the previous certified block) we no longer hold, so it
+    /// always verifies as a prefix.
+    pub fn serve_sync(&self, request: &SyncRequest) -> SyncResponse {
+        let mut certificates: Vec<_> = self
+            .votor
+            .finalized_blocks()
+            .iter()
+            .filter(|cert| request.contains(cert.slot))
+            .cloned()
+            .collect();
+        certificates.sort_by_key(|cert| cert.slot);
+        certificates.dedup_by_key(|cert| cert.slot);
+
+        let mut response = SyncResponse::default();
+        let mut previous = self
+            .votor
+            .finalized_blocks()
+            .iter()
+            .filter(|cert| cert.slot < request.from)
+            .max_by_key(|cert| cert.slot)
+            .map(|cert| cert.block_id);
+        for cert in certificates {
+            let mut chain = Vec::new();
+            let mut next = Some(cert.block_id);
+            while next != previous {
+                match next.and_then(|id| self.local_block(&id)) {
+                    Some(block) if block.header.slot >= request.from => {
+                        next = block.header.parent;
+                        chain.push(block.clone());
+                    }
+                    _ => return response,
+                }
+            }
+            previous = Some(cert.block_id);
+            response.blocks.extend(chain.into_iter().rev());
+            response.certificates.push(cert);
+        }
+        response
+    }
+
+    /// Apply a peer's answer to our sync request
+    ///
+    /// The certificate chain is verified in full before anything changes.
+    /// The certified blocks are then finalized and we move to the slot after
+    /// the last one, rejoining voting through the usual startup phases once
+    /// we're within the lag of the network tip. Returns the number of slots
+    /// finalized.
+    pub fn apply_sync(
+        &mut self,
+        request: &SyncRequest,
+        mut response: SyncResponse,
+    ) -> Result<usize, ConsensusError> {
+        response
+            .certificates
+            .retain(|cert| !self.votor.is_finalized(&cert.block_id));
+        let anchor = self.latest_finalized().map(|cert| cert.block_id);
+        let (params, chain_id) = (&self.config.params, &self.config.chain_id);
+        let finalized =
+            sync::verify_response(&self.epochs, params, chain_id, request, anchor, response)?;
+
+        let count = finalized.len();
+        let next = finalized.last().map(|(cert, _)| Slot(cert.slot.0 + 1));
+        for (cert, block) in finalized {
+            while self.votor.current_slot() < cert.slot {
+                self.next_slot();
+            }
+            self.fetched_bodies.insert(cert.slot, block);
+            self.votor.import_certificate(cert.clone());
+            self.on_finalized(&cert);
+        }
+        while next.is_some_and(|next| self.votor.current_slot() < next) {
+            self.next_slot();
+        }
+        tracing::info!("Synced {} finalized slots, now at slot {}", count, self.current_slot());
+
+        self.startup.update(self.votor.current_slot());
+        Ok(count)
+    }
+
+    /// Height of a block whose body we hold
+    pub fn block_height(&self, block_id: &BlockId) -> Option<u64> {
+        self.local_block(block_id).map(|block| block.header.height)
+    }
+
+    /// A block body we hold, whether reconstructed, archived or fetched
+    fn local_block(&self, block_id: &BlockId) -> Option<&Block> {
+        self.rotor
+            .get_block(block_id)
+            .or_else(|| self.rotor.archived_block(block_id))
+            .or_else(|| self.fetched_bodies.values().find(|block| block.id == *block_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stake::StakeDistribution;
+    use crate::votor::VotorError;
+    use ed25519_dalek::SigningKey;
+
+    fn create_test_validator_set(count: usize) -> ValidatorSet {
+        let mut vset = ValidatorSet::new();
+        for i in 0..count {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(i as u64),
+                stake: StakeWeight(100),
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
+        }
+        vset
+    }
+
+    fn create_test_block(slot: u64, leader: ValidatorId) -> Block {
+        Block::new(Slot(slot), None, leader, vec![], 1000 + slot)
+    }
+
+    #[test]
+    fn test_consensus_engine_creation() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig::default();
+        let engine = ConsensusEngine::new(ValidatorId::Index(0), vset, config);
+
+        assert_eq!(engine.current_slot(), Slot(0));
+        assert!(engine.is_leader());
+    }
+
+    #[test]
+    fn test_block_proposal_and_finalization() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig::default();
+
+        // Create engines for all validators
+        let mut engines: Vec<_> = (0..5)
+            .map(|i| ConsensusEngine::new(ValidatorId::Index(i), vset.clone(), config.clone()))
+            .collect();
+
+        // Leader (validator 0) proposes a block
+        let block = create_test_block(0, ValidatorId::Index(0));
+        let shreds = engines[0].propose_block(block.clone()).unwrap();
+
+        // Distribute shreds to all validators and collect votes
+        let mut votes = Vec::new();
+        for (i, engine) in engines.iter_mut().enumerate() {
+            for shred in shreds.clone() {
+                engine.receive_shred(shred).ok();
+            }
+            // Create vote from this validator
+            votes.push(Vote {
+                validator: ValidatorId::Index(i as u64),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind: VoteKind::Notar,
+                signature: vec![],
+            });
+        }
+
+        // Process all votes in all engines
+        for engine in &mut engines {
+            for vote in votes.clone() {
+                engine.process_vote(vote).ok();
+            }
+        }
+
+        // Check if block is finalized (should be with 5/5 = 100% > 80%)
+        for engine in &engines {
+            assert!(engine.is_finalized(&block.id));
+        }
+    }
+
+    #[test]
+    fn test_fetch_block_body_on_demand() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        let mut archive = HashMap::new();
+
+        // Nothing finalized yet
+        assert!(matches!(
+            engine.fetch_block_body(Slot(0), &mut archive),
+            Err(ConsensusError::NotFinalized(_))
+        ));
+
+        for i in 0..4 {
+            engine
+                .process_vote(Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .unwrap();
+        }
+
+        // A body that doesn't match the certificate is rejected
+        archive.insert(Slot(0), create_test_block(0, ValidatorId::Index(2)));
+        assert!(matches!(
+            engine.fetch_block_body(Slot(0), &mut archive),
+            Err(ConsensusError::BlockBodyMismatch(_))
+        ));
+
+        archive.insert(Slot(0), block.clone());
+        assert_eq!(engine.fetch_block_body(Slot(0), &mut archive).unwrap().id, block.id);
+
+        // Served from the cache once verified
+        archive.clear();
+        assert_eq!(engine.fetch_block_body(Slot(0), &mut archive).unwrap().id, block.id);
+    }
+
+    #[test]
+    fn test_no_proposal_before_startup_completes() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig {
+            startup: Some(StartupConfig {
+                warmup_slots: 0,
+                ..StartupConfig::default()
+            }),
+            ..ConsensusConfig::default()
+        };
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(0), vset, config);
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        assert!(matches!(
+            engine.propose_block(block.clone()),
+            Err(ConsensusError::NotActive(StartupPhase::Initializing))
+        ));
+
+        engine.mark_voting_safety_loaded();
+        engine.observe_network_slot(Slot(0));
+        assert_eq!(engine.observe_clock_drift(5), StartupPhase::WarmUp);
+        assert!(engine.propose_block(block.clone()).is_err());
+
+        engine.observe_clock_drift(5);
+        assert_eq!(engine.startup_status().phase, StartupPhase::Active);
+        assert!(engine.propose_block(block).is_ok());
+    }
+
+    #[test]
+    fn test_rollback_on_corruption_keeps_double_sign_protection() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        engine.vote_for_block(block.clone()).unwrap();
+        for i in [0, 2, 3] {
+            engine
+                .process_vote(Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .unwrap();
+        }
+        assert!(engine.run_integrity_check().is_none());
+        assert_eq!(engine.checkpoint().unwrap().finalized.len(), 1);
+
+        // Corrupt the finalized state locally
+        engine.votor.finalized_mut()[0].total_stake = StakeWeight(1);
+        assert!(matches!(
+            engine.run_integrity_check(),
+            Some(InvariantViolation::StakeMismatch(_))
+        ));
+        assert!(engine.check_invariants().is_ok());
+        assert!(engine.is_finalized(&block.id));
+        assert_eq!(engine.startup_status().phase, StartupPhase::Syncing);
+
+        // Rolled-back state still refuses a conflicting vote in slot 0
+        let mut conflicting = create_test_block(0, ValidatorId::Index(2));
+        conflicting.header.timestamp += 1;
+        conflicting.id = conflicting.compute_id();
+        engine.startup = StartupState::active();
+        assert!(matches!(
+            engine.vote_for_block(conflicting),
+            Err(ConsensusError::WouldDoubleSign(_))
+        ));
+    }
+
+    #[test]
+    fn test_gossip_certificate_window() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig {
+            certificate_window: CertificateWindow {
+                max_slots_behind: 2,
+                max_slots_ahead: 1,
+            },
+            ..ConsensusConfig::default()
+        };
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(0), vset, config);
+        for _ in 0..5 {
+            engine.next_slot();
+        }
+
+        let cert = |slot| FinalizationCertificate {
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(slot),
+            round: VoteRound::Round1,
+            votes: vec![],
+            total_stake: StakeWeight(0),
+        };
+
+        assert!(matches!(
+            engine.admit_gossip_certificate(&cert(2)),
+            Err(ConsensusError::CertificateTooOld { .. })
+        ));
+        assert!(engine.admit_gossip_certificate(&cert(3)).is_ok());
+        assert!(engine.admit_gossip_certificate(&cert(6)).is_ok());
+        assert!(matches!(
+            engine.admit_gossip_certificate(&cert(7)),
+            Err(ConsensusError::CertificateTooNew { .. })
+        ));
+
+        assert_eq!(
+            engine.gossip_counters(),
+            GossipCounters {
+                accepted: 2,
+                rejected_too_old: 1,
+                rejected_too_new: 1,
+            }
+        );
     }
 
     #[test]
-    fn test_block_proposal_and_finalization() {
+    fn test_resume_partial_block_after_restart() {
+        use crate::shred_store::FileShredStore;
+
+        let dir = std::env::temp_dir()
+            .join(format!("alpenglow-engine-resume-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
         let vset = create_test_validator_set(5);
+        let block = create_test_block(0, ValidatorId::Index(0));
+        let shreds = Rotor::new(vset.clone()).encode_block(&block).unwrap();
+        let store = || Box::new(FileShredStore::open(&dir).unwrap());
+
+        {
+            let config = ConsensusConfig::default();
+            let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+            assert_eq!(engine.resume_from_store(store()).unwrap(), 0);
+            for shred in &shreds[..20] {
+                engine.receive_shred(shred.clone()).unwrap();
+            }
+        }
+
+        // After the restart only the shreds still missing are needed
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+        assert_eq!(engine.resume_from_store(store()).unwrap(), 1);
+        let events = engine.subscribe_rotor_events();
+        for shred in &shreds[20..32] {
+            engine.receive_shred(shred.clone()).unwrap();
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Ok(RotorEvent::BlockReconstructed { block_id, .. }) if block_id == block.id
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_forged_votes_dropped() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
         let config = ConsensusConfig::default();
+        let chain_id = config.chain_id;
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(2), vset, config);
+        let block_id = BlockId::new([9u8; 32]);
+        let vote = |validator: u64| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id,
+            slot: Slot(0),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
 
-        // Create engines for all validators
+        // One peer claims everyone's votes, unsigned or with its own key
+        for i in [0, 1, 3, 4] {
+            let mut forged = vote(i);
+            if i % 2 == 1 {
+                forged.sign(&keys[4], &chain_id);
+            }
+            assert!(matches!(
+                engine.dispatch(ValidatorId::Index(4), ConsensusMessage::Vote(forged)),
+                Err(ConsensusError::InvalidVoteSignature { .. })
+            ));
+        }
+        assert!(!engine.is_finalized(&block_id));
+        assert!(engine.votor.voters(Slot(0)).is_empty());
+
+        // The real validator's vote still counts
+        let mut honest = vote(0);
+        honest.sign(&keys[0], &chain_id);
+        engine.dispatch(ValidatorId::Index(0), ConsensusMessage::Vote(honest)).unwrap();
+    }
+
+    #[test]
+    fn test_archive_pruned_behind_retention_window() {
+        use crate::shred_store::MemoryShredStore;
+
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig {
+            archive_retention_slots: Some(2),
+            ..ConsensusConfig::default()
+        };
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        engine.resume_from_store(Box::new(MemoryShredStore::new())).unwrap();
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for shred in Rotor::new(vset).encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+        for i in [0, 2, 3, 4] {
+            let vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            engine.process_vote(vote).unwrap();
+        }
+        assert!(engine.is_finalized(&block.id));
+
+        // Still servable within the window, gone from archive and store after
+        engine.next_slot();
+        engine.next_slot();
+        assert!(engine.rotor.archived_block(&block.id).is_some());
+        engine.next_slot();
+        assert!(engine.rotor.archived_block(&block.id).is_none());
+        assert_eq!(engine.rotor.prune_store_before(Slot(1)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_restore_state_keeps_rotor_store_archive_and_subscribers() {
+        use crate::shred_store::MemoryShredStore;
+
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), Default::default());
+        engine.resume_from_store(Box::new(MemoryShredStore::new())).unwrap();
+        let events = engine.subscribe_rotor_events();
+        engine.restore_state(engine.engine_state());
+
+        // Subscribers from before the reset still hear of new blocks
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for shred in Rotor::new(vset).encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+        assert!(events.try_iter().any(|event| matches!(
+            event,
+            RotorEvent::BlockReconstructed { block_id, .. } if block_id == block.id
+        )));
+        for i in [0, 2, 3, 4] {
+            let vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            engine.process_vote(vote).unwrap();
+        }
+        assert!(engine.is_finalized(&block.id));
+        let key = SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        engine.rotor.register_leader_key(ValidatorId::Index(9), key);
+
+        // The archive, the store and registered keys survive the reset too
+        engine.restore_state(engine.engine_state());
+        assert!(engine.rotor.archived_block(&block.id).is_some());
+        assert_eq!(engine.rotor.leader_key(&ValidatorId::Index(9)), Some(key));
+        assert_eq!(engine.rotor.prune_store_before(Slot(1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_validator_set_switches_at_epoch_boundary() {
+        let config = ConsensusConfig {
+            epoch_schedule: EpochSchedule::new(4),
+            ..ConsensusConfig::default()
+        };
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), create_test_validator_set(5), config);
+        let vote = |validator: u64, block: &Block| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: block.id,
+            slot: block.header.slot,
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+
+        // Slot 0 is finalized under the genesis set
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for i in 0..4 {
+            engine.process_vote(vote(i, &block)).unwrap();
+        }
+        assert!(engine.is_finalized(&block.id));
+
+        assert!(matches!(
+            engine.schedule_validator_set(Epoch(0), create_test_validator_set(2)),
+            Err(ConsensusError::Epoch(EpochError::NotFutureEpoch { .. }))
+        ));
+        engine.schedule_validator_set(Epoch(1), create_test_validator_set(2)).unwrap();
+        // The leader schedule already rotates through the next epoch's set
+        assert_eq!(engine.leader_of(Slot(2)), ValidatorId::Index(2));
+        assert_eq!(engine.leader_of(Slot(6)), ValidatorId::Index(0));
+
+        let late = create_test_block(3, ValidatorId::Index(3));
+        for _ in 0..4 {
+            engine.next_slot();
+        }
+        assert_eq!(engine.current_slot(), Slot(4));
+        assert_eq!(engine.epochs().for_slot(Slot(4)).len(), 2);
+
+        // Votes from the previous epoch no longer count, nor do its validators
+        assert!(matches!(
+            engine.process_vote(vote(0, &late)),
+            Err(ConsensusError::VotorError(VotorError::PreviousEpoch { .. }))
+        ));
+        let block = create_test_block(4, ValidatorId::Index(0));
+        assert!(matches!(
+            engine.process_vote(vote(4, &block)),
+            Err(ConsensusError::VotorError(VotorError::UnknownValidator(_)))
+        ));
+        engine.process_vote(vote(0, &block)).unwrap();
+        engine.process_vote(vote(1, &block)).unwrap();
+        assert!(engine.is_finalized(&block.id));
+
+        // Earlier certificates are still checked against their own epoch
+        assert!(engine.check_invariants().is_ok());
+    }
+
+    #[test]
+    fn test_vote_requires_scheduled_leader_and_certified_parent() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(2), vset, ConsensusConfig::default());
+        let genesis = create_test_block(0, ValidatorId::Index(0));
+        for i in [0, 1, 3, 4] {
+            engine
+                .process_vote(Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: genesis.id,
+                    slot: genesis.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .unwrap();
+        }
+        engine.next_slot();
+
+        let child = |leader: u64, parent: Option<BlockId>| {
+            let mut block = create_test_block(1, ValidatorId::Index(leader));
+            block.header.parent = parent;
+            block.id = block.compute_id();
+            block
+        };
+        assert!(matches!(
+            engine.vote_for_block(child(3, Some(genesis.id))),
+            Err(ConsensusError::WrongLeader { expected: ValidatorId::Index(1), .. })
+        ));
+        assert!(matches!(
+            engine.vote_for_block(child(1, None)),
+            Err(ConsensusError::InvalidParent { expected: Some(_), got: None, .. })
+        ));
+        assert!(engine.take_outgoing_votes().is_empty());
+
+        engine.vote_for_block(child(1, Some(genesis.id))).unwrap();
+        assert_eq!(engine.take_outgoing_votes().len(), 1);
+
+        // Slot 2 has no certified block and isn't skipped
+        let mut orphan = create_test_block(3, ValidatorId::Index(3));
+        orphan.header.parent = Some(genesis.id);
+        assert!(matches!(
+            engine.vote_for_block(orphan),
+            Err(ConsensusError::ParentNotReady(Slot(2)))
+        ));
+    }
+
+    #[test]
+    fn test_catch_up_sync_before_rejoining() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let mut ahead = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config.clone());
+        let mut behind = ConsensusEngine::new(ValidatorId::Index(2), vset, config);
+
+        // The network finalizes a chain of four blocks that `behind` misses
+        let mut parent = None;
+        for slot in 0..4 {
+            let mut block = create_test_block(slot, ValidatorId::Index(slot));
+            block.header.parent = parent;
+            block.id = block.compute_id();
+            let leader_key = &keys[slot as usize];
+            let shreds = ahead.rotor.encode_block_signed(&block, leader_key).unwrap();
+            ahead.rotor.receive_shreds(shreds);
+            for i in 0..4 {
+                let mut vote = Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                };
+                vote.sign(&keys[i as usize], &ahead.config.chain_id);
+                ahead.process_vote(vote).unwrap();
+            }
+            ahead.next_slot();
+            parent = Some(block.id);
+        }
+
+        assert!(behind.sync_request(Slot(1)).is_none());
+        let request = behind.sync_request(ahead.current_slot()).unwrap();
+        assert_eq!(request, SyncRequest { from: Slot(0), to: Slot(4) });
+        assert_eq!(behind.startup_status().phase, StartupPhase::Syncing);
+
+        // An incomplete response is rejected as a whole
+        let response = ahead.serve_sync(&request);
+        let mut partial = response.clone();
+        partial.blocks.remove(1);
+        assert!(matches!(
+            behind.apply_sync(&request, partial),
+            Err(ConsensusError::Sync(SyncError::MissingBlock(Slot(1))))
+        ));
+        assert_eq!(behind.current_slot(), Slot(0));
+
+        assert_eq!(behind.apply_sync(&request, response).unwrap(), 4);
+        assert_eq!(behind.current_slot(), Slot(4));
+        assert_eq!(behind.latest_finalized().unwrap().block_id, parent.unwrap());
+        assert_eq!(behind.block_tree().root(), parent);
+        assert!(behind.check_invariants().is_ok());
+
+        // Caught up, it rejoins voting through warm-up
+        assert!(behind.sync_request(Slot(5)).is_none());
+        assert_eq!(behind.observe_clock_drift(0), StartupPhase::WarmUp);
+    }
+
+    #[test]
+    fn test_silent_leader_slot_is_skipped() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig {
+            timing: TimingConfig::with_timeouts(RoundTimeouts {
+                round1: Duration::ZERO,
+                ..RoundTimeouts::default()
+            }),
+            ..ConsensusConfig::default()
+        };
         let mut engines: Vec<_> = (0..5)
-            .map(|i| ConsensusEngine::new(ValidatorId(i), vset.clone(), config.clone()))
+            .map(|i| ConsensusEngine::new(ValidatorId::Index(i), vset.clone(), config.clone()))
             .collect();
 
-        // Leader (validator 0) proposes a block
-        let block = create_test_block(0, ValidatorId(0));
-        let shreds = engines[0].propose_block(block.clone()).unwrap();
+        // The leader of slot 0 never sends its block
+        for engine in &mut engines {
+            engine.start_round1_timer();
+            assert!(engine.check_round1_timeout());
+        }
+        let votes: Vec<Vote> = engines.iter_mut().flat_map(|e| e.take_outgoing_votes()).collect();
+        assert_eq!(votes.len(), 5);
+        assert!(votes.iter().all(|vote| vote.kind == VoteKind::Skip && vote.slot == Slot(0)));
 
-        // Distribute shreds to all validators and collect votes
-        let mut votes = Vec::new();
+        // Three skip votes (60%) form the certificate and move every node on
         for (i, engine) in engines.iter_mut().enumerate() {
-            for shred in shreds.clone() {
-                engine.receive_shred(shred).ok();
+            let own = ValidatorId::Index(i as u64);
+            for vote in votes.iter().filter(|vote| vote.validator != own).take(2) {
+                engine.process_vote(vote.clone()).unwrap();
             }
-            // Create vote from this validator
-            votes.push(Vote {
-                validator: ValidatorId(i as u64),
+            assert!(engine.is_skipped(Slot(0)));
+            assert_eq!(engine.current_slot(), Slot(1));
+            assert_eq!(engine.parent_for_slot(Slot(1)).unwrap(), None);
+        }
+
+        // Having voted skip, we never vote for the slot's block
+        let late = create_test_block(0, ValidatorId::Index(0));
+        assert!(matches!(
+            engines[1].vote_for_block(late),
+            Err(ConsensusError::WouldDoubleSign(Slot(0)))
+        ));
+    }
+
+    #[test]
+    fn test_round2_timeout_casts_skip_fallback() {
+        let config = ConsensusConfig {
+            timing: TimingConfig::with_timeouts(RoundTimeouts::new(Duration::ZERO, Duration::ZERO)),
+            ..ConsensusConfig::default()
+        };
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), create_test_validator_set(5), config);
+        assert_eq!(engine.next_deadline(), None);
+
+        // We vote for the block, but it never gathers a quorum
+        let block = create_test_block(0, ValidatorId::Index(0));
+        engine.vote_for_block(block).unwrap();
+        engine.start_round1_timer();
+        assert!(engine.next_deadline().is_some());
+
+        assert_eq!(engine.fire_timers(), vec![Timeout::Round1(Slot(0)), Timeout::Round2(Slot(0))]);
+        assert_eq!(engine.current_round(), VoteRound::Round2);
+        let kinds: Vec<_> = engine.take_outgoing_votes().iter().map(|vote| vote.kind).collect();
+        assert_eq!(kinds, vec![VoteKind::Notar, VoteKind::SkipFallback]);
+        assert!(engine.fire_timers().is_empty());
+    }
+
+    #[test]
+    fn test_subscribers_receive_consensus_events() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let chain_id = config.chain_id;
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        let events = engine.subscribe();
+        let vote = |validator: u64, block: &Block, kind: VoteKind| {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(validator),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind,
+                signature: vec![],
+            };
+            vote.sign(&keys[validator as usize], &chain_id);
+            vote
+        };
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for i in 0..4 {
+            engine.process_vote(vote(i, &block, VoteKind::Notar)).unwrap();
+        }
+        let other = create_test_block(0, ValidatorId::Index(2));
+        assert!(engine.process_vote(vote(0, &other, VoteKind::Notar)).is_err());
+
+        // Slot 1's leader is silent and the slot gets skipped
+        engine.next_slot();
+        engine.advance_to_round2(Slot(1));
+        let silent = create_test_block(1, ValidatorId::Index(1));
+        for i in 0..3 {
+            engine.process_vote(vote(i, &silent, VoteKind::Skip)).unwrap();
+        }
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(matches!(
+            &events[0],
+            ConsensusEvent::FinalizedBlock(cert) if cert.block_id == block.id
+        ));
+        assert!(matches!(
+            &events[1],
+            ConsensusEvent::EvidenceDetected(evidence)
+                if evidence.first.validator == ValidatorId::Index(0)
+        ));
+        assert!(matches!(
+            events[2..],
+            [
+                ConsensusEvent::RoundAdvanced { slot: Slot(1), round: VoteRound::Round1 },
+                ConsensusEvent::LeaderChanged { slot: Slot(1), leader: ValidatorId::Index(1) },
+                ConsensusEvent::RoundAdvanced { slot: Slot(1), round: VoteRound::Round2 },
+                ConsensusEvent::SkippedSlot(Slot(1)),
+                ConsensusEvent::RoundAdvanced { slot: Slot(2), round: VoteRound::Round1 },
+                ConsensusEvent::LeaderChanged { slot: Slot(2), leader: ValidatorId::Index(2) },
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_unverified_evidence_not_announced() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+        let events = engine.subscribe();
+        for leader in [0, 2] {
+            let block = create_test_block(0, ValidatorId::Index(leader));
+            let _ = engine.process_vote(Vote {
+                validator: ValidatorId::Index(3),
                 block_id: block.id,
-                slot: block.slot,
-                round: VoteRound::Round1,
+                slot: Slot(0),
+                kind: VoteKind::Notar,
                 signature: vec![],
             });
         }
 
-        // Process all votes in all engines
-        for engine in &mut engines {
-            for vote in votes.clone() {
-                engine.process_vote(vote).ok();
-            }
+        // Votor keeps the pair, but without keys nobody is accused
+        assert_eq!(engine.votor.evidence(&ValidatorId::Index(3)).len(), 1);
+        assert!(!events
+            .try_iter()
+            .any(|event| matches!(event, ConsensusEvent::EvidenceDetected(_))));
+    }
+
+    #[test]
+    fn test_certificate_finalizes_without_votes() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let mut voter = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config.clone());
+        let mut partitioned = ConsensusEngine::new(ValidatorId::Index(2), vset, config.clone());
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        let mut cert = None;
+        for i in [0, 1, 3, 4] {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            vote.sign(&keys[i as usize], &config.chain_id);
+            cert = voter.process_vote(vote).unwrap();
         }
+        let cert = cert.unwrap();
 
-        // Check if block is finalized (should be with 5/5 = 100% > 80%)
-        for engine in &engines {
-            assert!(engine.is_finalized(&block.id));
+        let mut forged = cert.clone();
+        forged.votes.truncate(3);
+        assert!(matches!(
+            partitioned.process_certificate(forged),
+            Err(ConsensusError::InvalidCertificate(InvariantViolation::StakeMismatch(_)))
+        ));
+
+        // Quorum arithmetic alone isn't enough: every vote must be signed by
+        // its voter, on this chain, with a key on record
+        let mut unsigned = cert.clone();
+        unsigned.votes[2].signature.clear();
+        let unsigned_voter = unsigned.votes[2].validator;
+        assert!(matches!(
+            partitioned.process_certificate(unsigned),
+            Err(ConsensusError::InvalidCertificate(
+                InvariantViolation::InvalidVoteSignature(_, voter)
+            )) if voter == unsigned_voter
+        ));
+        let other_chain = ConsensusConfig { chain_id: [7u8; 32], ..config.clone() };
+        let vset = voter.validator_set.clone();
+        let mut elsewhere = ConsensusEngine::new(ValidatorId::Index(2), vset, other_chain);
+        assert!(elsewhere.process_certificate(cert.clone()).is_err());
+        let vset = create_test_validator_set(5);
+        let mut keyless = ConsensusEngine::new(ValidatorId::Index(2), vset, config);
+        assert!(matches!(
+            keyless.process_certificate(cert.clone()),
+            Err(ConsensusError::InvalidCertificate(InvariantViolation::UnknownVoterKey(..)))
+        ));
+        assert!(!keyless.is_finalized(&block.id));
+
+        assert!(partitioned.process_certificate(cert.clone()).unwrap());
+        assert!(partitioned.is_finalized(&block.id));
+        assert_eq!(partitioned.block_tree().root(), Some(block.id));
+        assert!(!partitioned.process_certificate(cert.clone()).unwrap());
+        for vote in cert.votes.clone() {
+            assert!(partitioned.process_vote(vote).unwrap().is_none());
+        }
+        assert_eq!(partitioned.finalized_blocks().len(), 1);
+
+        // A second certificate for the slot can't be accepted
+        let mut conflicting = cert;
+        conflicting.block_id = BlockId::new([9u8; 32]);
+        for vote in &mut conflicting.votes {
+            vote.block_id = conflicting.block_id;
+        }
+        assert!(matches!(
+            partitioned.process_certificate(conflicting),
+            Err(ConsensusError::InvalidCertificate(
+                InvariantViolation::ConflictingFinalization(Slot(0))
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_votes_rebroadcast_until_slot_finalizes() {
+        let config = ConsensusConfig {
+            vote_rebroadcast_interval: Duration::ZERO,
+            ..ConsensusConfig::default()
+        };
+        let vset = create_test_validator_set(5);
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config.clone());
+        let mut peer = ConsensusEngine::new(ValidatorId::Index(2), vset, config);
+        assert!(engine.rebroadcast_votes().is_empty());
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        engine.vote_for_block(block.clone()).unwrap();
+        let sent = engine.take_outgoing_votes();
+        assert_eq!(sent.len(), 1);
+
+        // The first copy was lost; the rebroadcast reaches the peer
+        let resent = engine.rebroadcast_votes();
+        assert_eq!(resent, sent);
+        assert!(!peer.has_vote(&resent[0]));
+        peer.process_vote(resent[0].clone()).unwrap();
+        assert!(peer.has_vote(&resent[0]));
+        assert_eq!(engine.rebroadcast_votes(), sent);
+
+        for i in [0, 3, 4] {
+            engine
+                .process_vote(Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .unwrap();
+        }
+        assert!(engine.is_finalized(&block.id));
+        assert!(engine.rebroadcast_votes().is_empty());
+    }
+
+    #[test]
+    fn test_pipeline_starts_next_slot_before_finalization() {
+        let config = ConsensusConfig {
+            pipeline_depth: 2,
+            ..ConsensusConfig::default()
+        };
+        let vset = create_test_validator_set(5);
+        let mut serial =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), Default::default());
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        let vote = |validator: u64, block: &Block| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: block.id,
+            slot: block.header.slot,
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+
+        // Slot 0 is notarized but its finalization votes are still coming in
+        let first = create_test_block(0, ValidatorId::Index(0));
+        for i in [0, 2, 3] {
+            engine.process_vote(vote(i, &first)).unwrap();
+            serial.process_vote(vote(i, &first)).unwrap();
+        }
+        assert!(!serial.advance_pipeline());
+        assert!(engine.advance_pipeline());
+        assert_eq!(engine.current_slot(), Slot(1));
+        assert_eq!(engine.parent_for_slot(Slot(1)).unwrap(), Some(first.id));
+
+        // Two slots in flight fill the pipeline
+        let mut second = create_test_block(1, ValidatorId::Index(1));
+        second.header.parent = Some(first.id);
+        second.id = second.compute_id();
+        for i in [0, 2, 3] {
+            engine.process_vote(vote(i, &second)).unwrap();
+        }
+        assert_eq!(engine.in_flight_slots(), vec![Slot(0), Slot(1)]);
+        assert!(!engine.advance_pipeline());
+
+        // Slot 0 finalizing frees a place
+        engine.process_vote(vote(4, &first)).unwrap();
+        assert!(engine.is_finalized(&first.id));
+        assert_eq!(engine.in_flight_slots(), vec![Slot(1)]);
+        assert!(engine.advance_pipeline());
+        assert_eq!(engine.current_slot(), Slot(2));
+    }
+
+    #[test]
+    fn test_confirmation_status_levels() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), Default::default());
+        let leader_rotor = Rotor::new(vset);
+        let vote = |validator: u64, block: &Block, kind| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: block.id,
+            slot: block.header.slot,
+            kind,
+            signature: vec![],
+        };
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        assert_eq!(engine.confirmation_status(&block.id), ConfirmationStatus::Unknown);
+
+        // Reconstructing the block casts our own notar vote
+        for shred in leader_rotor.encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+        assert_eq!(engine.confirmation_status(&block.id), ConfirmationStatus::Received);
+        for i in [0, 2] {
+            engine.process_vote(vote(i, &block, VoteKind::Notar)).unwrap();
+        }
+        assert_eq!(engine.confirmation_status(&block.id), ConfirmationStatus::Notarized(60));
+        engine.process_vote(vote(3, &block, VoteKind::Notar)).unwrap();
+        assert_eq!(engine.confirmation_status(&block.id), ConfirmationStatus::FastFinalized);
+
+        // Slot 1 only gathers a fallback quorum of final votes in round 2
+        engine.next_slot();
+        engine.advance_to_round2(Slot(1));
+        let mut next = create_test_block(1, ValidatorId::Index(1));
+        next.header.parent = Some(block.id);
+        next.id = next.compute_id();
+        for i in [0, 2, 3] {
+            engine.process_vote(vote(i, &next, VoteKind::Notar)).unwrap();
+            engine.process_vote(vote(i, &next, VoteKind::Final)).unwrap();
+        }
+        let status = engine.confirmation_status(&next.id);
+        assert_eq!(status, ConfirmationStatus::FallbackFinalized);
+        assert!(status > ConfirmationStatus::Notarized(100));
+    }
+
+    #[test]
+    fn test_leader_equivocation_recorded_and_not_voted() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), Default::default());
+        let events = engine.subscribe();
+        let leader_rotor = Rotor::new(vset);
+        let chain_id = engine.config.chain_id;
+
+        let mut first = create_test_block(0, ValidatorId::Index(0));
+        first.header.sign(&keys[0], &chain_id);
+        let variant = |timestamp: u64, key: &SigningKey| {
+            let mut block = first.clone();
+            block.header.timestamp = timestamp;
+            block.id = block.compute_id();
+            block.header.sign(key, &chain_id);
+            block
+        };
+        let deliver = |engine: &mut ConsensusEngine, block: &Block| {
+            let shreds = leader_rotor.encode_block_signed(block, &keys[0]).unwrap();
+            let results: Vec<_> = shreds.into_iter().map(|s| engine.receive_shred(s)).collect();
+            results.into_iter().last().unwrap()
+        };
+        deliver(&mut engine, &first).unwrap();
+
+        // A header the leader didn't sign fails verification and proves nothing
+        let forged = variant(first.header.timestamp + 2, &keys[1]);
+        assert!(matches!(
+            deliver(&mut engine, &forged),
+            Err(ConsensusError::InvalidBlock(VerifyError::InvalidHeaderSignature(
+                ValidatorId::Index(0)
+            )))
+        ));
+        assert!(engine.equivocations().is_empty());
+
+        let second = variant(first.header.timestamp + 1, &keys[0]);
+        assert!(matches!(
+            deliver(&mut engine, &second),
+            Err(ConsensusError::LeaderEquivocation { slot: Slot(0), leader: ValidatorId::Index(0) })
+        ));
+
+        let evidence = EquivocationEvidence {
+            leader: ValidatorId::Index(0),
+            slot: Slot(0),
+            first: first.header.clone(),
+            second: second.header.clone(),
+        };
+        assert_eq!(evidence.block_ids(), (first.id, second.id));
+        assert!(evidence.verify(&keys[0].verifying_key(), &chain_id));
+        assert_eq!(engine.equivocations(), std::slice::from_ref(&evidence));
+        assert!(engine.is_equivocated(Slot(0)));
+        assert!(events
+            .try_iter()
+            .any(|event| matches!(event, ConsensusEvent::LeaderEquivocated(e) if e == evidence)));
+
+        // Only the vote for the first block went out, and no final vote follows
+        let votes = engine.take_outgoing_votes();
+        assert_eq!(votes.len(), 1);
+        assert_eq!(votes[0].block_id, first.id);
+        engine.advance_to_round2(Slot(0));
+        engine.vote_for_block(first).unwrap();
+        assert!(engine.take_outgoing_votes().is_empty());
+    }
+
+    #[test]
+    fn test_observer_tracks_finality_without_voting() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig {
+            observer: true,
+            ..ConsensusConfig::default()
+        };
+        let mut observer =
+            ConsensusEngine::new(ValidatorId::Index(9), vset.clone(), config.clone());
+        assert!(observer.is_observer());
+
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for shred in Rotor::new(vset.clone()).encode_block(&block).unwrap() {
+            observer.receive_shred(shred).unwrap();
+        }
+        assert!(observer.take_outgoing_votes().is_empty());
+        for i in 0..4 {
+            observer
+                .process_vote(Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .unwrap();
+        }
+        assert!(observer.is_finalized(&block.id));
+        assert_eq!(observer.votor.voters(Slot(0)).len(), 4);
+
+        // Timeouts don't make it vote to skip either, and it never proposes
+        observer.next_slot();
+        observer.on_round1_timeout(Slot(1));
+        assert!(observer.take_outgoing_votes().is_empty());
+        let mut leader = ConsensusEngine::new(ValidatorId::Index(0), vset, config);
+        assert!(matches!(leader.propose_block(block), Err(ConsensusError::Observer)));
+    }
+
+    #[test]
+    fn test_pause_holds_votes_and_shutdown_persists_them() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig::default();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config.clone());
+        engine.start_round1_timer();
+        let block = create_test_block(0, ValidatorId::Index(0));
+        for shred in Rotor::new(vset.clone()).encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+
+        // Nothing leaves unsigned or gets signed, and no deadline fires
+        engine.pause().unwrap();
+        assert!(engine.take_outgoing_votes().is_empty());
+        assert!(engine.rebroadcast_votes().is_empty());
+        assert_eq!(engine.next_deadline(), None);
+        assert!(matches!(
+            engine.propose_block(block.clone()),
+            Err(ConsensusError::NotRunning(EngineStatus::Paused))
+        ));
+
+        engine.resume().unwrap();
+        assert_eq!(engine.take_outgoing_votes().len(), 1);
+        assert!(engine.next_deadline().is_some());
+
+        let storage = crate::storage::MemoryStorage::new();
+        engine.shutdown(&storage).unwrap();
+        assert_eq!(engine.status(), EngineStatus::ShutDown);
+        assert_eq!(engine.next_deadline(), None);
+        assert!(engine.resume().is_err());
+        let restarted =
+            ConsensusEngine::recover(ValidatorId::Index(1), vset, config, &storage).unwrap();
+        assert_eq!(restarted.engine_state().signed_votes.len(), 1);
+    }
+
+    #[test]
+    fn test_reconfigure_takes_effect_at_next_slot() {
+        let vset = create_test_validator_set(5);
+        let config = ConsensusConfig::default();
+        let clock = crate::clock::ManualClock::new();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config.clone());
+        engine.set_clock(Arc::new(clock.clone()));
+        engine.start_round1_timer();
+
+        let timeouts = config.timing.timeouts;
+        let slower = ConsensusConfig {
+            timing: TimingConfig::with_timeouts(RoundTimeouts::new(
+                timeouts.round1 * 2,
+                timeouts.round2 * 2,
+            )),
+            pipeline_depth: 2,
+            ..config.clone()
+        };
+        engine.reconfigure(slower.clone()).unwrap();
+        assert_eq!(engine.config().pipeline_depth, 1);
+        assert_eq!(engine.next_deadline(), Some(clock.now() + timeouts.round1));
+
+        engine.next_slot();
+        assert!(engine.pending_config().is_none());
+        assert_eq!(engine.config().pipeline_depth, 2);
+        engine.timers.cancel(Slot(0));
+        engine.start_round1_timer();
+        assert_eq!(engine.next_deadline(), Some(clock.now() + slower.timing.timeouts.round1));
+
+        let observer = ConsensusConfig { observer: true, ..slower.clone() };
+        assert_eq!(engine.reconfigure(observer), Err(ConfigError::Immutable("observer")));
+        let stalled = ConsensusConfig { pipeline_depth: 0, ..slower };
+        assert_eq!(engine.reconfigure(stalled), Err(ConfigError::ZeroPipelineDepth));
+    }
+
+    #[test]
+    fn test_leader_windows_rotate_at_boundaries_and_survive_skips() {
+        let config = ConsensusConfig {
+            leader_window: 2,
+            ..ConsensusConfig::default()
+        };
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), create_test_validator_set(3), config);
+        let leaders: Vec<_> =
+            (0..7).map(|slot| engine.leader_of(Slot(slot)).index().unwrap()).collect();
+        assert_eq!(leaders, [0, 0, 1, 1, 2, 2, 0]);
+        assert_eq!(engine.window_start(Slot(3)), Slot(2));
+        let events = engine.subscribe();
+        let vote = |slot: u64, validator: u64, kind: VoteKind, block_id: BlockId| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id,
+            slot: Slot(slot),
+            kind,
+            signature: vec![],
+        };
+        let zero = BlockId::new([0u8; 32]);
+
+        // A skip inside validator 0's window keeps it leader for the next slot
+        for validator in [0, 2] {
+            engine.process_vote(vote(0, validator, VoteKind::Skip, zero)).unwrap();
+        }
+        assert_eq!(
+            (engine.current_slot(), engine.current_leader()),
+            (Slot(1), ValidatorId::Index(0))
+        );
+        for validator in [0, 2] {
+            engine.process_vote(vote(1, validator, VoteKind::Skip, zero)).unwrap();
+        }
+        assert!(engine.is_leader());
+        assert_eq!(engine.parent_for_slot(Slot(2)).unwrap(), None);
+
+        // Our block in slot 2 is notarized, slot 3 is skipped: the window's
+        // second block would have built on it, and so does the next leader
+        let block = create_test_block(2, ValidatorId::Index(1));
+        for validator in [0, 2] {
+            engine.process_vote(vote(2, validator, VoteKind::Notar, block.id)).unwrap();
+        }
+        engine.next_slot();
+        assert_eq!(engine.parent_for_slot(Slot(3)).unwrap(), Some(block.id));
+        for validator in [0, 2] {
+            engine.process_vote(vote(3, validator, VoteKind::Skip, zero)).unwrap();
+        }
+        assert_eq!(
+            (engine.current_slot(), engine.current_leader()),
+            (Slot(4), ValidatorId::Index(2))
+        );
+        assert_eq!(engine.parent_for_slot(Slot(4)).unwrap(), Some(block.id));
+
+        let changes: Vec<_> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                ConsensusEvent::LeaderChanged { slot, leader } => Some((slot.0, leader.index()?)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changes, [(2, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn test_votes_before_block_held_back_until_block_or_slot() {
+        let vset = create_test_validator_set(5);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), ConsensusConfig::default());
+        let votes = |block_id: BlockId, slot: u64| -> Vec<Vote> {
+            [0, 2, 3, 4]
+                .into_iter()
+                .map(|i| Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id,
+                    slot: Slot(slot),
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .collect()
+        };
+
+        // Peers already in slot 1 vote for its block before we have it
+        let block = create_test_block(1, ValidatorId::Index(1));
+        for vote in votes(block.id, 1) {
+            assert!(engine.process_vote(vote.clone()).unwrap().is_none());
+            assert!(engine.has_vote(&vote));
+        }
+        assert_eq!(engine.early_votes().len(), 4);
+        assert!(!engine.is_finalized(&block.id));
+
+        // The block arrives and the held back votes finalize it
+        for shred in Rotor::new(vset).encode_block(&block).unwrap() {
+            let _ = engine.receive_shred(shred);
+        }
+        assert!(engine.early_votes().is_empty());
+        assert!(engine.is_finalized(&block.id));
+
+        // Without the block, the votes count once we reach their slot
+        let missing = BlockId::new([9u8; 32]);
+        for vote in votes(missing, 3) {
+            engine.process_vote(vote).unwrap();
+        }
+        engine.next_slot();
+        assert_eq!(engine.early_votes().len(), 4);
+        engine.next_slot();
+        engine.next_slot();
+        assert!(engine.early_votes().is_empty());
+        assert!(engine.is_finalized(&missing));
+    }
+
+    #[test]
+    fn test_received_shreds_queued_for_relay_children() {
+        let vset = create_test_validator_set(10);
+        let mut config = ConsensusConfig::default();
+        config.rotor.fanout = 2;
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(3), vset.clone(), config);
+        let block = create_test_block(0, ValidatorId::Index(0));
+        let shreds = Rotor::new(vset).encode_block(&block).unwrap();
+
+        let mut expected = Vec::new();
+        for shred in &shreds {
+            let targets = engine.relay_targets(shred);
+            expected.extend(targets.into_iter().map(|to| (to, shred.position())));
+            engine.receive_shred(shred.clone()).unwrap();
+        }
+        let sent: Vec<_> = engine
+            .take_outgoing_shreds()
+            .into_iter()
+            .map(|(to, shred)| (to, shred.position()))
+            .collect();
+        assert!(!sent.is_empty());
+        let skipped = [ValidatorId::Index(0), ValidatorId::Index(3)];
+        assert!(sent.iter().all(|(to, _)| !skipped.contains(to)));
+        assert_eq!(sent, expected);
+
+        // Copies we already stored aren't relayed again
+        engine.receive_shred(shreds[0].clone()).unwrap();
+        assert!(engine.take_outgoing_shreds().is_empty());
+    }
+
+    #[test]
+    fn test_validators_identified_by_public_key() {
+        let keys: Vec<_> = (0..5u8).map(|i| SigningKey::from_bytes(&[i + 1; 32])).collect();
+        let mut vset = ValidatorSet::new();
+        for key in &keys {
+            let pubkey = key.verifying_key();
+            vset.try_add_validator(ValidatorConfig {
+                id: ValidatorId::from_pubkey(&pubkey),
+                stake: StakeWeight(100),
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: Some(pubkey),
+                address: None,
+            })
+            .unwrap();
+        }
+        let ids: Vec<_> = vset.iter().map(|v| v.id).collect();
+        let key = keys[3].verifying_key();
+        assert_eq!(vset.find_by_pubkey(&key).unwrap().id, ValidatorId::from_pubkey(&key));
+
+        // Leaders rotate through the sparse IDs in ID order
+        let new_engine = |id| ConsensusEngine::new(id, vset.clone(), ConsensusConfig::default());
+        let mut follower = new_engine(ids[1]);
+        assert_eq!(follower.current_leader(), ids[0]);
+        for slot in 0..7 {
+            assert_eq!(follower.leader_of(Slot(slot)), ids[slot as usize % ids.len()]);
+        }
+
+        // The leader's block gets a vote from a validator known only by key
+        let leader_key = keys
+            .iter()
+            .find(|key| ValidatorId::from_pubkey(&key.verifying_key()) == ids[0])
+            .unwrap();
+        let mut block = Block::new(Slot(0), None, ids[0], vec![], 1000);
+        block.header.sign(leader_key, &[0u8; 32]);
+        let shreds = Rotor::new(vset.clone()).encode_block_signed(&block, leader_key).unwrap();
+        for shred in shreds {
+            follower.receive_shred(shred).unwrap();
         }
+        let votes = follower.take_outgoing_votes();
+        assert_eq!(votes.len(), 1);
+        assert_eq!(votes[0].validator, ids[1]);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,179 @@
+//! Epochs and per-epoch validator sets
+//!
+//! Slots are grouped into fixed-length epochs. The validator set may only
+//! change at an epoch boundary: a set scheduled for a future epoch takes
+//! effect at that epoch's first slot, and every slot is judged against the
+//! set of the epoch it belongs to.
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Default epoch length in slots
+pub const DEFAULT_SLOTS_PER_EPOCH: u64 = 1024;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EpochError {
+    #[error("{epoch} is not after the current epoch {current}")]
+    NotFutureEpoch { epoch: Epoch, current: Epoch },
+
+    #[error("Validator set for {0} is empty")]
+    EmptyValidatorSet(Epoch),
+}
+
+/// Mapping between slots and epochs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochSchedule {
+    pub slots_per_epoch: u64,
+}
+
+impl Default for EpochSchedule {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOTS_PER_EPOCH)
+    }
+}
+
+impl EpochSchedule {
+    pub fn new(slots_per_epoch: u64) -> Self {
+        assert!(slots_per_epoch > 0, "epochs must contain at least one slot");
+        Self { slots_per_epoch }
+    }
+
+    pub fn epoch_of(&self, slot: Slot) -> Epoch {
+        Epoch(slot.0 / self.slots_per_epoch)
+    }
+
+    pub fn first_slot(&self, epoch: Epoch) -> Slot {
+        Slot(epoch.0.saturating_mul(self.slots_per_epoch))
+    }
+
+    pub fn last_slot(&self, epoch: Epoch) -> Slot {
+        Slot(self.first_slot(epoch).0.saturating_add(self.slots_per_epoch - 1))
+    }
+
+    /// Position of `slot` within its epoch, from 0
+    pub fn slot_index(&self, slot: Slot) -> u64 {
+        slot.0 % self.slots_per_epoch
+    }
+
+    /// Whether `slot` is the first slot of its epoch
+    pub fn is_epoch_start(&self, slot: Slot) -> bool {
+        self.slot_index(slot) == 0
+    }
+}
+
+/// Validator sets of past, current and scheduled epochs
+///
+/// An epoch without its own set keeps the set of the latest earlier epoch.
+#[derive(Debug, Clone)]
+pub struct EpochValidatorSets {
+    schedule: EpochSchedule,
+    /// Sets by the epoch they take effect in; epoch 0 is always present
+    sets: BTreeMap<Epoch, ValidatorSet>,
+}
+
+impl EpochValidatorSets {
+    pub fn new(schedule: EpochSchedule, genesis: ValidatorSet) -> Self {
+        Self {
+            schedule,
+            sets: BTreeMap::from([(Epoch(0), genesis)]),
+        }
+    }
+
+    pub fn schedule(&self) -> &EpochSchedule {
+        &self.schedule
+    }
+
+    /// Schedule `validator_set` to take effect at the start of `epoch`
+    ///
+    /// Only future epochs can be changed, so slots already in progress keep
+    /// the set they started with. Scheduling an epoch again replaces the
+    /// earlier choice.
+    pub fn schedule_set(
+        &mut self,
+        epoch: Epoch,
+        validator_set: ValidatorSet,
+        current_slot: Slot,
+    ) -> Result<(), EpochError> {
+        let current = self.schedule.epoch_of(current_slot);
+        if epoch <= current {
+            return Err(EpochError::NotFutureEpoch { epoch, current });
+        }
+        if validator_set.is_empty() {
+            return Err(EpochError::EmptyValidatorSet(epoch));
+        }
+        self.sets.insert(epoch, validator_set);
+        Ok(())
+    }
+
+    /// Validator set that applies to `slot`, with the slot it took effect at
+    pub fn active(&self, slot: Slot) -> (Slot, &ValidatorSet) {
+        let (epoch, set) = self
+            .sets
+            .range(..=self.schedule.epoch_of(slot))
+            .next_back()
+            .expect("epoch 0 always has a set");
+        (self.schedule.first_slot(*epoch), set)
+    }
+
+    pub fn for_slot(&self, slot: Slot) -> &ValidatorSet {
+        self.active(slot).1
+    }
+
+    /// Scheduled leader of `slot`: the validators of its epoch take turns in
+    /// ID order, each leading for `leader_window` consecutive slots
+    pub fn leader_of(&self, slot: Slot, leader_window: u64) -> ValidatorId {
+        let window = slot.0 / leader_window.max(1);
+        let validators = self.for_slot(slot);
+        let turn = window % validators.len().max(1) as u64;
+        validators.iter().nth(turn as usize).map_or(ValidatorId::Index(turn), |v| v.id)
+    }
+
+    /// Every set, past and scheduled, in epoch order
+    pub fn sets(&self) -> impl Iterator<Item = &ValidatorSet> {
+        self.sets.values()
+    }
+
+    /// Validator set newly taking effect at `slot`, if any
+    ///
+    /// Used at slot boundaries to swap in the next epoch's set.
+    pub fn starting_at(&self, slot: Slot) -> Option<&ValidatorSet> {
+        if !self.schedule.is_epoch_start(slot) {
+            return None;
+        }
+        self.sets.get(&self.schedule.epoch_of(slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_sets_change_only_at_future_boundaries() {
+        let schedule = EpochSchedule::new(10);
+        assert_eq!(schedule.epoch_of(Slot(19)), Epoch(1));
+        assert_eq!(schedule.first_slot(Epoch(2)), Slot(20));
+        assert_eq!(schedule.last_slot(Epoch(2)), Slot(29));
+        assert_eq!(schedule.slot_index(Slot(27)), 7);
+        assert!(schedule.is_epoch_start(Slot(20)));
+        assert_eq!(schedule.last_slot(Epoch(u64::MAX)), Slot(u64::MAX));
+
+        let vset = StakeDistribution::Equal(100).validator_set(4, 0);
+        let mut sets = EpochValidatorSets::new(schedule, vset);
+        assert_eq!(
+            sets.schedule_set(Epoch(0), StakeDistribution::Equal(100).validator_set(5, 0), Slot(3)),
+            Err(EpochError::NotFutureEpoch { epoch: Epoch(0), current: Epoch(0) })
+        );
+        let vset = StakeDistribution::Equal(100).validator_set(6, 0);
+        sets.schedule_set(Epoch(2), vset, Slot(3)).unwrap();
+
+        assert_eq!(sets.for_slot(Slot(19)).len(), 4);
+        assert_eq!(sets.active(Slot(25)).0, Slot(20));
+        assert_eq!(sets.for_slot(Slot(95)).len(), 6);
+        assert!(sets.starting_at(Slot(10)).is_none());
+        assert_eq!(sets.starting_at(Slot(20)).unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,268 @@
+//! Erasure codes for FEC sets
+//!
+//! Rotor encodes each FEC set with an `ErasureCoder`, which turns the set's
+//! bytes into `data_shreds + coding_shreds` shards and rebuilds the bytes
+//! from whichever shards arrive. Two coders ship with the crate:
+//! - `RepetitionCoder`: splits the data, coding shards repeat the data shards
+//! - `ReedSolomonCoder`: any `data_shreds` shards recover the set
+
+use crate::rotor::RotorError;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+/// Shard counts of an erasure code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErasureParams {
+    pub data_shreds: usize,
+    pub coding_shreds: usize,
+}
+
+impl ErasureParams {
+    pub fn total_shreds(&self) -> usize {
+        self.data_shreds + self.coding_shreds
+    }
+}
+
+/// Erasure code applied to one FEC set at a time
+pub trait ErasureCoder: Send + Sync {
+    fn params(&self) -> ErasureParams;
+
+    /// Bytes of set data that fit when no shard may exceed `max_shard_size`
+    fn set_capacity(&self, max_shard_size: usize) -> usize {
+        self.params().data_shreds * max_shard_size
+    }
+
+    /// Encode set data into `total_shreds` shards, data shards first
+    fn encode(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, RotorError>;
+
+    /// Rebuild set data from the shards received so far
+    ///
+    /// Returns `Ok(None)` while too few shards are present.
+    fn reconstruct(&self, shards: &[Option<&[u8]>]) -> Result<Option<Vec<u8>>, RotorError>;
+}
+
+/// Which built-in coder Rotor uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErasureCode {
+    Repetition,
+    #[default]
+    ReedSolomon,
+}
+
+impl ErasureCode {
+    pub fn build(self, params: ErasureParams) -> Box<dyn ErasureCoder> {
+        match self {
+            ErasureCode::Repetition => Box::new(RepetitionCoder::new(params)),
+            ErasureCode::ReedSolomon => Box::new(ReedSolomonCoder::new(params)),
+        }
+    }
+}
+
+/// Naive splitter: coding shards repeat the data shards round-robin
+///
+/// A set decodes once every data piece is covered by its data shard or a
+/// coding shard repeating it.
+#[derive(Debug, Clone)]
+pub struct RepetitionCoder {
+    params: ErasureParams,
+}
+
+impl RepetitionCoder {
+    pub fn new(params: ErasureParams) -> Self {
+        Self { params }
+    }
+}
+
+impl ErasureCoder for RepetitionCoder {
+    fn params(&self) -> ErasureParams {
+        self.params
+    }
+
+    fn encode(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, RotorError> {
+        let data_shreds = self.params.data_shreds;
+        if data_shreds == 0 {
+            return Err(RotorError::ErasureCodingFailed);
+        }
+
+        let chunk_size = data.len().div_ceil(data_shreds).max(1);
+        let mut pieces: Vec<Vec<u8>> = data.chunks(chunk_size).map(<[u8]>::to_vec).collect();
+        pieces.resize(data_shreds, Vec::new());
+
+        Ok((0..self.params.total_shreds())
+            .map(|index| pieces[index % data_shreds].clone())
+            .collect())
+    }
+
+    fn reconstruct(&self, shards: &[Option<&[u8]>]) -> Result<Option<Vec<u8>>, RotorError> {
+        let data_shreds = self.params.data_shreds;
+        if data_shreds == 0 || shards.len() != self.params.total_shreds() {
+            return Err(RotorError::InvalidShred);
+        }
+
+        let mut pieces: Vec<Option<&[u8]>> = vec![None; data_shreds];
+        for (index, shard) in shards.iter().enumerate() {
+            if let Some(shard) = shard {
+                pieces[index % data_shreds].get_or_insert(shard);
+            }
+        }
+
+        if pieces.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+        Ok(Some(pieces.into_iter().flatten().flatten().copied().collect()))
+    }
+}
+
+/// Length prefix prepended to the set data before padding
+const LENGTH_PREFIX: usize = 4;
+
+/// Reed-Solomon over GF(2^8): any `data_shreds` shards recover the set
+///
+/// The set data is length-prefixed and zero-padded to equal shard sizes.
+/// At most 256 shards per set are supported.
+pub struct ReedSolomonCoder {
+    params: ErasureParams,
+    /// `None` without coding shards, or if the parameters are unsupported
+    codec: Option<ReedSolomon>,
+}
+
+impl ReedSolomonCoder {
+    pub fn new(params: ErasureParams) -> Self {
+        let codec = if params.coding_shreds > 0 {
+            ReedSolomon::new(params.data_shreds, params.coding_shreds).ok()
+        } else {
+            None
+        };
+        Self { params, codec }
+    }
+
+    fn check_params(&self) -> Result<(), RotorError> {
+        if self.params.data_shreds == 0 || (self.params.coding_shreds > 0 && self.codec.is_none()) {
+            return Err(RotorError::ErasureCodingFailed);
+        }
+        Ok(())
+    }
+}
+
+impl ErasureCoder for ReedSolomonCoder {
+    fn params(&self) -> ErasureParams {
+        self.params
+    }
+
+    fn set_capacity(&self, max_shard_size: usize) -> usize {
+        (self.params.data_shreds * max_shard_size).saturating_sub(LENGTH_PREFIX)
+    }
+
+    fn encode(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, RotorError> {
+        self.check_params()?;
+        let data_shreds = self.params.data_shreds;
+        let length = u32::try_from(data.len()).map_err(|_| RotorError::ErasureCodingFailed)?;
+
+        let shard_size = (data.len() + LENGTH_PREFIX).div_ceil(data_shreds);
+        let mut padded = Vec::with_capacity(shard_size * data_shreds);
+        padded.extend_from_slice(&length.to_le_bytes());
+        padded.extend_from_slice(data);
+        padded.resize(shard_size * data_shreds, 0);
+
+        let mut shards: Vec<Vec<u8>> = padded.chunks(shard_size).map(<[u8]>::to_vec).collect();
+        shards.resize(self.params.total_shreds(), vec![0; shard_size]);
+
+        if let Some(codec) = &self.codec {
+            codec
+                .encode(&mut shards)
+                .map_err(|_| RotorError::ErasureCodingFailed)?;
+        }
+        Ok(shards)
+    }
+
+    fn reconstruct(&self, shards: &[Option<&[u8]>]) -> Result<Option<Vec<u8>>, RotorError> {
+        self.check_params()?;
+        let data_shreds = self.params.data_shreds;
+        if shards.len() != self.params.total_shreds() {
+            return Err(RotorError::InvalidShred);
+        }
+        if shards.iter().flatten().count() < data_shreds {
+            return Ok(None);
+        }
+
+        let mut lengths = shards.iter().flatten().map(|shard| shard.len());
+        let shard_size = lengths.next().unwrap_or(0);
+        if shard_size == 0 || lengths.any(|len| len != shard_size) {
+            return Err(RotorError::InvalidShred);
+        }
+
+        let data: Vec<u8> = if shards[..data_shreds].iter().all(Option::is_some) {
+            shards[..data_shreds].iter().flatten().flat_map(|s| s.iter()).copied().collect()
+        } else {
+            let codec = self.codec.as_ref().ok_or(RotorError::InsufficientShreds)?;
+            let mut owned: Vec<Option<Vec<u8>>> =
+                shards.iter().map(|shard| shard.map(<[u8]>::to_vec)).collect();
+            codec
+                .reconstruct_data(&mut owned)
+                .map_err(|_| RotorError::ErasureCodingFailed)?;
+            owned[..data_shreds].iter().flatten().flatten().copied().collect()
+        };
+
+        let prefix: [u8; LENGTH_PREFIX] = data
+            .get(..LENGTH_PREFIX)
+            .and_then(|p| p.try_into().ok())
+            .ok_or(RotorError::ErasureCodingFailed)?;
+        let length = u32::from_le_bytes(prefix) as usize;
+        data.get(LENGTH_PREFIX..LENGTH_PREFIX + length)
+            .map(|body| Some(body.to_vec()))
+            .ok_or(RotorError::ErasureCodingFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(
+        coder: &dyn ErasureCoder,
+        data: &[u8],
+        keep: impl Fn(usize) -> bool,
+    ) -> Option<Vec<u8>> {
+        let shards = coder.encode(data).unwrap();
+        assert_eq!(shards.len(), coder.params().total_shreds());
+        let received: Vec<Option<&[u8]>> = shards
+            .iter()
+            .enumerate()
+            .map(|(i, shard)| keep(i).then_some(shard.as_slice()))
+            .collect();
+        coder.reconstruct(&received).unwrap()
+    }
+
+    #[test]
+    fn test_reed_solomon_recovers_from_any_data_count() {
+        let params = ErasureParams {
+            data_shreds: 4,
+            coding_shreds: 4,
+        };
+        let coder = ReedSolomonCoder::new(params);
+        let data: Vec<u8> = (0..=250).collect();
+
+        // Drop the first four shards, i.e. every data shard
+        assert_eq!(roundtrip(&coder, &data, |i| i >= 4), Some(data.clone()));
+        // Any four shards suffice; three don't
+        assert_eq!(roundtrip(&coder, &data, |i| i % 2 == 0), Some(data.clone()));
+        assert_eq!(roundtrip(&coder, &data, |i| i < 3), None);
+
+        // Shards stay within the capacity the coder advertises
+        let capacity = coder.set_capacity(64);
+        let shards = coder.encode(&vec![1u8; capacity]).unwrap();
+        assert!(shards.iter().all(|s| s.len() <= 64));
+    }
+
+    #[test]
+    fn test_repetition_needs_every_piece() {
+        let coder = RepetitionCoder::new(ErasureParams {
+            data_shreds: 4,
+            coding_shreds: 4,
+        });
+        let data: Vec<u8> = (0..100).collect();
+
+        assert_eq!(roundtrip(&coder, &data, |i| i >= 4), Some(data.clone()));
+        // Shards 0 and 4 carry the same piece
+        assert_eq!(roundtrip(&coder, &data, |i| i != 0 && i != 4), None);
+    }
+}
@@ -0,0 +1,274 @@
+//! Numeric error codes for protocol error responses
+//!
+//! Every `VotorError`, `RotorError` and `ConsensusError` variant maps to an
+//! `ErrorCode` (votor errors in the 100s, rotor errors in the 200s, engine
+//! errors in the 300s; an engine error wrapping a Votor or Rotor error keeps
+//! the inner code). A node that cannot serve a request answers with an
+//! `ErrorResponse`, so the peer can tell, say, a block that is gone for good
+//! from a request it should retry elsewhere without parsing messages.
+//!
+//! Responses carry the code as a plain `u16`: codes added later decode
+//! everywhere and read as `None` from `ErrorResponse::code` on older nodes.
+
+use crate::consensus::ConsensusError;
+use crate::rotor::RotorError;
+use crate::votor::VotorError;
+use serde::{Deserialize, Serialize};
+
+/// Stable number of a protocol error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ErrorCode {
+    DoubleVote = 100,
+    InvalidRound = 101,
+    UnknownValidator = 102,
+    BlockNotFound = 103,
+    PreviousEpoch = 104,
+
+    ErasureCodingFailed = 200,
+    InsufficientShreds = 201,
+    InvalidShred = 202,
+    InvalidShredSignature = 203,
+    ConflictingShred = 204,
+    BlockTooLarge = 205,
+    CompressionFailed = 206,
+    InconsistentShredHeader = 207,
+    NotArchived = 208,
+    InsufficientFanout = 209,
+    WrongShredLeader = 210,
+    UnknownShredLeader = 211,
+    ShredTooFarAhead = 212,
+
+    NotLeader = 300,
+    Observer = 301,
+    InvalidSlot = 302,
+    NotActive = 303,
+    WouldDoubleSign = 304,
+    LeaderEquivocation = 305,
+    CertificateTooOld = 306,
+    CertificateTooNew = 307,
+    NotFinalized = 308,
+    BlockBodyUnavailable = 309,
+    BlockBodyMismatch = 310,
+    ShredStore = 311,
+    WrongLeader = 312,
+    InvalidParent = 313,
+    ParentNotReady = 314,
+    SignerKeyMismatch = 315,
+    Epoch = 316,
+    Sync = 317,
+    Storage = 318,
+    Signing = 319,
+    InvalidCertificate = 320,
+    NotRunning = 321,
+    InvalidBlock = 322,
+    Snapshot = 323,
+    Banned = 324,
+    InvalidVoteSignature = 325,
+}
+
+impl ErrorCode {
+    /// Every code, in numeric order
+    pub const ALL: [ErrorCode; 44] = [
+        ErrorCode::DoubleVote,
+        ErrorCode::InvalidRound,
+        ErrorCode::UnknownValidator,
+        ErrorCode::BlockNotFound,
+        ErrorCode::PreviousEpoch,
+        ErrorCode::ErasureCodingFailed,
+        ErrorCode::InsufficientShreds,
+        ErrorCode::InvalidShred,
+        ErrorCode::InvalidShredSignature,
+        ErrorCode::ConflictingShred,
+        ErrorCode::BlockTooLarge,
+        ErrorCode::CompressionFailed,
+        ErrorCode::InconsistentShredHeader,
+        ErrorCode::NotArchived,
+        ErrorCode::InsufficientFanout,
+        ErrorCode::WrongShredLeader,
+        ErrorCode::UnknownShredLeader,
+        ErrorCode::ShredTooFarAhead,
+        ErrorCode::NotLeader,
+        ErrorCode::Observer,
+        ErrorCode::InvalidSlot,
+        ErrorCode::NotActive,
+        ErrorCode::WouldDoubleSign,
+        ErrorCode::LeaderEquivocation,
+        ErrorCode::CertificateTooOld,
+        ErrorCode::CertificateTooNew,
+        ErrorCode::NotFinalized,
+        ErrorCode::BlockBodyUnavailable,
+        ErrorCode::BlockBodyMismatch,
+        ErrorCode::ShredStore,
+        ErrorCode::WrongLeader,
+        ErrorCode::InvalidParent,
+        ErrorCode::ParentNotReady,
+        ErrorCode::SignerKeyMismatch,
+        ErrorCode::Epoch,
+        ErrorCode::Sync,
+        ErrorCode::Storage,
+        ErrorCode::Signing,
+        ErrorCode::InvalidCertificate,
+        ErrorCode::NotRunning,
+        ErrorCode::InvalidBlock,
+        ErrorCode::Snapshot,
+        ErrorCode::Banned,
+        ErrorCode::InvalidVoteSignature,
+    ];
+
+    pub fn as_u16(self) -> u16 {
+        self as u16
+    }
+
+    /// `None` for codes this node doesn't know
+    pub fn from_u16(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|known| known.as_u16() == code)
+    }
+}
+
+impl VotorError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            VotorError::DoubleVote(_) => ErrorCode::DoubleVote,
+            VotorError::InvalidRound => ErrorCode::InvalidRound,
+            VotorError::UnknownValidator(_) => ErrorCode::UnknownValidator,
+            VotorError::BlockNotFound(_) => ErrorCode::BlockNotFound,
+            VotorError::PreviousEpoch { .. } => ErrorCode::PreviousEpoch,
+        }
+    }
+}
+
+impl RotorError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RotorError::ErasureCodingFailed => ErrorCode::ErasureCodingFailed,
+            RotorError::InsufficientShreds => ErrorCode::InsufficientShreds,
+            RotorError::InvalidShred => ErrorCode::InvalidShred,
+            RotorError::InvalidSignature { .. } => ErrorCode::InvalidShredSignature,
+            RotorError::ConflictingShred { .. } => ErrorCode::ConflictingShred,
+            RotorError::BlockTooLarge { .. } => ErrorCode::BlockTooLarge,
+            RotorError::CompressionFailed => ErrorCode::CompressionFailed,
+            RotorError::InconsistentHeader { .. } => ErrorCode::InconsistentShredHeader,
+            RotorError::NotArchived(_) => ErrorCode::NotArchived,
+            RotorError::InsufficientFanout { .. } => ErrorCode::InsufficientFanout,
+            RotorError::WrongLeader { .. } => ErrorCode::WrongShredLeader,
+            RotorError::UnknownLeader(_) => ErrorCode::UnknownShredLeader,
+            RotorError::SlotTooFarAhead { .. } => ErrorCode::ShredTooFarAhead,
+        }
+    }
+}
+
+impl ConsensusError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ConsensusError::VotorError(e) => e.code(),
+            ConsensusError::RotorError(e) => e.code(),
+            ConsensusError::NotLeader(_) => ErrorCode::NotLeader,
+            ConsensusError::Observer => ErrorCode::Observer,
+            ConsensusError::InvalidSlot { .. } => ErrorCode::InvalidSlot,
+            ConsensusError::NotActive(_) => ErrorCode::NotActive,
+            ConsensusError::WouldDoubleSign(_) => ErrorCode::WouldDoubleSign,
+            ConsensusError::LeaderEquivocation { .. } => ErrorCode::LeaderEquivocation,
+            ConsensusError::CertificateTooOld { .. } => ErrorCode::CertificateTooOld,
+            ConsensusError::CertificateTooNew { .. } => ErrorCode::CertificateTooNew,
+            ConsensusError::NotFinalized(_) => ErrorCode::NotFinalized,
+            ConsensusError::BlockBodyUnavailable(_) => ErrorCode::BlockBodyUnavailable,
+            ConsensusError::BlockBodyMismatch(_) => ErrorCode::BlockBodyMismatch,
+            ConsensusError::Store(_) => ErrorCode::ShredStore,
+            ConsensusError::WrongLeader { .. } => ErrorCode::WrongLeader,
+            ConsensusError::InvalidParent { .. } => ErrorCode::InvalidParent,
+            ConsensusError::ParentNotReady(_) => ErrorCode::ParentNotReady,
+            ConsensusError::SignerKeyMismatch(_) => ErrorCode::SignerKeyMismatch,
+            ConsensusError::Epoch(_) => ErrorCode::Epoch,
+            ConsensusError::Sync(_) => ErrorCode::Sync,
+            ConsensusError::Storage(_) => ErrorCode::Storage,
+            ConsensusError::Signing(_) => ErrorCode::Signing,
+            ConsensusError::InvalidCertificate(_) => ErrorCode::InvalidCertificate,
+            ConsensusError::NotRunning(_) => ErrorCode::NotRunning,
+            ConsensusError::InvalidBlock(_) => ErrorCode::InvalidBlock,
+            ConsensusError::Snapshot(_) => ErrorCode::Snapshot,
+            ConsensusError::Banned(_) => ErrorCode::Banned,
+            ConsensusError::InvalidVoteSignature { .. } => ErrorCode::InvalidVoteSignature,
+        }
+    }
+}
+
+/// Error sent back in place of the reply a request asked for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct ErrorResponse {
+    /// `ErrorCode` number
+    pub code: u16,
+    /// Human-readable detail, for logs only
+    pub detail: String,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, detail: impl ToString) -> Self {
+        Self { code: code.as_u16(), detail: detail.to_string() }
+    }
+
+    /// The code, if this node knows it
+    pub fn code(&self) -> Option<ErrorCode> {
+        ErrorCode::from_u16(self.code)
+    }
+}
+
+impl From<&ConsensusError> for ErrorResponse {
+    fn from(error: &ConsensusError) -> Self {
+        Self::new(error.code(), error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::message::ConsensusMessage;
+    use crate::rotor::{RepairRequest, ShredIndex};
+    use crate::stake::StakeDistribution;
+    use crate::types::*;
+
+    #[test]
+    fn test_error_codes_stable_and_sent_for_failed_repairs() {
+        let codes: Vec<u16> = ErrorCode::ALL.iter().map(|code| code.as_u16()).collect();
+        assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_u16(code.as_u16()), Some(code));
+        }
+        assert_eq!(ErrorCode::from_u16(999), None);
+
+        // Wrapped Votor and Rotor errors keep their own code
+        let block_id = BlockId::new([4u8; 32]);
+        let error = ConsensusError::from(RotorError::NotArchived(block_id));
+        assert_eq!(error.code(), ErrorCode::NotArchived);
+        assert_eq!(ErrorResponse::from(&error).code, 208);
+        let error = ConsensusError::from(VotorError::UnknownValidator(ValidatorId::Index(9)));
+        assert_eq!(ErrorResponse::from(&error).code(), Some(ErrorCode::UnknownValidator));
+        let error = ConsensusError::NotLeader(Slot(3));
+        assert_eq!(error.code(), ErrorCode::NotLeader);
+
+        // A repair for a block we never saw is answered with its code
+        let vset = StakeDistribution::Equal(100).validator_set(4, 0);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), ConsensusConfig::default());
+        let missing_indices = vec![ShredIndex { fec_set: 0, index: 0 }];
+        let request = RepairRequest { block_id, missing_indices };
+        let replies = engine
+            .dispatch(ValidatorId::Index(2), ConsensusMessage::RepairRequest(request))
+            .unwrap()
+            .replies;
+        let [ConsensusMessage::Error(response)] = &replies[..] else {
+            panic!("expected one error response, got {replies:?}");
+        };
+        assert_eq!(response.code(), Some(ErrorCode::NotArchived));
+
+        // And the requester sees the code
+        let response = response.clone();
+        let mut requester =
+            ConsensusEngine::new(ValidatorId::Index(2), vset, ConsensusConfig::default());
+        let dispatched =
+            requester.dispatch(ValidatorId::Index(1), ConsensusMessage::Error(response)).unwrap();
+        assert_eq!(dispatched.error.and_then(|e| e.code()), Some(ErrorCode::NotArchived));
+    }
+}
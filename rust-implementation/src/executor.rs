@@ -0,0 +1,144 @@
+//! State-machine replication hook
+//!
+//! An `Executor` set with `ConsensusEngine::set_executor` receives every
+//! finalized block exactly once, in slot order, whether it was finalized by
+//! votes, by a gossiped certificate or during catch-up sync. Blocks finalized
+//! implicitly as ancestors of a finalized block are applied too. A block whose
+//! body hasn't arrived yet holds back its descendants until it does.
+
+use crate::types::*;
+
+/// Application replicated by consensus
+pub trait Executor: Send {
+    /// Apply a finalized block to the application state
+    fn apply(&mut self, block: &Block);
+
+    /// Hash of the application state, recorded in snapshots
+    fn state_hash(&self) -> Option<[u8; 32]> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<Slot>>>);
+
+    impl Executor for Recorder {
+        fn apply(&mut self, block: &Block) {
+            self.0.lock().unwrap().push(block.header.slot);
+        }
+    }
+
+    fn block(slot: u64, parent: Option<&Block>, keys: &[SigningKey]) -> Block {
+        let height = parent.map_or(0, |parent| parent.header.height + 1);
+        let parent = parent.map(|parent| parent.id);
+        let mut block =
+            Block::new(Slot(slot), parent, ValidatorId::Index(slot % 5), vec![], 1000 + slot)
+            .at_position(height, Epoch(0));
+        block.header.sign(&keys[slot as usize % 5], &[0u8; 32]);
+        block
+    }
+
+    fn notar_votes(block: &Block, validators: &[u64], keys: &[SigningKey]) -> Vec<Vote> {
+        validators
+            .iter()
+            .map(|i| {
+                let mut vote = Vote {
+                    validator: ValidatorId::Index(*i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                };
+                vote.sign(&keys[*i as usize], &[0u8; 32]);
+                vote
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_finalized_blocks_applied_once_in_slot_order() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let leader_rotor = Rotor::new(vset.clone());
+        let config = ConsensusConfig::default();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        engine.set_executor(Box::new(Recorder(applied.clone())));
+
+        let genesis = block(0, None, &keys);
+        let first = block(1, Some(&genesis), &keys);
+        let second = block(2, Some(&first), &keys);
+        for block in [&genesis, &first, &second] {
+            while engine.current_slot() < block.header.slot {
+                engine.next_slot();
+            }
+            let leader_key = &keys[block.header.leader.index().unwrap() as usize];
+            for shred in leader_rotor.encode_block_signed(block, leader_key).unwrap() {
+                engine.receive_shred(shred).unwrap();
+            }
+            // Notarize it so the next slot can build on it
+            for vote in notar_votes(block, &[0, 2], &keys) {
+                engine.process_vote(vote).unwrap();
+            }
+        }
+        assert!(applied.lock().unwrap().is_empty());
+
+        // Slot 2's certificate also settles its uncertified parent in slot 1
+        let votes = notar_votes(&second, &[0, 2, 3, 4], &keys);
+        let voters: HashSet<ValidatorId> = votes.iter().map(|vote| vote.validator).collect();
+        let cert = FinalizationCertificate {
+            block_id: second.id,
+            slot: second.header.slot,
+            round: VoteRound::Round1,
+            total_stake: vset.calculate_stake(&voters),
+            votes,
+        };
+        assert!(engine.process_certificate(cert.clone()).unwrap());
+        assert_eq!(*applied.lock().unwrap(), vec![Slot(0), Slot(1), Slot(2)]);
+
+        assert!(!engine.process_certificate(cert).unwrap());
+        assert_eq!(engine.last_executed(), Some(Slot(2)));
+        assert_eq!(applied.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_body_not_matching_certified_id_not_applied() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        engine.set_executor(Box::new(Recorder(applied.clone())));
+
+        // The leader swaps the body under the header it signed
+        let genesis = block(0, None, &keys);
+        let mut swapped = genesis.clone();
+        swapped.body.transactions.push(Transaction::new(&keys[0], 0, vec![1]));
+        let shreds = Rotor::new(vset.clone()).encode_block_signed(&swapped, &keys[0]).unwrap();
+        let mut result = Ok(());
+        for shred in shreds {
+            result = result.and(engine.receive_shred(shred));
+        }
+        assert!(result.is_err());
+
+        let votes = notar_votes(&genesis, &[0, 2, 3, 4], &keys);
+        let voters: HashSet<ValidatorId> = votes.iter().map(|vote| vote.validator).collect();
+        let cert = FinalizationCertificate {
+            block_id: genesis.id,
+            slot: genesis.header.slot,
+            round: VoteRound::Round1,
+            total_stake: vset.calculate_stake(&voters),
+            votes,
+        };
+        assert!(engine.process_certificate(cert).unwrap());
+        assert!(applied.lock().unwrap().is_empty());
+        assert_eq!(engine.last_executed(), None);
+    }
+}
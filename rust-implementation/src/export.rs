@@ -0,0 +1,241 @@
+//! Simulation metrics export
+//!
+//! Writes per-slot and per-validator simulation metrics as CSV with a stable,
+//! versioned schema, ready for pandas or DuckDB. Every row starts with the
+//! schema version so files from different releases can be told apart.
+//!
+//! Schema v1, slots table:
+//! `schema_version, slot, leader, path, latency_ms, participation_pct, vote_count, bytes_sent`
+//!
+//! Schema v1, validators table:
+//! `schema_version, slot, validator, stake, voted, vote_latency_ms, bytes_sent, bytes_received`
+//!
+//! `path` is one of `fast`, `fallback`, `skipped`, `none`. Optional values
+//! are written as empty fields.
+
+use crate::types::*;
+use std::io::{self, Write};
+
+/// Version of the CSV schema; bump on any column change
+pub const METRICS_SCHEMA_VERSION: u32 = 1;
+
+const SLOT_COLUMNS: &str =
+    "schema_version,slot,leader,path,latency_ms,participation_pct,vote_count,bytes_sent";
+const VALIDATOR_COLUMNS: &str =
+    "schema_version,slot,validator,stake,voted,vote_latency_ms,bytes_sent,bytes_received";
+
+/// How a slot ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalizationPath {
+    Fast,
+    Fallback,
+    Skipped,
+    None,
+}
+
+impl FinalizationPath {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FinalizationPath::Fast => "fast",
+            FinalizationPath::Fallback => "fallback",
+            FinalizationPath::Skipped => "skipped",
+            FinalizationPath::None => "none",
+        }
+    }
+}
+
+/// Metrics for one slot of a simulation run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotMetrics {
+    pub slot: Slot,
+    pub leader: ValidatorId,
+    pub path: FinalizationPath,
+    pub latency_ms: Option<u64>,
+    pub participation_pct: f64,
+    pub vote_count: usize,
+    pub bytes_sent: u64,
+}
+
+impl SlotMetrics {
+    /// Slot metrics for a finalized block
+    pub fn from_certificate(
+        cert: &FinalizationCertificate,
+        leader: ValidatorId,
+        total_stake: StakeWeight,
+        latency_ms: u64,
+    ) -> Self {
+        let path = match cert.round {
+            VoteRound::Round1 => FinalizationPath::Fast,
+            VoteRound::Round2 => FinalizationPath::Fallback,
+        };
+        let participation_pct = if total_stake.as_u64() == 0 {
+            0.0
+        } else {
+            cert.total_stake.as_u64() as f64 * 100.0 / total_stake.as_u64() as f64
+        };
+
+        Self {
+            slot: cert.slot,
+            leader,
+            path,
+            latency_ms: Some(latency_ms),
+            participation_pct,
+            vote_count: cert.votes.len(),
+            bytes_sent: 0,
+        }
+    }
+}
+
+/// Metrics for one validator in one slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorMetrics {
+    pub slot: Slot,
+    pub validator: ValidatorId,
+    pub stake: StakeWeight,
+    pub voted: bool,
+    pub vote_latency_ms: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Collects simulation metrics and writes them out as CSV
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+    slots: Vec<SlotMetrics>,
+    validators: Vec<ValidatorMetrics>,
+}
+
+impl MetricsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_slot(&mut self, metrics: SlotMetrics) {
+        self.slots.push(metrics);
+    }
+
+    pub fn record_validator(&mut self, metrics: ValidatorMetrics) {
+        self.validators.push(metrics);
+    }
+
+    pub fn slots(&self) -> &[SlotMetrics] {
+        &self.slots
+    }
+
+    pub fn validators(&self) -> &[ValidatorMetrics] {
+        &self.validators
+    }
+
+    /// Write the slots table
+    pub fn write_slots_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", SLOT_COLUMNS)?;
+        for m in &self.slots {
+            writeln!(
+                out,
+                "{},{},{},{},{},{:.2},{},{}",
+                METRICS_SCHEMA_VERSION,
+                m.slot.0,
+                csv_id(&m.leader),
+                m.path.as_str(),
+                optional(m.latency_ms),
+                m.participation_pct,
+                m.vote_count,
+                m.bytes_sent
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the validators table
+    pub fn write_validators_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", VALIDATOR_COLUMNS)?;
+        for m in &self.validators {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                METRICS_SCHEMA_VERSION,
+                m.slot.0,
+                csv_id(&m.validator),
+                m.stake.as_u64(),
+                m.voted,
+                optional(m.vote_latency_ms),
+                m.bytes_sent,
+                m.bytes_received
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn optional(value: Option<u64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// An index as a number, a key as hex
+fn csv_id(id: &ValidatorId) -> String {
+    match id {
+        ValidatorId::Index(index) => index.to_string(),
+        ValidatorId::Key(key) => hex::encode(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_csv() {
+        let cert = FinalizationCertificate {
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(3),
+            round: VoteRound::Round1,
+            votes: vec![],
+            total_stake: StakeWeight(400),
+        };
+
+        let mut recorder = MetricsRecorder::new();
+        recorder.record_slot(SlotMetrics::from_certificate(
+            &cert,
+            ValidatorId::Index(2),
+            StakeWeight(500),
+            95,
+        ));
+        recorder.record_slot(SlotMetrics {
+            slot: Slot(4),
+            leader: ValidatorId::Index(3),
+            path: FinalizationPath::Skipped,
+            latency_ms: None,
+            participation_pct: 60.0,
+            vote_count: 3,
+            bytes_sent: 10,
+        });
+
+        let mut out = Vec::new();
+        recorder.write_slots_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}\n1,3,2,fast,95,80.00,0,0\n1,4,3,skipped,,60.00,3,10\n", SLOT_COLUMNS)
+        );
+    }
+
+    #[test]
+    fn test_validators_csv() {
+        let mut recorder = MetricsRecorder::new();
+        recorder.record_validator(ValidatorMetrics {
+            slot: Slot(0),
+            validator: ValidatorId::Index(1),
+            stake: StakeWeight(100),
+            voted: true,
+            vote_latency_ms: Some(30),
+            bytes_sent: 1200,
+            bytes_received: 2400,
+        });
+
+        let mut out = Vec::new();
+        recorder.write_validators_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(VALIDATOR_COLUMNS));
+        assert_eq!(lines.next(), Some("1,0,1,100,true,30,1200,2400"));
+    }
+}
@@ -0,0 +1,218 @@
+//! Genesis configuration
+//!
+//! Describes the initial validator set and protocol parameters of a network,
+//! and checks them before launch: parameter safety, key validity, stake sums,
+//! duplicates, and a canonical genesis hash that peers can compare.
+
+use crate::consensus::ConsensusConfig;
+use crate::params::{ParamsError, ProtocolParams};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GenesisError {
+    #[error("Unsafe protocol parameters: {0}")]
+    UnsafeParams(#[from] ParamsError),
+
+    #[error("No validators")]
+    NoValidators,
+
+    #[error("Validator {0} has zero stake")]
+    ZeroStake(ValidatorId),
+
+    #[error("Total stake overflows")]
+    StakeOverflow,
+
+    #[error("Duplicate validator ID {0}")]
+    DuplicateValidator(ValidatorId),
+
+    #[error("Validator {0} has an invalid public key")]
+    InvalidPublicKey(ValidatorId),
+
+    #[error("Validator {0} reuses another validator's public key")]
+    DuplicatePublicKey(ValidatorId),
+}
+
+/// A validator entry in the genesis file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisValidator {
+    pub id: ValidatorId,
+    pub stake: StakeWeight,
+    /// Hex-encoded Ed25519 public key
+    pub pubkey: String,
+}
+
+/// Genesis configuration of a network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Genesis {
+    pub chain_id: String,
+    pub params: ProtocolParams,
+    pub validators: Vec<GenesisValidator>,
+}
+
+/// Result of verifying a genesis configuration
+#[derive(Debug, Clone)]
+pub struct GenesisReport {
+    pub hash: [u8; 32],
+    pub total_stake: Option<StakeWeight>,
+    pub problems: Vec<GenesisError>,
+}
+
+impl GenesisReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Genesis {
+    /// Load a genesis file (JSON)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&data).map_err(|e| e.to_string())
+    }
+
+    /// Canonical genesis hash (SHA-256 over the bincode encoding)
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(self).unwrap());
+        hasher.finalize().into()
+    }
+
+    /// Run every check and compute the genesis hash
+    pub fn verify(&self) -> GenesisReport {
+        let mut problems: Vec<GenesisError> =
+            self.params.check().into_iter().map(GenesisError::from).collect();
+
+        if self.validators.is_empty() {
+            problems.push(GenesisError::NoValidators);
+        }
+
+        let mut ids = HashSet::new();
+        let mut keys = HashSet::new();
+        let mut total_stake = Some(0u64);
+        for validator in &self.validators {
+            if !ids.insert(validator.id) {
+                problems.push(GenesisError::DuplicateValidator(validator.id));
+            }
+            if validator.stake.as_u64() == 0 {
+                problems.push(GenesisError::ZeroStake(validator.id));
+            }
+            total_stake = total_stake.and_then(|t| t.checked_add(validator.stake.as_u64()));
+
+            match parse_public_key(&validator.pubkey) {
+                Some(key) => {
+                    if !keys.insert(key) {
+                        problems.push(GenesisError::DuplicatePublicKey(validator.id));
+                    }
+                }
+                None => problems.push(GenesisError::InvalidPublicKey(validator.id)),
+            }
+        }
+
+        if total_stake.is_none() {
+            problems.push(GenesisError::StakeOverflow);
+        }
+
+        GenesisReport {
+            hash: self.hash(),
+            total_stake: total_stake.map(StakeWeight),
+            problems,
+        }
+    }
+
+    /// Build the initial validator set, with each validator's public key
+    pub fn validator_set(&self) -> Result<ValidatorSet, GenesisError> {
+        let mut vset = ValidatorSet::new();
+        for validator in &self.validators {
+            let pubkey = parse_public_key(&validator.pubkey)
+                .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok());
+            let config = ValidatorConfig {
+                id: validator.id,
+                stake: validator.stake,
+                is_byzantine: false,
+                is_offline: false,
+                pubkey,
+                address: None,
+            };
+            vset.try_add_validator(config).map_err(|e| match e {
+                ValidatorSetError::StakeOverflow(_) => GenesisError::StakeOverflow,
+                _ => GenesisError::DuplicateValidator(validator.id),
+            })?;
+        }
+        Ok(vset)
+    }
+
+    /// Engine configuration with the genesis parameters, bound to this chain
+    pub fn consensus_config(&self) -> ConsensusConfig {
+        ConsensusConfig {
+            chain_id: self.hash(),
+            ..ConsensusConfig::from_params(self.params)
+        }
+    }
+}
+
+/// Parse a hex-encoded Ed25519 public key
+fn parse_public_key(hex_key: &str) -> Option<[u8; 32]> {
+    let bytes: [u8; 32] = hex::decode(hex_key).ok()?.try_into().ok()?;
+    ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn test_genesis(count: u8) -> Genesis {
+        Genesis {
+            chain_id: "alpenglow-test".to_string(),
+            params: ProtocolParams::default(),
+            validators: (0..count)
+                .map(|i| GenesisValidator {
+                    id: ValidatorId::Index(i as u64),
+                    stake: StakeWeight(100),
+                    pubkey: hex::encode(SigningKey::from_bytes(&[i + 1; 32]).verifying_key()),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_genesis() {
+        let genesis = test_genesis(4);
+        let report = genesis.verify();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.total_stake, Some(StakeWeight(400)));
+        assert_eq!(report.hash, test_genesis(4).hash());
+        assert_ne!(report.hash, test_genesis(5).hash());
+
+        let key = SigningKey::from_bytes(&[3u8; 32]).verifying_key();
+        let vset = genesis.validator_set().unwrap();
+        assert_eq!(vset.get_validator(&ValidatorId::Index(2)).unwrap().pubkey, Some(key));
+
+        let config = genesis.consensus_config();
+        assert_eq!(config.chain_id, report.hash);
+        assert_eq!(config.params, genesis.params);
+    }
+
+    #[test]
+    fn test_genesis_problems_detected() {
+        let mut genesis = test_genesis(3);
+        genesis.params.fallback_quorum_pct = 55;
+        genesis.validators[1].pubkey = "zz".to_string();
+        genesis.validators[2].id = ValidatorId::Index(0);
+        genesis.validators[2].pubkey = genesis.validators[0].pubkey.clone();
+
+        let problems = genesis.verify().problems;
+        assert!(problems.contains(&GenesisError::UnsafeParams(
+            ParamsError::UnsafeQuorumIntersection { fallback: 55, byzantine: 20 }
+        )));
+        assert!(problems.contains(&GenesisError::InvalidPublicKey(ValidatorId::Index(1))));
+        assert!(problems.contains(&GenesisError::DuplicateValidator(ValidatorId::Index(0))));
+        assert!(problems.contains(&GenesisError::DuplicatePublicKey(ValidatorId::Index(0))));
+    }
+}
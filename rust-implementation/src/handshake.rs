@@ -0,0 +1,259 @@
+//! Peer capability handshake
+//!
+//! On connect, peers exchange a `Handshake` carrying their protocol version
+//! and a feature bitmap. Both sides then use the intersection of the two
+//! bitmaps, so nodes of different versions interoperate on the richest
+//! common feature set. Peers one protocol version behind are accepted
+//! during rolling upgrades. Negotiated capabilities are kept per peer.
+//! Transports run the exchange when a peer connects (see
+//! `LoopbackNetwork::join`).
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    #[error("Peer {peer} speaks protocol version {version}, we speak {ours}")]
+    IncompatibleVersion { peer: ValidatorId, version: u8, ours: u8 },
+}
+
+/// Optional protocol features a peer may support
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Answers `RepairRequest`s with the shreds asked for
+    RepairProtocol,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 1] = [Feature::RepairProtocol];
+
+    fn bit(self) -> u32 {
+        match self {
+            Feature::RepairProtocol => 1 << 0,
+        }
+    }
+}
+
+/// Feature bitmap; unknown bits from newer peers are preserved but unused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+
+    /// Every feature this build implements
+    pub const SUPPORTED: Capabilities = Capabilities(1 << 0);
+
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn with(self, feature: Feature) -> Self {
+        Self(self.0 | feature.bit())
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+
+    /// Features both sides support
+    pub fn intersect(&self, other: &Capabilities) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Known features in this bitmap
+    pub fn features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|f| self.supports(*f))
+            .collect()
+    }
+}
+
+impl FromIterator<Feature> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Feature>>(iter: I) -> Self {
+        iter.into_iter().fold(Capabilities::NONE, Capabilities::with)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+/// Message sent by each side when a connection opens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub validator: ValidatorId,
+    pub protocol_version: u8,
+    pub capabilities: Capabilities,
+}
+
+/// What we know about a connected peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCapabilities {
+    pub protocol_version: u8,
+    /// Bitmap the peer advertised
+    pub advertised: Capabilities,
+    /// Features both sides will use
+    pub negotiated: Capabilities,
+}
+
+/// Performs handshakes and keeps per-peer capability records
+#[derive(Debug, Clone)]
+pub struct CapabilityRegistry {
+    local: Handshake,
+    peers: HashMap<ValidatorId, PeerCapabilities>,
+}
+
+impl CapabilityRegistry {
+    pub fn new(validator: ValidatorId, capabilities: Capabilities) -> Self {
+        Self::with_handshake(Handshake {
+            validator,
+            protocol_version: crate::PROTOCOL_VERSION,
+            capabilities,
+        })
+    }
+
+    /// Registry sending `local` as our side, to stand in for a node of
+    /// another protocol version
+    pub fn with_handshake(local: Handshake) -> Self {
+        Self {
+            local,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Our side of the handshake
+    pub fn local_handshake(&self) -> &Handshake {
+        &self.local
+    }
+
+    /// Whether `accept` would take a peer's handshake, without recording it
+    pub fn check(&self, remote: &Handshake) -> Result<(), HandshakeError> {
+        let supported = crate::MIN_PROTOCOL_VERSION..=self.local.protocol_version;
+        if !supported.contains(&remote.protocol_version) {
+            return Err(HandshakeError::IncompatibleVersion {
+                peer: remote.validator,
+                version: remote.protocol_version,
+                ours: self.local.protocol_version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Process a peer's handshake and record the negotiated feature set
+    pub fn accept(&mut self, remote: &Handshake) -> Result<Capabilities, HandshakeError> {
+        self.check(remote)?;
+        let negotiated = self.local.capabilities.intersect(&remote.capabilities);
+        self.peers.insert(
+            remote.validator,
+            PeerCapabilities {
+                protocol_version: remote.protocol_version,
+                advertised: remote.capabilities,
+                negotiated,
+            },
+        );
+        Ok(negotiated)
+    }
+
+    /// Forget a disconnected peer
+    pub fn disconnect(&mut self, peer: &ValidatorId) {
+        self.peers.remove(peer);
+    }
+
+    pub fn peer(&self, peer: &ValidatorId) -> Option<&PeerCapabilities> {
+        self.peers.get(peer)
+    }
+
+    /// Whether a feature may be used with a peer (false if not connected)
+    pub fn supports(&self, peer: &ValidatorId, feature: Feature) -> bool {
+        self.peers
+            .get(peer)
+            .is_some_and(|p| p.negotiated.supports(feature))
+    }
+
+    /// Connected peers that can use a feature, ordered by ID
+    pub fn peers_supporting(&self, feature: Feature) -> Vec<ValidatorId> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|(_, p)| p.negotiated.supports(feature))
+            .map(|(id, _)| *id)
+            .collect();
+        peers.sort();
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiates_common_features() {
+        let ours: Capabilities = Feature::ALL.into_iter().collect();
+        assert_eq!(ours, Capabilities::SUPPORTED);
+        let mut registry = CapabilityRegistry::new(ValidatorId::Index(0), ours);
+
+        // A newer peer advertises a feature we don't know as an unknown bit
+        let remote = Handshake {
+            validator: ValidatorId::Index(1),
+            protocol_version: crate::PROTOCOL_VERSION,
+            capabilities: Capabilities::from_bits(1 << 31).with(Feature::RepairProtocol),
+        };
+        let negotiated = registry.accept(&remote).unwrap();
+
+        assert_eq!(negotiated, Capabilities::SUPPORTED);
+        assert_eq!(negotiated.features(), vec![Feature::RepairProtocol]);
+        assert!(registry.supports(&ValidatorId::Index(1), Feature::RepairProtocol));
+
+        // A peer advertising nothing negotiates nothing
+        let plain = Handshake {
+            validator: ValidatorId::Index(2),
+            protocol_version: crate::PROTOCOL_VERSION,
+            capabilities: Capabilities::NONE,
+        };
+        assert_eq!(registry.accept(&plain), Ok(Capabilities::NONE));
+        assert!(!registry.supports(&ValidatorId::Index(2), Feature::RepairProtocol));
+        assert_eq!(
+            registry.peer(&ValidatorId::Index(1)).unwrap().advertised,
+            remote.capabilities
+        );
+        assert_eq!(
+            registry.peers_supporting(Feature::RepairProtocol),
+            vec![ValidatorId::Index(1)]
+        );
+
+        registry.disconnect(&ValidatorId::Index(1));
+        assert!(!registry.supports(&ValidatorId::Index(1), Feature::RepairProtocol));
+    }
+
+    #[test]
+    fn test_rejects_incompatible_version() {
+        let mut registry = CapabilityRegistry::new(ValidatorId::Index(0), Capabilities::NONE);
+        let remote = Handshake {
+            validator: ValidatorId::Index(2),
+            protocol_version: crate::PROTOCOL_VERSION + 1,
+            capabilities: Capabilities::NONE,
+        };
+
+        assert!(matches!(
+            registry.accept(&remote),
+            Err(HandshakeError::IncompatibleVersion { .. })
+        ));
+        assert!(registry.peer(&ValidatorId::Index(2)).is_none());
+
+        // A peer one version behind is mid-upgrade and still welcome
+        let remote = Handshake { protocol_version: crate::MIN_PROTOCOL_VERSION, ..remote };
+        assert!(registry.accept(&remote).is_ok());
+    }
+}
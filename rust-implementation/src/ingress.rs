@@ -0,0 +1,235 @@
+//! Bounded ingress queue for peer messages
+//!
+//! The run loop doesn't handle messages the moment they arrive: it queues
+//! them and handles one per loop iteration, so round deadlines still get
+//! their turn during a flood. The queue holds at most `capacity` messages
+//! and at most `per_peer` from any one peer, so a single peer can't exhaust
+//! memory or crowd out the others. `OverflowPolicy` decides what happens to
+//! a message that doesn't fit; every drop is counted.
+//!
+//! Each message has a `Priority`. Higher classes are handled first and lower
+//! ones evicted first, so votes and certificates for undecided slots don't
+//! wait behind shreds, nor shreds behind repair traffic for older slots.
+//! Within a class the queue is FIFO.
+
+use crate::types::*;
+use std::collections::{HashMap, VecDeque};
+
+/// What to do with a message when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the arriving message
+    DropNewest,
+    /// Evict the oldest queued message to make room
+    DropOldest,
+    /// Stop reading from the transport until there is room again, leaving
+    /// flow control to the transport
+    Backpressure,
+}
+
+/// Handling class of a queued message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Repair traffic and messages about slots already decided
+    Low,
+    /// Shreds of current and upcoming slots
+    Normal,
+    /// Votes and certificates for undecided slots
+    High,
+}
+
+/// How the run loop assigns priorities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityPolicy {
+    /// Everything is `Normal`: handled in arrival order
+    Fifo,
+    /// Consensus traffic first, then new shreds, then repair traffic
+    ConsensusFirst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IngressConfig {
+    pub capacity: usize,
+    /// Most messages queued from one peer; further ones are dropped
+    pub per_peer: usize,
+    pub policy: OverflowPolicy,
+    pub priority: PriorityPolicy,
+}
+
+impl Default for IngressConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 4096,
+            per_peer: 1024,
+            policy: OverflowPolicy::DropOldest,
+            priority: PriorityPolicy::ConsensusFirst,
+        }
+    }
+}
+
+/// Outcomes of queued messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngressCounters {
+    pub accepted: u64,
+    /// Arriving messages dropped because the queue was full
+    pub dropped_full: u64,
+    /// Queued messages evicted for newer ones of the same or a higher
+    /// priority (`DropOldest`)
+    pub evicted: u64,
+    /// Messages dropped because their peer had `per_peer` queued
+    pub dropped_peer_limit: u64,
+    /// Messages the run loop's rate limiter dropped before queueing
+    pub rate_limited: u64,
+}
+
+/// Priority queue of messages from peers, bounded overall and per peer
+#[derive(Debug)]
+pub struct IngressQueue<M> {
+    config: IngressConfig,
+    /// One FIFO per priority, indexed by `Priority as usize`
+    queues: [VecDeque<(ValidatorId, M)>; 3],
+    queued_per_peer: HashMap<ValidatorId, usize>,
+    counters: IngressCounters,
+}
+
+impl<M> IngressQueue<M> {
+    pub fn new(config: IngressConfig) -> Self {
+        Self {
+            config,
+            queues: Default::default(),
+            queued_per_peer: HashMap::new(),
+            counters: IngressCounters::default(),
+        }
+    }
+
+    /// Queue a message from `from`; whether it was accepted
+    pub fn push(&mut self, from: ValidatorId, message: M, priority: Priority) -> bool {
+        if self.queued_per_peer.get(&from).copied().unwrap_or(0) >= self.config.per_peer {
+            self.counters.dropped_peer_limit += 1;
+            return false;
+        }
+        if self.len() >= self.config.capacity {
+            if self.config.policy != OverflowPolicy::DropOldest || !self.evict(priority) {
+                self.counters.dropped_full += 1;
+                return false;
+            }
+            self.counters.evicted += 1;
+        }
+        *self.queued_per_peer.entry(from).or_default() += 1;
+        self.queues[priority as usize].push_back((from, message));
+        self.counters.accepted += 1;
+        true
+    }
+
+    /// Oldest message of the highest non-empty priority
+    pub fn pop(&mut self) -> Option<(ValidatorId, M)> {
+        let index = (0..self.queues.len()).rev().find(|i| !self.queues[*i].is_empty())?;
+        self.take(index)
+    }
+
+    /// Drop the oldest message of the lowest priority, unless that is above
+    /// `for_priority`
+    fn evict(&mut self, for_priority: Priority) -> bool {
+        let lowest = (0..=for_priority as usize).find(|i| !self.queues[*i].is_empty());
+        lowest.and_then(|index| self.take(index)).is_some()
+    }
+
+    fn take(&mut self, index: usize) -> Option<(ValidatorId, M)> {
+        let (from, message) = self.queues[index].pop_front()?;
+        if let Some(queued) = self.queued_per_peer.get_mut(&from) {
+            *queued -= 1;
+            if *queued == 0 {
+                self.queued_per_peer.remove(&from);
+            }
+        }
+        Some((from, message))
+    }
+
+    /// Whether the run loop should read more from the transport
+    ///
+    /// Only `Backpressure` stops reading; the other policies read on and
+    /// drop what doesn't fit.
+    pub fn has_room(&self) -> bool {
+        self.config.policy != OverflowPolicy::Backpressure
+            || self.len() < self.config.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    pub fn counters(&self) -> IngressCounters {
+        self.counters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flooding_peer_is_bounded() {
+        let config = IngressConfig {
+            capacity: 4,
+            per_peer: 2,
+            policy: OverflowPolicy::DropOldest,
+            priority: PriorityPolicy::Fifo,
+        };
+        let mut queue = IngressQueue::new(config);
+
+        // One peer can't take more than its share
+        for i in 0..5 {
+            queue.push(ValidatorId::Index(0), i, Priority::Normal);
+        }
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.counters().dropped_peer_limit, 3);
+
+        // A full queue evicts its oldest message for a new one
+        queue.push(ValidatorId::Index(1), 10, Priority::Normal);
+        queue.push(ValidatorId::Index(2), 20, Priority::Normal);
+        assert!(queue.push(ValidatorId::Index(3), 30, Priority::Normal));
+        assert_eq!(queue.counters().evicted, 1);
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|(_, m)| m).collect();
+        assert_eq!(order, vec![1, 10, 20, 30]);
+
+        let mut queue = IngressQueue::new(IngressConfig {
+            policy: OverflowPolicy::Backpressure,
+            ..config
+        });
+        for i in 0..4 {
+            queue.push(ValidatorId::Index(i), i, Priority::Normal);
+        }
+        assert!(!queue.has_room());
+        assert!(!queue.push(ValidatorId::Index(9), 9, Priority::Normal));
+        assert_eq!(queue.counters().dropped_full, 1);
+        queue.pop();
+        assert!(queue.has_room());
+    }
+
+    #[test]
+    fn test_consensus_traffic_handled_and_kept_first() {
+        let mut queue = IngressQueue::new(IngressConfig {
+            capacity: 3,
+            per_peer: 3,
+            policy: OverflowPolicy::DropOldest,
+            priority: PriorityPolicy::ConsensusFirst,
+        });
+        queue.push(ValidatorId::Index(0), "repair", Priority::Low);
+        queue.push(ValidatorId::Index(0), "shred", Priority::Normal);
+        queue.push(ValidatorId::Index(1), "vote", Priority::High);
+
+        // A full queue gives up repair traffic for a certificate...
+        assert!(queue.push(ValidatorId::Index(2), "cert", Priority::High));
+        // ...but no consensus traffic for repair traffic
+        assert!(!queue.push(ValidatorId::Index(2), "repair", Priority::Low));
+        assert_eq!(queue.counters().evicted, 1);
+        assert_eq!(queue.counters().dropped_full, 1);
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|(_, m)| m).collect();
+        assert_eq!(order, vec!["vote", "cert", "shred"]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Local state integrity checks and checkpoints
+//!
+//! Detects local corruption (not protocol-level faults) in the engine's
+//! finalized state, and describes the last verified checkpoint the engine
+//! can roll back to. Certificates from peers must in addition carry a valid
+//! signature for every vote, which `verify_certificates` checks.
+
+use crate::params::ProtocolParams;
+use crate::types::*;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    #[error("Conflicting finalized blocks in slot {0}")]
+    ConflictingFinalization(Slot),
+
+    #[error("Certificate for {0} contains a vote for another block or slot")]
+    ForeignVote(BlockId),
+
+    #[error("Certificate for {0} records stake that its votes don't add up to")]
+    StakeMismatch(BlockId),
+
+    #[error("Certificate for {0} is below its quorum threshold")]
+    BelowQuorum(BlockId),
+
+    #[error("Certificate for {0} holds a vote by {1}, whose key is unknown")]
+    UnknownVoterKey(BlockId, ValidatorId),
+
+    #[error("Certificate for {0} holds a vote by {1} that is unsigned or badly signed")]
+    InvalidVoteSignature(BlockId, ValidatorId),
+}
+
+/// Last verified engine state that is safe to roll back to
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub slot: Slot,
+    pub leader: ValidatorId,
+    pub finalized: Vec<FinalizationCertificate>,
+}
+
+/// Check a list of finalized certificates for internal consistency, with
+/// quorums set by `params`
+pub fn check_certificates(
+    validator_set: &ValidatorSet,
+    params: &ProtocolParams,
+    certificates: &[FinalizationCertificate],
+) -> Result<(), InvariantViolation> {
+    check_certificates_with(|_| validator_set, params, certificates)
+}
+
+/// Like `check_certificates`, judging each certificate by the validator set
+/// of its slot
+pub fn check_certificates_with<'a>(
+    set_for_slot: impl Fn(Slot) -> &'a ValidatorSet,
+    params: &ProtocolParams,
+    certificates: &[FinalizationCertificate],
+) -> Result<(), InvariantViolation> {
+    let mut by_slot: HashMap<Slot, BlockId> = HashMap::new();
+
+    for cert in certificates {
+        if let Some(existing) = by_slot.insert(cert.slot, cert.block_id) {
+            if existing != cert.block_id {
+                return Err(InvariantViolation::ConflictingFinalization(cert.slot));
+            }
+        }
+
+        if cert
+            .votes
+            .iter()
+            .any(|vote| vote.block_id != cert.block_id || vote.slot != cert.slot)
+        {
+            return Err(InvariantViolation::ForeignVote(cert.block_id));
+        }
+
+        let validator_set = set_for_slot(cert.slot);
+        let voters: HashSet<ValidatorId> = cert.votes.iter().map(|vote| vote.validator).collect();
+        let stake = validator_set.calculate_stake(&voters);
+        if stake != cert.total_stake {
+            return Err(InvariantViolation::StakeMismatch(cert.block_id));
+        }
+
+        let quorum = match cert.round {
+            VoteRound::Round1 => params.is_fast_quorum(validator_set, stake),
+            VoteRound::Round2 => params.is_fallback_quorum(validator_set, stake),
+        };
+        if !quorum {
+            return Err(InvariantViolation::BelowQuorum(cert.block_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Like `check_certificates`, also checking every vote is signed on
+/// `chain_id` with its voter's key
+///
+/// Certificates from peers go through this; our own finalized state may
+/// hold our votes before they are signed, so the local checks above don't.
+pub fn verify_certificates(
+    validator_set: &ValidatorSet,
+    params: &ProtocolParams,
+    chain_id: &[u8; 32],
+    certificates: &[FinalizationCertificate],
+) -> Result<(), InvariantViolation> {
+    verify_certificates_with(|_| validator_set, params, chain_id, certificates)
+}
+
+/// Like `verify_certificates`, judging each certificate by the validator set
+/// of its slot
+pub fn verify_certificates_with<'a>(
+    set_for_slot: impl Fn(Slot) -> &'a ValidatorSet,
+    params: &ProtocolParams,
+    chain_id: &[u8; 32],
+    certificates: &[FinalizationCertificate],
+) -> Result<(), InvariantViolation> {
+    check_certificates_with(&set_for_slot, params, certificates)?;
+    for cert in certificates {
+        let validator_set = set_for_slot(cert.slot);
+        for vote in &cert.votes {
+            let key = validator_set
+                .get_validator(&vote.validator)
+                .and_then(|validator| validator.pubkey)
+                .ok_or(InvariantViolation::UnknownVoterKey(cert.block_id, vote.validator))?;
+            if !vote.verify(&key, chain_id) {
+                return Err(InvariantViolation::InvalidVoteSignature(cert.block_id, vote.validator));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stake::StakeDistribution;
+
+    fn test_certificate(block: u8, voters: u64) -> FinalizationCertificate {
+        let block_id = BlockId::new([block; 32]);
+        FinalizationCertificate {
+            block_id,
+            slot: Slot(0),
+            round: VoteRound::Round1,
+            votes: (0..voters)
+                .map(|i| Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id,
+                    slot: Slot(0),
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                })
+                .collect(),
+            total_stake: StakeWeight(100 * voters),
+        }
+    }
+
+    #[test]
+    fn test_check_certificates() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let params = ProtocolParams::default();
+        assert!(check_certificates(&vset, &params, &[test_certificate(1, 4)]).is_ok());
+
+        assert_eq!(
+            check_certificates(&vset, &params, &[test_certificate(1, 3)]),
+            Err(InvariantViolation::BelowQuorum(BlockId::new([1; 32])))
+        );
+
+        assert_eq!(
+            check_certificates(&vset, &params, &[test_certificate(1, 4), test_certificate(2, 4)]),
+            Err(InvariantViolation::ConflictingFinalization(Slot(0)))
+        );
+
+        let mut corrupt = test_certificate(1, 4);
+        corrupt.total_stake = StakeWeight(500);
+        assert_eq!(
+            check_certificates(&vset, &params, &[corrupt]),
+            Err(InvariantViolation::StakeMismatch(BlockId::new([1; 32])))
+        );
+    }
+}
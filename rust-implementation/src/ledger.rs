@@ -0,0 +1,313 @@
+//! Misbehavior ledger
+//!
+//! Records confirmed validator misbehavior (equivocation, invalid certificates,
+//! corrupt shreds) with per-validator history and a ban list. When opened with
+//! a path the ledger is persisted after every change, so bans survive restarts.
+//!
+//! An engine given a ledger with `set_misbehavior_ledger` records every double
+//! vote and leader equivocation whose signatures verify, before handing the
+//! evidence to its `SlashingManager`, and refuses messages from banned peers.
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LedgerError {
+    #[error("Ledger I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Ledger serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+/// Kind of confirmed misbehavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MisbehaviorKind {
+    Equivocation,
+    InvalidCertificate,
+    CorruptShred,
+    DoubleVote,
+}
+
+/// A single confirmed misbehavior entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MisbehaviorRecord {
+    pub validator: ValidatorId,
+    pub kind: MisbehaviorKind,
+    pub slot: Slot,
+    /// Validator whose signed attestation confirmed the misbehavior, if any
+    pub reported_by: Option<ValidatorId>,
+    /// Serialized evidence backing the record
+    pub evidence: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LedgerState {
+    history: BTreeMap<ValidatorId, Vec<MisbehaviorRecord>>,
+    banned: BTreeSet<ValidatorId>,
+}
+
+/// Per-validator misbehavior history and ban list
+pub struct MisbehaviorLedger {
+    /// Backing file, `None` for an in-memory ledger
+    path: Option<PathBuf>,
+
+    /// Number of records after which a validator is banned
+    ban_threshold: usize,
+
+    state: LedgerState,
+}
+
+impl MisbehaviorLedger {
+    /// Create an in-memory ledger that bans on the first offense
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            ban_threshold: 1,
+            state: LedgerState::default(),
+        }
+    }
+
+    /// Open a persistent ledger, loading existing history if the file exists
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LedgerError> {
+        let path = path.as_ref().to_path_buf();
+        let state = if path.exists() {
+            bincode::deserialize(&fs::read(&path)?)?
+        } else {
+            LedgerState::default()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            ban_threshold: 1,
+            state,
+        })
+    }
+
+    /// Set how many records it takes before a validator is banned
+    pub fn with_ban_threshold(mut self, threshold: usize) -> Self {
+        self.ban_threshold = threshold.max(1);
+        self
+    }
+
+    /// Record confirmed misbehavior, banning the validator once the threshold is hit
+    ///
+    /// Each offense, a validator and kind in a slot, is recorded once; returns
+    /// false without touching the file for one already on record.
+    pub fn record(&mut self, record: MisbehaviorRecord) -> Result<bool, LedgerError> {
+        let validator = record.validator;
+        let history = self.state.history.entry(validator).or_default();
+        if history.iter().any(|known| (known.kind, known.slot) == (record.kind, record.slot)) {
+            return Ok(false);
+        }
+        tracing::warn!("Recording {:?} by {} in slot {}", record.kind, validator, record.slot);
+
+        history.push(record);
+        if history.len() >= self.ban_threshold {
+            self.state.banned.insert(validator);
+        }
+
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Lift a ban (operator action); history is kept
+    pub fn unban(&mut self, validator: &ValidatorId) -> Result<bool, LedgerError> {
+        let removed = self.state.banned.remove(validator);
+        if removed {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Check if a validator is banned
+    pub fn is_banned(&self, validator: &ValidatorId) -> bool {
+        self.state.banned.contains(validator)
+    }
+
+    /// Banned validators, ordered by ID
+    pub fn banned(&self) -> impl Iterator<Item = &ValidatorId> {
+        self.state.banned.iter()
+    }
+
+    /// Full misbehavior history of a validator, oldest first
+    pub fn history(&self, validator: &ValidatorId) -> &[MisbehaviorRecord] {
+        self.state
+            .history
+            .get(validator)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Number of recorded offenses of the given kind for a validator
+    pub fn offense_count(&self, validator: &ValidatorId, kind: MisbehaviorKind) -> usize {
+        self.history(validator)
+            .iter()
+            .filter(|record| record.kind == kind)
+            .count()
+    }
+
+    /// Validators with at least one record, ordered by ID
+    pub fn offenders(&self) -> impl Iterator<Item = &ValidatorId> {
+        self.state.history.keys()
+    }
+
+    /// Write the ledger to its backing file (no-op for in-memory ledgers)
+    fn persist(&self) -> Result<(), LedgerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        // Write to a temporary file first so a crash never leaves a torn ledger
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bincode::serialize(&self.state)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl Default for MisbehaviorLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusError};
+    use crate::message::ConsensusMessage;
+    use crate::slashing::{Evidence, SlashingRules};
+    use crate::stake::StakeDistribution;
+
+    fn equivocation(validator: u64, slot: u64) -> MisbehaviorRecord {
+        MisbehaviorRecord {
+            validator: ValidatorId::Index(validator),
+            kind: MisbehaviorKind::Equivocation,
+            slot: Slot(slot),
+            reported_by: None,
+            evidence: vec![],
+        }
+    }
+
+    #[test]
+    fn test_ban_threshold() {
+        let mut ledger = MisbehaviorLedger::new().with_ban_threshold(2);
+
+        ledger.record(equivocation(1, 0)).unwrap();
+        assert!(!ledger.is_banned(&ValidatorId::Index(1)));
+
+        ledger.record(equivocation(1, 1)).unwrap();
+        assert!(ledger.is_banned(&ValidatorId::Index(1)));
+        assert_eq!(ledger.offense_count(&ValidatorId::Index(1), MisbehaviorKind::Equivocation), 2);
+
+        assert!(ledger.unban(&ValidatorId::Index(1)).unwrap());
+        assert!(!ledger.is_banned(&ValidatorId::Index(1)));
+        assert_eq!(ledger.history(&ValidatorId::Index(1)).len(), 2);
+    }
+
+    #[test]
+    fn test_record_once_per_offense() {
+        let path = std::env::temp_dir()
+            .join(format!("alpenglow-ledger-once-{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut ledger = MisbehaviorLedger::open(&path).unwrap();
+        assert!(ledger.record(equivocation(2, 5)).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        // Replays change nothing, so nothing is written either
+        for _ in 0..100 {
+            assert!(!ledger.record(equivocation(2, 5)).unwrap());
+        }
+        assert_eq!(ledger.history(&ValidatorId::Index(2)).len(), 1);
+        assert!(!path.exists());
+
+        // Another slot or kind is a new offense
+        assert!(ledger.record(equivocation(2, 6)).unwrap());
+        let double_vote =
+            MisbehaviorRecord { kind: MisbehaviorKind::DoubleVote, ..equivocation(2, 5) };
+        assert!(ledger.record(double_vote).unwrap());
+        assert_eq!(ledger.history(&ValidatorId::Index(2)).len(), 3);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ledger_survives_reopen() {
+        let path = std::env::temp_dir()
+            .join(format!("alpenglow-ledger-{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        {
+            let mut ledger = MisbehaviorLedger::open(&path).unwrap();
+            ledger.record(equivocation(3, 7)).unwrap();
+        }
+
+        let ledger = MisbehaviorLedger::open(&path).unwrap();
+        assert!(ledger.is_banned(&ValidatorId::Index(3)));
+        assert_eq!(ledger.history(&ValidatorId::Index(3)), &[equivocation(3, 7)]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_engine_bans_verified_double_voter() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let chain_id = config.chain_id;
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        engine.set_misbehavior_ledger(MisbehaviorLedger::new());
+        engine.enable_slashing(SlashingRules::default());
+        let vote = |validator: u64, block: u8, signed: bool| {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(validator),
+                block_id: BlockId::new([block; 32]),
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            if signed {
+                vote.sign(&keys[validator as usize], &chain_id);
+            }
+            vote
+        };
+        let send = |engine: &mut ConsensusEngine, from: u64, vote: Vote| {
+            engine.dispatch(ValidatorId::Index(from), ConsensusMessage::Vote(vote))
+        };
+
+        // Unsigned votes are dropped and prove nothing against their supposed author
+        for block in [1, 2] {
+            assert!(matches!(
+                send(&mut engine, 4, vote(4, block, false)),
+                Err(ConsensusError::InvalidVoteSignature { .. })
+            ));
+        }
+        assert!(!engine.is_banned(&ValidatorId::Index(4)));
+
+        send(&mut engine, 3, vote(3, 1, true)).unwrap();
+        assert!(send(&mut engine, 3, vote(3, 2, true)).is_err());
+        let ledger = engine.misbehavior_ledger().unwrap();
+        assert!(ledger.is_banned(&ValidatorId::Index(3)));
+        let record = &ledger.history(&ValidatorId::Index(3))[0];
+        assert_eq!((record.kind, record.slot), (MisbehaviorKind::DoubleVote, Slot(0)));
+        let evidence: Evidence = bincode::deserialize(&record.evidence).unwrap();
+        assert_eq!(engine.slashing().unwrap().slashed()[0].evidence, evidence);
+        assert!(ledger.offenders().eq([&ValidatorId::Index(3)]));
+
+        // From now on neither its messages nor its relayed votes are heard
+        assert!(matches!(
+            send(&mut engine, 3, vote(3, 1, true)),
+            Err(ConsensusError::Banned(ValidatorId::Index(3)))
+        ));
+        assert!(matches!(
+            send(&mut engine, 2, vote(3, 3, true)),
+            Err(ConsensusError::Banned(ValidatorId::Index(3)))
+        ));
+        send(&mut engine, 2, vote(2, 1, true)).unwrap();
+    }
+}
@@ -4,7 +4,7 @@
 //!
 //! ## Overview
 //!
-//! Alpenglow provides:
+//! Alpenglow provides, with the default `params::ProtocolParams`:
 //! - **Fast path**: 1-round finality with 80% stake
 //! - **Fallback path**: 2-round finality with 60% stake
 //! - **Fault tolerance**: 20% Byzantine + 20% offline (40% total)
@@ -13,37 +13,125 @@
 //!
 //! - `votor`: Voting mechanism with concurrent dual-path finalization
 //! - `rotor`: Data propagation with erasure coding
+//! - `runtime`: Async event loop driving the engine (feature `runtime`)
 //! - `types`: Core data structures and message formats
 //! - `consensus`: Main consensus engine
+//! - `audit`: Hash-chained certificate log catching safety violations
+//! - `bandwidth`: Per-validator egress accounting for relay strategies
+//! - `block_tree`: Fork-aware tree of pending blocks
+//! - `builder`: Engine builder validating its configuration
+//! - `clock`: Injectable time source (system and manual clocks)
+//! - `codec`: Pluggable wire serialization (bincode, optional borsh)
+//! - `compression`: Optional block payload compression (lz4, zstd)
+//! - `conformance`: Black-box protocol conformance suite
+//! - `epoch`: Epoch schedule and validator sets changing at epoch boundaries
+//! - `error_code`: Numeric error codes for protocol error responses
+//! - `erasure`: Pluggable erasure coders (repetition, Reed-Solomon)
+//! - `executor`: State-machine replication hook for finalized blocks
+//! - `export`: Simulation metrics export (CSV, versioned schema)
+//! - `genesis`: Genesis configuration and pre-launch verification
+//! - `handshake`: Peer capability handshake and per-peer feature records
+//! - `ingress`: Bounded per-peer ingress queue with overflow and priority policies
+//! - `integrity`: Local state integrity checks and checkpoints
+//! - `ledger`: Persistent misbehavior ledger and ban list
+//! - `mempool`: Pending transaction pool
+//! - `message`: Unified protocol message and versioned network envelope
+//! - `metrics`: Engine metrics: finalization latency and path utilization
+//! - `params`: Protocol parameters: quorums, fault tolerance, timeouts, retention
+//! - `producer`: Block production for slots this validator leads
+//! - `proof`: Finality proofs verifiable by stateless light clients
+//! - `rate_limit`: Per-peer token-bucket limits on incoming messages
+//! - `shred_store`: Shred storage (in-memory, on-disk) for restarts and repair
+//! - `signer`: Signing service trait with local and remote signers
+//! - `slashing`: Stake penalties for detected misbehavior
+//! - `snapshot`: Signed state snapshots for bootstrapping new validators
+//! - `stake`: Stake distributions (equal, uniform, Zipf, whales) for tests and simulations
+//! - `slot_clock`: Wall-clock slot timing driving slot and round transitions
+//! - `startup`: Startup state machine gating when a node may sign
+//! - `storage`: Durable engine state for crash recovery
+//! - `sync`: Catch-up sync from finalization certificates
+//! - `testing`: In-process multi-validator cluster harness
+//! - `timer`: Per-slot round deadlines
+//! - `timing`: Slot length and per-epoch round timeouts
+//! - `transport`: Network transport trait and in-process loopback (feature `runtime`)
+//! - `verifier`: Block verification gating which blocks get votes
+//! - `watchdog`: Standstill detection and recovery
+//! - `wire`: Compact wire formats for UDP-sized shreds and canonical certificates
 
+pub mod audit;
+pub mod bandwidth;
+pub mod block_tree;
+pub mod builder;
+pub mod clock;
+pub mod codec;
+pub mod compression;
 pub mod consensus;
+pub mod conformance;
+pub mod epoch;
+pub mod error_code;
+pub mod erasure;
+pub mod executor;
+pub mod export;
+pub mod genesis;
+pub mod handshake;
+pub mod ingress;
+pub mod integrity;
+pub mod ledger;
+pub mod mempool;
+pub mod message;
+pub mod metrics;
+pub mod params;
+pub mod producer;
+pub mod proof;
+pub mod rate_limit;
 pub mod rotor;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod shred_store;
+pub mod signer;
+pub mod slashing;
+pub mod snapshot;
+pub mod stake;
+pub mod slot_clock;
+pub mod startup;
+pub mod storage;
+pub mod sync;
+pub mod testing;
+pub mod timer;
+pub mod timing;
+#[cfg(feature = "runtime")]
+pub mod transport;
 pub mod types;
+pub mod verifier;
 pub mod votor;
+pub mod watchdog;
+pub mod wire;
 
 pub use consensus::ConsensusEngine;
-pub use types::{Block, BlockId, Slot, StakeWeight, ValidatorId, Vote};
+pub use types::{
+    Block, BlockBody, BlockHeader, BlockId, Slot, StakeWeight, Transaction, ValidatorId, Vote,
+};
 
 /// Protocol version
-pub const PROTOCOL_VERSION: u8 = 1;
+///
+/// Version 2 rejects envelopes for other chains; version 1 envelopes share
+/// its layout but went unchecked.
+pub const PROTOCOL_VERSION: u8 = 2;
 
-/// Default timeout for round 1 (milliseconds)
-pub const ROUND1_TIMEOUT_MS: u64 = 100;
-
-/// Default timeout for round 2 (milliseconds)
-pub const ROUND2_TIMEOUT_MS: u64 = 150;
+/// Oldest protocol version still accepted, so nodes can upgrade one at a time
+pub const MIN_PROTOCOL_VERSION: u8 = PROTOCOL_VERSION - 1;
 
 /// Fast path quorum threshold (80%)
-pub const FAST_QUORUM_PCT: u8 = 80;
+pub const FAST_QUORUM_PCT: u8 = params::ProtocolParams::DEFAULT.fast_quorum_pct;
 
 /// Fallback path quorum threshold (60%)
-pub const FALLBACK_QUORUM_PCT: u8 = 60;
+pub const FALLBACK_QUORUM_PCT: u8 = params::ProtocolParams::DEFAULT.fallback_quorum_pct;
 
 /// Maximum Byzantine fault tolerance (20%)
-pub const MAX_BYZANTINE_PCT: u8 = 20;
+pub const MAX_BYZANTINE_PCT: u8 = params::ProtocolParams::DEFAULT.max_byzantine_pct;
 
 /// Maximum offline tolerance (20%)
-pub const MAX_OFFLINE_PCT: u8 = 20;
+pub const MAX_OFFLINE_PCT: u8 = params::ProtocolParams::DEFAULT.max_offline_pct;
 
 #[cfg(test)]
 mod tests {
@@ -53,7 +141,7 @@ mod tests {
     fn test_constants() {
         assert_eq!(FAST_QUORUM_PCT, 80);
         assert_eq!(FALLBACK_QUORUM_PCT, 60);
-        assert!(FAST_QUORUM_PCT > FALLBACK_QUORUM_PCT);
+        const { assert!(FAST_QUORUM_PCT > FALLBACK_QUORUM_PCT) };
         assert_eq!(MAX_BYZANTINE_PCT + MAX_OFFLINE_PCT, 40);
     }
-}
\ No newline at end of file
+}
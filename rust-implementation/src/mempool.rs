@@ -0,0 +1,174 @@
+//! Pool of transactions waiting to be included in a block
+//!
+//! Only transactions whose payer signature verifies are admitted. The pool
+//! keeps them in arrival order, drops duplicates of pending ones (same payer,
+//! nonce and payload), and is bounded by count and total encoded size.
+
+use crate::types::{Transaction, TransactionError};
+use std::collections::{HashSet, VecDeque};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MempoolError {
+    #[error("Invalid transaction: {0}")]
+    Invalid(#[from] TransactionError),
+
+    #[error("Transaction is already pending")]
+    Duplicate,
+
+    #[error("Transaction of {size} bytes exceeds the {max} byte limit")]
+    TooLarge { size: usize, max: usize },
+
+    #[error("Mempool is full")]
+    Full,
+}
+
+/// Limits of a `Mempool`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MempoolConfig {
+    pub max_transactions: usize,
+    /// Total encoded bytes of pending transactions
+    pub max_bytes: usize,
+    /// Most encoded bytes of a single transaction
+    pub max_transaction_size: usize,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_transactions: 100_000,
+            max_bytes: 64 * 1024 * 1024,
+            max_transaction_size: 64 * 1024,
+        }
+    }
+}
+
+/// FIFO pool of pending transactions
+#[derive(Debug, Default)]
+pub struct Mempool {
+    config: MempoolConfig,
+    pending: VecDeque<Transaction>,
+    /// Hashes of pending transactions
+    hashes: HashSet<[u8; 32]>,
+    bytes: usize,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Queue a transaction behind the pending ones
+    pub fn insert(&mut self, transaction: Transaction) -> Result<(), MempoolError> {
+        let size = transaction.encoded_size();
+        if size > self.config.max_transaction_size {
+            return Err(MempoolError::TooLarge {
+                size,
+                max: self.config.max_transaction_size,
+            });
+        }
+        if self.pending.len() >= self.config.max_transactions
+            || self.bytes + size > self.config.max_bytes
+        {
+            return Err(MempoolError::Full);
+        }
+        if self.hashes.contains(&transaction.hash()) {
+            return Err(MempoolError::Duplicate);
+        }
+        transaction.verify()?;
+
+        self.hashes.insert(transaction.hash());
+        self.bytes += size;
+        self.pending.push_back(transaction);
+        Ok(())
+    }
+
+    /// Remove the oldest transactions whose encoded size fits `max_bytes`
+    ///
+    /// Stops at the first transaction that doesn't fit, so arrival order is
+    /// kept.
+    pub fn take(&mut self, max_bytes: usize) -> Vec<Transaction> {
+        let mut taken = Vec::new();
+        let mut used = 0;
+        while let Some(next) = self.pending.front() {
+            let size = next.encoded_size();
+            if used + size > max_bytes {
+                break;
+            }
+            used += size;
+            let transaction = self.pending.pop_front().expect("front exists");
+            self.bytes -= size;
+            self.hashes.remove(&transaction.hash());
+            taken.push(transaction);
+        }
+        taken
+    }
+
+    /// Put transactions back at the front, e.g. after a failed proposal
+    pub fn requeue(&mut self, transactions: Vec<Transaction>) {
+        for transaction in transactions.into_iter().rev() {
+            if self.hashes.insert(transaction.hash()) {
+                self.bytes += transaction.encoded_size();
+                self.pending.push_front(transaction);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Total encoded bytes of pending transactions
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn transaction(nonce: u64, len: usize) -> Transaction {
+        Transaction::new(&SigningKey::from_bytes(&[1u8; 32]), nonce, vec![nonce as u8; len])
+    }
+
+    #[test]
+    fn test_mempool_order_limits_and_requeue() {
+        let mut pool = Mempool::new(MempoolConfig {
+            max_transactions: 3,
+            max_bytes: 1000,
+            max_transaction_size: 200,
+        });
+        pool.insert(transaction(1, 10)).unwrap();
+        assert_eq!(pool.insert(transaction(1, 10)), Err(MempoolError::Duplicate));
+        assert!(matches!(pool.insert(transaction(0, 100)), Err(MempoolError::TooLarge { .. })));
+        let mut forged = transaction(2, 10);
+        forged.payload[0] ^= 1;
+        assert_eq!(
+            pool.insert(forged),
+            Err(MempoolError::Invalid(TransactionError::InvalidSignature))
+        );
+        pool.insert(transaction(2, 10)).unwrap();
+        pool.insert(transaction(3, 10)).unwrap();
+        assert_eq!(pool.insert(transaction(4, 10)), Err(MempoolError::Full));
+        assert_eq!(pool.bytes(), 3 * 130);
+
+        // Two 130-byte transactions fit 260 bytes; order is kept
+        let taken = pool.take(260);
+        assert_eq!(taken, vec![transaction(1, 10), transaction(2, 10)]);
+        assert_eq!(pool.len(), 1);
+
+        pool.requeue(taken);
+        assert_eq!(pool.take(usize::MAX)[0], transaction(1, 10));
+        assert!(pool.is_empty());
+        assert_eq!(pool.bytes(), 0);
+    }
+}
@@ -0,0 +1,248 @@
+//! Protocol messages on the wire
+//!
+//! Every message validators exchange is a `ConsensusMessage`, and every
+//! message sent over a network travels in an `Envelope` naming the protocol
+//! version, the chain and the sender. Transports move envelopes (or, in
+//! process, bare messages) without knowing what they carry and hand them to
+//! `ConsensusEngine::dispatch`, which routes each kind to Rotor or Votor and
+//! returns what has to go back out.
+//!
+//! Envelopes of the current protocol version and the one before it are
+//! accepted, so a network upgrades one node at a time. The version is
+//! checked from the first byte before the rest is decoded (every codec
+//! writes it as is), and `Envelope::open` rejects messages for another
+//! chain. Skip votes are `Vote`s of kind
+//! `VoteKind::Skip` and travel as `ConsensusMessage::Vote`. A repair request
+//! that can't be served is answered with `ConsensusMessage::Error`.
+
+use crate::codec::{BincodeCodec, Codec, CodecError};
+use crate::error_code::ErrorResponse;
+use crate::rate_limit::MessageKind;
+use crate::rotor::{RepairRequest, Shred};
+use crate::types::*;
+use crate::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MessageError {
+    #[error("Message serialization error: {0}")]
+    Serialization(#[from] CodecError),
+
+    #[error("Protocol version {version} is not supported (accepting {min} to {max})")]
+    UnsupportedVersion { version: u8, min: u8, max: u8 },
+
+    #[error("Message is for chain {}, we are on {}", hex::encode(.got), hex::encode(.expected))]
+    WrongChain { expected: [u8; 32], got: [u8; 32] },
+}
+
+/// Reject versions other than the current one and the one before it
+fn check_version(version: u8) -> Result<(), MessageError> {
+    if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(MessageError::UnsupportedVersion {
+            version,
+            min: MIN_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        })
+    }
+}
+
+/// Any message validators exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub enum ConsensusMessage {
+    Shred(Shred),
+    /// Notar, skip and finalization votes alike
+    Vote(Vote),
+    Certificate(FinalizationCertificate),
+    /// Shreds a peer is missing; answered with `Shred` messages
+    RepairRequest(RepairRequest),
+    /// A request of ours could not be served
+    Error(ErrorResponse),
+}
+
+impl ConsensusMessage {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            ConsensusMessage::Shred(_) => MessageKind::Shred,
+            ConsensusMessage::Vote(_) => MessageKind::Vote,
+            ConsensusMessage::Certificate(_) => MessageKind::Certificate,
+            // Errors answer repair requests and are limited along with them
+            ConsensusMessage::RepairRequest(_) | ConsensusMessage::Error(_) => MessageKind::Repair,
+        }
+    }
+}
+
+/// A message with the header every network message carries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct Envelope {
+    /// `PROTOCOL_VERSION` of the sender
+    pub version: u8,
+    /// Chain the message belongs to: the hash of its genesis
+    pub chain_id: [u8; 32],
+    pub sender: ValidatorId,
+    pub message: ConsensusMessage,
+}
+
+impl Envelope {
+    /// Wrap `message` at the current protocol version
+    pub fn new(chain_id: [u8; 32], sender: ValidatorId, message: ConsensusMessage) -> Self {
+        Self { version: PROTOCOL_VERSION, chain_id, sender, message }
+    }
+
+    /// Encode with bincode
+    pub fn encode(&self) -> Result<Vec<u8>, MessageError> {
+        self.encode_with(&BincodeCodec)
+    }
+
+    /// Decode with bincode
+    pub fn decode(bytes: &[u8]) -> Result<Self, MessageError> {
+        Self::decode_with(&BincodeCodec, bytes)
+    }
+
+    pub fn encode_with(&self, codec: &impl Codec<Self>) -> Result<Vec<u8>, MessageError> {
+        Ok(codec.encode(self)?)
+    }
+
+    /// Decode an envelope of a supported version
+    ///
+    /// Versions 1 and 2 share a layout; a future version changing it picks
+    /// its decoding here.
+    pub fn decode_with(codec: &impl Codec<Self>, bytes: &[u8]) -> Result<Self, MessageError> {
+        if let Some(&version) = bytes.first() {
+            check_version(version)?;
+        }
+        let envelope: Self = codec.decode(bytes)?;
+        check_version(envelope.version)?;
+        Ok(envelope)
+    }
+
+    /// Sender and message, if the envelope is for `chain_id` and of a
+    /// supported version
+    pub fn open(
+        self,
+        chain_id: &[u8; 32],
+    ) -> Result<(ValidatorId, ConsensusMessage), MessageError> {
+        check_version(self.version)?;
+        if self.chain_id != *chain_id {
+            return Err(MessageError::WrongChain { expected: *chain_id, got: self.chain_id });
+        }
+        Ok((self.sender, self.message))
+    }
+}
+
+/// What the engine made of one message
+#[derive(Debug, Default)]
+pub struct Dispatched {
+    /// Certificate of a block the message finalized
+    pub finalized: Option<FinalizationCertificate>,
+    /// Whether we assembled that certificate, so peers may not have it yet
+    pub assembled: bool,
+    /// Messages to send back to the sender
+    pub replies: Vec<ConsensusMessage>,
+    /// Error the sender answered one of our requests with
+    pub error: Option<ErrorResponse>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_every_message_kind_dispatched_through_one_envelope() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let chain_id = [7u8; 32];
+        let new_engine = |id| {
+            ConsensusEngine::new(ValidatorId::Index(id), vset.clone(), ConsensusConfig::default())
+        };
+        let mut leader = new_engine(0);
+        let mut peer = new_engine(1);
+        let mut late = new_engine(2);
+        let over_the_wire = |sender, message| {
+            let bytes =
+                Envelope::new(chain_id, ValidatorId::Index(sender), message).encode().unwrap();
+            let envelope = Envelope::decode(&bytes).unwrap();
+            assert_eq!((envelope.version, envelope.chain_id), (PROTOCOL_VERSION, chain_id));
+            (envelope.sender, envelope.message)
+        };
+
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        let shreds = leader.propose_block(block).unwrap();
+        for shred in &shreds {
+            let (from, message) = over_the_wire(0, ConsensusMessage::Shred(shred.clone()));
+            peer.dispatch(from, message).unwrap();
+        }
+        let vote = peer.take_outgoing_votes().remove(0);
+
+        // Votes are counted once; a rebroadcast is no error
+        let (from, message) = over_the_wire(1, ConsensusMessage::Vote(vote.clone()));
+        assert!(leader.dispatch(from, message.clone()).unwrap().finalized.is_none());
+        assert!(leader.dispatch(from, message).is_ok());
+        assert!(leader.has_vote(&vote));
+
+        // A repair request is answered with the shreds asked for
+        let (from, message) = over_the_wire(0, ConsensusMessage::Shred(shreds[0].clone()));
+        late.dispatch(from, message).unwrap();
+        let request = RepairRequest {
+            block_id: shreds[0].block_id,
+            missing_indices: shreds[1..].iter().map(Shred::position).collect(),
+        };
+        let (from, message) = over_the_wire(2, ConsensusMessage::RepairRequest(request));
+        assert_eq!(message.kind(), MessageKind::Repair);
+        let replies = peer.dispatch(from, message).unwrap().replies;
+        assert_eq!(replies.len(), shreds.len() - 1);
+        for reply in replies {
+            late.dispatch(ValidatorId::Index(1), reply).unwrap();
+        }
+        assert_eq!(late.take_outgoing_votes().len(), 1);
+    }
+
+    #[test]
+    fn test_envelopes_checked_for_version_and_chain() {
+        let chain_id = [7u8; 32];
+        let vote = Vote {
+            validator: ValidatorId::Index(1),
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(0),
+            kind: VoteKind::Skip,
+            signature: vec![],
+        };
+        let envelope = |version| Envelope {
+            version,
+            ..Envelope::new(chain_id, ValidatorId::Index(1), ConsensusMessage::Vote(vote.clone()))
+        };
+        let receive = |envelope: Envelope| {
+            Envelope::decode(&envelope.encode().unwrap())
+                .and_then(|envelope| envelope.open(&chain_id))
+        };
+
+        // Peers a version behind are understood during a rolling upgrade
+        for version in [PROTOCOL_VERSION, MIN_PROTOCOL_VERSION] {
+            let (sender, message) = receive(envelope(version)).unwrap();
+            assert_eq!(sender, ValidatorId::Index(1));
+            assert!(matches!(message, ConsensusMessage::Vote(v) if v.kind == VoteKind::Skip));
+        }
+        for version in [PROTOCOL_VERSION + 1, MIN_PROTOCOL_VERSION - 1] {
+            assert!(matches!(
+                receive(envelope(version)),
+                Err(MessageError::UnsupportedVersion { version: v, .. }) if v == version
+            ));
+        }
+
+        // A newer version is turned away before its body is decoded
+        let mut bytes = vec![PROTOCOL_VERSION + 1];
+        bytes.extend_from_slice(b"a layout we don't know");
+        assert!(matches!(Envelope::decode(&bytes), Err(MessageError::UnsupportedVersion { .. })));
+
+        let foreign = Envelope { chain_id: [8u8; 32], ..envelope(PROTOCOL_VERSION) };
+        assert!(matches!(
+            receive(foreign),
+            Err(MessageError::WrongChain { got, .. }) if got == [8u8; 32]
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! Engine-level metrics for operators
+//!
+//! Counts how slots end (fast or fallback finalization, skip), how often
+//! round deadlines expire, and how long blocks take from proposal to
+//! reconstruction and from reconstruction to finalization. Nodes that don't
+//! propose a block time it from its first shred instead.
+//! `ConsensusEngine::metrics` returns a snapshot.
+
+use crate::timer::Timeout;
+use crate::types::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Snapshot of the engine's metrics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineMetrics {
+    /// Blocks finalized by round 1 votes
+    pub fast_finalizations: u64,
+    /// Blocks finalized by round 2 votes
+    pub fallback_finalizations: u64,
+    pub skipped_slots: u64,
+    pub round1_timeouts: u64,
+    pub round2_timeouts: u64,
+    /// Mean time from proposal (or first shred) to reconstruction
+    pub mean_proposal_to_reconstruction: Option<Duration>,
+    /// Mean time from reconstruction to finalization
+    pub mean_reconstruction_to_finalization: Option<Duration>,
+}
+
+impl EngineMetrics {
+    /// Fraction of decided slots that were skipped
+    pub fn skip_rate(&self) -> f64 {
+        let decided = self.finalizations() + self.skipped_slots;
+        if decided == 0 {
+            0.0
+        } else {
+            self.skipped_slots as f64 / decided as f64
+        }
+    }
+
+    /// Fraction of finalizations that took the fast path
+    pub fn fast_path_rate(&self) -> f64 {
+        let finalizations = self.finalizations();
+        if finalizations == 0 {
+            0.0
+        } else {
+            self.fast_finalizations as f64 / finalizations as f64
+        }
+    }
+
+    fn finalizations(&self) -> u64 {
+        self.fast_finalizations + self.fallback_finalizations
+    }
+}
+
+/// When a pending block was first seen and reconstructed
+#[derive(Debug, Clone, Copy)]
+struct BlockTiming {
+    slot: Slot,
+    started: Instant,
+    reconstructed: Option<Instant>,
+}
+
+/// Running sum of latency samples
+#[derive(Debug, Clone, Copy, Default)]
+struct Latency {
+    total: Duration,
+    samples: u32,
+}
+
+impl Latency {
+    fn record(&mut self, sample: Duration) {
+        self.total += sample;
+        self.samples += 1;
+    }
+
+    fn mean(&self) -> Option<Duration> {
+        (self.samples > 0).then(|| self.total / self.samples)
+    }
+}
+
+/// Metrics as the engine collects them
+#[derive(Debug, Default)]
+pub(crate) struct MetricsRecorder {
+    counters: EngineMetrics,
+    pending: HashMap<BlockId, BlockTiming>,
+    proposal_to_reconstruction: Latency,
+    reconstruction_to_finalization: Latency,
+}
+
+impl MetricsRecorder {
+    /// A block was proposed or its first shred arrived
+    pub(crate) fn block_started(&mut self, block_id: BlockId, slot: Slot, now: Instant) {
+        self.pending.entry(block_id).or_insert(BlockTiming {
+            slot,
+            started: now,
+            reconstructed: None,
+        });
+    }
+
+    pub(crate) fn block_reconstructed(&mut self, block_id: &BlockId, now: Instant) {
+        if let Some(timing) = self.pending.get_mut(block_id) {
+            if timing.reconstructed.is_none() {
+                timing.reconstructed = Some(now);
+                self.proposal_to_reconstruction.record(now - timing.started);
+            }
+        }
+    }
+
+    /// A block was finalized; blocks of earlier slots stop being tracked
+    pub(crate) fn finalized(&mut self, cert: &FinalizationCertificate, now: Instant) {
+        match cert.round {
+            VoteRound::Round1 => self.counters.fast_finalizations += 1,
+            VoteRound::Round2 => self.counters.fallback_finalizations += 1,
+        }
+        let reconstructed = self
+            .pending
+            .get(&cert.block_id)
+            .and_then(|timing| timing.reconstructed);
+        if let Some(reconstructed) = reconstructed {
+            self.reconstruction_to_finalization.record(now - reconstructed);
+        }
+        self.pending.retain(|_, timing| timing.slot > cert.slot);
+    }
+
+    pub(crate) fn skipped(&mut self, slot: Slot) {
+        self.counters.skipped_slots += 1;
+        self.pending.retain(|_, timing| timing.slot != slot);
+    }
+
+    pub(crate) fn timed_out(&mut self, timeout: Timeout) {
+        match timeout {
+            Timeout::Round1(_) => self.counters.round1_timeouts += 1,
+            Timeout::Round2(_) => self.counters.round2_timeouts += 1,
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> EngineMetrics {
+        EngineMetrics {
+            mean_proposal_to_reconstruction: self.proposal_to_reconstruction.mean(),
+            mean_reconstruction_to_finalization: self.reconstruction_to_finalization.mean(),
+            ..self.counters.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_metrics_track_finalization_paths_and_skips() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        let vote = |validator: u64, block: &Block, kind| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: block.id,
+            slot: block.header.slot,
+            kind,
+            signature: vec![],
+        };
+
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        for shred in Rotor::new(vset).encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+        for i in [0, 2, 3] {
+            engine.process_vote(vote(i, &block, VoteKind::Notar)).unwrap();
+        }
+        let metrics = engine.metrics();
+        assert_eq!(metrics.fast_finalizations, 1);
+        assert!(metrics.mean_proposal_to_reconstruction.is_some());
+        assert!(metrics.mean_reconstruction_to_finalization.is_some());
+
+        // Slot 1 times out and is skipped
+        engine.next_slot();
+        engine.on_round1_timeout(Slot(1));
+        let mut silent = block.clone();
+        silent.header.slot = Slot(1);
+        silent.id = BlockId::new([0u8; 32]);
+        for i in [0, 2] {
+            engine.process_vote(vote(i, &silent, VoteKind::Skip)).unwrap();
+        }
+        let metrics = engine.metrics();
+        assert_eq!(metrics.round1_timeouts, 1);
+        assert_eq!(metrics.skipped_slots, 1);
+        assert_eq!(metrics.skip_rate(), 0.5);
+        assert_eq!(metrics.fast_path_rate(), 1.0);
+    }
+}
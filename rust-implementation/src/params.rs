@@ -0,0 +1,214 @@
+//! Protocol parameters
+//!
+//! The quorum thresholds, the fault tolerance they are sized for, the round
+//! timeouts, the shred retention window and whether blocks carry a time are
+//! fixed per network in its genesis. `ProtocolParams::DEFAULT` holds the
+//! values from the Alpenglow paper, also exported as `FAST_QUORUM_PCT` and
+//! friends: 80% and 60% quorums tolerating 20% Byzantine and 20% offline
+//! stake. The engine hands its parameters to Votor for its quorums, to
+//! Rotor for the stake its relay tree must reach, and to every certificate
+//! check, so a network with other thresholds judges certificates alike.
+
+use crate::timing::RoundTimeouts;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParamsError {
+    #[error("Fast quorum {fast}% must be above fallback quorum {fallback}% and at most 100%")]
+    QuorumOrder { fast: u8, fallback: u8 },
+
+    #[error("Two {fallback}% quorums overlap in less than the {byzantine}% Byzantine bound")]
+    UnsafeQuorumIntersection { fallback: u8, byzantine: u8 },
+
+    #[error("Honest online stake {honest}% cannot reach the {fallback}% fallback quorum")]
+    NoLiveness { honest: u8, fallback: u8 },
+
+    #[error("Round 1 timeout must be nonzero and shorter than round 2 timeout")]
+    TimeoutOrder,
+
+    #[error("Shreds must be retained for at least one slot")]
+    ZeroRetention,
+}
+
+/// Whether blocks carry a wall-clock time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockTimePolicy {
+    /// Leaders stamp blocks with their clock in milliseconds; a stamp must
+    /// be after the parent's and not run ahead of the verifier's clock
+    #[default]
+    WallClock,
+    /// Blocks carry no time and their timestamp is zero, so replaying the
+    /// chain never depends on a clock
+    Omitted,
+}
+
+/// Protocol parameters fixed at genesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolParams {
+    /// Stake voting in round 1 that finalizes a block on the fast path, in percent
+    pub fast_quorum_pct: u8,
+    /// Stake that notarizes, skips or finalizes on the fallback path, in percent
+    pub fallback_quorum_pct: u8,
+    pub max_byzantine_pct: u8,
+    pub max_offline_pct: u8,
+    pub round1_timeout_ms: u64,
+    pub round2_timeout_ms: u64,
+    /// Slots of shreds and blocks kept behind the current slot
+    pub retention_slots: u64,
+    pub block_time: BlockTimePolicy,
+}
+
+impl Default for ProtocolParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl ProtocolParams {
+    pub const DEFAULT: Self = Self {
+        fast_quorum_pct: 80,
+        fallback_quorum_pct: 60,
+        max_byzantine_pct: 20,
+        max_offline_pct: 20,
+        round1_timeout_ms: 100,
+        round2_timeout_ms: 150,
+        retention_slots: 64,
+        block_time: BlockTimePolicy::WallClock,
+    };
+
+    pub fn round_timeouts(&self) -> RoundTimeouts {
+        RoundTimeouts::new(
+            Duration::from_millis(self.round1_timeout_ms),
+            Duration::from_millis(self.round2_timeout_ms),
+        )
+    }
+
+    /// Least stake making up a fast path quorum of `validator_set`
+    pub fn fast_quorum_threshold(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.total_stake().percent_ceil(self.fast_quorum_pct.into())
+    }
+
+    /// Least stake making up a fallback quorum of `validator_set`
+    pub fn fallback_quorum_threshold(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.total_stake().percent_ceil(self.fallback_quorum_pct.into())
+    }
+
+    pub fn is_fast_quorum(&self, validator_set: &ValidatorSet, stake: StakeWeight) -> bool {
+        stake >= self.fast_quorum_threshold(validator_set)
+    }
+
+    pub fn is_fallback_quorum(&self, validator_set: &ValidatorSet, stake: StakeWeight) -> bool {
+        stake >= self.fallback_quorum_threshold(validator_set)
+    }
+
+    /// Every way the parameters break the protocol's safety or liveness
+    /// assumptions
+    pub fn check(&self) -> Vec<ParamsError> {
+        let mut problems = Vec::new();
+        let fast = self.fast_quorum_pct;
+        let fallback = self.fallback_quorum_pct;
+        let byzantine = self.max_byzantine_pct;
+
+        if fast <= fallback || fast > 100 {
+            problems.push(ParamsError::QuorumOrder { fast, fallback });
+        }
+
+        // Any two fallback quorums must share at least the Byzantine bound
+        let overlap = (2 * fallback as i16) - 100;
+        if overlap < byzantine as i16 {
+            problems.push(ParamsError::UnsafeQuorumIntersection { fallback, byzantine });
+        }
+
+        let honest = 100u8.saturating_sub(byzantine.saturating_add(self.max_offline_pct));
+        if honest < fallback {
+            problems.push(ParamsError::NoLiveness { honest, fallback });
+        }
+
+        problems.extend(check_timeouts(&self.round_timeouts()));
+        if self.retention_slots == 0 {
+            problems.push(ParamsError::ZeroRetention);
+        }
+        problems
+    }
+
+    /// The first problem `check` finds
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        self.check().into_iter().next().map_or(Ok(()), Err)
+    }
+}
+
+/// Round 1 must time out, and before round 2 does
+pub(crate) fn check_timeouts(timeouts: &RoundTimeouts) -> Option<ParamsError> {
+    let ordered = !timeouts.round1.is_zero() && timeouts.round1 < timeouts.round2;
+    (!ordered).then_some(ParamsError::TimeoutOrder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_default_params() {
+        let params = ProtocolParams::DEFAULT;
+        assert_eq!(params.fast_quorum_pct, 80);
+        assert_eq!(params.fallback_quorum_pct, 60);
+        const { assert!(ProtocolParams::DEFAULT.fast_quorum_pct > 60) };
+        assert_eq!(params.max_byzantine_pct + params.max_offline_pct, 40);
+        assert_eq!(params.round_timeouts(), RoundTimeouts::default());
+        assert_eq!(params.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_params_thread_through_engine() {
+        let unsafe_params = ProtocolParams { fallback_quorum_pct: 55, ..ProtocolParams::DEFAULT };
+        assert_eq!(
+            unsafe_params.validate(),
+            Err(ParamsError::UnsafeQuorumIntersection { fallback: 55, byzantine: 20 })
+        );
+        let slow = ProtocolParams { round1_timeout_ms: 200, ..ProtocolParams::DEFAULT };
+        assert_eq!(slow.check(), vec![ParamsError::TimeoutOrder]);
+
+        let vset = StakeDistribution::Equal(100).validator_set(10, 0);
+        let notar = |validator, block_id| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id,
+            slot: Slot(0),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+
+        // 80% of the stake finalizes under the defaults but not with a 90% fast quorum
+        let strict = ProtocolParams {
+            fast_quorum_pct: 90,
+            fallback_quorum_pct: 70,
+            max_byzantine_pct: 10,
+            ..ProtocolParams::DEFAULT
+        };
+        assert_eq!(strict.validate(), Ok(()));
+        let block_id = BlockId::new([1u8; 32]);
+        let mut default_engine =
+            ConsensusEngine::new(ValidatorId::Index(0), vset.clone(), ConsensusConfig::default());
+        let config = ConsensusConfig::from_params(strict);
+        assert_eq!(config.rotor.retention_slots, Some(64));
+        let mut strict_engine =
+            ConsensusEngine::new(ValidatorId::Index(0), vset.clone(), config.clone());
+        for i in 0..8 {
+            default_engine.process_vote(notar(i, block_id)).unwrap();
+            strict_engine.process_vote(notar(i, block_id)).unwrap();
+        }
+        assert!(default_engine.is_finalized(&block_id));
+        assert!(!strict_engine.is_finalized(&block_id));
+        strict_engine.process_vote(notar(8, block_id)).unwrap();
+        assert!(strict_engine.is_finalized(&block_id));
+
+        // Its certificates fall short of the stricter quorum elsewhere too
+        let cert = default_engine.latest_finalized().unwrap().clone();
+        let mut peer = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        assert!(peer.process_certificate(cert).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Block production for slots this validator leads
+//!
+//! `BlockProducer` turns pending mempool transactions into a `Block` for the
+//! engine's current slot, so callers no longer assemble blocks by hand. It
+//! also fills in the block's height, epoch and, if the chain keeps block
+//! times, its timestamp.
+
+use crate::consensus::{ConsensusEngine, ConsensusError};
+use crate::mempool::Mempool;
+use crate::params::BlockTimePolicy;
+use crate::rotor::Shred;
+use crate::types::*;
+
+/// Room left in a block for everything but the transactions
+const BLOCK_OVERHEAD: usize = 256;
+
+/// Builds and proposes blocks when the engine leads the current slot
+#[derive(Debug, Default)]
+pub struct BlockProducer {
+    /// Timestamp of the last block produced, in milliseconds
+    last_timestamp: u64,
+}
+
+impl BlockProducer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the current slot's block if we lead it and hold its parent
+    ///
+    /// The parent comes from `ConsensusEngine::parent_for_slot`, and its body
+    /// must be known to number the block's height. The block holds as many
+    /// pending transactions as fit the Rotor block size limit and, under
+    /// `BlockTimePolicy::WallClock`, is stamped with the wall clock in
+    /// milliseconds (strictly increasing across blocks).
+    pub fn build_block(
+        &mut self,
+        engine: &ConsensusEngine,
+        mempool: &mut Mempool,
+    ) -> Option<Block> {
+        if !engine.is_leader() {
+            return None;
+        }
+        let slot = engine.current_slot();
+        let parent = engine.parent_for_slot(slot).ok()?;
+        let height = match parent {
+            Some(parent) => engine.block_height(&parent)? + 1,
+            None => 0,
+        };
+        let config = engine.config();
+        let epoch = config.epoch_schedule.epoch_of(slot);
+
+        let timestamp = match config.params.block_time {
+            BlockTimePolicy::WallClock => {
+                let now = engine.clock().unix_millis();
+                self.last_timestamp = now.max(self.last_timestamp + 1);
+                self.last_timestamp
+            }
+            BlockTimePolicy::Omitted => 0,
+        };
+
+        let max_bytes = config.rotor.max_block_size.saturating_sub(BLOCK_OVERHEAD);
+        let block = Block::new(
+            slot,
+            parent,
+            engine.current_leader(),
+            mempool.take(max_bytes),
+            timestamp,
+        );
+        Some(block.at_position(height, epoch))
+    }
+
+    /// Build the current slot's block and hand it to `propose_block`
+    ///
+    /// Returns `None` when we don't lead the slot or its parent isn't
+    /// certified yet. If the proposal fails, its transactions go back to the
+    /// front of the mempool.
+    pub fn produce(
+        &mut self,
+        engine: &mut ConsensusEngine,
+        mempool: &mut Mempool,
+    ) -> Result<Option<(Block, Vec<Shred>)>, ConsensusError> {
+        let Some(block) = self.build_block(engine, mempool) else {
+            return Ok(None);
+        };
+        match engine.propose_block(block.clone()) {
+            Ok(shreds) => Ok(Some((block, shreds))),
+            Err(e) => {
+                mempool.requeue(block.body.transactions);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::ConsensusConfig;
+    use crate::mempool::MempoolConfig;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_leader_produces_block_from_mempool() {
+        let mut mempool = Mempool::new(MempoolConfig::default());
+        for i in 0..10u8 {
+            let payer = SigningKey::from_bytes(&[1u8; 32]);
+            mempool.insert(Transaction::new(&payer, i.into(), vec![i; 100])).unwrap();
+        }
+        let mut producer = BlockProducer::new();
+
+        let config = ConsensusConfig::default();
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut follower = ConsensusEngine::new(ValidatorId::Index(1), vset, config.clone());
+        assert!(producer.produce(&mut follower, &mut mempool).unwrap().is_none());
+        assert_eq!(mempool.len(), 10);
+
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut leader = ConsensusEngine::new(ValidatorId::Index(0), vset, config.clone());
+        let (block, shreds) = producer.produce(&mut leader, &mut mempool).unwrap().unwrap();
+        assert_eq!(block.header.slot, Slot(0));
+        assert_eq!(block.header.parent, None);
+        assert_eq!(block.body.transactions.len(), 10);
+        assert_eq!(shreds[0].block_id, block.id);
+        assert!(mempool.is_empty());
+
+        // Finalize it; the next leader's block extends it once it holds the body
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut blind = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        for i in 1..5 {
+            let vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id: block.id,
+                slot: block.header.slot,
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            leader.process_vote(vote.clone()).unwrap();
+            blind.process_vote(vote.clone()).unwrap();
+            if i == 1 {
+                for shred in shreds.clone() {
+                    follower.receive_shred(shred).unwrap();
+                }
+            } else {
+                follower.process_vote(vote).unwrap();
+            }
+        }
+        blind.next_slot();
+        assert!(producer.build_block(&blind, &mut mempool).is_none());
+        follower.next_slot();
+        let next = producer.build_block(&follower, &mut mempool).unwrap();
+        assert_eq!(next.header.slot, Slot(1));
+        assert_eq!(next.header.parent, Some(block.id));
+        assert!(next.header.timestamp > block.header.timestamp);
+        assert_eq!((block.header.height, next.header.height), (0, 1));
+        assert_eq!(next.header.epoch, Epoch(0));
+    }
+}
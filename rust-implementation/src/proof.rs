@@ -0,0 +1,164 @@
+//! Finality proofs for light clients
+//!
+//! A `FinalityProof` carries a block's finalization certificate together with
+//! the stakes and keys of the epoch it was finalized in. A stateless client
+//! that only knows the commitment of that epoch's validator set (for epoch 0,
+//! the genesis set) checks that the validators match the commitment and that
+//! the certificate's signed votes reach its quorum against them, without
+//! running the protocol or keeping any other state.
+
+use crate::integrity::{self, InvariantViolation};
+use crate::params::ProtocolParams;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    #[error("Validator set in the proof does not match the trusted commitment")]
+    CommitmentMismatch,
+
+    #[error("Invalid validator set in proof: {0}")]
+    InvalidValidatorSet(#[from] ValidatorSetError),
+
+    #[error("Invalid certificate in proof: {0}")]
+    InvalidCertificate(#[from] InvariantViolation),
+}
+
+/// Commitment to a validator set: SHA-256 over its IDs, stakes and public
+/// keys in ID order
+pub fn validator_set_commitment(validator_set: &ValidatorSet) -> [u8; 32] {
+    commitment(&validator_set.entries())
+}
+
+fn commitment(validators: &[ValidatorEntry]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for (id, stake, pubkey) in validators {
+        let mut id_bytes = Vec::new();
+        id.write_bytes(&mut id_bytes);
+        hasher.update(id_bytes);
+        hasher.update(stake.0.to_le_bytes());
+        hasher.update([u8::from(pubkey.is_some())]);
+        if let Some(key) = pubkey {
+            hasher.update(key);
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// Self-contained proof that a block was finalized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityProof {
+    pub certificate: FinalizationCertificate,
+    /// Epoch the block was finalized in
+    pub epoch: Epoch,
+    /// Every validator of that epoch with its stake and key, in ID order
+    pub validators: Vec<ValidatorEntry>,
+}
+
+impl FinalityProof {
+    pub fn new(
+        certificate: FinalizationCertificate,
+        epoch: Epoch,
+        validator_set: &ValidatorSet,
+    ) -> Self {
+        Self { certificate, epoch, validators: validator_set.entries() }
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.certificate.slot
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.certificate.block_id
+    }
+
+    /// Check the proof against the trusted commitment of the epoch's
+    /// validator set, the network's quorums and every vote's signature under
+    /// `chain_id`; the finalized block on success
+    pub fn verify(
+        &self,
+        trusted: &[u8; 32],
+        params: &ProtocolParams,
+        chain_id: &[u8; 32],
+    ) -> Result<BlockId, ProofError> {
+        if commitment(&self.validators) != *trusted {
+            return Err(ProofError::CommitmentMismatch);
+        }
+        let validator_set = ValidatorSet::from_entries(&self.validators)?;
+        let certificate = std::slice::from_ref(&self.certificate);
+        integrity::verify_certificates(&validator_set, params, chain_id, certificate)?;
+        Ok(self.certificate.block_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_finality_proof_verified_against_genesis_commitment() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let genesis = validator_set_commitment(&vset);
+        let params = ProtocolParams::default();
+        let chain_id = ConsensusConfig::default().chain_id;
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+        assert!(engine.finality_proof(Slot(0)).is_none());
+
+        let block_id = BlockId::new([7u8; 32]);
+        for i in 0..4 {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id,
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            vote.sign(&keys[i as usize], &chain_id);
+            engine.process_vote(vote).unwrap();
+        }
+
+        // The proof survives a round trip and needs nothing but the commitment
+        let proof = engine.finality_proof(Slot(0)).unwrap();
+        let bytes = bincode::serialize(&proof).unwrap();
+        let proof: FinalityProof = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(proof.verify(&genesis, &params, &chain_id), Ok(block_id));
+        assert!(matches!(
+            proof.verify(&genesis, &params, &[1u8; 32]),
+            Err(ProofError::InvalidCertificate(InvariantViolation::InvalidVoteSignature(..)))
+        ));
+
+        // Inflating a voter's stake or swapping its key changes the commitment
+        let mut forged = proof.clone();
+        forged.validators[0].1 = StakeWeight(1000);
+        let mismatch = Err(ProofError::CommitmentMismatch);
+        assert_eq!(forged.verify(&genesis, &params, &chain_id), mismatch);
+        let mut forged = proof.clone();
+        forged.validators[0].2 = Some(keys[4].verifying_key().to_bytes());
+        assert_eq!(forged.verify(&genesis, &params, &chain_id), mismatch);
+
+        // A certificate short of its quorum is rejected
+        let mut forged = proof.clone();
+        forged.certificate.votes.truncate(3);
+        forged.certificate.total_stake = StakeWeight(300);
+        assert_eq!(
+            forged.verify(&genesis, &params, &chain_id),
+            Err(ProofError::InvalidCertificate(InvariantViolation::BelowQuorum(block_id)))
+        );
+
+        // So is one whose votes aren't all signed by their validators
+        let mut forged = proof;
+        forged.certificate.votes[1].signature.clear();
+        assert_eq!(
+            forged.verify(&genesis, &params, &chain_id),
+            Err(ProofError::InvalidCertificate(InvariantViolation::InvalidVoteSignature(
+                block_id,
+                ValidatorId::Index(1)
+            )))
+        );
+    }
+}
@@ -3,10 +3,36 @@
 //! Implements block dissemination with erasure coding and stake-weighted relay selection.
 //! Ensures that honest validators (≥80% of stake) receive blocks for voting.
 
+use crate::compression::Compression;
+use crate::epoch::EpochValidatorSets;
+use crate::erasure::{ErasureCode, ErasureCoder, ErasureParams};
+use crate::params::ProtocolParams;
+use crate::shred_store::{ShredStore, StoreError};
+use crate::wire::SHRED_WIRE_VERSION;
 use crate::types::*;
-use std::collections::{HashMap, HashSet};
+use bytes::Bytes;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Domain tag prefixed to shred signing bytes
+const SHRED_DOMAIN: &[u8] = b"alpenglow-shred-v1";
+
+/// Peers each node sends a shred to in the relay tree
+pub const DEFAULT_FANOUT: usize = 200;
+
+/// Domain tag for slot-derived relay sampling seeds
+const RELAY_SEED_DOMAIN: &[u8] = b"alpenglow-relay-seed-v1";
+
+/// Most conflicts held as evidence until taken
+const MAX_CONFLICTS: usize = 1024;
+
 #[derive(Error, Debug)]
 pub enum RotorError {
     #[error("Erasure coding failed")]
@@ -17,15 +43,494 @@ pub enum RotorError {
 
     #[error("Invalid shred")]
     InvalidShred,
+
+    #[error("Invalid leader signature on shred {fec_set}:{index} of {block_id}")]
+    InvalidSignature { block_id: BlockId, fec_set: usize, index: usize },
+
+    #[error("Shred for slot {slot} is from {got}, not the scheduled leader {expected}")]
+    WrongLeader { slot: Slot, expected: ValidatorId, got: ValidatorId },
+
+    #[error("Shred leader {0} has no registered key")]
+    UnknownLeader(ValidatorId),
+
+    #[error("Shred for slot {slot} is too far ahead of the current slot {current}")]
+    SlotTooFarAhead { slot: Slot, current: Slot },
+
+    #[error("Conflicting shred {fec_set}:{index} for {block_id}")]
+    ConflictingShred { block_id: BlockId, fec_set: usize, index: usize },
+
+    #[error("Block of {size} bytes exceeds the {max} byte limit")]
+    BlockTooLarge { size: usize, max: usize },
+
+    #[error("Block payload compression failed")]
+    CompressionFailed,
+
+    #[error("Shred of {block_id} disagrees with earlier shreds on {field}")]
+    InconsistentHeader { block_id: BlockId, field: &'static str },
+
+    #[error("Block {0} is not archived")]
+    NotArchived(BlockId),
+
+    #[error("Fan-out {fanout} reaches {reached_pct:.1}% of stake, below {required_pct}%")]
+    InsufficientFanout { fanout: usize, reached_pct: f64, required_pct: u8 },
+}
+
+/// Shred counts per FEC set
+///
+/// Independent of the validator-set size so redundancy can be tuned on its
+/// own: each FEC set is split into `data_shreds` pieces and `coding_shreds`
+/// extra shreds are added for redundancy. Blocks larger than
+/// `data_shreds * max_shred_payload` bytes are split into several FEC sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotorConfig {
+    pub data_shreds: usize,
+    pub coding_shreds: usize,
+    pub max_shred_payload: usize,
+
+    /// Built-in erasure code used for each FEC set
+    pub erasure_code: ErasureCode,
+
+    /// Slots of shreds and blocks kept behind the current slot;
+    /// `None` keeps everything until `prune_before` is called
+    pub retention_slots: Option<u64>,
+
+    /// Slots ahead of the current slot whose shreds are accepted
+    pub max_future_slots: u64,
+
+    /// Largest serialized block accepted, in bytes
+    pub max_block_size: usize,
+
+    /// Compression applied to blocks before shredding; blocks that don't
+    /// shrink are sent uncompressed
+    pub compression: Compression,
+
+    /// Threads verifying and decoding shred batches: 1 decodes on the
+    /// calling thread, 0 uses rayon's global pool
+    pub decode_workers: usize,
+
+    /// Peers each node sends a shred to per layer of the relay tree
+    pub fanout: usize,
+
+    /// Per-hop shred loss assumed by `validate_fanout`, in percent
+    pub modeled_loss_pct: u8,
+}
+
+impl Default for RotorConfig {
+    fn default() -> Self {
+        Self {
+            data_shreds: 32,
+            coding_shreds: 32,
+            max_shred_payload: 1024,
+            erasure_code: ErasureCode::default(),
+            retention_slots: Some(64),
+            max_future_slots: 64,
+            max_block_size: 16 * 1024 * 1024,
+            compression: Compression::None,
+            decode_workers: 1,
+            fanout: DEFAULT_FANOUT,
+            modeled_loss_pct: 5,
+        }
+    }
+}
+
+impl RotorConfig {
+    pub fn total_shreds(&self) -> usize {
+        self.data_shreds + self.coding_shreds
+    }
+
+    pub fn erasure_params(&self) -> ErasureParams {
+        ErasureParams {
+            data_shreds: self.data_shreds,
+            coding_shreds: self.coding_shreds,
+        }
+    }
+
+    /// Shred payload bytes a block of `max_block_size` may be sent as,
+    /// coding shreds included
+    pub fn max_encoded_size(&self) -> usize {
+        let redundancy = self.total_shreds().div_ceil(self.data_shreds.max(1));
+        self.max_block_size.saturating_mul(redundancy.max(1))
+    }
+}
+
+/// Position of a shred within its block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct ShredIndex {
+    pub fec_set: usize,
+    pub index: usize,
 }
 
 /// Shred: A piece of an erasure-coded block
-#[derive(Debug, Clone)]
+///
+/// Within each FEC set, shreds `0..data_shreds` carry the set's data and the
+/// rest are coding shreds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct Shred {
+    /// Header format version, see `wire`
+    pub version: u8,
     pub block_id: BlockId,
+    pub slot: Slot,
+    pub leader: ValidatorId,
+    pub fec_set: usize,
+    pub fec_set_count: usize,
     pub index: usize,
     pub total_shreds: usize,
-    pub data: Vec<u8>,
+    pub data_shreds: usize,
+    /// Compression of the block payload the shreds carry
+    pub compression: Compression,
+    /// Payload; cloning a shred shares it rather than copying
+    #[cfg_attr(
+        feature = "borsh",
+        borsh(
+            serialize_with = "crate::codec::borsh_bytes::serialize",
+            deserialize_with = "crate::codec::borsh_bytes::deserialize"
+        )
+    )]
+    pub data: Bytes,
+    /// Leader's Ed25519 signature over `signing_bytes()`; empty if unsigned
+    pub signature: Vec<u8>,
+}
+
+impl Shred {
+    /// Bytes covered by the leader signature
+    ///
+    /// From version 2 on the version is signed too, so a shred can't be
+    /// replayed under another version's rules.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SHRED_DOMAIN.len() + 1 + 32 + 8 * 7 + 32);
+        bytes.extend_from_slice(SHRED_DOMAIN);
+        if self.version >= 2 {
+            bytes.push(self.version);
+        }
+        bytes.extend_from_slice(self.block_id.as_bytes());
+        bytes.extend_from_slice(&self.slot.0.to_le_bytes());
+        self.leader.write_bytes(&mut bytes);
+        bytes.extend_from_slice(&(self.fec_set as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.fec_set_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.index as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.total_shreds as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.data_shreds as u64).to_le_bytes());
+        bytes.push(self.compression as u8);
+        bytes.extend_from_slice(&Sha256::digest(&self.data));
+        bytes
+    }
+
+    pub fn position(&self) -> ShredIndex {
+        ShredIndex {
+            fec_set: self.fec_set,
+            index: self.index,
+        }
+    }
+
+    pub fn is_coding(&self) -> bool {
+        self.index >= self.data_shreds
+    }
+
+    /// Sign the shred with the leader's key
+    pub fn sign(&mut self, key: &SigningKey) {
+        self.signature = key.sign(&self.signing_bytes()).to_bytes().to_vec();
+    }
+
+    /// Verify the leader signature
+    pub fn verify(&self, key: &VerifyingKey) -> bool {
+        Signature::from_slice(&self.signature)
+            .map(|sig| key.verify(&self.signing_bytes(), &sig).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Two different shreds claiming the same block and index
+///
+/// If both carry valid leader signatures the leader equivocated; otherwise
+/// a relay tampered with one of them.
+#[derive(Debug, Clone)]
+pub struct ShredConflict {
+    pub first: Shred,
+    pub second: Shred,
+}
+
+/// Request for shreds a node is missing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct RepairRequest {
+    pub block_id: BlockId,
+    pub missing_indices: Vec<ShredIndex>,
+}
+
+/// Propagation health counters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RotorMetrics {
+    pub shreds_received: u64,
+    pub shreds_duplicate: u64,
+    /// Shreds rejected as forged, malformed or conflicting
+    pub shreds_invalid: u64,
+    /// Shreds dropped because their slot was already pruned
+    pub shreds_stale: u64,
+    pub blocks_reconstructed: u64,
+    /// Sum of first-shred-to-reconstruction times
+    pub total_reconstruction_time: Duration,
+    pub max_reconstruction_time: Duration,
+    pub repair_requests_sent: u64,
+    pub repair_requests_served: u64,
+    pub repair_shreds_served: u64,
+}
+
+impl RotorMetrics {
+    pub fn mean_reconstruction_time(&self) -> Option<Duration> {
+        (self.blocks_reconstructed > 0)
+            .then(|| self.total_reconstruction_time / self.blocks_reconstructed as u32)
+    }
+
+    /// Fraction of received shreds that were rejected
+    pub fn invalid_rate(&self) -> f64 {
+        if self.shreds_received == 0 {
+            0.0
+        } else {
+            self.shreds_invalid as f64 / self.shreds_received as f64
+        }
+    }
+}
+
+/// Part of a block decoded ahead of full reconstruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockRegion {
+    /// Block header, checked against the shreds that carried it
+    Header { block_id: BlockId, header: BlockHeader },
+
+    /// Consecutive transactions starting at `first_index`
+    Transactions {
+        first_index: usize,
+        transactions: Vec<Transaction>,
+    },
+}
+
+/// How far a block's decoded prefix has been handed out as regions
+#[derive(Debug, Default)]
+struct StreamCursor {
+    /// Bytes of the serialized block consumed so far
+    offset: usize,
+    header_done: bool,
+    transaction_count: Option<u64>,
+    next_transaction: usize,
+}
+
+/// Reconstruction progress reported to subscribers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RotorEvent {
+    BlockReconstructed {
+        block_id: BlockId,
+        slot: Slot,
+        /// Time from the first shred to reconstruction
+        elapsed: Duration,
+    },
+
+    /// A block is still incomplete long after its first shred; reported once
+    ReconstructionStalled {
+        block_id: BlockId,
+        slot: Slot,
+        elapsed: Duration,
+        decoded_sets: usize,
+        total_sets: usize,
+    },
+}
+
+/// A finalized block kept to re-shred for repair once its shreds are pruned
+#[derive(Debug, Clone)]
+struct ArchivedBlock {
+    block: Block,
+    version: u8,
+    compression: Compression,
+    /// Leader signatures of the original shreds, by position
+    signatures: HashMap<ShredIndex, Vec<u8>>,
+}
+
+/// Reader over decoded FEC sets in order, without joining them
+struct SetReader<'a> {
+    sets: std::slice::Iter<'a, Option<Vec<u8>>>,
+    current: &'a [u8],
+}
+
+impl<'a> SetReader<'a> {
+    fn new(sets: &'a [Option<Vec<u8>>]) -> Self {
+        Self {
+            sets: sets.iter(),
+            current: &[],
+        }
+    }
+}
+
+impl std::io::Read for SetReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.current.is_empty() {
+            match self.sets.next() {
+                Some(set) => self.current = set.as_deref().unwrap_or_default(),
+                None => return Ok(0),
+            }
+        }
+        self.current.read(buf)
+    }
+}
+
+/// Shreds received for one block
+#[derive(Debug)]
+struct BlockShreds {
+    version: u8,
+    slot: Slot,
+    leader: ValidatorId,
+    compression: Compression,
+    total_shreds: usize,
+    first_shred_at: Instant,
+    /// Time from first shred to reconstruction
+    reconstruction_time: Option<Duration>,
+    /// Payload bytes of all stored shreds
+    bytes_received: usize,
+
+    /// Shreds by FEC set, then index within the set; a set is allocated
+    /// when its first shred arrives
+    sets: Vec<Vec<Option<Shred>>>,
+
+    /// Data of each FEC set, once decoded
+    decoded: Vec<Option<Vec<u8>>>,
+
+    /// Progress of `take_decoded_regions`
+    stream: StreamCursor,
+
+    stall_reported: bool,
+
+    /// Whether the shred the header was taken from verified against its
+    /// leader's key
+    verified: bool,
+}
+
+impl BlockShreds {
+    /// Tracking for the block of `shred`, whose header the rest must match
+    fn new(shred: &Shred, verified: bool) -> Self {
+        Self {
+            version: shred.version,
+            slot: shred.slot,
+            leader: shred.leader,
+            compression: shred.compression,
+            total_shreds: shred.total_shreds,
+            first_shred_at: Instant::now(),
+            reconstruction_time: None,
+            bytes_received: 0,
+            sets: vec![Vec::new(); shred.fec_set_count],
+            decoded: vec![None; shred.fec_set_count],
+            stream: StreamCursor::default(),
+            stall_reported: false,
+            verified,
+        }
+    }
+
+    fn get(&self, position: ShredIndex) -> Option<&Shred> {
+        self.sets.get(position.fec_set)?.get(position.index)?.as_ref()
+    }
+
+    /// Header field of `shred` that differs from this block's, if any
+    fn mismatch(&self, shred: &Shred) -> Option<&'static str> {
+        if shred.version != self.version {
+            Some("version")
+        } else if shred.slot != self.slot {
+            Some("slot")
+        } else if shred.leader != self.leader {
+            Some("leader")
+        } else if shred.compression != self.compression {
+            Some("compression")
+        } else if shred.fec_set_count != self.sets.len() {
+            Some("fec_set_count")
+        } else if shred.total_shreds != self.total_shreds {
+            Some("total_shreds")
+        } else {
+            None
+        }
+    }
+
+    /// Payloads of one FEC set, as the erasure coder takes them
+    fn set_shards(&self, fec_set: usize) -> Vec<Option<&[u8]>> {
+        self.sets[fec_set]
+            .iter()
+            .map(|shred| shred.as_ref().map(|s| &s.data[..]))
+            .collect()
+    }
+
+    fn shreds(&self) -> impl Iterator<Item = &Shred> {
+        self.sets.iter().flatten().flatten()
+    }
+}
+
+/// Turbine-style propagation tree for one shred
+///
+/// Validators are ordered by a stake-weighted shuffle. The leader sends to
+/// the first `fanout` validators (layer 0); the validator at position `p`
+/// forwards to positions `(p + 1) * fanout .. (p + 2) * fanout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayTree {
+    fanout: usize,
+    order: Vec<ValidatorId>,
+}
+
+impl RelayTree {
+    /// Validators the leader sends to directly
+    pub fn root_layer(&self) -> &[ValidatorId] {
+        &self.order[..self.fanout.min(self.order.len())]
+    }
+
+    /// Layers from the root down
+    pub fn layers(&self) -> Vec<&[ValidatorId]> {
+        let mut layers = Vec::new();
+        let (mut start, mut width) = (0, self.fanout);
+        while start < self.order.len() {
+            let end = (start + width).min(self.order.len());
+            layers.push(&self.order[start..end]);
+            start = end;
+            width = width.saturating_mul(self.fanout);
+        }
+        layers
+    }
+
+    /// Peers a validator forwards to
+    pub fn children(&self, id: &ValidatorId) -> &[ValidatorId] {
+        let Some(position) = self.position(id) else {
+            return &[];
+        };
+        let start = (position + 1).saturating_mul(self.fanout).min(self.order.len());
+        let end = start.saturating_add(self.fanout).min(self.order.len());
+        &self.order[start..end]
+    }
+
+    /// Peer a validator receives from (`None` means directly from the leader)
+    pub fn parent(&self, id: &ValidatorId) -> Option<ValidatorId> {
+        let position = self.position(id)?;
+        if position < self.fanout {
+            None
+        } else {
+            Some(self.order[position / self.fanout - 1])
+        }
+    }
+
+    /// Number of layers
+    pub fn depth(&self) -> usize {
+        self.layers().len()
+    }
+
+    /// Layer a validator sits in (0 receives from the leader)
+    pub fn layer_of(&self, id: &ValidatorId) -> Option<usize> {
+        let mut position = self.position(id)?;
+        let mut width = self.fanout;
+        let mut layer = 0;
+        while position >= width {
+            position -= width;
+            width = width.saturating_mul(self.fanout);
+            layer += 1;
+        }
+        Some(layer)
+    }
+
+    fn position(&self, id: &ValidatorId) -> Option<usize> {
+        self.order.iter().position(|v| v == id)
+    }
 }
 
 /// Rotor handles block propagation with erasure coding
@@ -33,144 +538,1250 @@ pub struct Rotor {
     /// Validator set for relay selection
     validator_set: ValidatorSet,
 
-    /// Received shreds per block
-    received_shreds: HashMap<BlockId, Vec<Option<Shred>>>,
+    /// Shred counts used when encoding
+    config: RotorConfig,
+
+    /// Erasure code for each FEC set
+    coder: Box<dyn ErasureCoder>,
+
+    /// Received shreds per block, with per-FEC-set completion
+    received_shreds: HashMap<BlockId, BlockShreds>,
 
     /// Reconstructed blocks
     reconstructed_blocks: HashMap<BlockId, Block>,
+
+    /// Finalized blocks that outlive `prune_before`, see `archive_block`
+    archived_blocks: HashMap<BlockId, ArchivedBlock>,
+
+    /// Known leader keys; once there are any, every shred must carry a valid
+    /// signature by one
+    leader_keys: HashMap<ValidatorId, VerifyingKey>,
+
+    /// Validator sets and leader window naming the leader of each slot
+    leader_schedule: Option<(EpochValidatorSets, u64)>,
+
+    /// Conflicting shreds seen, kept as evidence; one per block, pruned
+    /// with its slot
+    conflicts: Vec<ShredConflict>,
+
+    /// State for slots below this has been pruned
+    pruned_before: Slot,
+
+    /// Local slot bounding retention and how far ahead shreds may be
+    current_slot: Slot,
+
+    /// Blocks dropped for exceeding the size limit, with their slot
+    oversized_blocks: HashMap<BlockId, Slot>,
+
+    /// Write-through storage for accepted shreds
+    store: Option<Box<dyn ShredStore>>,
+
+    /// Dedicated pool when `decode_workers` > 1
+    pool: Option<ThreadPool>,
+
+    /// Event subscribers; dropped once their receiver is gone
+    subscribers: Vec<Sender<RotorEvent>>,
+
+    /// Peers known to lack shreds of a block; `None` means all of them
+    lagging: HashMap<BlockId, HashMap<ValidatorId, Option<Vec<ShredIndex>>>>,
+
+    /// Share of stake, in percent, the relay tree must reach
+    fast_quorum_pct: u8,
+
+    metrics: RotorMetrics,
 }
 
 impl Rotor {
     pub fn new(validator_set: ValidatorSet) -> Self {
+        Self::with_config(validator_set, RotorConfig::default())
+    }
+
+    pub fn with_config(validator_set: ValidatorSet, config: RotorConfig) -> Self {
+        let coder = config.erasure_code.build(config.erasure_params());
+        Self::with_coder(validator_set, config, coder)
+    }
+
+    /// Use a custom erasure coder; its parameters override the shred counts
+    /// in `config`
+    pub fn with_coder(
+        validator_set: ValidatorSet,
+        mut config: RotorConfig,
+        coder: Box<dyn ErasureCoder>,
+    ) -> Self {
+        let params = coder.params();
+        config.data_shreds = params.data_shreds;
+        config.coding_shreds = params.coding_shreds;
+
+        let pool = if config.decode_workers > 1 {
+            ThreadPoolBuilder::new()
+                .num_threads(config.decode_workers)
+                .build()
+                .ok()
+        } else {
+            None
+        };
+
         Self {
+            leader_keys: public_keys(&validator_set),
+            leader_schedule: None,
             validator_set,
+            config,
+            coder,
             received_shreds: HashMap::new(),
             reconstructed_blocks: HashMap::new(),
+            archived_blocks: HashMap::new(),
+            conflicts: Vec::new(),
+            pruned_before: Slot(0),
+            current_slot: Slot(0),
+            oversized_blocks: HashMap::new(),
+            store: None,
+            pool,
+            subscribers: Vec::new(),
+            lagging: HashMap::new(),
+            fast_quorum_pct: ProtocolParams::DEFAULT.fast_quorum_pct,
+            metrics: RotorMetrics::default(),
         }
     }
 
+    /// Size relay checks by the fast quorum of `params`
+    pub fn with_params(mut self, params: &ProtocolParams) -> Self {
+        self.fast_quorum_pct = params.fast_quorum_pct;
+        self
+    }
+
+    /// Persist every accepted shred to `store`
+    ///
+    /// Call `restore` afterwards to pick up shreds stored before a restart.
+    /// The store is not pruned with in-memory state, so it keeps serving
+    /// repairs for older blocks; see `prune_store_before`.
+    pub fn with_store(mut self, store: Box<dyn ShredStore>) -> Self {
+        self.set_store(store);
+        self
+    }
+
+    /// Like `with_store`, replacing any store already set
+    pub fn set_store(&mut self, store: Box<dyn ShredStore>) {
+        self.store = Some(store);
+    }
+
+    /// Replay stored shreds of unpruned slots into memory
+    ///
+    /// FEC sets the stored shreds complete are decoded again, so partially
+    /// received blocks continue from where they stopped and only the missing
+    /// shreds need to arrive. Register leader keys first so stored shreds are
+    /// re-verified. Returns the blocks reconstructed from stored shreds alone.
+    pub fn restore(&mut self) -> Result<Vec<Block>, StoreError> {
+        // Taken out so replayed shreds aren't written back
+        let Some(store) = self.store.take() else {
+            return Ok(Vec::new());
+        };
+
+        let result = self.replay(store.as_ref());
+        self.store = Some(store);
+        result
+    }
+
+    fn replay(&mut self, store: &dyn ShredStore) -> Result<Vec<Block>, StoreError> {
+        // Oldest first, as they arrived
+        let mut blocks = store.blocks()?;
+        blocks.sort_by_key(|(block_id, slot)| (*slot, *block_id));
+
+        let mut restored = Vec::new();
+        for (block_id, slot) in blocks {
+            if slot < self.pruned_before || self.has_block(&block_id) {
+                continue;
+            }
+            for shred in store.block_shreds(&block_id)? {
+                match self.accept_shred(shred) {
+                    Ok(Some(block)) => {
+                        restored.push(block);
+                        break;
+                    }
+                    Ok(None) => {}
+                    Err(err) => tracing::warn!("Dropping stored shred of {}: {}", block_id, err),
+                }
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Delete stored shreds of slots before `slot`
+    pub fn prune_store_before(&mut self, slot: Slot) -> Result<usize, StoreError> {
+        match &mut self.store {
+            Some(store) => store.prune_before(slot),
+            None => Ok(0),
+        }
+    }
+
+    /// Register a leader's public key so its shreds are authenticated
+    pub fn register_leader_key(&mut self, leader: ValidatorId, key: VerifyingKey) {
+        self.leader_keys.insert(leader, key);
+    }
+
+    pub fn leader_key(&self, leader: &ValidatorId) -> Option<VerifyingKey> {
+        self.leader_keys.get(leader).copied()
+    }
+
+    /// Only accept shreds from the leader `epochs` schedule for their slot,
+    /// each leading `leader_window` consecutive slots
+    ///
+    /// Public keys in every epoch's set are registered as leader keys.
+    pub fn set_leader_schedule(&mut self, epochs: EpochValidatorSets, leader_window: u64) {
+        for validator_set in epochs.sets() {
+            self.leader_keys.extend(public_keys(validator_set));
+        }
+        self.leader_schedule = Some((epochs, leader_window));
+    }
+
+    /// Sample relays from a new validator set, e.g. at an epoch boundary
+    ///
+    /// Public keys in the set are registered as leader keys.
+    pub fn set_validator_set(&mut self, validator_set: ValidatorSet) {
+        self.leader_keys.extend(public_keys(&validator_set));
+        self.validator_set = validator_set;
+    }
+
+    /// Forget shreds and blocks in flight, starting over with `validator_set`
+    ///
+    /// The store, archived blocks, leader keys and schedule, subscribers and
+    /// metrics are kept.
+    pub fn reset(&mut self, validator_set: ValidatorSet) {
+        self.received_shreds.clear();
+        self.reconstructed_blocks.clear();
+        self.conflicts.clear();
+        self.oversized_blocks.clear();
+        self.lagging.clear();
+        self.pruned_before = Slot(0);
+        self.current_slot = Slot(0);
+        self.set_validator_set(validator_set);
+    }
+
+    pub fn config(&self) -> &RotorConfig {
+        &self.config
+    }
+
     /// Encode a block into shreds using erasure coding
     ///
-    /// Simplified implementation: splits block data into N equal parts
-    /// In production, use Reed-Solomon or similar erasure coding
+    /// The serialized block is optionally compressed, cut into FEC sets that
+    /// fit `max_shred_payload` per shred, and each set is encoded with the
+    /// configured erasure coder.
     pub fn encode_block(&self, block: &Block) -> Result<Vec<Shred>, RotorError> {
+        let serialized = self.serialize_block(block)?;
+        let (compression, payload) = match self.config.compression {
+            Compression::None => (Compression::None, serialized),
+            compression => {
+                let compressed = compression.compress(&serialized)?;
+                if compressed.len() < serialized.len() {
+                    (compression, compressed)
+                } else {
+                    (Compression::None, serialized)
+                }
+            }
+        };
+        self.shred_payload(block, SHRED_WIRE_VERSION, compression, &payload)
+    }
+
+    fn serialize_block(&self, block: &Block) -> Result<Vec<u8>, RotorError> {
         let serialized = bincode::serialize(block)
             .map_err(|_| RotorError::ErasureCodingFailed)?;
+        if serialized.len() > self.config.max_block_size {
+            return Err(RotorError::BlockTooLarge {
+                size: serialized.len(),
+                max: self.config.max_block_size,
+            });
+        }
+        Ok(serialized)
+    }
 
-        // Split into N shreds (equal to number of validators)
-        let num_validators = self.validator_set.len();
-        let chunk_size = (serialized.len() + num_validators - 1) / num_validators;
+    /// Cut a (possibly compressed) serialized block into FEC sets and encode them
+    fn shred_payload(
+        &self,
+        block: &Block,
+        version: u8,
+        compression: Compression,
+        serialized: &[u8],
+    ) -> Result<Vec<Shred>, RotorError> {
+        let ErasureParams { data_shreds, .. } = self.coder.params();
+        let total_shreds = self.coder.params().total_shreds();
 
-        let mut shreds = Vec::new();
-        for (i, chunk) in serialized.chunks(chunk_size).enumerate() {
-            shreds.push(Shred {
-                block_id: block.id,
-                index: i,
-                total_shreds: num_validators,
-                data: chunk.to_vec(),
-            });
+        let set_capacity = self.coder.set_capacity(self.config.max_shred_payload);
+        if data_shreds == 0 || set_capacity == 0 {
+            return Err(RotorError::ErasureCodingFailed);
         }
+        let fec_set_count = serialized.len().div_ceil(set_capacity).max(1);
+
+        let mut shreds = Vec::with_capacity(fec_set_count * total_shreds);
+        for fec_set in 0..fec_set_count {
+            let start = (fec_set * set_capacity).min(serialized.len());
+            let end = (start + set_capacity).min(serialized.len());
+            let shards = self.coder.encode(&serialized[start..end])?;
 
-        // Pad to ensure we have enough shreds
-        while shreds.len() < num_validators {
-            shreds.push(Shred {
+            shreds.extend(shards.into_iter().enumerate().map(|(index, data)| Shred {
+                version,
                 block_id: block.id,
-                index: shreds.len(),
-                total_shreds: num_validators,
-                data: vec![],
-            });
+                slot: block.header.slot,
+                leader: block.header.leader,
+                fec_set,
+                fec_set_count,
+                index,
+                total_shreds,
+                data_shreds,
+                compression,
+                data: data.into(),
+                signature: vec![],
+            }));
         }
 
         Ok(shreds)
     }
 
+    /// Encode a block into shreds signed by the leader
+    pub fn encode_block_signed(
+        &self,
+        block: &Block,
+        key: &SigningKey,
+    ) -> Result<Vec<Shred>, RotorError> {
+        let mut shreds = self.encode_block(block)?;
+        for shred in &mut shreds {
+            shred.sign(key);
+        }
+        Ok(shreds)
+    }
+
     /// Process a received shred
     pub fn receive_shred(&mut self, shred: Shred) -> Result<Option<Block>, RotorError> {
+        self.metrics.shreds_received += 1;
+        let result = self.accept_shred(shred);
+        if let Err(err) = &result {
+            self.record_rejection(err);
+        }
+        result
+    }
+
+    /// Process a batch of shreds, verifying and decoding in parallel
+    ///
+    /// Signatures are checked and completed FEC sets decoded on
+    /// `decode_workers` threads. Returns the blocks the batch completed and
+    /// the errors of rejected shreds.
+    pub fn receive_shreds(&mut self, shreds: Vec<Shred>) -> (Vec<Block>, Vec<RotorError>) {
+        self.metrics.shreds_received += shreds.len() as u64;
+        let mut errors = Vec::new();
+
+        let (keys, schedule) = (&self.leader_keys, self.leader_schedule.as_ref());
+        let verified = self.parallel(shreds, |shred| {
+            check_signature(keys, schedule, &shred).map(|()| shred)
+        });
+
+        let mut touched = Vec::new();
+        for shred in verified {
+            match shred.and_then(|shred| self.store_shred(shred)) {
+                Ok(Some(position)) => touched.push(position),
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+
+        // Decode every set the batch may have completed
+        touched.sort();
+        touched.dedup();
+        let jobs: Vec<_> = touched
+            .iter()
+            .filter_map(|(block_id, fec_set)| {
+                let block_shreds = self.received_shreds.get(block_id)?;
+                block_shreds.decoded[*fec_set].is_none().then(|| {
+                    (*block_id, *fec_set, block_shreds.set_shards(*fec_set))
+                })
+            })
+            .collect();
+        let coder = &self.coder;
+        let decoded = self.parallel(jobs, |(block_id, fec_set, shards)| {
+            (block_id, fec_set, coder.reconstruct(&shards))
+        });
+        for (block_id, fec_set, result) in decoded {
+            match result {
+                Ok(data) => {
+                    if let Some(block_shreds) = self.received_shreds.get_mut(&block_id) {
+                        block_shreds.decoded[fec_set] = data;
+                    }
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_ids: Vec<BlockId> = touched.into_iter().map(|(id, _)| id).collect();
+        block_ids.dedup();
+        for block_id in block_ids {
+            if self.has_block(&block_id) {
+                continue;
+            }
+            match self.try_reconstruct_block(block_id) {
+                Ok(Some(block)) => blocks.push(block),
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+
+        for err in &errors {
+            self.record_rejection(err);
+        }
+        (blocks, errors)
+    }
+
+    /// Run `f` over `items` on the configured decode workers
+    fn parallel<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Send + Sync,
+    {
+        match (&self.pool, self.config.decode_workers) {
+            (Some(pool), _) => pool.install(|| items.into_par_iter().map(f).collect()),
+            (None, 1) => items.into_iter().map(f).collect(),
+            (None, _) => items.into_par_iter().map(f).collect(),
+        }
+    }
+
+    fn record_rejection(&mut self, err: &RotorError) {
+        if matches!(
+            err,
+            RotorError::InvalidShred
+                | RotorError::CompressionFailed
+                | RotorError::InconsistentHeader { .. }
+                | RotorError::InvalidSignature { .. }
+                | RotorError::WrongLeader { .. }
+                | RotorError::UnknownLeader(_)
+                | RotorError::SlotTooFarAhead { .. }
+                | RotorError::ConflictingShred { .. }
+                | RotorError::BlockTooLarge { .. }
+        ) {
+            self.metrics.shreds_invalid += 1;
+        }
+    }
+
+    fn accept_shred(&mut self, shred: Shred) -> Result<Option<Block>, RotorError> {
+        // Discard forged shreds before they reach reconstruction
+        check_signature(&self.leader_keys, self.leader_schedule.as_ref(), &shred)?;
+
+        let Some((block_id, fec_set)) = self.store_shred(shred)? else {
+            return Ok(None);
+        };
+
+        // Decode the FEC set if this shred completed it
+        if let Some(block_shreds) = self.received_shreds.get_mut(&block_id) {
+            if block_shreds.decoded[fec_set].is_none() {
+                let shards = block_shreds.set_shards(fec_set);
+                block_shreds.decoded[fec_set] = self.coder.reconstruct(&shards)?;
+            }
+        }
+
+        // Try to reconstruct the block
+        self.try_reconstruct_block(block_id)
+    }
+
+    /// Validate and store a shred whose signature was checked
+    ///
+    /// Returns the block and FEC set it belongs to, or `None` if it was
+    /// dropped without being an error.
+    fn store_shred(&mut self, shred: Shred) -> Result<Option<(BlockId, usize)>, RotorError> {
+        // Shreds must use the network's erasure parameters, and their sizes
+        // must fit a block within the size limit before anything is allocated
+        let params = self.coder.params();
+        if shred.data_shreds != params.data_shreds
+            || shred.total_shreds != params.total_shreds()
+            || shred.index >= shred.total_shreds
+            || shred.fec_set >= shred.fec_set_count
+            || shred.fec_set_count > self.max_fec_sets()
+            || shred.data.len() > self.config.max_shred_payload
+        {
+            return Err(RotorError::InvalidShred);
+        }
+
+        // Pruned slots are not tracked again
+        if shred.slot < self.pruned_before {
+            tracing::debug!("Dropping shred for pruned slot {}", shred.slot);
+            self.metrics.shreds_stale += 1;
+            return Ok(None);
+        }
+
+        // Far-future slots would otherwise pin memory the window can't free
+        if shred.slot.0 > self.current_slot.0.saturating_add(self.config.max_future_slots) {
+            return Err(RotorError::SlotTooFarAhead {
+                slot: shred.slot,
+                current: self.current_slot,
+            });
+        }
+
         let block_id = shred.block_id;
-        let index = shred.index;
-        let total_shreds = shred.total_shreds;
+        let ShredIndex { fec_set, index } = shred.position();
+        let payload_len = shred.data.len();
+
+        if self.oversized_blocks.contains_key(&block_id) {
+            tracing::debug!("Dropping shred for oversized block {}", block_id);
+            self.metrics.shreds_invalid += 1;
+            return Ok(None);
+        }
 
-        // Initialize storage for this block's shreds
-        let shreds = self
+        // Initialize storage for this block's shreds. The caller checked the
+        // signature, so the shred verified if its leader has a key.
+        let verified = self.leader_keys.contains_key(&shred.leader);
+        let block_shreds = self
             .received_shreds
             .entry(block_id)
-            .or_insert_with(|| vec![None; total_shreds]);
+            .or_insert_with(|| BlockShreds::new(&shred, verified));
+        if verified && !block_shreds.verified {
+            // Shreds taken before the leader's key was known may be forged:
+            // start over from the first one the leader signed
+            tracing::debug!("Dropping unverified shreds of {}", block_id);
+            *block_shreds = BlockShreds::new(&shred, verified);
+        } else if let Some(field) = block_shreds.mismatch(&shred) {
+            return Err(RotorError::InconsistentHeader { block_id, field });
+        }
 
-        // Store the shred
-        if index < shreds.len() {
-            shreds[index] = Some(shred);
-        } else {
-            return Err(RotorError::InvalidShred);
+        let set = &mut block_shreds.sets[fec_set];
+        if set.is_empty() {
+            set.resize(block_shreds.total_shreds, None);
         }
 
-        // Try to reconstruct the block
-        self.try_reconstruct_block(block_id)
+        // Store the shred, keeping the first copy if another one disagrees
+        match set.get_mut(index) {
+            Some(Some(existing)) => {
+                if existing.data != shred.data
+                    || existing.compression != shred.compression
+                    || existing.total_shreds != shred.total_shreds
+                    || existing.data_shreds != shred.data_shreds
+                {
+                    tracing::warn!("Conflicting shred {}:{} for {}", fec_set, index, block_id);
+                    // One conflict proves the leader equivocated on the block
+                    let known = self.conflicts.iter().any(|c| c.first.block_id == block_id);
+                    if !known && self.conflicts.len() < MAX_CONFLICTS {
+                        self.conflicts.push(ShredConflict {
+                            first: existing.clone(),
+                            second: shred,
+                        });
+                    }
+                    return Err(RotorError::ConflictingShred { block_id, fec_set, index });
+                }
+                self.metrics.shreds_duplicate += 1;
+            }
+            Some(slot) => {
+                if let Some(store) = &mut self.store {
+                    if let Err(err) = store.put(&shred) {
+                        tracing::warn!(
+                            "Failed to store shred {}:{} of {}: {}",
+                            fec_set, index, block_id, err
+                        );
+                    }
+                }
+                *slot = Some(shred);
+                block_shreds.bytes_received += payload_len;
+            }
+            None => return Err(RotorError::InvalidShred),
+        }
+
+        // A leader sending more data than any valid block needs is cut off
+        let max = self.config.max_encoded_size();
+        if block_shreds.bytes_received > max {
+            let (size, slot) = (block_shreds.bytes_received, block_shreds.slot);
+            tracing::warn!("Dropping {}: {} shred bytes exceed {}", block_id, size, max);
+            self.received_shreds.remove(&block_id);
+            self.oversized_blocks.insert(block_id, slot);
+            return Err(RotorError::BlockTooLarge { size, max });
+        }
+
+        Ok(Some((block_id, fec_set)))
+    }
+
+    /// Most FEC sets a block within `max_block_size` can be split into
+    fn max_fec_sets(&self) -> usize {
+        let set_capacity = self.coder.set_capacity(self.config.max_shred_payload).max(1);
+        self.config.max_block_size.div_ceil(set_capacity).max(1)
     }
 
-    /// Attempt to reconstruct a block from received shreds
+    /// Attempt to reconstruct a block once all of its FEC sets are decoded
     fn try_reconstruct_block(&mut self, block_id: BlockId) -> Result<Option<Block>, RotorError> {
         // Check if already reconstructed
         if self.reconstructed_blocks.contains_key(&block_id) {
             return Ok(Some(self.reconstructed_blocks[&block_id].clone()));
         }
 
-        let shreds = self
+        let block_shreds = self
             .received_shreds
-            .get(&block_id)
+            .get_mut(&block_id)
             .ok_or(RotorError::InsufficientShreds)?;
 
-        // Calculate minimum shreds needed (80% for reconstruction threshold)
-        let min_shreds_needed = (shreds.len() * 80) / 100;
-        let received_count = shreds.iter().filter(|s| s.is_some()).count();
-
-        if received_count < min_shreds_needed {
+        if block_shreds.decoded.iter().any(Option::is_none) {
             return Ok(None); // Not enough shreds yet
         }
 
-        // Reconstruct block data
-        let mut reconstructed_data = Vec::new();
-        for shred_opt in shreds.iter() {
-            if let Some(shred) = shred_opt {
-                reconstructed_data.extend_from_slice(&shred.data);
+        let first = block_shreds.shreds().next().ok_or(RotorError::InsufficientShreds)?;
+
+        let size: usize = block_shreds.decoded.iter().flatten().map(Vec::len).sum();
+        if size > self.config.max_block_size {
+            return Err(RotorError::BlockTooLarge {
+                size,
+                max: self.config.max_block_size,
+            });
+        }
+
+        // Uncompressed blocks deserialize straight from the decoded sets
+        let sets = SetReader::new(&block_shreds.decoded);
+        let block: Block = match first.compression {
+            Compression::None => bincode::deserialize_from(sets),
+            compression => {
+                let payload: Vec<u8> =
+                    block_shreds.decoded.iter().flatten().flatten().copied().collect();
+                bincode::deserialize(&compression.decompress(&payload, self.config.max_block_size)?)
+            }
+        }
+        .map_err(|_| RotorError::ErasureCodingFailed)?;
+
+        // Verify block ID and header match what the shreds claimed
+        let header = &block.header;
+        if block.id != block_id || header.slot != first.slot || header.leader != first.leader {
+            return Err(RotorError::InvalidShred);
+        }
+
+        let elapsed = block_shreds.first_shred_at.elapsed();
+        block_shreds.reconstruction_time = Some(elapsed);
+        let slot = block_shreds.slot;
+        self.metrics.blocks_reconstructed += 1;
+        self.metrics.total_reconstruction_time += elapsed;
+        self.metrics.max_reconstruction_time = self.metrics.max_reconstruction_time.max(elapsed);
+
+        // Cache reconstructed block
+        self.reconstructed_blocks.insert(block_id, block.clone());
+        self.emit(RotorEvent::BlockReconstructed { block_id, slot, elapsed });
+
+        Ok(Some(block))
+    }
+
+    /// Block regions decoded since the last call for this block
+    ///
+    /// Regions are yielded in block order as soon as the leading FEC sets
+    /// covering them decode: the header first, then transaction batches. This
+    /// lets the header and parent link be checked before the block completes.
+    /// Compressed blocks yield their regions only once fully decoded.
+    pub fn take_decoded_regions(
+        &mut self,
+        block_id: &BlockId,
+    ) -> Result<Vec<BlockRegion>, RotorError> {
+        let Some(block_shreds) = self.received_shreds.get_mut(block_id) else {
+            return Ok(Vec::new());
+        };
+        let Some((leader, compression)) =
+            block_shreds.shreds().next().map(|s| (s.leader, s.compression))
+        else {
+            return Ok(Vec::new());
+        };
+
+        // Only the contiguous run of decoded sets from the start is usable
+        let prefix: Vec<u8> = block_shreds
+            .decoded
+            .iter()
+            .map_while(Option::as_ref)
+            .flatten()
+            .copied()
+            .collect();
+
+        // A compressed payload can only be read once every set has decoded
+        let prefix = match compression {
+            Compression::None => prefix,
+            _ if block_shreds.decoded.iter().any(Option::is_none) => return Ok(Vec::new()),
+            _ => compression.decompress(&prefix, self.config.max_block_size)?,
+        };
+        let slot = block_shreds.slot;
+        let cursor = &mut block_shreds.stream;
+        let mut regions = Vec::new();
+
+        if !cursor.header_done {
+            type Prefix = (BlockId, BlockHeader);
+            let Ok(decoded) = bincode::deserialize::<Prefix>(&prefix[cursor.offset..]) else {
+                return Ok(regions);
+            };
+            let (id, header) = &decoded;
+            if id != block_id || header.slot != slot || header.leader != leader {
+                return Err(RotorError::InvalidShred);
+            }
+            cursor.offset += bincode::serialized_size(&decoded)
+                .map_err(|_| RotorError::ErasureCodingFailed)? as usize;
+            cursor.header_done = true;
+            let (block_id, header) = decoded;
+            regions.push(BlockRegion::Header { block_id, header });
+        }
+
+        if cursor.transaction_count.is_none() {
+            let Ok(count) = bincode::deserialize::<u64>(&prefix[cursor.offset..]) else {
+                return Ok(regions);
+            };
+            cursor.offset += std::mem::size_of::<u64>();
+            cursor.transaction_count = Some(count);
+        }
+
+        let first_index = cursor.next_transaction;
+        let mut transactions = Vec::new();
+        while (cursor.next_transaction as u64) < cursor.transaction_count.unwrap_or(0) {
+            let Ok(transaction) = bincode::deserialize::<Transaction>(&prefix[cursor.offset..])
+            else {
+                break;
+            };
+            cursor.offset += bincode::serialized_size(&transaction)
+                .map_err(|_| RotorError::ErasureCodingFailed)? as usize;
+            cursor.next_transaction += 1;
+            transactions.push(transaction);
+        }
+        if !transactions.is_empty() {
+            regions.push(BlockRegion::Transactions {
+                first_index,
+                transactions,
+            });
+        }
+
+        Ok(regions)
+    }
+
+    /// Receive reconstruction events from now on
+    pub fn subscribe(&mut self) -> Receiver<RotorEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: RotorEvent) {
+        self.subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Report blocks still incomplete `timeout` after their first shred
+    ///
+    /// Each stalled block is reported once, as a `ReconstructionStalled`
+    /// event and in the returned list; `repair_request` builds the repair.
+    pub fn check_stalled(&mut self, timeout: Duration) -> Vec<BlockId> {
+        let mut stalled = Vec::new();
+        for (block_id, block_shreds) in &mut self.received_shreds {
+            let elapsed = block_shreds.first_shred_at.elapsed();
+            if block_shreds.stall_reported
+                || block_shreds.reconstruction_time.is_some()
+                || elapsed < timeout
+            {
+                continue;
+            }
+            block_shreds.stall_reported = true;
+            stalled.push(RotorEvent::ReconstructionStalled {
+                block_id: *block_id,
+                slot: block_shreds.slot,
+                elapsed,
+                decoded_sets: block_shreds.decoded.iter().filter(|d| d.is_some()).count(),
+                total_sets: block_shreds.decoded.len(),
+            });
+        }
+
+        let mut block_ids = Vec::with_capacity(stalled.len());
+        for event in stalled {
+            if let RotorEvent::ReconstructionStalled { block_id, .. } = event {
+                block_ids.push(block_id);
+            }
+            self.emit(event);
+        }
+        block_ids.sort();
+        block_ids
+    }
+
+    /// Decoded and total FEC sets of a block, if any shred of it arrived
+    pub fn fec_set_progress(&self, block_id: &BlockId) -> Option<(usize, usize)> {
+        let block_shreds = self.received_shreds.get(block_id)?;
+        let decoded = block_shreds.decoded.iter().filter(|d| d.is_some()).count();
+        Some((decoded, block_shreds.decoded.len()))
+    }
+
+    /// Whether the shred at `position` of a block is stored
+    pub fn has_shred(&self, block_id: &BlockId, position: ShredIndex) -> bool {
+        self.received_shreds
+            .get(block_id)
+            .is_some_and(|block_shreds| block_shreds.get(position).is_some())
+    }
+
+    pub fn metrics(&self) -> &RotorMetrics {
+        &self.metrics
+    }
+
+    /// Time from a block's first shred to its reconstruction
+    pub fn reconstruction_time(&self, block_id: &BlockId) -> Option<Duration> {
+        self.received_shreds.get(block_id)?.reconstruction_time
+    }
+
+    /// Conflicting shreds seen so far
+    pub fn conflicts(&self) -> &[ShredConflict] {
+        &self.conflicts
+    }
+
+    /// Drain conflict evidence, e.g. to report it
+    pub fn take_conflicts(&mut self) -> Vec<ShredConflict> {
+        std::mem::take(&mut self.conflicts)
+    }
+
+    /// Repair request for a block whose reconstruction has stalled
+    ///
+    /// Returns `None` if the block is complete or no shred of it has arrived
+    /// (the shred count is unknown until one does).
+    pub fn repair_request(&self, block_id: &BlockId) -> Option<RepairRequest> {
+        if self.reconstructed_blocks.contains_key(block_id) {
+            return None;
+        }
+
+        // Only incomplete FEC sets need repair
+        let block_shreds = self.received_shreds.get(block_id)?;
+        let missing_indices: Vec<ShredIndex> = block_shreds
+            .sets
+            .iter()
+            .zip(&block_shreds.decoded)
+            .enumerate()
+            .filter(|(_, (_, decoded))| decoded.is_none())
+            .flat_map(|(fec_set, _)| {
+                (0..block_shreds.total_shreds)
+                    .map(move |index| ShredIndex { fec_set, index })
+                    .filter(|position| block_shreds.get(*position).is_none())
+            })
+            .collect();
+
+        if missing_indices.is_empty() {
+            None
+        } else {
+            Some(RepairRequest {
+                block_id: *block_id,
+                missing_indices,
+            })
+        }
+    }
+
+    /// Blocks with shreds received but not yet reconstructed, ordered by ID
+    pub fn incomplete_blocks(&self) -> Vec<BlockId> {
+        let mut block_ids: Vec<BlockId> = self
+            .received_shreds
+            .keys()
+            .filter(|block_id| !self.reconstructed_blocks.contains_key(block_id))
+            .copied()
+            .collect();
+        block_ids.sort();
+        block_ids
+    }
+
+    /// Repair requests to send for every incomplete block, ordered by block ID
+    pub fn pending_repairs(&mut self) -> Vec<RepairRequest> {
+        let mut requests: Vec<_> = self
+            .received_shreds
+            .keys()
+            .filter_map(|block_id| self.repair_request(block_id))
+            .collect();
+        requests.sort_by_key(|r| r.block_id);
+        self.metrics.repair_requests_sent += requests.len() as u64;
+        requests
+    }
+
+    /// Answer a peer's repair request from our own shreds
+    ///
+    /// Only shreds we hold are returned, from memory or else from the shred
+    /// store; the requester verifies them through `receive_shred` like any
+    /// other shred.
+    pub fn serve_repair(&mut self, request: &RepairRequest) -> Vec<Shred> {
+        self.metrics.repair_requests_served += 1;
+        let mut shreds: Vec<_> = match (self.received_shreds.get(&request.block_id), &self.store) {
+            (Some(block_shreds), _) => request
+                .missing_indices
+                .iter()
+                .filter_map(|position| block_shreds.get(*position).cloned())
+                .collect(),
+            // Blocks no longer in memory are served from the store
+            (None, Some(store)) => request
+                .missing_indices
+                .iter()
+                .filter_map(|position| store.get(&request.block_id, *position).ok().flatten())
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+
+        // Finalized blocks whose shreds are gone are shredded again
+        if shreds.is_empty() {
+            if let Ok(all) = self.shreds_for_block(&request.block_id) {
+                shreds = all
+                    .into_iter()
+                    .filter(|shred| request.missing_indices.contains(&shred.position()))
+                    .collect();
+            }
+        }
+        self.metrics.repair_shreds_served += shreds.len() as u64;
+        shreds
+    }
+
+    /// Derive a relay sampling seed from the slot
+    ///
+    /// Used when no VRF output is available; every node derives the same seed.
+    pub fn slot_seed(slot: Slot) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(RELAY_SEED_DOMAIN);
+        hasher.update(slot.0.to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Select relays by stake-weighted sampling without replacement
+    ///
+    /// Deterministic in `seed` (a VRF output or `slot_seed`), so all nodes
+    /// agree on the relays while the choice stays unpredictable before the
+    /// seed is known. Validators with zero stake are never selected.
+    pub fn select_relays(&self, seed: &[u8; 32], count: usize) -> Vec<ValidatorId> {
+        self.validator_set.sample_by_stake(seed, count)
+    }
+
+    /// Build the propagation tree for a seed, excluding the leader
+    ///
+    /// Each validator forwards to at most `fanout` peers, so dissemination
+    /// costs O(fanout) per validator and takes O(log n) hops.
+    pub fn relay_tree(&self, seed: &[u8; 32], fanout: usize, leader: ValidatorId) -> RelayTree {
+        let mut order: Vec<ValidatorId> = self
+            .select_relays(seed, self.validator_set.len())
+            .into_iter()
+            .filter(|id| *id != leader)
+            .collect();
+
+        // Zero-stake validators still need the block; they go last
+        for v in self.validator_set.sorted_validators() {
+            if v.id != leader && !order.contains(&v.id) {
+                order.push(v.id);
+            }
+        }
+
+        RelayTree {
+            fanout: fanout.max(1),
+            order,
+        }
+    }
+
+    /// Seed for the propagation tree of one shred
+    pub fn shred_seed(slot: Slot, position: ShredIndex) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(Self::slot_seed(slot));
+        hasher.update((position.fec_set as u64).to_le_bytes());
+        hasher.update((position.index as u64).to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Which shreds `me` should forward to which peers
+    ///
+    /// Every shred gets its own tree, so each validator relays a different
+    /// subset and the forwarding load is spread across the network.
+    pub fn forwarding_plan(
+        &self,
+        shreds: &[Shred],
+        fanout: usize,
+        me: ValidatorId,
+    ) -> Vec<(ValidatorId, Vec<ShredIndex>)> {
+        let mut plan: HashMap<ValidatorId, Vec<ShredIndex>> = HashMap::new();
+        for shred in shreds {
+            let seed = Self::shred_seed(shred.slot, shred.position());
+            let tree = self.relay_tree(&seed, fanout, shred.leader);
+            for child in tree.children(&me) {
+                plan.entry(*child).or_default().push(shred.position());
+            }
+        }
+
+        let mut plan: Vec<_> = plan.into_iter().collect();
+        plan.sort_by_key(|(id, _)| *id);
+        plan
+    }
+
+    /// Which shreds the leader sends to which relays
+    ///
+    /// Each shred goes to the root layer of its own relay tree (of the
+    /// configured fan-out), from where `forwarding_plan` takes over.
+    /// Validators are ordered by ID.
+    pub fn broadcast_plan(&self, shreds: &[Shred]) -> Vec<(ValidatorId, Vec<ShredIndex>)> {
+        let mut plan: HashMap<ValidatorId, Vec<ShredIndex>> = HashMap::new();
+        for shred in shreds {
+            let seed = Self::shred_seed(shred.slot, shred.position());
+            let tree = self.relay_tree(&seed, self.config.fanout, shred.leader);
+            for relay in tree.root_layer() {
+                plan.entry(*relay).or_default().push(shred.position());
+            }
+        }
+
+        let mut plan: Vec<_> = plan.into_iter().collect();
+        plan.sort_by_key(|(id, _)| *id);
+        plan
+    }
+
+    /// Expected fraction of stake able to decode a FEC set of `slot`
+    ///
+    /// Each shred travels down its own relay tree and is lost on every hop
+    /// with probability `modeled_loss_pct`; a validator decodes the set once
+    /// it holds `data_shreds` of the set's shreds. The leader counts as reached.
+    pub fn expected_stake_reached(&self, slot: Slot, leader: ValidatorId) -> f64 {
+        let total_stake = self.validator_set.total_stake().as_u64();
+        if total_stake == 0 {
+            return 0.0;
+        }
+
+        let delivery = 1.0 - f64::from(self.config.modeled_loss_pct.min(100)) / 100.0;
+        let ErasureParams { data_shreds, coding_shreds } = self.coder.params();
+        let trees: Vec<RelayTree> = (0..data_shreds + coding_shreds)
+            .map(|index| {
+                let seed = Self::shred_seed(slot, ShredIndex { fec_set: 0, index });
+                self.relay_tree(&seed, self.config.fanout, leader)
+            })
+            .collect();
+
+        let mut reached = 0.0;
+        for validator in self.validator_set.sorted_validators() {
+            let stake = validator.stake.as_u64() as f64;
+            if validator.id == leader {
+                reached += stake;
+                continue;
+            }
+
+            // received[k]: probability of holding exactly k shreds so far
+            let mut received = vec![0.0; trees.len() + 1];
+            received[0] = 1.0;
+            for tree in &trees {
+                let hops = tree.layer_of(&validator.id).map_or(0, |layer| layer + 1);
+                let p = delivery.powi(hops as i32);
+                for k in (0..trees.len()).rev() {
+                    received[k + 1] += received[k] * p;
+                    received[k] *= 1.0 - p;
+                }
+            }
+            reached += stake * received[data_shreds.min(trees.len())..].iter().sum::<f64>();
+        }
+
+        reached / total_stake as f64
+    }
+
+    /// Check that the configured fan-out lets at least the fast-quorum share
+    /// of stake decode blocks under the modeled loss rate
+    ///
+    /// Returns the expected fraction of stake reached.
+    pub fn validate_fanout(&self, slot: Slot, leader: ValidatorId) -> Result<f64, RotorError> {
+        let reached = self.expected_stake_reached(slot, leader);
+        let required_pct = self.fast_quorum_pct;
+        if reached * 100.0 < f64::from(required_pct) {
+            return Err(RotorError::InsufficientFanout {
+                fanout: self.config.fanout,
+                reached_pct: reached * 100.0,
+                required_pct,
+            });
+        }
+        Ok(reached)
+    }
+
+    /// Record that `peer` asked for shreds of a block
+    pub fn note_repair_request(&mut self, peer: ValidatorId, request: &RepairRequest) {
+        let missing = self
+            .lagging
+            .entry(request.block_id)
+            .or_default()
+            .entry(peer)
+            .or_insert_with(|| Some(Vec::new()));
+        if let Some(missing) = missing {
+            missing.extend_from_slice(&request.missing_indices);
+        }
+    }
+
+    /// Record that `peer` hasn't voted on a block, so may lack any of its shreds
+    pub fn note_missing_vote(&mut self, block_id: BlockId, peer: ValidatorId) {
+        self.lagging.entry(block_id).or_default().insert(peer, None);
+    }
+
+    /// Shreds `me` should resend to lagging peers of a block
+    ///
+    /// Each peer only gets the shreds `me` is assigned to send it in their
+    /// relay trees (the root layer when `me` leads), limited to the ones it
+    /// reported missing. Peers with more stake come first, so the most
+    /// valuable votes can still make round 1. Reports are consumed.
+    pub fn retransmission_plan(
+        &mut self,
+        block_id: &BlockId,
+        me: ValidatorId,
+    ) -> Vec<(ValidatorId, Vec<Shred>)> {
+        let Some(lagging) = self.lagging.remove(block_id) else {
+            return Vec::new();
+        };
+        let Some(block_shreds) = self.received_shreds.get(block_id) else {
+            return Vec::new();
+        };
+
+        let trees: Vec<(&Shred, RelayTree)> = block_shreds
+            .shreds()
+            .map(|shred| {
+                let seed = Self::shred_seed(shred.slot, shred.position());
+                (shred, self.relay_tree(&seed, self.config.fanout, shred.leader))
+            })
+            .collect();
+
+        let mut plan: Vec<(ValidatorId, Vec<Shred>)> = lagging
+            .into_iter()
+            .filter(|(peer, _)| *peer != me)
+            .map(|(peer, missing)| {
+                let shreds = trees
+                    .iter()
+                    .filter(|(shred, tree)| {
+                        let assigned = if me == shred.leader {
+                            tree.root_layer().contains(&peer)
+                        } else {
+                            tree.parent(&peer) == Some(me)
+                        };
+                        assigned
+                            && missing
+                                .as_ref()
+                                .is_none_or(|missing| missing.contains(&shred.position()))
+                    })
+                    .map(|(shred, _)| (*shred).clone())
+                    .collect();
+                (peer, shreds)
+            })
+            .filter(|(_, shreds): &(_, Vec<Shred>)| !shreds.is_empty())
+            .collect();
+
+        let stake = |id: &ValidatorId| {
+            self.validator_set
+                .get_validator(id)
+                .map_or(0, |v| v.stake.as_u64())
+        };
+        plan.sort_by_key(|(peer, _)| (std::cmp::Reverse(stake(peer)), *peer));
+        plan
+    }
+
+    /// Blocks of a slot with shreds held, ordered by ID
+    pub fn blocks_in_slot(&self, slot: Slot) -> Vec<BlockId> {
+        let mut block_ids: Vec<BlockId> = self
+            .received_shreds
+            .iter()
+            .filter(|(_, block_shreds)| block_shreds.slot == slot)
+            .map(|(block_id, _)| *block_id)
+            .collect();
+        block_ids.sort();
+        block_ids
+    }
+
+    /// Check if we have a complete block
+    pub fn has_block(&self, block_id: &BlockId) -> bool {
+        self.reconstructed_blocks.contains_key(block_id)
+    }
+
+    /// Get a reconstructed block
+    pub fn get_block(&self, block_id: &BlockId) -> Option<&Block> {
+        self.reconstructed_blocks.get(block_id)
+    }
+
+    /// Body of an archived block
+    pub fn archived_block(&self, block_id: &BlockId) -> Option<&Block> {
+        self.archived_blocks.get(block_id).map(|archived| &archived.block)
+    }
+
+    /// Keep a reconstructed block so `shreds_for_block` can serve it after
+    /// its shreds are pruned
+    ///
+    /// Meant for finalized blocks. The header version, compression and leader
+    /// signatures of the held shreds are kept alongside the block. Returns
+    /// false if the block hasn't been reconstructed.
+    pub fn archive_block(&mut self, block_id: &BlockId) -> bool {
+        let (Some(block), Some(block_shreds)) = (
+            self.reconstructed_blocks.get(block_id),
+            self.received_shreds.get(block_id),
+        ) else {
+            return self.archived_blocks.contains_key(block_id);
+        };
+        let signatures = block_shreds
+            .shreds()
+            .filter(|shred| !shred.signature.is_empty())
+            .map(|shred| (shred.position(), shred.signature.clone()))
+            .collect();
+        self.archived_blocks.insert(
+            *block_id,
+            ArchivedBlock {
+                block: block.clone(),
+                version: block_shreds.version,
+                compression: block_shreds.compression,
+                signatures,
+            },
+        );
+        true
+    }
+
+    /// Shreds of an archived block, re-encoded as the leader sent them
+    ///
+    /// Encoding is deterministic, so the shreds match the originals and carry
+    /// the leader's signatures where those were kept. Fails if the block isn't
+    /// archived or a re-encoded shred no longer verifies against its leader
+    /// key (e.g. the erasure configuration changed since).
+    pub fn shreds_for_block(&self, block_id: &BlockId) -> Result<Vec<Shred>, RotorError> {
+        let archived = self
+            .archived_blocks
+            .get(block_id)
+            .ok_or(RotorError::NotArchived(*block_id))?;
+
+        let serialized = self.serialize_block(&archived.block)?;
+        let payload = match archived.compression {
+            Compression::None => serialized,
+            compression => compression.compress(&serialized)?,
+        };
+        let mut shreds =
+            self.shred_payload(&archived.block, archived.version, archived.compression, &payload)?;
+        for shred in &mut shreds {
+            if let Some(signature) = archived.signatures.get(&shred.position()) {
+                shred.signature = signature.clone();
+                check_signature(&self.leader_keys, self.leader_schedule.as_ref(), shred)?;
             }
         }
+        Ok(shreds)
+    }
 
-        // Deserialize block
-        let block: Block = bincode::deserialize(&reconstructed_data)
-            .map_err(|_| RotorError::ErasureCodingFailed)?;
-
-        // Verify block ID matches
-        if block.id != block_id {
-            return Err(RotorError::InvalidShred);
+    /// Move the local slot forward to `slot`
+    ///
+    /// Shreds are accepted up to `max_future_slots` ahead of it, and with
+    /// `retention_slots` set, state for slots older than the window is pruned.
+    pub fn set_current_slot(&mut self, slot: Slot) {
+        self.current_slot = self.current_slot.max(slot);
+        if let Some(retention) = self.config.retention_slots {
+            let horizon = Slot(self.current_slot.0.saturating_sub(retention));
+            if horizon > self.pruned_before {
+                self.prune_before(horizon);
+            }
         }
+    }
 
-        // Cache reconstructed block
-        self.reconstructed_blocks.insert(block_id, block.clone());
+    /// Current local slot, see `set_current_slot`
+    pub fn current_slot(&self) -> Slot {
+        self.current_slot
+    }
 
-        Ok(Some(block))
+    /// Drop archived blocks for slots before `slot`; returns how many
+    pub fn prune_archive_before(&mut self, slot: Slot) -> usize {
+        let before = self.archived_blocks.len();
+        self.archived_blocks.retain(|_, archived| archived.block.header.slot >= slot);
+        before - self.archived_blocks.len()
     }
 
-    /// Select relays using stake-weighted random selection
+    /// Drop shreds, reconstructed blocks and conflicts for slots before `slot`
     ///
-    /// In production, this should use VRF or deterministic selection
-    /// based on validator stake distribution
-    pub fn select_relays(&self, count: usize) -> Vec<ValidatorId> {
-        // Simplified: return first N honest validators
-        self.validator_set
-            .honest_validators()
-            .take(count)
-            .map(|v| v.id)
-            .collect()
-    }
+    /// Returns the number of blocks dropped. Shreds for pruned slots that
+    /// arrive later are ignored. Archived blocks are kept.
+    pub fn prune_before(&mut self, slot: Slot) -> usize {
+        let before = self.received_shreds.len();
+        self.received_shreds.retain(|_, shreds| shreds.slot >= slot);
+        self.reconstructed_blocks.retain(|_, block| block.header.slot >= slot);
+        self.oversized_blocks.retain(|_, block_slot| *block_slot >= slot);
+        self.conflicts.retain(|conflict| conflict.first.slot >= slot);
+        let received_shreds = &self.received_shreds;
+        self.lagging.retain(|block_id, _| received_shreds.contains_key(block_id));
+        self.pruned_before = self.pruned_before.max(slot);
 
-    /// Check if we have a complete block
-    pub fn has_block(&self, block_id: &BlockId) -> bool {
-        self.reconstructed_blocks.contains_key(block_id)
+        let dropped = before - self.received_shreds.len();
+        if dropped > 0 {
+            tracing::debug!("Pruned {} blocks before {}", dropped, slot);
+        }
+        dropped
     }
 
-    /// Get a reconstructed block
-    pub fn get_block(&self, block_id: &BlockId) -> Option<&Block> {
-        self.reconstructed_blocks.get(block_id)
+    /// Number of blocks with shreds held in memory
+    pub fn tracked_blocks(&self) -> usize {
+        self.received_shreds.len()
     }
 
     /// Simulate network propagation delay (for testing)
@@ -180,31 +1791,76 @@ impl Rotor {
     }
 }
 
+/// Public keys of the validators in `validator_set` that have one
+fn public_keys(validator_set: &ValidatorSet) -> HashMap<ValidatorId, VerifyingKey> {
+    validator_set
+        .sorted_validators()
+        .into_iter()
+        .filter_map(|validator| Some((validator.id, validator.pubkey?)))
+        .collect()
+}
+
+/// Reject a shred that isn't signed by the scheduled leader of its slot
+///
+/// The leader is only checked against a schedule if there is one. Without
+/// any leader keys, as in unkeyed test networks, shreds pass unsigned; once
+/// keys are configured, the leader must have one and the shred must verify
+/// against it.
+fn check_signature(
+    keys: &HashMap<ValidatorId, VerifyingKey>,
+    schedule: Option<&(EpochValidatorSets, u64)>,
+    shred: &Shred,
+) -> Result<(), RotorError> {
+    if let Some((epochs, leader_window)) = schedule {
+        let expected = epochs.leader_of(shred.slot, *leader_window);
+        if shred.leader != expected {
+            return Err(RotorError::WrongLeader { slot: shred.slot, expected, got: shred.leader });
+        }
+    }
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let key = keys.get(&shred.leader).ok_or(RotorError::UnknownLeader(shred.leader))?;
+    if !shred.verify(key) {
+        return Err(RotorError::InvalidSignature {
+            block_id: shred.block_id,
+            fec_set: shred.fec_set,
+            index: shred.index,
+        });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::epoch::EpochSchedule;
+    use crate::stake::StakeDistribution;
+    use std::collections::HashSet;
+
+    fn transaction(nonce: u64, payload: Vec<u8>) -> Transaction {
+        Transaction::new(&SigningKey::from_bytes(&[1u8; 32]), nonce, payload)
+    }
 
     fn create_test_block() -> Block {
-        let block_id = BlockId::new([1u8; 32]);
-        Block {
-            id: block_id,
-            slot: Slot(0),
-            parent: None,
-            leader: ValidatorId(0),
-            transactions: vec![vec![1, 2, 3, 4]],
-            timestamp: 1000,
-        }
+        let transactions = vec![transaction(0, vec![1, 2, 3, 4])];
+        let mut block = Block::new(Slot(0), None, ValidatorId::Index(0), transactions, 1000);
+        block.id = BlockId::new([1u8; 32]);
+        block
     }
 
     fn create_test_validator_set() -> ValidatorSet {
         let mut vset = ValidatorSet::new();
         for i in 0..5 {
             vset.add_validator(ValidatorConfig {
-                id: ValidatorId(i),
+                id: ValidatorId::Index(i),
                 stake: StakeWeight(100),
                 is_byzantine: false,
                 is_offline: false,
-            });
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
         }
         vset
     }
@@ -231,7 +1887,7 @@ mod tests {
         assert!(rotor.has_block(&block_id));
         let reconstructed = rotor.get_block(&block_id).unwrap();
         assert_eq!(reconstructed.id, block.id);
-        assert_eq!(reconstructed.slot, block.slot);
+        assert_eq!(reconstructed.header.slot, block.header.slot);
     }
 
     #[test]
@@ -240,14 +1896,13 @@ mod tests {
         let mut rotor = Rotor::new(vset);
 
         let block = create_test_block();
-        let block_id = block.id;
         let shreds = rotor.encode_block(&block).unwrap();
         let total_shreds = shreds.len();
 
         // Test the 80% threshold logic
         // In a real erasure coding implementation, 80% of shreds would be sufficient
         // Our simplified implementation stores shreds and checks the threshold
-        let min_shreds = (total_shreds * 80 + 99) / 100;
+        let min_shreds = (total_shreds * 80).div_ceil(100);
 
         // Receive shreds one by one
         let mut received_count = 0;
@@ -275,11 +1930,854 @@ mod tests {
         let vset = create_test_validator_set();
         let rotor = Rotor::new(vset);
 
-        let relays = rotor.select_relays(3);
+        let seed = Rotor::slot_seed(Slot(0));
+        let relays = rotor.select_relays(&seed, 3);
         assert_eq!(relays.len(), 3);
 
         // All relays should be unique
         let unique: HashSet<_> = relays.iter().collect();
         assert_eq!(unique.len(), relays.len());
+
+        // Same seed, same relays
+        assert_eq!(rotor.select_relays(&seed, 3), relays);
+        assert_eq!(rotor.select_relays(&seed, 10).len(), 5);
+    }
+
+    #[test]
+    fn test_relay_selection_is_stake_weighted() {
+        let vset = ValidatorSet::with_stakes([1000, 10, 10, 0].map(StakeWeight));
+        let rotor = Rotor::new(vset);
+
+        let mut whale_first = 0;
+        for slot in 0..100 {
+            let relays = rotor.select_relays(&Rotor::slot_seed(Slot(slot)), 4);
+            assert!(!relays.contains(&ValidatorId::Index(3)));
+            if relays[0] == ValidatorId::Index(0) {
+                whale_first += 1;
+            }
+        }
+        assert!(whale_first > 90);
+    }
+
+    #[test]
+    fn test_forged_shreds_rejected() {
+        let vset = create_test_validator_set();
+        let leader_key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut rotor = Rotor::new(vset);
+        rotor.register_leader_key(ValidatorId::Index(0), leader_key.verifying_key());
+
+        let block = create_test_block();
+        let shreds = rotor.encode_block_signed(&block, &leader_key).unwrap();
+
+        // Tampered payload
+        let mut forged = shreds[0].clone();
+        forged.data = [&forged.data[..], &[0xff]].concat().into();
+        assert!(matches!(
+            rotor.receive_shred(forged),
+            Err(RotorError::InvalidSignature { index: 0, .. })
+        ));
+
+        // Signed by someone else
+        let mut forged = shreds[1].clone();
+        forged.sign(&SigningKey::from_bytes(&[8u8; 32]));
+        assert!(rotor.receive_shred(forged).is_err());
+
+        // Once keys are known, unsigned shreds and leaders without a key are refused
+        let mut unsigned = shreds[2].clone();
+        unsigned.signature.clear();
+        assert!(matches!(
+            rotor.receive_shred(unsigned),
+            Err(RotorError::InvalidSignature { index: 2, .. })
+        ));
+        let mut keyless = shreds[3].clone();
+        keyless.leader = ValidatorId::Index(1);
+        assert!(matches!(
+            rotor.receive_shred(keyless),
+            Err(RotorError::UnknownLeader(ValidatorId::Index(1)))
+        ));
+
+        // With a schedule, validator 0 may only send shreds for the slots it leads
+        let epochs = EpochValidatorSets::new(EpochSchedule::default(), rotor.validator_set.clone());
+        rotor.set_leader_schedule(epochs, 1);
+        let mut next = create_test_block();
+        next.header.slot = Slot(1);
+        let shred = rotor.encode_block_signed(&next, &leader_key).unwrap().remove(0);
+        assert!(matches!(
+            rotor.receive_shred(shred),
+            Err(RotorError::WrongLeader { slot: Slot(1), expected: ValidatorId::Index(1), .. })
+        ));
+
+        // Reconstruction may fail until every shred is in; only signatures matter here
+        for shred in shreds {
+            let result = rotor.receive_shred(shred);
+            assert!(!matches!(result, Err(RotorError::InvalidSignature { .. })));
+        }
+        assert!(rotor.has_block(&block.id));
+    }
+
+    #[test]
+    fn test_verified_shred_replaces_unverified_header() {
+        let vset = create_test_validator_set();
+        let leader_key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut rotor = Rotor::new(vset);
+
+        let block = create_test_block();
+        let shreds = rotor.encode_block_signed(&block, &leader_key).unwrap();
+
+        // Before any key is known, a forged header claims the block
+        let mut forged = shreds[0].clone();
+        forged.slot = Slot(3);
+        rotor.receive_shred(forged).unwrap();
+        assert_eq!(rotor.tracked_blocks(), 1);
+
+        // The leader's signed shreds take the block back
+        rotor.register_leader_key(ValidatorId::Index(0), leader_key.verifying_key());
+        for shred in shreds {
+            let result = rotor.receive_shred(shred);
+            assert!(!matches!(result, Err(RotorError::InconsistentHeader { .. })));
+        }
+        assert!(rotor.has_block(&block.id));
+        assert_eq!(rotor.get_block(&block.id).unwrap().header.slot, Slot(0));
+    }
+
+    #[test]
+    fn test_relay_tree_layers() {
+        let vset = ValidatorSet::with_stakes((0..15).map(|i| StakeWeight(100 + i)));
+        let rotor = Rotor::new(vset);
+        let tree = rotor.relay_tree(&Rotor::slot_seed(Slot(0)), 2, ValidatorId::Index(0));
+
+        // 14 non-leaders with fan-out 2: layers of 2, 4, 8
+        let sizes: Vec<_> = tree.layers().iter().map(|l| l.len()).collect();
+        assert_eq!(sizes, vec![2, 4, 8]);
+        assert!(!tree.layers().concat().contains(&ValidatorId::Index(0)));
+
+        // Every non-root validator has exactly one parent that lists it as a child
+        for layer in &tree.layers()[1..] {
+            for id in *layer {
+                let parent = tree.parent(id).unwrap();
+                assert!(tree.children(&parent).contains(id));
+            }
+        }
+        for id in tree.root_layer() {
+            assert_eq!(tree.parent(id), None);
+        }
+    }
+
+    #[test]
+    fn test_forwarding_plan_covers_everyone() {
+        let vset = create_test_validator_set();
+        let rotor = Rotor::new(vset);
+        let shreds = rotor.encode_block(&create_test_block()).unwrap();
+
+        for shred in &shreds {
+            let seed = Rotor::shred_seed(shred.slot, shred.position());
+            let tree = rotor.relay_tree(&seed, 2, shred.leader);
+            let mut reached: HashSet<_> = tree.root_layer().iter().copied().collect();
+            for id in (1..5).map(ValidatorId::Index) {
+                let plan = rotor.forwarding_plan(std::slice::from_ref(shred), 2, id);
+                reached.extend(plan.into_iter().map(|(peer, _)| peer));
+            }
+            assert_eq!(reached.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_shred_repair() {
+        let vset = create_test_validator_set();
+        let block = create_test_block();
+        let config = RotorConfig {
+            data_shreds: 5,
+            coding_shreds: 0,
+            ..RotorConfig::default()
+        };
+        let leader = Rotor::with_config(vset.clone(), config);
+        let shreds = leader.encode_block(&block).unwrap();
+
+        // Shreds 1 and 3 are dropped in transit
+        let mut rotor = Rotor::with_config(vset, config);
+        for index in [0, 2, 4] {
+            assert!(rotor.receive_shred(shreds[index].clone()).unwrap().is_none());
+        }
+
+        let request = rotor.repair_request(&block.id).unwrap();
+        let missing: Vec<_> = request
+            .missing_indices
+            .iter()
+            .map(|p| (p.fec_set, p.index))
+            .collect();
+        assert_eq!(missing, vec![(0, 1), (0, 3)]);
+        assert_eq!(rotor.pending_repairs(), vec![request.clone()]);
+
+        // A peer holding the full block answers the request
+        let mut peer = Rotor::with_config(create_test_validator_set(), config);
+        for shred in &shreds {
+            let _ = peer.receive_shred(shred.clone());
+        }
+        let repaired = peer.serve_repair(&request);
+        assert_eq!(repaired.len(), 2);
+
+        let mut result = None;
+        for shred in repaired {
+            result = rotor.receive_shred(shred).unwrap();
+        }
+        assert_eq!(result.unwrap().id, block.id);
+        assert!(rotor.repair_request(&block.id).is_none());
+        assert!(rotor.pending_repairs().is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_shred_detected() {
+        let vset = create_test_validator_set();
+        let block = create_test_block();
+        let mut rotor = Rotor::new(vset);
+        let shreds = rotor.encode_block(&block).unwrap();
+
+        rotor.receive_shred(shreds[0].clone()).unwrap();
+
+        // An identical copy is harmless
+        rotor.receive_shred(shreds[0].clone()).unwrap();
+        assert!(rotor.conflicts().is_empty());
+
+        let mut forged = shreds[0].clone();
+        forged.data = vec![0xff; forged.data.len()].into();
+        assert!(matches!(
+            rotor.receive_shred(forged.clone()),
+            Err(RotorError::ConflictingShred { index: 0, .. })
+        ));
+
+        let conflicts = rotor.take_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].first.data, shreds[0].data);
+        assert_eq!(conflicts[0].second.data, forged.data);
+        assert!(rotor.conflicts().is_empty());
+
+        // The original copy is kept and the block still reconstructs
+        let mut result = None;
+        for shred in &shreds[1..] {
+            result = rotor.receive_shred(shred.clone()).unwrap();
+        }
+        assert_eq!(result.unwrap().id, block.id);
+    }
+
+    #[test]
+    fn test_conflicts_bounded_per_block_and_pruned() {
+        let vset = create_test_validator_set();
+        let mut rotor = Rotor::new(vset);
+        for slot in 0..2 {
+            let shreds = rotor.encode_block(&block_in_slot(slot)).unwrap();
+            for shred in &shreds[..2] {
+                rotor.receive_shred(shred.clone()).unwrap();
+                // Every further conflict on the block adds no evidence
+                for byte in 0xfd..=0xff {
+                    let mut forged = shred.clone();
+                    forged.data = vec![byte; forged.data.len()].into();
+                    assert!(rotor.receive_shred(forged).is_err());
+                }
+            }
+        }
+        assert_eq!(rotor.conflicts().len(), 2);
+
+        rotor.prune_before(Slot(1));
+        assert_eq!(rotor.conflicts().len(), 1);
+        assert_eq!(rotor.conflicts()[0].first.slot, Slot(1));
+    }
+
+    #[test]
+    fn test_data_coding_ratio() {
+        let config = RotorConfig {
+            data_shreds: 4,
+            coding_shreds: 8,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        let block = create_test_block();
+
+        let shreds = rotor.encode_block(&block).unwrap();
+        assert_eq!(shreds.len(), 12);
+        assert_eq!(shreds.iter().filter(|s| s.is_coding()).count(), 8);
+        assert!(shreds.iter().all(|s| s.total_shreds == 12 && s.data_shreds == 4));
+
+        // Coding shreds alone cover every data piece
+        let mut result = None;
+        for shred in shreds.into_iter().filter(|s| s.is_coding()).take(4) {
+            result = rotor.receive_shred(shred).unwrap();
+        }
+        assert_eq!(result.unwrap().id, block.id);
+    }
+
+    #[test]
+    fn test_large_block_uses_multiple_fec_sets() {
+        let config = RotorConfig {
+            data_shreds: 4,
+            coding_shreds: 2,
+            max_shred_payload: 64,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        let mut block = create_test_block();
+        block.body.transactions = vec![transaction(0, vec![7u8; 1000])];
+
+        let shreds = rotor.encode_block(&block).unwrap();
+        let fec_set_count = shreds[0].fec_set_count;
+        assert!(fec_set_count > 1);
+        assert_eq!(shreds.len(), fec_set_count * 6);
+        assert!(shreds.iter().all(|s| s.data.len() <= 64));
+
+        // Only the data shreds of all but the last set: not enough yet
+        let (last, rest): (Vec<_>, Vec<_>) = shreds
+            .into_iter()
+            .filter(|s| !s.is_coding())
+            .partition(|s| s.fec_set == fec_set_count - 1);
+        for shred in rest {
+            assert!(rotor.receive_shred(shred).unwrap().is_none());
+        }
+        assert_eq!(
+            rotor.fec_set_progress(&block.id),
+            Some((fec_set_count - 1, fec_set_count))
+        );
+
+        let mut result = None;
+        for shred in last {
+            result = rotor.receive_shred(shred).unwrap();
+        }
+        assert_eq!(result.unwrap().body.transactions, block.body.transactions);
+    }
+
+    fn block_in_slot(slot: u64) -> Block {
+        let mut block = create_test_block();
+        block.header.slot = Slot(slot);
+        block.id = BlockId::new([slot as u8 + 1; 32]);
+        block
+    }
+
+    #[test]
+    fn test_prune_before() {
+        let config = RotorConfig {
+            retention_slots: None,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        for slot in 0..4 {
+            for shred in rotor.encode_block(&block_in_slot(slot)).unwrap() {
+                rotor.receive_shred(shred).unwrap();
+            }
+        }
+        assert_eq!(rotor.tracked_blocks(), 4);
+
+        assert_eq!(rotor.prune_before(Slot(2)), 2);
+        assert!(!rotor.has_block(&block_in_slot(1).id));
+        assert!(rotor.has_block(&block_in_slot(2).id));
+
+        // Late shreds for a pruned slot are ignored
+        let late = rotor.encode_block(&block_in_slot(0)).unwrap();
+        assert!(rotor.receive_shred(late[0].clone()).unwrap().is_none());
+        assert_eq!(rotor.tracked_blocks(), 2);
+    }
+
+    #[test]
+    fn test_retention_window() {
+        let config = RotorConfig {
+            retention_slots: Some(2),
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        for slot in 0..10 {
+            rotor.set_current_slot(Slot(slot));
+            for shred in rotor.encode_block(&block_in_slot(slot)).unwrap() {
+                rotor.receive_shred(shred).unwrap();
+            }
+            assert!(rotor.tracked_blocks() <= 3);
+        }
+        assert!(rotor.has_block(&block_in_slot(9).id));
+        assert!(!rotor.has_block(&block_in_slot(6).id));
+    }
+
+    #[test]
+    fn test_future_shreds_bounded_by_local_slot() {
+        let config = RotorConfig {
+            retention_slots: Some(2),
+            max_future_slots: 4,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        for shred in rotor.encode_block(&block_in_slot(1)).unwrap() {
+            rotor.receive_shred(shred).unwrap();
+        }
+
+        // A shred far ahead is refused rather than pruning the window
+        let far = rotor.encode_block(&block_in_slot(9)).unwrap().remove(0);
+        assert!(matches!(
+            rotor.receive_shred(far.clone()),
+            Err(RotorError::SlotTooFarAhead { slot: Slot(9), current: Slot(0) })
+        ));
+        assert!(rotor.has_block(&block_in_slot(1).id));
+
+        // Within reach of the local slot it is taken, and old slots age out
+        rotor.set_current_slot(Slot(5));
+        assert!(rotor.receive_shred(far).is_ok());
+        assert!(!rotor.has_block(&block_in_slot(1).id));
+        assert_eq!(rotor.tracked_blocks(), 1);
+    }
+
+    #[test]
+    fn test_rotor_metrics() {
+        let config = RotorConfig {
+            data_shreds: 4,
+            coding_shreds: 0,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        let block = create_test_block();
+        let shreds = rotor.encode_block(&block).unwrap();
+
+        rotor.receive_shred(shreds[0].clone()).unwrap();
+        rotor.receive_shred(shreds[0].clone()).unwrap();
+        let mut bad = shreds[1].clone();
+        bad.data_shreds = 0;
+        assert!(rotor.receive_shred(bad).is_err());
+        assert_eq!(rotor.pending_repairs().len(), 1);
+        for shred in &shreds[1..] {
+            rotor.receive_shred(shred.clone()).unwrap();
+        }
+
+        let metrics = rotor.metrics();
+        assert_eq!(metrics.shreds_received, 6);
+        assert_eq!(metrics.shreds_duplicate, 1);
+        assert_eq!(metrics.shreds_invalid, 1);
+        assert_eq!(metrics.repair_requests_sent, 1);
+        assert_eq!(metrics.blocks_reconstructed, 1);
+        assert!(metrics.mean_reconstruction_time().is_some());
+        assert!(rotor.reconstruction_time(&block.id).is_some());
+    }
+
+    #[test]
+    fn test_block_size_limit() {
+        let config = RotorConfig {
+            data_shreds: 4,
+            coding_shreds: 4,
+            max_block_size: 512,
+            ..RotorConfig::default()
+        };
+        let rotor = Rotor::with_config(create_test_validator_set(), config);
+        let mut block = create_test_block();
+        block.body.transactions = vec![transaction(0, vec![0u8; 1000])];
+        assert!(matches!(
+            rotor.encode_block(&block),
+            Err(RotorError::BlockTooLarge { max: 512, .. })
+        ));
+
+        // A malicious leader shreds the same block without the limit
+        let leader = Rotor::with_config(
+            create_test_validator_set(),
+            RotorConfig {
+                max_block_size: usize::MAX,
+                ..config
+            },
+        );
+        let shreds = leader.encode_block(&block).unwrap();
+
+        let mut receiver = Rotor::with_config(create_test_validator_set(), config);
+        let results: Vec<_> = shreds
+            .into_iter()
+            .map(|shred| receiver.receive_shred(shred))
+            .collect();
+        assert!(results
+            .iter()
+            .any(|r| matches!(r, Err(RotorError::BlockTooLarge { .. }))));
+        assert!(results.iter().all(|r| !matches!(r, Ok(Some(_)))));
+        assert_eq!(receiver.tracked_blocks(), 0);
+    }
+
+    #[test]
+    fn test_streaming_regions() {
+        let config = RotorConfig {
+            data_shreds: 4,
+            coding_shreds: 4,
+            max_shred_payload: 128,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+        let mut block = create_test_block();
+        block.header.parent = Some(BlockId::new([7u8; 32]));
+        block.body.transactions = (0..20u8).map(|i| transaction(i.into(), vec![i; 30])).collect();
+
+        let shreds = rotor.encode_block(&block).unwrap();
+        let (first_set, rest): (Vec<_>, Vec<_>) =
+            shreds.into_iter().partition(|s| s.fec_set == 0);
+        assert!(!rest.is_empty());
+
+        // The first FEC set alone yields the header and a few transactions
+        for shred in first_set {
+            assert!(rotor.receive_shred(shred).unwrap().is_none());
+        }
+        let regions = rotor.take_decoded_regions(&block.id).unwrap();
+        assert_eq!(
+            regions[0],
+            BlockRegion::Header {
+                block_id: block.id,
+                header: block.header.clone(),
+            }
+        );
+        let BlockRegion::Transactions { first_index: 0, transactions: early } = &regions[1] else {
+            panic!("expected transactions after the header");
+        };
+        assert!(!early.is_empty() && early.len() < 20);
+        assert!(rotor.take_decoded_regions(&block.id).unwrap().is_empty());
+
+        // The remaining transactions follow once the block completes
+        for shred in rest {
+            rotor.receive_shred(shred).unwrap();
+        }
+        let regions = rotor.take_decoded_regions(&block.id).unwrap();
+        let [BlockRegion::Transactions { first_index, transactions }] = regions.as_slice() else {
+            panic!("expected one transaction batch");
+        };
+        assert_eq!(*first_index, early.len());
+        let streamed: Vec<_> = early.iter().chain(transactions).cloned().collect();
+        assert_eq!(streamed, block.body.transactions);
+    }
+
+    #[test]
+    fn test_compressed_blocks() {
+        let mut block = create_test_block();
+        block.body.transactions = (0..4).map(|i| transaction(i, vec![0u8; 8000])).collect();
+
+        let plain = Rotor::new(create_test_validator_set()).encode_block(&block).unwrap();
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            let config = RotorConfig {
+                compression,
+                ..RotorConfig::default()
+            };
+            let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+            let shreds = rotor.encode_block(&block).unwrap();
+            let bytes = |shreds: &[Shred]| shreds.iter().map(|s| s.data.len()).sum::<usize>();
+            assert!(bytes(&shreds) * 10 < bytes(&plain));
+            assert!(shreds.iter().all(|s| s.compression == compression));
+
+            let mut result = None;
+            for shred in shreds {
+                result = result.or(rotor.receive_shred(shred).unwrap());
+            }
+            assert_eq!(result.unwrap().body.transactions, block.body.transactions);
+        }
+
+        // Incompressible blocks go out uncompressed
+        let config = RotorConfig {
+            compression: Compression::Zstd,
+            ..RotorConfig::default()
+        };
+        let rotor = Rotor::with_config(create_test_validator_set(), config);
+        // Fields with runs of equal bytes would compress, so they're hashes too
+        let noise = |tag: &[u8]| u64::from_le_bytes(Sha256::digest(tag)[..8].try_into().unwrap());
+        let payload = (0..32u8).flat_map(|i| Sha256::digest([i])).collect();
+        let transactions = vec![transaction(noise(b"nonce"), payload)];
+        let parent = Some(BlockId::new(Sha256::digest(b"parent").into()));
+        let (slot, timestamp) = (Slot(noise(b"slot")), noise(b"timestamp"));
+        let mut random = Block::new(slot, parent, ValidatorId::Index(0), transactions, timestamp)
+            .at_position(noise(b"height"), Epoch(noise(b"epoch")));
+        random.header.sign(&SigningKey::from_bytes(&[2u8; 32]), &[0u8; 32]);
+        let shreds = rotor.encode_block(&random).unwrap();
+        assert_eq!(shreds[0].compression, Compression::None);
+    }
+
+    #[test]
+    fn test_partial_block_survives_restart() {
+        use crate::shred_store::FileShredStore;
+
+        let dir = std::env::temp_dir()
+            .join(format!("alpenglow-rotor-store-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let block = create_test_block();
+        let shreds = Rotor::new(create_test_validator_set()).encode_block(&block).unwrap();
+        let open = || {
+            Rotor::new(create_test_validator_set())
+                .with_store(Box::new(FileShredStore::open(&dir).unwrap()))
+        };
+
+        // Half of the data shreds arrive before a restart
+        {
+            let mut rotor = open();
+            for shred in &shreds[..16] {
+                assert!(rotor.receive_shred(shred.clone()).unwrap().is_none());
+            }
+        }
+
+        let mut rotor = open();
+        assert!(rotor.restore().unwrap().is_empty());
+        assert_eq!(rotor.tracked_blocks(), 1);
+        let mut result = None;
+        for shred in &shreds[16..32] {
+            result = result.or(rotor.receive_shred(shred.clone()).unwrap());
+        }
+        assert_eq!(result.unwrap().id, block.id);
+
+        // Pruned from memory, the block is still served from the store
+        rotor.prune_before(Slot(1));
+        let request = RepairRequest {
+            block_id: block.id,
+            missing_indices: vec![ShredIndex { fec_set: 0, index: 3 }],
+        };
+        assert_eq!(rotor.serve_repair(&request)[0].data, shreds[3].data);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_broadcast_plan() {
+        let vset = ValidatorSet::with_stakes((0..250).map(|i| StakeWeight(100 + i)));
+        let rotor = Rotor::new(vset);
+        let shreds = &rotor.encode_block(&create_test_block()).unwrap()[..4];
+        let plan = rotor.broadcast_plan(shreds);
+
+        // Every shred goes to exactly its tree's root layer, never the leader
+        for shred in shreds {
+            let seed = Rotor::shred_seed(shred.slot, shred.position());
+            let mut expected = rotor
+                .relay_tree(&seed, DEFAULT_FANOUT, shred.leader)
+                .root_layer()
+                .to_vec();
+            expected.sort();
+            let recipients: Vec<ValidatorId> = plan
+                .iter()
+                .filter(|(_, positions)| positions.contains(&shred.position()))
+                .map(|(id, _)| *id)
+                .collect();
+            assert_eq!(recipients, expected);
+            assert_eq!(recipients.len(), DEFAULT_FANOUT);
+        }
+        assert!(plan.iter().all(|(id, _)| *id != ValidatorId::Index(0)));
+        assert!(plan.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_parallel_batch_decoding() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let mut block = create_test_block();
+        let transactions = (0..4u8).map(|i| transaction(i.into(), vec![i; 20_000]));
+        block.body.transactions = transactions.collect();
+
+        let mut results = Vec::new();
+        for decode_workers in [1, 4] {
+            let config = RotorConfig {
+                decode_workers,
+                ..RotorConfig::default()
+            };
+            let mut rotor = Rotor::with_config(create_test_validator_set(), config);
+            rotor.register_leader_key(ValidatorId::Index(0), key.verifying_key());
+
+            let mut shreds = rotor.encode_block_signed(&block, &key).unwrap();
+            assert!(shreds.iter().map(|s| s.fec_set).max().unwrap() >= 2);
+            // Set 0 must be rebuilt from coding shreds; one later shred is forged
+            shreds.retain(|s| s.fec_set != 0 || s.is_coding());
+            let forged = shreds.last_mut().unwrap();
+            let mut data = forged.data.to_vec();
+            data[0] ^= 1;
+            forged.data = data.into();
+
+            let (blocks, errors) = rotor.receive_shreds(shreds);
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(errors[0], RotorError::InvalidSignature { .. }));
+            assert_eq!(rotor.metrics().shreds_invalid, 1);
+            results.push(blocks);
+        }
+
+        for blocks in results {
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0].body.transactions, block.body.transactions);
+        }
+    }
+
+    #[test]
+    fn test_fanout_validation() {
+        let vset = StakeDistribution::Equal(100).validator_set(40, 0);
+        let rotor = |fanout, modeled_loss_pct| {
+            let config = RotorConfig {
+                fanout,
+                modeled_loss_pct,
+                ..RotorConfig::default()
+            };
+            Rotor::with_config(vset.clone(), config)
+        };
+
+        // Without loss any fan-out reaches everyone
+        assert_eq!(rotor(1, 0).expected_stake_reached(Slot(0), ValidatorId::Index(0)), 1.0);
+
+        // A wide tree tolerates heavy loss thanks to the coding shreds
+        assert!(rotor(40, 30).validate_fanout(Slot(0), ValidatorId::Index(0)).unwrap() > 0.9);
+
+        // A chain compounds the loss over up to 39 hops
+        let narrow = rotor(1, 10);
+        assert_eq!(narrow.relay_tree(&[0u8; 32], 1, ValidatorId::Index(0)).depth(), 39);
+        assert!(matches!(
+            narrow.validate_fanout(Slot(0), ValidatorId::Index(0)),
+            Err(RotorError::InsufficientFanout { fanout: 1, .. })
+        ));
+
+        // Relays are picked by stake, so whales sit near the root and lose less
+        let whales = StakeDistribution::Whales { whales: 4, whale_pct: 80, total: 40_000 };
+        let skewed = Rotor::with_config(whales.validator_set(40, 5), narrow.config);
+        let leader = ValidatorId::Index(0);
+        assert!(
+            skewed.expected_stake_reached(Slot(0), leader)
+                > narrow.expected_stake_reached(Slot(0), leader)
+        );
+    }
+
+    #[test]
+    fn test_rotor_events() {
+        let mut rotor = Rotor::new(create_test_validator_set());
+        let events = rotor.subscribe();
+        let dropped = rotor.subscribe();
+        drop(dropped);
+
+        let complete = create_test_block();
+        let stalled = block_in_slot(1);
+        let partial = rotor.encode_block(&stalled).unwrap().remove(0);
+        rotor.receive_shred(partial).unwrap();
+        for shred in rotor.encode_block(&complete).unwrap() {
+            rotor.receive_shred(shred).unwrap();
+        }
+
+        assert!(matches!(
+            events.try_recv(),
+            Ok(RotorEvent::BlockReconstructed { block_id, slot: Slot(0), .. })
+                if block_id == complete.id
+        ));
+        assert_eq!(rotor.subscribers.len(), 1);
+
+        assert!(rotor.check_stalled(Duration::from_secs(60)).is_empty());
+        assert_eq!(rotor.check_stalled(Duration::ZERO), vec![stalled.id]);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(RotorEvent::ReconstructionStalled { decoded_sets: 0, total_sets: 1, .. })
+        ));
+        // Reported only once
+        assert!(rotor.check_stalled(Duration::ZERO).is_empty());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_inconsistent_shreds_rejected() {
+        let mut rotor = Rotor::new(create_test_validator_set());
+        let block = create_test_block();
+        let shreds = rotor.encode_block(&block).unwrap();
+        rotor.receive_shred(shreds[0].clone()).unwrap();
+
+        // Absurd counts are refused before anything is allocated for them
+        let mut huge = shreds[1].clone();
+        huge.fec_set_count = 1_000_000_000;
+        assert!(matches!(rotor.receive_shred(huge), Err(RotorError::InvalidShred)));
+        let mut out_of_range = shreds[1].clone();
+        out_of_range.index = out_of_range.total_shreds;
+        assert!(matches!(rotor.receive_shred(out_of_range), Err(RotorError::InvalidShred)));
+
+        // Later shreds must agree with the block's first shred
+        let mut other_count = shreds[1].clone();
+        other_count.fec_set_count = 2;
+        assert!(matches!(
+            rotor.receive_shred(other_count),
+            Err(RotorError::InconsistentHeader { field: "fec_set_count", .. })
+        ));
+        let mut other_leader = shreds[1].clone();
+        other_leader.leader = ValidatorId::Index(3);
+        assert!(matches!(
+            rotor.receive_shred(other_leader),
+            Err(RotorError::InconsistentHeader { field: "leader", .. })
+        ));
+        assert_eq!(rotor.metrics().shreds_invalid, 4);
+
+        let mut result = None;
+        for shred in shreds.into_iter().skip(1) {
+            result = result.or(rotor.receive_shred(shred).unwrap());
+        }
+        assert_eq!(result.unwrap().id, block.id);
+    }
+
+    #[test]
+    fn test_retransmission_plan() {
+        let vset = ValidatorSet::with_stakes((0..12).map(|i| StakeWeight(100 + i)));
+        let config = RotorConfig {
+            fanout: 3,
+            ..RotorConfig::default()
+        };
+        let mut rotor = Rotor::with_config(vset, config);
+        let block = create_test_block();
+        let (blocks, _) = rotor.receive_shreds(rotor.encode_block(&block).unwrap());
+        assert_eq!(blocks.len(), 1);
+
+        // As leader: a silent voter gets every shred whose root layer it is in
+        rotor.note_missing_vote(block.id, ValidatorId::Index(4));
+        rotor.note_missing_vote(block.id, ValidatorId::Index(9));
+        let plan = rotor.retransmission_plan(&block.id, ValidatorId::Index(0));
+        assert_eq!(plan[0].0, ValidatorId::Index(9), "higher stake first");
+        for (peer, shreds) in &plan {
+            for shred in shreds {
+                let seed = Rotor::shred_seed(shred.slot, shred.position());
+                let tree = rotor.relay_tree(&seed, 3, ValidatorId::Index(0));
+                assert!(tree.root_layer().contains(peer));
+            }
+        }
+        assert!(rotor.retransmission_plan(&block.id, ValidatorId::Index(0)).is_empty());
+
+        // As a relay: only the requested shreds this node forwards to the peer
+        let position = ShredIndex { fec_set: 0, index: 5 };
+        let seed = Rotor::shred_seed(Slot(0), position);
+        let tree = rotor.relay_tree(&seed, 3, ValidatorId::Index(0));
+        let relay = tree.root_layer()[0];
+        let child = tree.children(&relay)[0];
+        let request = RepairRequest {
+            block_id: block.id,
+            missing_indices: vec![position],
+        };
+        rotor.note_repair_request(child, &request);
+        let plan = rotor.retransmission_plan(&block.id, relay);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].0, child);
+        assert_eq!(plan[0].1.len(), 1);
+        assert_eq!(plan[0].1[0].position(), position);
+    }
+
+    #[test]
+    fn test_archived_block_served_after_prune() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let config = RotorConfig {
+            compression: Compression::Zstd,
+            ..RotorConfig::default()
+        };
+        let vset = create_test_validator_set();
+        let mut rotor = Rotor::with_config(vset, config);
+        rotor.register_leader_key(ValidatorId::Index(0), key.verifying_key());
+        let mut block = block_in_slot(3);
+        block.body.transactions = vec![transaction(0, vec![9u8; 5000])];
+        let shreds = rotor.encode_block_signed(&block, &key).unwrap();
+        assert_eq!(shreds[0].compression, Compression::Zstd);
+        let (blocks, _) = rotor.receive_shreds(shreds.clone());
+        assert_eq!(blocks.len(), 1);
+
+        assert!(matches!(
+            rotor.shreds_for_block(&block.id),
+            Err(RotorError::NotArchived(_))
+        ));
+        assert!(rotor.archive_block(&block.id));
+        rotor.prune_before(Slot(10));
+        assert!(!rotor.has_block(&block.id));
+
+        let request = RepairRequest {
+            block_id: block.id,
+            missing_indices: shreds.iter().map(Shred::position).collect(),
+        };
+        let served = rotor.serve_repair(&request);
+        assert_eq!(served.len(), shreds.len());
+        for (served, original) in served.iter().zip(&shreds) {
+            assert_eq!(served.data, original.data);
+            assert_eq!(served.signature, original.signature);
+        }
+
+        // A lagging node accepts the re-shredded block
+        let mut peer = Rotor::with_config(create_test_validator_set(), config);
+        peer.register_leader_key(ValidatorId::Index(0), key.verifying_key());
+        let (blocks, errors) = peer.receive_shreds(served);
+        assert!(errors.is_empty());
+        assert_eq!(blocks[0].id, block.id);
     }
-}
\ No newline at end of file
+}
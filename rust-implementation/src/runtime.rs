@@ -0,0 +1,360 @@
+//! Async event loop driving a `ConsensusEngine`
+//!
+//! `ConsensusEngine` itself is passive: something has to feed it shreds and
+//! votes and tell it when timeouts fire. `ConsensusEngine::run` does that on
+//! tokio. It exchanges shreds, votes and certificates with peers through a
+//! `Transport`, relays received shreds to our children in their relay trees,
+//! takes local commands such as proposals from a channel, fires the engine's
+//! round deadlines as they fall due, and reports progress on an event channel.
+//! Incoming messages wait in a bounded `IngressQueue` and are handled one
+//! per iteration, so a flood from one peer can neither exhaust memory nor
+//! keep deadlines from firing. Under the default `ConsensusFirst` policy
+//! votes and certificates for undecided slots jump the queue. Messages a
+//! peer sends over its `RateLimits` are dropped before they are queued.
+//! The loop ends when the command channel or the transport closes and
+//! returns the engine.
+
+use crate::consensus::{ConsensusEngine, ConsensusError};
+use crate::ingress::{IngressConfig, IngressCounters, IngressQueue, Priority, PriorityPolicy};
+use crate::message::ConsensusMessage;
+use crate::rate_limit::{RateLimiter, RateLimits};
+use crate::signer::SignerError;
+use crate::timer::Timeout;
+use crate::transport::{Transport, TransportError};
+use crate::types::*;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+/// Local instructions to the loop
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// A block to propose when we lead its slot
+    Propose(Box<Block>),
+    /// Stop signing until `Resume` (see `ConsensusEngine::pause`)
+    Pause,
+    Resume,
+}
+
+/// Progress reported by the loop
+#[derive(Debug)]
+pub enum EngineEvent {
+    Finalized(FinalizationCertificate),
+    Round2Started(Slot),
+    SlotAdvanced { slot: Slot, leader: ValidatorId },
+    /// A message or command the engine refused
+    Rejected(ConsensusError),
+    /// A message that couldn't be sent
+    SendFailed(TransportError),
+    /// A vote the signer didn't sign; it is retried on rebroadcast
+    SigningFailed(SignerError),
+}
+
+/// Timer and queue settings of the loop; round timeouts come from
+/// `ConsensusConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunConfig {
+    /// How often retransmissions are planned and votes rebroadcast (at the
+    /// engine's `vote_rebroadcast_interval`)
+    pub tick: Duration,
+    /// Bounds on messages received but not yet handled
+    pub ingress: IngressConfig,
+    /// Per-peer limits on incoming messages
+    pub rate_limits: RateLimits,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            tick: Duration::from_millis(10),
+            ingress: IngressConfig::default(),
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+impl ConsensusEngine {
+    /// Drive the engine until `commands` or `transport` closes
+    ///
+    /// A slot ends once its block is finalized or skipped, or when its round
+    /// 2 deadline passes without either; with a `pipeline_depth` above 1 the
+    /// next slot starts once the current one is notarized. On the round 1
+    /// timeout the engine votes to skip a slot whose block never arrived.
+    /// Certificates we assemble from votes are broadcast; gossiped ones are
+    /// not forwarded. Send failures on `events` (receiver dropped) are ignored.
+    /// To stop the node, close `commands` and `shutdown` the returned engine.
+    pub async fn run<T: Transport>(
+        mut self,
+        mut transport: T,
+        mut commands: mpsc::Receiver<Command>,
+        events: mpsc::Sender<EngineEvent>,
+        run_config: RunConfig,
+    ) -> Self {
+        let mut ticker = time::interval(run_config.tick);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut ingress = IngressQueue::new(run_config.ingress);
+        let mut limiter = RateLimiter::new(run_config.rate_limits);
+        self.start_round1_timer();
+
+        loop {
+            let mut outcome = Ok(None);
+            let mut slot_done = false;
+            let slot = self.current_slot();
+            let deadline = self.next_deadline().map(Instant::from_std);
+            let round_timeout = time::sleep_until(deadline.unwrap_or_else(Instant::now));
+            let (room, queued) = (ingress.has_room(), !ingress.is_empty());
+            tokio::select! {
+                command = commands.recv() => {
+                    let Some(command) = command else { break };
+                    outcome = match command {
+                        Command::Propose(block) => self.propose(*block, &transport).await,
+                        Command::Pause => self.pause().map(|()| None).map_err(LoopError::from),
+                        Command::Resume => self.resume().map(|()| None).map_err(LoopError::from),
+                    };
+                }
+                message = transport.recv(), if room => {
+                    let Some((from, message)) = message else { break };
+                    let now = self.clock().now();
+                    if limiter.allow(from, message.kind(), now) {
+                        let priority = self.priority(&message, run_config.ingress.priority);
+                        ingress.push(from, message, priority);
+                    }
+                    self.set_ingress_counters(IngressCounters {
+                        rate_limited: limiter.limited(),
+                        ..ingress.counters()
+                    });
+                }
+                // Branches are polled in random order, so deadlines and
+                // commands get their turn however much is queued
+                _ = std::future::ready(()), if queued => {
+                    if let Some((from, message)) = ingress.pop() {
+                        outcome = self.handle_message(from, message, &transport);
+                    }
+                }
+                _ = round_timeout, if deadline.is_some() => {
+                    for timeout in self.fire_timers() {
+                        match timeout {
+                            Timeout::Round1(slot) => {
+                                let _ = events.send(EngineEvent::Round2Started(slot)).await;
+                            }
+                            Timeout::Round2(_) => slot_done = true,
+                        }
+                    }
+                }
+                _ = ticker.tick() => {
+                    let votes = self.rebroadcast_votes();
+                    self.broadcast_votes(votes, &transport, &events).await;
+                    for (to, shreds) in self.plan_retransmissions() {
+                        for shred in shreds {
+                            if let Err(e) = transport.send_to(to, ConsensusMessage::Shred(shred)) {
+                                let _ = events.send(EngineEvent::SendFailed(e)).await;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let votes = self.take_outgoing_votes();
+            self.broadcast_votes(votes, &transport, &events).await;
+            for (to, shred) in self.take_outgoing_shreds() {
+                if let Err(e) = transport.send_to(to, ConsensusMessage::Shred(shred)) {
+                    let _ = events.send(EngineEvent::SendFailed(e)).await;
+                }
+            }
+            match outcome {
+                Ok(Some((cert, assembled))) => {
+                    slot_done |= cert.slot == self.current_slot();
+                    // Pick up the signature our own vote got in the meantime
+                    let cert = self
+                        .finalized_blocks()
+                        .iter()
+                        .find(|stored| stored.block_id == cert.block_id)
+                        .cloned()
+                        .unwrap_or(cert);
+                    let message = ConsensusMessage::Certificate(cert.clone());
+                    if assembled {
+                        if let Err(e) = transport.broadcast(message) {
+                            let _ = events.send(EngineEvent::SendFailed(e)).await;
+                        }
+                    }
+                    let _ = events.send(EngineEvent::Finalized(cert)).await;
+                }
+                Ok(None) => {}
+                Err(LoopError::Engine(e)) => {
+                    let _ = events.send(EngineEvent::Rejected(e)).await;
+                }
+                Err(LoopError::Send(e)) => {
+                    let _ = events.send(EngineEvent::SendFailed(e)).await;
+                }
+            }
+
+            // A skip certificate already moved the engine on
+            if slot_done && self.current_slot() == slot {
+                self.next_slot();
+            } else {
+                self.advance_pipeline();
+            }
+            if self.current_slot() != slot {
+                self.start_round1_timer();
+                let event = EngineEvent::SlotAdvanced {
+                    slot: self.current_slot(),
+                    leader: self.current_leader(),
+                };
+                let _ = events.send(event).await;
+            }
+        }
+
+        self
+    }
+
+    /// Sign votes and send them to every peer
+    async fn broadcast_votes<T: Transport>(
+        &mut self,
+        votes: Vec<Vote>,
+        transport: &T,
+        events: &mpsc::Sender<EngineEvent>,
+    ) {
+        for vote in self.sign_votes(votes).await {
+            let sent = match vote {
+                Ok(vote) => transport
+                    .broadcast(ConsensusMessage::Vote(vote))
+                    .map_err(EngineEvent::SendFailed),
+                Err(e) => Err(EngineEvent::SigningFailed(e)),
+            };
+            if let Err(event) = sent {
+                let _ = events.send(event).await;
+            }
+        }
+    }
+
+    /// Propose a block, signed if we have a signer, and send each relay its shreds
+    async fn propose<T: Transport>(&mut self, block: Block, transport: &T) -> Outcome {
+        let shreds = self.propose_block_signed(block).await?;
+        for (to, positions) in self.broadcast_plan(&shreds) {
+            for shred in shreds.iter().filter(|shred| positions.contains(&shred.position())) {
+                transport
+                    .send_to(to, ConsensusMessage::Shred(shred.clone()))
+                    .map_err(LoopError::Send)?;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Handling class of a message under `policy`
+    ///
+    /// Shreds of slots before the current one can only be repair traffic.
+    fn priority(&self, message: &ConsensusMessage, policy: PriorityPolicy) -> Priority {
+        if policy == PriorityPolicy::Fifo {
+            return Priority::Normal;
+        }
+        let finalized = self.latest_finalized().map(|cert| cert.slot);
+        let consensus = |slot| {
+            if finalized.is_some_and(|finalized| slot <= finalized) || self.is_slot_decided(slot) {
+                Priority::Low
+            } else {
+                Priority::High
+            }
+        };
+        match message {
+            ConsensusMessage::Vote(vote) => consensus(vote.slot),
+            ConsensusMessage::Certificate(cert) => consensus(cert.slot),
+            ConsensusMessage::Shred(shred) if shred.slot < self.current_slot() => Priority::Low,
+            ConsensusMessage::Shred(_) => Priority::Normal,
+            ConsensusMessage::RepairRequest(_) | ConsensusMessage::Error(_) => Priority::Low,
+        }
+    }
+
+    /// Dispatch a message and send its replies back to the sender
+    fn handle_message<T: Transport>(
+        &mut self,
+        from: ValidatorId,
+        message: ConsensusMessage,
+        transport: &T,
+    ) -> Outcome {
+        let dispatched = self.dispatch(from, message)?;
+        for reply in dispatched.replies {
+            transport.send_to(from, reply).map_err(LoopError::Send)?;
+        }
+        Ok(dispatched.finalized.map(|cert| (cert, dispatched.assembled)))
+    }
+}
+
+/// Why handling one loop iteration failed
+enum LoopError {
+    Engine(ConsensusError),
+    Send(TransportError),
+}
+
+impl From<ConsensusError> for LoopError {
+    fn from(e: ConsensusError) -> Self {
+        LoopError::Engine(e)
+    }
+}
+
+/// A certificate finalized in this iteration, and whether we assembled it
+type Outcome = Result<Option<(FinalizationCertificate, bool)>, LoopError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::ConsensusConfig;
+    use crate::stake::StakeDistribution;
+    use crate::timing::{RoundTimeouts, TimingConfig};
+    use crate::transport::LoopbackNetwork;
+    use ed25519_dalek::SigningKey;
+
+    #[tokio::test]
+    async fn test_cluster_finalizes_over_loopback() {
+        let config = ConsensusConfig {
+            timing: TimingConfig::with_timeouts(RoundTimeouts::new(
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+            )),
+            ..ConsensusConfig::default()
+        };
+        let network = LoopbackNetwork::new();
+        let (events_tx, mut events_rx) = mpsc::channel(256);
+        let mut commands = Vec::new();
+        let mut handles = Vec::new();
+        for i in 0..5 {
+            let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+            let engine = ConsensusEngine::new(ValidatorId::Index(i), vset, config.clone());
+            let (command_tx, command_rx) = mpsc::channel(8);
+            let transport = network.join(ValidatorId::Index(i)).unwrap();
+            commands.push(command_tx);
+            handles.push(tokio::spawn(engine.run(
+                transport,
+                command_rx,
+                events_tx.clone(),
+                RunConfig::default(),
+            )));
+        }
+        drop(events_tx);
+
+        let transaction = Transaction::new(&SigningKey::from_bytes(&[1u8; 32]), 0, vec![1, 2, 3]);
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![transaction], 1000);
+        commands[0].send(Command::Propose(Box::new(block.clone()))).await.unwrap();
+
+        // Every node finalizes the block, then moves on to slot 1
+        let mut finalized = 0;
+        let mut advanced = 0;
+        while finalized < 5 || advanced < 5 {
+            match events_rx.recv().await.unwrap() {
+                EngineEvent::Finalized(cert) => {
+                    assert_eq!(cert.block_id, block.id);
+                    finalized += 1;
+                }
+                EngineEvent::SlotAdvanced { slot, leader } => {
+                    assert_eq!((slot, leader), (Slot(1), ValidatorId::Index(1)));
+                    advanced += 1;
+                }
+                event => panic!("unexpected {event:?}"),
+            }
+        }
+
+        drop(commands);
+        for handle in handles {
+            assert!(handle.await.unwrap().is_finalized(&block.id));
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Shred storage for Rotor
+//!
+//! Rotor writes every newly accepted shred through a `ShredStore`. With the
+//! on-disk `FileShredStore`, partially received blocks survive restarts (see
+//! `Rotor::restore`) and shreds of blocks already evicted from memory can
+//! still be served to repairing peers.
+
+use crate::rotor::{Shred, ShredIndex};
+use crate::types::*;
+use crate::wire::{self, WireError};
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Shred store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Corrupt shred record: {0}")]
+    Corrupt(#[from] WireError),
+}
+
+/// Storage for received shreds, keyed by block and position
+pub trait ShredStore: Send + Sync {
+    fn put(&mut self, shred: &Shred) -> Result<(), StoreError>;
+
+    fn get(&self, block_id: &BlockId, position: ShredIndex) -> Result<Option<Shred>, StoreError>;
+
+    /// Stored shreds of a block, ordered by position
+    fn block_shreds(&self, block_id: &BlockId) -> Result<Vec<Shred>, StoreError>;
+
+    /// Blocks with stored shreds, with their slot
+    fn blocks(&self) -> Result<Vec<(BlockId, Slot)>, StoreError>;
+
+    /// Delete shreds of blocks before `slot`; returns the number of blocks deleted
+    fn prune_before(&mut self, slot: Slot) -> Result<usize, StoreError>;
+}
+
+/// Shreds of one block, first copy per position wins
+type BlockEntry = (Slot, BTreeMap<ShredIndex, Shred>);
+
+/// Volatile store, for tests and nodes that don't need restarts
+#[derive(Debug, Default)]
+pub struct MemoryShredStore {
+    blocks: HashMap<BlockId, BlockEntry>,
+}
+
+impl MemoryShredStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ShredStore for MemoryShredStore {
+    fn put(&mut self, shred: &Shred) -> Result<(), StoreError> {
+        let (_, shreds) = self
+            .blocks
+            .entry(shred.block_id)
+            .or_insert_with(|| (shred.slot, BTreeMap::new()));
+        shreds.entry(shred.position()).or_insert_with(|| shred.clone());
+        Ok(())
+    }
+
+    fn get(&self, block_id: &BlockId, position: ShredIndex) -> Result<Option<Shred>, StoreError> {
+        Ok(self
+            .blocks
+            .get(block_id)
+            .and_then(|(_, shreds)| shreds.get(&position))
+            .cloned())
+    }
+
+    fn block_shreds(&self, block_id: &BlockId) -> Result<Vec<Shred>, StoreError> {
+        Ok(self
+            .blocks
+            .get(block_id)
+            .map(|(_, shreds)| shreds.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn blocks(&self) -> Result<Vec<(BlockId, Slot)>, StoreError> {
+        Ok(self.blocks.iter().map(|(id, (slot, _))| (*id, *slot)).collect())
+    }
+
+    fn prune_before(&mut self, slot: Slot) -> Result<usize, StoreError> {
+        let before = self.blocks.len();
+        self.blocks.retain(|_, (block_slot, _)| *block_slot >= slot);
+        Ok(before - self.blocks.len())
+    }
+}
+
+/// Store keeping one append-only file per block in a directory
+///
+/// Files are named `<slot>-<block id hex>.shreds` and hold length-prefixed
+/// shreds in the `wire` format. A record torn by a crash ends the file.
+#[derive(Debug)]
+pub struct FileShredStore {
+    dir: PathBuf,
+    /// Slot of every block with a file
+    index: HashMap<BlockId, Slot>,
+}
+
+const FILE_EXTENSION: &str = "shreds";
+
+impl FileShredStore {
+    /// Open a store directory, creating it if needed
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut index = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if let Some((block_id, slot)) = parse_file_name(&path) {
+                index.insert(block_id, slot);
+            }
+        }
+
+        Ok(Self { dir, index })
+    }
+
+    fn path(&self, block_id: &BlockId, slot: Slot) -> PathBuf {
+        self.dir.join(format!(
+            "{}-{}.{}",
+            slot.0,
+            hex::encode(block_id.as_bytes()),
+            FILE_EXTENSION
+        ))
+    }
+
+    fn read_block(&self, block_id: &BlockId) -> Result<BTreeMap<ShredIndex, Shred>, StoreError> {
+        let mut shreds = BTreeMap::new();
+        let Some(slot) = self.index.get(block_id) else {
+            return Ok(shreds);
+        };
+
+        // Shred payloads are slices of the file buffer
+        let bytes = Bytes::from(fs::read(self.path(block_id, *slot))?);
+        let mut offset = 0;
+        while let Some(len) = bytes.get(offset..offset + 2) {
+            let start = offset + 2;
+            let end = start + u16::from_le_bytes([len[0], len[1]]) as usize;
+            if end > bytes.len() {
+                break;
+            }
+            let shred = wire::decode_shred_bytes(bytes.slice(start..end))?;
+            shreds.entry(shred.position()).or_insert(shred);
+            offset = end;
+        }
+        Ok(shreds)
+    }
+}
+
+fn parse_file_name(path: &Path) -> Option<(BlockId, Slot)> {
+    if path.extension()? != FILE_EXTENSION {
+        return None;
+    }
+    let (slot, id) = path.file_stem()?.to_str()?.split_once('-')?;
+    let id: [u8; 32] = hex::decode(id).ok()?.try_into().ok()?;
+    Some((BlockId::new(id), Slot(slot.parse().ok()?)))
+}
+
+impl ShredStore for FileShredStore {
+    fn put(&mut self, shred: &Shred) -> Result<(), StoreError> {
+        let packet = wire::encode_shred(shred)?;
+        let slot = *self.index.entry(shred.block_id).or_insert(shred.slot);
+
+        let mut record = Vec::with_capacity(2 + packet.len());
+        record.extend_from_slice(&(packet.len() as u16).to_le_bytes());
+        record.extend_from_slice(&packet);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(&shred.block_id, slot))?;
+        file.write_all(&record)?;
+        Ok(())
+    }
+
+    fn get(&self, block_id: &BlockId, position: ShredIndex) -> Result<Option<Shred>, StoreError> {
+        Ok(self.read_block(block_id)?.remove(&position))
+    }
+
+    fn block_shreds(&self, block_id: &BlockId) -> Result<Vec<Shred>, StoreError> {
+        Ok(self.read_block(block_id)?.into_values().collect())
+    }
+
+    fn blocks(&self) -> Result<Vec<(BlockId, Slot)>, StoreError> {
+        Ok(self.index.iter().map(|(id, slot)| (*id, *slot)).collect())
+    }
+
+    fn prune_before(&mut self, slot: Slot) -> Result<usize, StoreError> {
+        let stale: Vec<(BlockId, Slot)> = self
+            .index
+            .iter()
+            .filter(|(_, block_slot)| **block_slot < slot)
+            .map(|(id, block_slot)| (*id, *block_slot))
+            .collect();
+
+        for (block_id, block_slot) in &stale {
+            fs::remove_file(self.path(block_id, *block_slot))?;
+            self.index.remove(block_id);
+        }
+        Ok(stale.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+
+    fn test_shreds(slot: u64) -> Vec<Shred> {
+        let vset = StakeDistribution::Equal(100).validator_set(1, 0);
+        let payer = SigningKey::from_bytes(&[1u8; 32]);
+        let transaction = Transaction::new(&payer, slot, vec![slot as u8; 100]);
+        let block =
+            Block::new(Slot(slot), None, ValidatorId::Index(0), vec![transaction], 1000 + slot);
+        Rotor::new(vset).encode_block(&block).unwrap()
+    }
+
+    #[test]
+    fn test_stores_agree() {
+        let dir = std::env::temp_dir()
+            .join(format!("alpenglow-shred-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let old = test_shreds(1);
+        let new = test_shreds(5);
+        let stores: [Box<dyn ShredStore>; 2] = [
+            Box::new(MemoryShredStore::new()),
+            Box::new(FileShredStore::open(&dir).unwrap()),
+        ];
+
+        for mut store in stores {
+            for shred in old.iter().chain(&new).step_by(2) {
+                store.put(shred).unwrap();
+            }
+            assert_eq!(store.blocks().unwrap().len(), 2);
+
+            let block_shreds = store.block_shreds(&new[0].block_id).unwrap();
+            assert_eq!(block_shreds.len(), new.len() / 2);
+            assert!(store.get(&new[0].block_id, new[2].position()).unwrap().is_some());
+            assert!(store.get(&new[0].block_id, new[1].position()).unwrap().is_none());
+
+            assert_eq!(store.prune_before(Slot(3)).unwrap(), 1);
+            assert!(store.block_shreds(&old[0].block_id).unwrap().is_empty());
+        }
+
+        // A reopened file store sees what was written before
+        let reopened = FileShredStore::open(&dir).unwrap();
+        assert_eq!(reopened.blocks().unwrap(), vec![(new[0].block_id, Slot(5))]);
+        assert_eq!(reopened.block_shreds(&new[0].block_id).unwrap().len(), new.len() / 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
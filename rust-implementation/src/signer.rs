@@ -0,0 +1,213 @@
+//! Signing service for votes and proposals
+//!
+//! The engine doesn't have to hold a key itself. It hands the bytes to sign to a
+//! `Signer`, which may sign in-process (`LocalSigner`), forward to a signer
+//! daemon (`RemoteSigner`, feature `runtime`) or wrap an HSM. Signing is
+//! asynchronous and may fail; votes that couldn't be signed stay queued for
+//! rebroadcast and are signed again later.
+
+use ed25519_dalek::{Signer as _, SigningKey, VerifyingKey};
+use std::future::Future;
+use std::pin::Pin;
+use thiserror::Error;
+
+/// Pending signature over a message
+pub type SignFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>, SignerError>> + Send>>;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    #[error("Signer unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Signer refused to sign: {0}")]
+    Refused(String),
+
+    #[error("Signer did not answer in time")]
+    Timeout,
+}
+
+/// Produces Ed25519 signatures with a key the engine doesn't see
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> VerifyingKey;
+
+    /// Sign `message`, resolving to the signature bytes
+    fn sign(&self, message: Vec<u8>) -> SignFuture;
+}
+
+/// Signer holding its key in process memory
+pub struct LocalSigner {
+    key: SigningKey,
+}
+
+impl LocalSigner {
+    pub fn new(key: SigningKey) -> Self {
+        Self { key }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    fn sign(&self, message: Vec<u8>) -> SignFuture {
+        let signature = self.key.sign(&message).to_bytes().to_vec();
+        Box::pin(std::future::ready(Ok(signature)))
+    }
+}
+
+#[cfg(feature = "runtime")]
+pub use remote::{serve_signer, RemoteSigner, SignRequest};
+
+#[cfg(feature = "runtime")]
+mod remote {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    /// A message to sign, sent to a signer daemon
+    #[derive(Debug)]
+    pub struct SignRequest {
+        pub message: Vec<u8>,
+        pub reply: oneshot::Sender<Result<Vec<u8>, SignerError>>,
+    }
+
+    /// Signer forwarding requests to a signer daemon over a channel
+    ///
+    /// The daemon end may be `serve_signer` in another task, or a bridge to
+    /// a separate process or HSM.
+    #[derive(Debug, Clone)]
+    pub struct RemoteSigner {
+        public_key: VerifyingKey,
+        requests: mpsc::Sender<SignRequest>,
+        timeout: Duration,
+    }
+
+    impl RemoteSigner {
+        /// `public_key` is the daemon's key; requests fail after `timeout`
+        pub fn new(
+            public_key: VerifyingKey,
+            requests: mpsc::Sender<SignRequest>,
+            timeout: Duration,
+        ) -> Self {
+            Self { public_key, requests, timeout }
+        }
+    }
+
+    impl Signer for RemoteSigner {
+        fn public_key(&self) -> VerifyingKey {
+            self.public_key
+        }
+
+        fn sign(&self, message: Vec<u8>) -> SignFuture {
+            let (requests, timeout) = (self.requests.clone(), self.timeout);
+            Box::pin(async move {
+                let stopped = || SignerError::Unavailable("signer daemon stopped".to_string());
+                let (reply, response) = oneshot::channel();
+                let exchange = async {
+                    requests
+                        .send(SignRequest { message, reply })
+                        .await
+                        .map_err(|_| stopped())?;
+                    response.await.map_err(|_| stopped())?
+                };
+                tokio::time::timeout(timeout, exchange)
+                    .await
+                    .map_err(|_| SignerError::Timeout)?
+            })
+        }
+    }
+
+    /// Answer sign requests with `signer` until every sender is gone
+    pub async fn serve_signer(signer: impl Signer, mut requests: mpsc::Receiver<SignRequest>) {
+        while let Some(request) = requests.recv().await {
+            let result = signer.sign(request.message).await;
+            // The requester may have given up already
+            let _ = request.reply.send(result);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusError};
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+    use crate::types::*;
+    use ed25519_dalek::{Signature, Verifier};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_votes_signed_by_remote_signer() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let (requests, daemon) = mpsc::channel(8);
+        let daemon = tokio::spawn(serve_signer(LocalSigner::new(key.clone()), daemon));
+        let signer = RemoteSigner::new(key.verifying_key(), requests, Duration::from_secs(1));
+
+        let config = ConsensusConfig {
+            vote_rebroadcast_interval: Duration::ZERO,
+            chain_id: [5u8; 32],
+            ..ConsensusConfig::default()
+        };
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        engine.set_signer(Arc::new(signer)).unwrap();
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        for shred in Rotor::new(vset).encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+
+        // Our vote goes out signed, and rebroadcasts carry the signature
+        let votes = engine.take_outgoing_votes();
+        let signed = engine.sign_votes(votes.clone()).await;
+        let vote = signed[0].as_ref().unwrap();
+        let signature = Signature::from_slice(&vote.signature).unwrap();
+        let message = votes[0].signing_bytes(&[5u8; 32]);
+        assert!(key.verifying_key().verify(&message, &signature).is_ok());
+        assert_eq!(engine.rebroadcast_votes(), vec![vote.clone()]);
+
+        // Once the daemon is gone signing fails instead of sending unsigned votes
+        daemon.abort();
+        let _ = daemon.await;
+        assert_eq!(
+            engine.sign_votes(votes).await,
+            vec![Err(SignerError::Unavailable("signer daemon stopped".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_registered_public_key_checked_and_used_for_leaders() {
+        let keys: Vec<_> = (0..4u8).map(|i| SigningKey::from_bytes(&[i + 1; 32])).collect();
+        let mut vset = ValidatorSet::new();
+        for (i, key) in keys.iter().enumerate() {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(i as u64),
+                stake: StakeWeight(100),
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: Some(key.verifying_key()),
+                address: Some(([127, 0, 0, 1], 8000 + i as u16).into()),
+            })
+            .unwrap();
+        }
+        let config = ConsensusConfig::default();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+
+        // A signer holding someone else's key is refused
+        assert!(matches!(
+            engine.set_signer(Arc::new(LocalSigner::new(keys[2].clone()))),
+            Err(ConsensusError::SignerKeyMismatch(ValidatorId::Index(1)))
+        ));
+        engine.set_signer(Arc::new(LocalSigner::new(keys[1].clone()))).unwrap();
+
+        // Shreds of a leader with a registered key must carry its signature
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        let rotor = Rotor::new(vset);
+        assert_eq!(rotor.leader_key(&ValidatorId::Index(0)), Some(keys[0].verifying_key()));
+        let shred = rotor.encode_block(&block).unwrap().remove(0);
+        assert!(engine.receive_shred(shred).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Slashing of misbehaving validators
+//!
+//! Votor catches validators signing conflicting votes and Rotor catches
+//! leaders proposing two blocks for a slot. A `SlashingManager` turns that
+//! evidence into consequences: it checks the evidence, signatures included,
+//! against the offender's key on record, takes the penalty its
+//! `SlashingRules` set for the offense out of the offender's stake, and keeps
+//! the resulting effective validator set. A validator left without stake
+//! leaves the set. Each validator is slashed at most once per slot.
+//!
+//! With slashing enabled the engine schedules the effective set for the next
+//! epoch and emits `ConsensusEvent::ValidatorSlashed`.
+
+use crate::types::*;
+use crate::ledger::{MisbehaviorKind, MisbehaviorRecord};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SlashingError {
+    #[error("Evidence against {0} does not show misbehavior")]
+    InvalidEvidence(ValidatorId),
+
+    #[error("Validator {0} not in the effective validator set")]
+    UnknownValidator(ValidatorId),
+
+    #[error("No public key on record for validator {0} to check evidence against")]
+    UnknownKey(ValidatorId),
+
+    #[error("Validator {validator} was already slashed for slot {slot}")]
+    AlreadySlashed { validator: ValidatorId, slot: Slot },
+}
+
+/// Proof of a slashable offense
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Evidence {
+    DoubleVote(DoubleVoteEvidence),
+    LeaderEquivocation(EquivocationEvidence),
+}
+
+impl Evidence {
+    pub fn offender(&self) -> ValidatorId {
+        match self {
+            Evidence::DoubleVote(evidence) => evidence.validator(),
+            Evidence::LeaderEquivocation(evidence) => evidence.leader,
+        }
+    }
+
+    pub fn slot(&self) -> Slot {
+        match self {
+            Evidence::DoubleVote(evidence) => evidence.slot(),
+            Evidence::LeaderEquivocation(evidence) => evidence.slot,
+        }
+    }
+
+    /// Check that the evidence really shows the offense
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Evidence::DoubleVote(evidence) => evidence.is_valid(),
+            Evidence::LeaderEquivocation(evidence) => evidence.is_valid(),
+        }
+    }
+
+    /// Ledger entry for the offense, with the evidence serialized
+    pub fn record(&self) -> MisbehaviorRecord {
+        let kind = match self {
+            Evidence::DoubleVote(_) => MisbehaviorKind::DoubleVote,
+            Evidence::LeaderEquivocation(_) => MisbehaviorKind::Equivocation,
+        };
+        MisbehaviorRecord {
+            validator: self.offender(),
+            kind,
+            slot: self.slot(),
+            reported_by: None,
+            evidence: bincode::serialize(self).expect("evidence always serializes"),
+        }
+    }
+
+    /// Check that the evidence shows the offense and that the offender,
+    /// holding `key`, signed it for `chain_id`
+    pub fn verify(&self, key: &VerifyingKey, chain_id: &[u8; 32]) -> bool {
+        match self {
+            Evidence::DoubleVote(evidence) => {
+                evidence.is_valid()
+                    && evidence.first.verify(key, chain_id)
+                    && evidence.second.verify(key, chain_id)
+            }
+            Evidence::LeaderEquivocation(evidence) => evidence.verify(key, chain_id),
+        }
+    }
+}
+
+/// Share of stake each offense costs, in percent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlashingRules {
+    pub double_vote_percent: u64,
+    pub equivocation_percent: u64,
+}
+
+impl Default for SlashingRules {
+    fn default() -> Self {
+        Self {
+            double_vote_percent: 10,
+            equivocation_percent: 20,
+        }
+    }
+}
+
+impl SlashingRules {
+    /// Stake `stake` loses for the offense `evidence` proves
+    pub fn penalty(&self, evidence: &Evidence, stake: StakeWeight) -> StakeWeight {
+        let percent = match evidence {
+            Evidence::DoubleVote(_) => self.double_vote_percent,
+            Evidence::LeaderEquivocation(_) => self.equivocation_percent,
+        };
+        stake.percent(percent.min(100))
+    }
+}
+
+/// A penalty taken from a validator's stake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorSlashed {
+    pub validator: ValidatorId,
+    pub slot: Slot,
+    pub penalty: StakeWeight,
+    /// Stake left; the validator left the set if this is zero
+    pub remaining: StakeWeight,
+    pub evidence: Evidence,
+}
+
+/// Applies slashing rules to evidence and tracks the effective validator set
+#[derive(Debug, Clone)]
+pub struct SlashingManager {
+    rules: SlashingRules,
+    validator_set: ValidatorSet,
+    /// Chain the evidence's signatures must be made for
+    chain_id: [u8; 32],
+    /// Validators and the slots they were slashed for
+    punished: HashSet<(ValidatorId, Slot)>,
+    slashed: Vec<ValidatorSlashed>,
+}
+
+impl SlashingManager {
+    pub fn new(rules: SlashingRules, validator_set: ValidatorSet, chain_id: [u8; 32]) -> Self {
+        Self {
+            rules,
+            validator_set,
+            chain_id,
+            punished: HashSet::new(),
+            slashed: Vec::new(),
+        }
+    }
+
+    /// Validate `evidence` against the offender's key and take the penalty
+    /// from its stake; evidence against a validator without a key is refused
+    pub fn slash(&mut self, evidence: Evidence) -> Result<ValidatorSlashed, SlashingError> {
+        let (validator, slot) = (evidence.offender(), evidence.slot());
+        let offender = self
+            .validator_set
+            .get_validator(&validator)
+            .ok_or(SlashingError::UnknownValidator(validator))?;
+        let key = offender.pubkey.ok_or(SlashingError::UnknownKey(validator))?;
+        if !evidence.verify(&key, &self.chain_id) {
+            return Err(SlashingError::InvalidEvidence(validator));
+        }
+        let stake = offender.stake;
+        if !self.punished.insert((validator, slot)) {
+            return Err(SlashingError::AlreadySlashed { validator, slot });
+        }
+
+        let penalty = self.rules.penalty(&evidence, stake);
+        let remaining = stake.saturating_sub(penalty);
+        // Lowering a known validator's stake can't fail
+        if remaining.0 > 0 {
+            let _ = self.validator_set.update_stake(&validator, remaining);
+        } else {
+            let _ = self.validator_set.remove_validator(&validator);
+        }
+
+        tracing::warn!(
+            "Slashed {} for slot {}: {} stake taken, {} left",
+            validator,
+            slot,
+            penalty.0,
+            remaining.0
+        );
+        let slashed = ValidatorSlashed { validator, slot, penalty, remaining, evidence };
+        self.slashed.push(slashed.clone());
+        Ok(slashed)
+    }
+
+    /// Validator set with every penalty so far applied
+    pub fn validator_set(&self) -> &ValidatorSet {
+        &self.validator_set
+    }
+
+    /// Penalties applied, oldest first
+    pub fn slashed(&self) -> &[ValidatorSlashed] {
+        &self.slashed
+    }
+
+    pub fn rules(&self) -> &SlashingRules {
+        &self.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusEvent};
+    use crate::epoch::EpochSchedule;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_double_vote_slashed_from_next_epoch() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig {
+            epoch_schedule: EpochSchedule::new(4),
+            ..ConsensusConfig::default()
+        };
+        let chain_id = config.chain_id;
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), config);
+        engine.enable_slashing(SlashingRules {
+            double_vote_percent: 50,
+            ..SlashingRules::default()
+        });
+        let events = engine.subscribe();
+
+        let vote = |block: u8| {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(3),
+                block_id: BlockId::new([block; 32]),
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            vote.sign(&keys[3], &chain_id);
+            vote
+        };
+        engine.process_vote(vote(1)).unwrap();
+        assert!(engine.process_vote(vote(2)).is_err());
+
+        let slashed = events
+            .try_iter()
+            .find_map(|event| match event {
+                ConsensusEvent::ValidatorSlashed(slashed) => Some(slashed),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(slashed.validator, ValidatorId::Index(3));
+        assert_eq!((slashed.penalty, slashed.remaining), (StakeWeight(50), StakeWeight(50)));
+
+        // The offense is punished once, and only from the next epoch on
+        let manager = engine.slashing().unwrap();
+        let evidence = slashed.evidence.clone();
+        assert_eq!(
+            manager.clone().slash(evidence),
+            Err(SlashingError::AlreadySlashed { validator: ValidatorId::Index(3), slot: Slot(0) })
+        );
+        assert_eq!(engine.epochs().for_slot(Slot(3)).total_stake(), StakeWeight(500));
+        assert_eq!(engine.epochs().for_slot(Slot(4)).total_stake(), StakeWeight(450));
+    }
+
+    #[test]
+    fn test_evidence_must_carry_the_offenders_signatures() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let chain_id = [0u8; 32];
+        let signed_by = |key: &SigningKey, block: u8| {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(3),
+                block_id: BlockId::new([block; 32]),
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            vote.sign(key, &chain_id);
+            vote
+        };
+        let evidence = |key: &SigningKey| {
+            let (first, second) = (signed_by(&keys[3], 1), signed_by(key, 2));
+            Evidence::DoubleVote(DoubleVoteEvidence { first, second })
+        };
+        let mut manager = SlashingManager::new(SlashingRules::default(), vset.clone(), chain_id);
+
+        // A vote framed with someone else's key, or unsigned, proves nothing
+        let framed = evidence(&keys[0]);
+        let invalid = Err(SlashingError::InvalidEvidence(ValidatorId::Index(3)));
+        assert_eq!(manager.slash(framed), invalid);
+        let (first, mut second) = (signed_by(&keys[3], 1), signed_by(&keys[3], 2));
+        second.signature.clear();
+        let unsigned = Evidence::DoubleVote(DoubleVoteEvidence { first, second });
+        assert_eq!(manager.slash(unsigned), invalid);
+        let mut other_chain = SlashingManager::new(SlashingRules::default(), vset, [1u8; 32]);
+        assert_eq!(other_chain.slash(evidence(&keys[3])), invalid);
+        assert!(manager.slash(evidence(&keys[3])).is_ok());
+
+        // Without a key on record there is nothing to check against
+        let unkeyed = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut manager = SlashingManager::new(SlashingRules::default(), unkeyed, chain_id);
+        assert_eq!(
+            manager.slash(evidence(&keys[3])),
+            Err(SlashingError::UnknownKey(ValidatorId::Index(3)))
+        );
+    }
+}
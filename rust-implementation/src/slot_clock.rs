@@ -0,0 +1,226 @@
+//! Wall-clock slot timing
+//!
+//! Slots have a fixed length counted from a genesis time, so every node
+//! agrees on the current slot without exchanging messages. `SlotClock` turns
+//! elapsed time into slot-start and round-1 timeout ticks, taking each
+//! slot's round 1 timeout from the `TimingConfig` of its epoch, and
+//! `ConsensusEngine::drive_clock` applies them, so callers no longer poll
+//! `check_round1_timeout` or call `next_slot` themselves. It reads the time
+//! from a `Clock`, normally the engine's own (`ConsensusEngine::clock`), so
+//! slot boundaries and round deadlines move together.
+
+use crate::clock::{Clock, SystemClock};
+use crate::consensus::ConsensusEngine;
+use crate::epoch::EpochSchedule;
+use crate::timing::TimingConfig;
+use crate::types::*;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Timing event produced by a `SlotClock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotTick {
+    /// A new slot began
+    SlotStarted(Slot),
+    /// Round 1 of the slot ran out without finalizing
+    Round1Timeout(Slot),
+}
+
+/// Derives the current slot from a time source
+pub struct SlotClock {
+    /// Start of slot 0
+    genesis: SystemTime,
+    slot_duration: Duration,
+    timing: TimingConfig,
+    epoch_schedule: EpochSchedule,
+    clock: Arc<dyn Clock>,
+    /// Latest slot whose start was reported
+    started: Option<Slot>,
+    /// Latest slot whose round 1 timeout was reported
+    timed_out: Option<Slot>,
+}
+
+impl SlotClock {
+    /// Clock on system time
+    pub fn new(genesis: SystemTime, timing: TimingConfig, epoch_schedule: EpochSchedule) -> Self {
+        Self::with_clock(genesis, timing, epoch_schedule, Arc::new(SystemClock))
+    }
+
+    /// Clock reading the wall time of `clock`, e.g. the engine's
+    pub fn with_clock(
+        genesis: SystemTime,
+        timing: TimingConfig,
+        epoch_schedule: EpochSchedule,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            genesis,
+            slot_duration: timing.slot_duration.as_duration(),
+            timing,
+            epoch_schedule,
+            clock,
+            started: None,
+            timed_out: None,
+        }
+    }
+
+    /// Slot in progress at `time`; slot 0 before genesis
+    pub fn slot_at(&self, time: SystemTime) -> Slot {
+        let elapsed = time.duration_since(self.genesis).unwrap_or_default();
+        Slot((elapsed.as_nanos() / self.slot_duration.as_nanos()) as u64)
+    }
+
+    pub fn current_slot(&self) -> Slot {
+        self.slot_at(self.clock.wall_time())
+    }
+
+    /// Start of `slot`; `None` if it lies beyond what `SystemTime` holds
+    pub fn slot_start(&self, slot: Slot) -> Option<SystemTime> {
+        let secs = self.slot_duration.as_secs().checked_mul(slot.0)?;
+        let nanos = u64::from(self.slot_duration.subsec_nanos()).checked_mul(slot.0)?;
+        let offset = Duration::from_secs(secs).checked_add(Duration::from_nanos(nanos))?;
+        self.genesis.checked_add(offset)
+    }
+
+    /// End of round 1 of `slot`
+    fn round1_end(&self, slot: Slot) -> Option<SystemTime> {
+        self.slot_start(slot)?.checked_add(self.round1_timeout(slot))
+    }
+
+    /// Round 1 timeout of the epoch `slot` belongs to, at most a slot long
+    fn round1_timeout(&self, slot: Slot) -> Duration {
+        let epoch = self.epoch_schedule.epoch_of(slot);
+        self.timing.timeouts_for(epoch).round1.min(self.slot_duration)
+    }
+
+    /// Ticks that fell due since the last poll
+    ///
+    /// If several slots went by, only the latest one is reported, so a late
+    /// poll never replays timeouts of slots already over.
+    pub fn poll(&mut self) -> Vec<SlotTick> {
+        let now = self.clock.wall_time();
+        let slot = self.slot_at(now);
+        let mut ticks = Vec::new();
+
+        if self.started.is_none_or(|started| slot > started) {
+            self.started = Some(slot);
+            ticks.push(SlotTick::SlotStarted(slot));
+        }
+        let round1_over = self.round1_end(slot).is_some_and(|end| now >= end);
+        if round1_over && self.timed_out.is_none_or(|timed_out| slot > timed_out) {
+            self.timed_out = Some(slot);
+            ticks.push(SlotTick::Round1Timeout(slot));
+        }
+        ticks
+    }
+
+    /// Time left until the next tick falls due; zero if one is pending,
+    /// `Duration::MAX` if none ever will
+    pub fn until_next_tick(&self) -> Duration {
+        let now = self.clock.wall_time();
+        let slot = self.slot_at(now);
+        if self.started != Some(slot) {
+            return Duration::ZERO;
+        }
+        let next = match self.round1_end(slot) {
+            Some(round1_end) if now < round1_end => Some(round1_end),
+            Some(_) if self.timed_out != Some(slot) => return Duration::ZERO,
+            Some(_) => slot.0.checked_add(1).and_then(|next| self.slot_start(Slot(next))),
+            None => None,
+        };
+        next.map_or(Duration::MAX, |next| next.duration_since(now).unwrap_or_default())
+    }
+}
+
+impl ConsensusEngine {
+    /// Poll `clock` and apply its ticks
+    ///
+    /// A slot start moves the engine forward to that slot, past any it
+    /// missed; a round 1 timeout of the current slot starts round 2 (and
+    /// votes to skip a slot whose block never arrived) unless the slot is
+    /// already finalized. Returns the ticks applied.
+    pub fn drive_clock(&mut self, clock: &mut SlotClock) -> Vec<SlotTick> {
+        let ticks = clock.poll();
+        for tick in &ticks {
+            match *tick {
+                SlotTick::SlotStarted(slot) => {
+                    while self.current_slot() < slot {
+                        self.next_slot();
+                    }
+                }
+                SlotTick::Round1Timeout(slot) => {
+                    let finalized = self.finalized_blocks().iter().any(|cert| cert.slot == slot);
+                    if slot == self.current_slot() && !finalized {
+                        self.on_round1_timeout(slot);
+                    }
+                }
+            }
+        }
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::consensus::ConsensusConfig;
+    use crate::stake::StakeDistribution;
+    use crate::timing::{RoundTimeouts, SlotDuration};
+
+    #[test]
+    fn test_clock_drives_engine() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut engine =
+            ConsensusEngine::new(ValidatorId::Index(1), vset, ConsensusConfig::default());
+
+        let genesis = SystemTime::UNIX_EPOCH;
+        let time = ManualClock::starting_at(genesis);
+        engine.set_clock(Arc::new(time.clone()));
+        let timing = TimingConfig {
+            slot_duration: SlotDuration::from_millis(400),
+            ..TimingConfig::with_timeouts(RoundTimeouts::new(
+                Duration::from_millis(100),
+                Duration::from_millis(150),
+            ))
+        };
+        let schedule = EpochSchedule::default();
+        let mut clock = SlotClock::with_clock(genesis, timing, schedule, engine.clock());
+
+        assert_eq!(engine.drive_clock(&mut clock), vec![SlotTick::SlotStarted(Slot(0))]);
+        assert!(engine.drive_clock(&mut clock).is_empty());
+        assert_eq!(clock.until_next_tick(), Duration::from_millis(100));
+
+        time.advance(Duration::from_millis(150));
+        assert_eq!(engine.drive_clock(&mut clock), vec![SlotTick::Round1Timeout(Slot(0))]);
+        assert_eq!(engine.current_round(), VoteRound::Round2);
+        assert_eq!(clock.until_next_tick(), Duration::from_millis(250));
+
+        // A late poll jumps straight to the current slot
+        time.advance(Duration::from_millis(1200));
+        assert_eq!(
+            engine.drive_clock(&mut clock),
+            vec![SlotTick::SlotStarted(Slot(3)), SlotTick::Round1Timeout(Slot(3))]
+        );
+        assert_eq!(engine.current_slot(), Slot(3));
+        assert_eq!(engine.current_leader(), ValidatorId::Index(3));
+    }
+
+    #[test]
+    fn test_far_slots_start_without_wrapping() {
+        let timing = TimingConfig {
+            slot_duration: SlotDuration::from_millis(400),
+            ..TimingConfig::default()
+        };
+        let genesis = SystemTime::UNIX_EPOCH;
+        let clock = SlotClock::new(genesis, timing, EpochSchedule::default());
+
+        // Slots past u32::MAX keep their own start
+        let far = Slot(u64::from(u32::MAX) + 10);
+        let start = clock.slot_start(far).unwrap();
+        assert_eq!(start.duration_since(genesis).unwrap(), Duration::from_millis(400 * far.0));
+        assert!(start > clock.slot_start(Slot(u64::from(u32::MAX))).unwrap());
+        assert_eq!(clock.slot_at(start), far);
+        assert_eq!(clock.slot_start(Slot(u64::MAX)), None);
+    }
+}
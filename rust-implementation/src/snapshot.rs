@@ -0,0 +1,200 @@
+//! State snapshots for bootstrapping new validators
+//!
+//! A node joining a long-running network doesn't replay from genesis. A
+//! validator exports a `Snapshot` of its finalized state (the certificates up
+//! to its finalized tip, the validator set of the tip's epoch with its keys
+//! and the application state hash) and signs it. The joining node checks the
+//! signature against a validator key it already trusts, such as one from the
+//! genesis file, and checks that the tip's certificate reaches its quorum
+//! under the snapshot's validator set, then starts at the slot after the tip.
+//! The application state itself travels out of band and is checked against
+//! the hash.
+
+use crate::integrity::{self, InvariantViolation};
+use crate::params::ProtocolParams;
+use crate::types::*;
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier as _, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+const SNAPSHOT_DOMAIN: &[u8] = b"alpenglow-snapshot-v1";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    #[error("Snapshot holds no finalized block")]
+    Empty,
+
+    #[error("Snapshot signer {0} is not trusted")]
+    UnknownSigner(ValidatorId),
+
+    #[error("Snapshot signature does not verify")]
+    InvalidSignature,
+
+    #[error("Snapshot certificate for slot {0} is out of slot order")]
+    Unordered(Slot),
+
+    #[error("Validator {0} appears twice in the snapshot")]
+    DuplicateValidator(ValidatorId),
+
+    #[error("Invalid validator set in snapshot: {0}")]
+    InvalidValidatorSet(#[from] ValidatorSetError),
+
+    #[error("Invalid tip certificate in snapshot: {0}")]
+    InvalidCertificate(#[from] InvariantViolation),
+
+    #[error("Snapshot tip at slot {tip} is not past our finalized slot {finalized}")]
+    Stale { tip: Slot, finalized: Slot },
+}
+
+/// Finalized state of a validator at its finalized tip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Finalization certificates in slot order; the last one is the tip
+    pub certificates: Vec<FinalizationCertificate>,
+    /// Epoch of the tip
+    pub epoch: Epoch,
+    /// Every validator of that epoch with its stake and key, in ID order
+    pub validators: Vec<ValidatorEntry>,
+    /// Application state after executing the tip, if the executor has one
+    pub state_hash: Option<[u8; 32]>,
+}
+
+impl Snapshot {
+    pub fn tip(&self) -> Option<&FinalizationCertificate> {
+        self.certificates.last()
+    }
+
+    /// Validator set of the tip's epoch, keys included; fails on a repeated
+    /// validator, a malformed key or stakes overflowing in total
+    pub fn validator_set(&self) -> Result<ValidatorSet, SnapshotError> {
+        Ok(ValidatorSet::from_entries(&self.validators)?)
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_DOMAIN.to_vec();
+        bytes.extend(bincode::serialize(self).expect("snapshots always serialize"));
+        bytes
+    }
+
+    /// Sign the snapshot as `signer`
+    pub fn sign(self, signer: ValidatorId, key: &SigningKey) -> SignedSnapshot {
+        let signature = key.sign(&self.signing_bytes()).to_bytes().to_vec();
+        SignedSnapshot { snapshot: self, signer, signature }
+    }
+}
+
+/// Snapshot with its exporter's signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSnapshot {
+    pub snapshot: Snapshot,
+    pub signer: ValidatorId,
+    pub signature: Vec<u8>,
+}
+
+impl SignedSnapshot {
+    /// Check the signature against `trusted` keys and the tip against the
+    /// snapshot's validator set under `params`
+    ///
+    /// Certificates before the tip may come from earlier epochs and are
+    /// taken on the signer's word.
+    pub fn verify(
+        &self,
+        trusted: &HashMap<ValidatorId, VerifyingKey>,
+        params: &ProtocolParams,
+    ) -> Result<&Snapshot, SnapshotError> {
+        let key = trusted
+            .get(&self.signer)
+            .ok_or(SnapshotError::UnknownSigner(self.signer))?;
+        let signature =
+            Signature::from_slice(&self.signature).map_err(|_| SnapshotError::InvalidSignature)?;
+        key.verify(&self.snapshot.signing_bytes(), &signature)
+            .map_err(|_| SnapshotError::InvalidSignature)?;
+
+        let snapshot = &self.snapshot;
+        let tip = snapshot.tip().ok_or(SnapshotError::Empty)?;
+        for pair in snapshot.certificates.windows(2) {
+            if pair[1].slot <= pair[0].slot {
+                return Err(SnapshotError::Unordered(pair[1].slot));
+            }
+        }
+        for pair in snapshot.validators.windows(2) {
+            if pair[1].0 <= pair[0].0 {
+                return Err(SnapshotError::DuplicateValidator(pair[1].0));
+            }
+        }
+        let validator_set = snapshot.validator_set()?;
+        integrity::check_certificates(&validator_set, params, std::slice::from_ref(tip))?;
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusError};
+    use crate::stake::StakeDistribution;
+
+    #[test]
+    fn test_fresh_node_bootstraps_from_signed_snapshot() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let config = ConsensusConfig::default();
+        let mut veteran = ConsensusEngine::new(ValidatorId::Index(0), vset.clone(), config.clone());
+        let mut tip = BlockId::new([0u8; 32]);
+        for slot in 0..3u8 {
+            tip = BlockId::new([slot + 1; 32]);
+            for i in 1..5 {
+                let mut vote = Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: tip,
+                    slot: Slot(slot.into()),
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                };
+                vote.sign(&keys[i as usize], &config.chain_id);
+                veteran.process_vote(vote).unwrap();
+            }
+            veteran.next_slot();
+        }
+
+        let key = SigningKey::from_bytes(&[1u8; 32]);
+        let trusted = HashMap::from([(ValidatorId::Index(0), key.verifying_key())]);
+        let signed = veteran.export_snapshot().unwrap().sign(ValidatorId::Index(0), &key);
+
+        // Only a signature from a trusted validator is accepted
+        let mut fresh = ConsensusEngine::new(ValidatorId::Index(4), vset.clone(), config.clone());
+        let forged = veteran.export_snapshot().unwrap().sign(ValidatorId::Index(1), &key);
+        assert!(matches!(
+            fresh.import_snapshot(&forged, &trusted),
+            Err(ConsensusError::Snapshot(SnapshotError::UnknownSigner(ValidatorId::Index(1))))
+        ));
+        let mut tampered = signed.clone();
+        tampered.snapshot.state_hash = Some([7u8; 32]);
+        assert!(matches!(
+            fresh.import_snapshot(&tampered, &trusted),
+            Err(ConsensusError::Snapshot(SnapshotError::InvalidSignature))
+        ));
+        let mut tampered = signed.clone();
+        tampered.snapshot.validators[2].2 = Some(key.verifying_key().to_bytes());
+        assert!(matches!(
+            fresh.import_snapshot(&tampered, &trusted),
+            Err(ConsensusError::Snapshot(SnapshotError::InvalidSignature))
+        ));
+
+        // The fresh node starts right after the tip without replaying
+        assert_eq!(fresh.import_snapshot(&signed, &trusted).unwrap(), Slot(3));
+        assert_eq!(fresh.current_slot(), Slot(3));
+        assert_eq!(fresh.latest_finalized().unwrap().block_id, tip);
+        assert_eq!(fresh.finalized_blocks().len(), 3);
+        assert_eq!(fresh.last_executed(), Some(Slot(2)));
+        let imported = fresh.epochs().for_slot(Slot(3));
+        for (i, key) in keys.iter().enumerate() {
+            let validator = imported.get_validator(&ValidatorId::Index(i as u64)).unwrap();
+            assert_eq!(validator.pubkey, Some(key.verifying_key()));
+        }
+        assert!(matches!(
+            fresh.import_snapshot(&signed, &trusted),
+            Err(ConsensusError::Snapshot(SnapshotError::Stale { .. }))
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+//! Stake distributions for tests and simulations
+//!
+//! Real networks are far from equal-stake: a few large validators hold much
+//! of the stake and a long tail holds the rest. A `StakeDistribution` builds
+//! a validator set of any size with stakes drawn from a seeded RNG, so a run
+//! with skewed stake is as reproducible as one with equal stake. Ranked
+//! distributions hand their stakes to validators in shuffled order, so the
+//! largest validator isn't always the first leader.
+
+use crate::types::*;
+use ed25519_dalek::SigningKey;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// How stake is spread over the validators of a set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StakeDistribution {
+    /// Every validator holds `stake`
+    Equal(u64),
+    /// Each validator holds a stake drawn uniformly from `min..=max`
+    Uniform { min: u64, max: u64 },
+    /// Power law: the validator ranked `r` (from 1) holds `top / r^exponent`
+    Zipf { top: u64, exponent: f64 },
+    /// `whales` validators share `whale_pct`% of `total` evenly, the rest
+    /// share what is left evenly
+    Whales { whales: usize, whale_pct: u8, total: u64 },
+}
+
+impl Default for StakeDistribution {
+    fn default() -> Self {
+        StakeDistribution::Equal(100)
+    }
+}
+
+impl StakeDistribution {
+    /// Stakes of `count` validators in ID order; each at least 1
+    pub fn stakes(&self, count: usize, seed: u64) -> Vec<StakeWeight> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut stakes: Vec<u64> = match *self {
+            StakeDistribution::Equal(stake) => vec![stake; count],
+            StakeDistribution::Uniform { min, max } => {
+                (0..count).map(|_| rng.gen_range(min..=max.max(min))).collect()
+            }
+            StakeDistribution::Zipf { top, exponent } => (1..=count)
+                .map(|rank| (top as f64 / (rank as f64).powf(exponent)) as u64)
+                .collect(),
+            StakeDistribution::Whales { whales, whale_pct, total } => {
+                let whales = whales.min(count);
+                let whale_total = StakeWeight(total).percent(whale_pct.min(100).into()).as_u64();
+                let whale_stake = whale_total / whales.max(1) as u64;
+                let tail_stake = (total - whale_total) / (count - whales).max(1) as u64;
+                (0..count)
+                    .map(|rank| if rank < whales { whale_stake } else { tail_stake })
+                    .collect()
+            }
+        };
+        stakes.shuffle(&mut rng);
+        stakes.into_iter().map(|stake| StakeWeight(stake.max(1))).collect()
+    }
+
+    /// Validators `0..count` with stakes from `stakes`
+    pub fn validator_set(&self, count: u64, seed: u64) -> ValidatorSet {
+        ValidatorSet::with_stakes(self.stakes(count as usize, seed))
+    }
+
+    /// Like `validator_set`, with validator `i` registered under the public
+    /// half of `keys[i]`, also drawn from `seed`
+    pub fn keyed_validator_set(&self, count: u64, seed: u64) -> (ValidatorSet, Vec<SigningKey>) {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+        let keys: Vec<_> = (0..count).map(|_| SigningKey::from_bytes(&rng.gen())).collect();
+        let mut vset = ValidatorSet::new();
+        for (i, stake) in self.stakes(count as usize, seed).into_iter().enumerate() {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(i as u64),
+                stake,
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: Some(keys[i].verifying_key()),
+                address: None,
+            })
+            .expect("total stake overflows");
+        }
+        (vset, keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Cluster, ClusterConfig};
+
+    #[test]
+    fn test_stake_distributions_are_seeded_and_skewed() {
+        let zipf = StakeDistribution::Zipf { top: 10_000, exponent: 1.0 };
+        assert_eq!(zipf.stakes(50, 3), zipf.stakes(50, 3));
+        assert_ne!(zipf.stakes(50, 3), zipf.stakes(50, 4));
+        let mut ranked = zipf.stakes(50, 3);
+        ranked.sort_by_key(|stake| std::cmp::Reverse(*stake));
+        assert_eq!(ranked[0], StakeWeight(10_000));
+        assert_eq!(ranked[1], StakeWeight(5_000));
+        assert_eq!(ranked[49], StakeWeight(200));
+
+        let whales = StakeDistribution::Whales { whales: 3, whale_pct: 70, total: 90_000 };
+        let vset = whales.validator_set(30, 1);
+        let top: StakeWeight = vset.top_by_stake(3).iter().map(|v| v.stake).sum();
+        assert_eq!(top, StakeWeight(63_000));
+        assert_eq!(vset.total_stake(), StakeWeight(90_000));
+
+        let uniform = StakeDistribution::Uniform { min: 10, max: 20 };
+        assert!(uniform.stakes(100, 0).iter().all(|s| (10..=20).contains(&s.as_u64())));
+        assert!(StakeDistribution::Equal(0).stakes(4, 0).iter().all(|s| s.as_u64() == 1));
+
+        // The cluster finalizes even when a handful of validators carry most stake
+        for seed in 0..3 {
+            let config = ClusterConfig {
+                stakes: StakeDistribution::Zipf { top: 1_000, exponent: 0.8 },
+                seed,
+                ..ClusterConfig::default()
+            };
+            let mut cluster = Cluster::new(10, config);
+            assert!(cluster.run_until_finalized(Slot(2), 500), "seed {seed}");
+            cluster.assert_finalized(Slot(2));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Startup state machine
+//!
+//! A node moves through `Initializing → Syncing → WarmUp → Active` and may
+//! only sign votes or proposals once it is `Active`:
+//! - Initializing: waiting for the voting-safety file to be loaded
+//! - Syncing: behind the network tip or clock drift out of bounds
+//! - WarmUp: caught up, observing a few slots before signing anything
+//! - Active: allowed to vote and propose
+
+use crate::types::*;
+
+/// Startup phase of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupPhase {
+    Initializing,
+    Syncing,
+    WarmUp,
+    Active,
+}
+
+/// Conditions a node must meet before it may sign
+#[derive(Debug, Clone)]
+pub struct StartupConfig {
+    /// Maximum slots behind the network tip to count as caught up
+    pub max_slot_lag: u64,
+
+    /// Maximum tolerated clock drift from network time (milliseconds)
+    pub max_clock_drift_ms: u64,
+
+    /// Slots to observe after catching up before signing
+    pub warmup_slots: u64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            max_slot_lag: 2,
+            max_clock_drift_ms: 50,
+            warmup_slots: 2,
+        }
+    }
+}
+
+/// Snapshot of startup progress, for status reporting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupStatus {
+    pub phase: StartupPhase,
+    pub safety_file_loaded: bool,
+    pub network_slot: Option<Slot>,
+    pub clock_drift_ms: Option<u64>,
+}
+
+/// Tracks startup conditions and drives phase transitions
+#[derive(Debug, Clone)]
+pub struct StartupState {
+    config: StartupConfig,
+    phase: StartupPhase,
+    safety_file_loaded: bool,
+    network_slot: Option<Slot>,
+    clock_drift_ms: Option<u64>,
+    warmup_start: Option<Slot>,
+}
+
+impl StartupState {
+    /// Start in `Initializing`
+    pub fn new(config: StartupConfig) -> Self {
+        Self {
+            config,
+            phase: StartupPhase::Initializing,
+            safety_file_loaded: false,
+            network_slot: None,
+            clock_drift_ms: None,
+            warmup_start: None,
+        }
+    }
+
+    /// Start directly in `Active` (no startup checks)
+    pub fn active() -> Self {
+        let mut state = Self::new(StartupConfig::default());
+        state.phase = StartupPhase::Active;
+        state.safety_file_loaded = true;
+        state
+    }
+
+    pub fn mark_safety_file_loaded(&mut self) {
+        self.safety_file_loaded = true;
+    }
+
+    pub fn observe_network_slot(&mut self, slot: Slot) {
+        self.network_slot = Some(self.network_slot.map_or(slot, |s| s.max(slot)));
+    }
+
+    pub fn observe_clock_drift(&mut self, drift_ms: u64) {
+        self.clock_drift_ms = Some(drift_ms);
+    }
+
+    /// Drop back to `Syncing` (e.g. after a local rollback), keeping the
+    /// safety file and observations
+    pub fn resync(&mut self) {
+        if self.phase != StartupPhase::Initializing {
+            self.phase = StartupPhase::Syncing;
+            self.warmup_start = None;
+        }
+    }
+
+    /// Whether an observed network tip is further ahead than the allowed lag
+    pub fn is_behind(&self, local_slot: Slot) -> bool {
+        self.network_slot.is_some() && !self.is_caught_up(local_slot)
+    }
+
+    /// Re-evaluate the phase given our local slot
+    pub fn update(&mut self, local_slot: Slot) -> StartupPhase {
+        let ready = self.is_caught_up(local_slot) && self.is_time_synced();
+
+        self.phase = match self.phase {
+            StartupPhase::Initializing if self.safety_file_loaded => StartupPhase::Syncing,
+            StartupPhase::Syncing if ready => {
+                self.warmup_start = Some(local_slot);
+                StartupPhase::WarmUp
+            }
+            StartupPhase::WarmUp if !ready => StartupPhase::Syncing,
+            StartupPhase::WarmUp => {
+                let start = self.warmup_start.unwrap_or(local_slot);
+                if local_slot.0 >= start.0 + self.config.warmup_slots {
+                    StartupPhase::Active
+                } else {
+                    StartupPhase::WarmUp
+                }
+            }
+            // Once active, the node stays active
+            phase => phase,
+        };
+
+        // A transition may unlock the next one immediately
+        if self.phase == StartupPhase::Syncing && ready {
+            return self.update(local_slot);
+        }
+
+        self.phase
+    }
+
+    pub fn phase(&self) -> StartupPhase {
+        self.phase
+    }
+
+    /// Whether the node may sign votes or proposals
+    pub fn may_sign(&self) -> bool {
+        self.phase == StartupPhase::Active
+    }
+
+    pub fn status(&self) -> StartupStatus {
+        StartupStatus {
+            phase: self.phase,
+            safety_file_loaded: self.safety_file_loaded,
+            network_slot: self.network_slot,
+            clock_drift_ms: self.clock_drift_ms,
+        }
+    }
+
+    fn is_caught_up(&self, local_slot: Slot) -> bool {
+        self.network_slot
+            .is_some_and(|tip| tip.0.saturating_sub(local_slot.0) <= self.config.max_slot_lag)
+    }
+
+    fn is_time_synced(&self) -> bool {
+        self.clock_drift_ms
+            .is_some_and(|drift| drift <= self.config.max_clock_drift_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_transitions() {
+        let mut state = StartupState::new(StartupConfig::default());
+        assert_eq!(state.update(Slot(0)), StartupPhase::Initializing);
+
+        state.mark_safety_file_loaded();
+        assert_eq!(state.update(Slot(0)), StartupPhase::Syncing);
+
+        // Far behind the network and no clock measurement yet
+        state.observe_network_slot(Slot(10));
+        assert_eq!(state.update(Slot(0)), StartupPhase::Syncing);
+
+        state.observe_clock_drift(10);
+        assert_eq!(state.update(Slot(8)), StartupPhase::WarmUp);
+        assert!(!state.may_sign());
+
+        assert_eq!(state.update(Slot(9)), StartupPhase::WarmUp);
+        assert_eq!(state.update(Slot(10)), StartupPhase::Active);
+        assert!(state.may_sign());
+    }
+
+    #[test]
+    fn test_warmup_falls_back_on_clock_drift() {
+        let mut state = StartupState::new(StartupConfig::default());
+        state.mark_safety_file_loaded();
+        state.observe_network_slot(Slot(0));
+        state.observe_clock_drift(0);
+        assert_eq!(state.update(Slot(0)), StartupPhase::WarmUp);
+
+        state.observe_clock_drift(500);
+        assert_eq!(state.update(Slot(1)), StartupPhase::Syncing);
+    }
+}
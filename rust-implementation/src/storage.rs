@@ -0,0 +1,219 @@
+//! Durable engine state for crash recovery
+//!
+//! `ConsensusEngine::persist` writes the state a restarted validator needs to
+//! resume safely: its slot and leader, every vote it signed (so it never
+//! signs a conflicting one after a crash) and the finalized certificates.
+//! `ConsensusEngine::recover` reads it back. State goes through the
+//! key-value `Storage` trait; `FileStorage` replaces each value atomically.
+
+use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusError};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Key the engine state is stored under
+pub const ENGINE_STATE_KEY: &str = "engine-state";
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+/// Key-value store for durable state
+pub trait Storage: Send + Sync {
+    /// Replace the value of `key`; a crash leaves either the old or the new value
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), StorageError>;
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+}
+
+/// Volatile storage, for tests
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        self.values.lock().unwrap().insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+}
+
+/// Storage keeping one file per key in a directory
+///
+/// Values are written to a temporary file and renamed over the old one.
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Open a storage directory, creating it if needed
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+impl Storage for FileStorage {
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        let path = self.dir.join(key);
+        let tmp = self.dir.join(format!("{key}.tmp"));
+        fs::write(&tmp, value)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match fs::read(self.dir.join(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Engine state written by `ConsensusEngine::persist`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineState {
+    pub slot: Slot,
+    pub leader: ValidatorId,
+    /// Every vote we signed, by slot and kind
+    pub signed_votes: Vec<(Slot, VoteKind, BlockId)>,
+    pub finalized: Vec<FinalizationCertificate>,
+}
+
+impl ConsensusEngine {
+    /// Write the state needed to resume after a crash to `storage`
+    ///
+    /// Call it after every signed vote, before the vote leaves the node, so
+    /// a restart can't lose a vote that peers have seen.
+    pub fn persist(&self, storage: &dyn Storage) -> Result<(), ConsensusError> {
+        let bytes = bincode::serialize(&self.engine_state()).map_err(StorageError::from)?;
+        storage.put(ENGINE_STATE_KEY, &bytes)?;
+        Ok(())
+    }
+
+    /// Restart an engine from the state in `storage`
+    ///
+    /// Without stored state the engine starts fresh at slot 0. Either way the
+    /// voting-safety file counts as loaded, so startup proceeds to syncing.
+    pub fn recover(
+        validator_id: ValidatorId,
+        validator_set: ValidatorSet,
+        config: ConsensusConfig,
+        storage: &dyn Storage,
+    ) -> Result<Self, ConsensusError> {
+        let mut engine = Self::new(validator_id, validator_set, config);
+        if let Some(bytes) = storage.get(ENGINE_STATE_KEY)? {
+            let state: EngineState = bincode::deserialize(&bytes).map_err(StorageError::from)?;
+            tracing::info!(
+                "Recovered at slot {} with {} finalized blocks and {} signed votes",
+                state.slot,
+                state.finalized.len(),
+                state.signed_votes.len()
+            );
+            engine.restore_state(state);
+        }
+        engine.mark_voting_safety_loaded();
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+
+    fn vote(validator: u64, block: &Block) -> Vote {
+        Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: block.id,
+            slot: block.header.slot,
+            kind: VoteKind::Notar,
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn test_restart_resumes_from_persisted_state() {
+        let dir = std::env::temp_dir().join(format!("alpenglow-storage-{}", std::process::id()));
+        let storage = FileStorage::open(&dir).unwrap();
+        let config = ConsensusConfig::default();
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(2), vset, config.clone());
+        let leader_rotor = Rotor::new(StakeDistribution::Equal(100).validator_set(5, 0));
+
+        let genesis = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        for i in [0, 1, 3, 4] {
+            engine.process_vote(vote(i, &genesis)).unwrap();
+        }
+        engine.next_slot();
+
+        // We vote for slot 1's block, then crash
+        let mut block = genesis.clone();
+        block.header.slot = Slot(1);
+        block.header.leader = ValidatorId::Index(1);
+        block.header.parent = Some(genesis.id);
+        block.id = block.compute_id();
+        for shred in leader_rotor.encode_block(&block).unwrap() {
+            engine.receive_shred(shred).unwrap();
+        }
+        assert_eq!(engine.take_outgoing_votes().len(), 1);
+        engine.persist(&storage).unwrap();
+        drop(engine);
+
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let mut engine =
+            ConsensusEngine::recover(ValidatorId::Index(2), vset, config, &storage).unwrap();
+        assert_eq!(engine.current_slot(), Slot(1));
+        assert_eq!(engine.current_leader(), ValidatorId::Index(1));
+        assert!(engine.is_finalized(&genesis.id));
+        assert_eq!(engine.parent_for_slot(Slot(1)).unwrap(), Some(genesis.id));
+
+        // The vote signed before the crash still rules out a conflicting one
+        let mut conflicting = block.clone();
+        conflicting.header.timestamp += 1;
+        conflicting.id = conflicting.compute_id();
+        let results: Vec<_> = leader_rotor
+            .encode_block(&conflicting)
+            .unwrap()
+            .into_iter()
+            .map(|shred| engine.receive_shred(shred))
+            .collect();
+        assert!(matches!(results.last(), Some(Err(ConsensusError::WouldDoubleSign(Slot(1))))));
+        assert!(engine.take_outgoing_votes().is_empty());
+
+        assert!(ConsensusEngine::recover(
+            ValidatorId::Index(1),
+            StakeDistribution::Equal(100).validator_set(5, 0),
+            ConsensusConfig::default(),
+            &MemoryStorage::new()
+        )
+        .unwrap()
+        .finalized_blocks()
+        .is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
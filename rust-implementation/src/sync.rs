@@ -0,0 +1,237 @@
+//! Catch-up sync from finalization certificates
+//!
+//! A node that falls behind the network asks a peer for the certificates and
+//! block bodies of the slots it missed. A response is only applied once every
+//! certificate verifies against its epoch's validator set, down to each
+//! vote's signature under our chain ID, and the certified blocks chain back
+//! to our latest finalized block. Until the node has caught up it stays in
+//! `Syncing` and signs nothing.
+
+use crate::epoch::EpochValidatorSets;
+use crate::integrity::{self, InvariantViolation};
+use crate::params::ProtocolParams;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Most slots a single sync request covers
+pub const MAX_SYNC_SLOTS: u64 = 256;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    #[error("Invalid certificate in sync response: {0}")]
+    InvalidCertificate(#[from] InvariantViolation),
+
+    #[error("Certificate for slot {0} is outside the requested range")]
+    OutOfRange(Slot),
+
+    #[error("Certificate for slot {0} is out of slot order")]
+    Unordered(Slot),
+
+    #[error("No block body for certified slot {0}")]
+    MissingBlock(Slot),
+
+    #[error("Block {0} does not match its ID or certificate")]
+    BlockMismatch(BlockId),
+
+    #[error("Block in slot {0} does not descend from the previous finalized block")]
+    BrokenChain(Slot),
+}
+
+/// Request for the certificates and blocks of slots `from..=to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub from: Slot,
+    pub to: Slot,
+}
+
+impl SyncRequest {
+    pub fn contains(&self, slot: Slot) -> bool {
+        self.from <= slot && slot <= self.to
+    }
+}
+
+/// Answer to a `SyncRequest`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncResponse {
+    /// Finalization certificates in slot order
+    pub certificates: Vec<FinalizationCertificate>,
+
+    /// Bodies of the certified blocks and of any uncertified ancestors
+    /// linking them
+    pub blocks: Vec<Block>,
+}
+
+/// Check a sync response and return its certified blocks in slot order
+///
+/// Every vote must be signed for `chain_id` by a key in its epoch's set.
+/// `anchor` is our latest finalized block; the first certified block must
+/// descend from it, and each later one from its predecessor, through the
+/// blocks in the response.
+pub fn verify_response(
+    epochs: &EpochValidatorSets,
+    params: &ProtocolParams,
+    chain_id: &[u8; 32],
+    request: &SyncRequest,
+    anchor: Option<BlockId>,
+    response: SyncResponse,
+) -> Result<Vec<(FinalizationCertificate, Block)>, SyncError> {
+    let mut last_slot = None;
+    for cert in &response.certificates {
+        if !request.contains(cert.slot) {
+            return Err(SyncError::OutOfRange(cert.slot));
+        }
+        if last_slot.is_some_and(|last| cert.slot <= last) {
+            return Err(SyncError::Unordered(cert.slot));
+        }
+        last_slot = Some(cert.slot);
+    }
+    let certificates = &response.certificates;
+    let set_for_slot = |slot| epochs.for_slot(slot);
+    integrity::verify_certificates_with(set_for_slot, params, chain_id, certificates)?;
+
+    let mut blocks = HashMap::new();
+    for block in response.blocks {
+        if block.compute_id() != block.id {
+            return Err(SyncError::BlockMismatch(block.id));
+        }
+        blocks.insert(block.id, block);
+    }
+
+    let mut previous = anchor;
+    let mut verified = Vec::with_capacity(response.certificates.len());
+    for cert in response.certificates {
+        let block = blocks
+            .get(&cert.block_id)
+            .ok_or(SyncError::MissingBlock(cert.slot))?;
+        if block.header.slot != cert.slot {
+            return Err(SyncError::BlockMismatch(block.id));
+        }
+
+        // Walk back through strictly older blocks until we meet `previous`
+        let (mut ancestor, mut slot) = (block.header.parent, block.header.slot);
+        while ancestor != previous {
+            match ancestor.and_then(|id| blocks.get(&id)) {
+                Some(parent) if parent.header.slot < slot => {
+                    (ancestor, slot) = (parent.header.parent, parent.header.slot);
+                }
+                _ => return Err(SyncError::BrokenChain(cert.slot)),
+            }
+        }
+
+        previous = Some(cert.block_id);
+        verified.push((cert, block.clone()));
+    }
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::EpochSchedule;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+    use std::collections::HashSet;
+
+    const CHAIN_ID: [u8; 32] = [0u8; 32];
+
+    fn block(slot: u64, parent: Option<&Block>) -> Block {
+        let parent = parent.map(|parent| parent.id);
+        Block::new(Slot(slot), parent, ValidatorId::Index(slot % 5), vec![], 1000 + slot)
+    }
+
+    fn certificate(
+        vset: &ValidatorSet,
+        keys: &[SigningKey],
+        block: &Block,
+    ) -> FinalizationCertificate {
+        let votes: Vec<Vote> = (0..4)
+            .map(|i| {
+                let mut vote = Vote {
+                    validator: ValidatorId::Index(i),
+                    block_id: block.id,
+                    slot: block.header.slot,
+                    kind: VoteKind::Notar,
+                    signature: vec![],
+                };
+                vote.sign(&keys[i as usize], &CHAIN_ID);
+                vote
+            })
+            .collect();
+        let voters: HashSet<ValidatorId> = votes.iter().map(|vote| vote.validator).collect();
+        FinalizationCertificate {
+            block_id: block.id,
+            slot: block.header.slot,
+            round: VoteRound::Round1,
+            total_stake: vset.calculate_stake(&voters),
+            votes,
+        }
+    }
+
+    #[test]
+    fn test_verify_certificate_chain() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let epochs = EpochValidatorSets::new(EpochSchedule::default(), vset.clone());
+        let params = ProtocolParams::default();
+        let request = SyncRequest { from: Slot(1), to: Slot(10) };
+
+        // 0 is ours; 2 is notarized but uncertified, 3 certified on top of it
+        let genesis = block(0, None);
+        let first = block(1, Some(&genesis));
+        let middle = block(2, Some(&first));
+        let last = block(3, Some(&middle));
+        let response = SyncResponse {
+            certificates: vec![
+                certificate(&vset, &keys, &first),
+                certificate(&vset, &keys, &last),
+            ],
+            blocks: vec![first.clone(), middle.clone(), last.clone()],
+        };
+
+        let (anchor, chain_id) = (Some(genesis.id), &CHAIN_ID);
+        let verified = verify_response(&epochs, &params, chain_id, &request, anchor, response.clone());
+        let verified = verified.unwrap();
+        assert_eq!(verified.len(), 2);
+        assert_eq!(verified[1].1.id, last.id);
+
+        // Without the linking block the chain is broken
+        let mut broken = response.clone();
+        broken.blocks.retain(|block| block.id != middle.id);
+        assert!(matches!(
+            verify_response(&epochs, &params, &CHAIN_ID, &request, Some(genesis.id), broken),
+            Err(SyncError::BrokenChain(Slot(3)))
+        ));
+
+        // Neither does it descend from a different anchor
+        assert!(matches!(
+            verify_response(&epochs, &params, &CHAIN_ID, &request, Some(last.id), response.clone()),
+            Err(SyncError::BrokenChain(Slot(1)))
+        ));
+
+        let mut forged = response.clone();
+        forged.certificates[0].votes.truncate(2);
+        assert!(matches!(
+            verify_response(&epochs, &params, &CHAIN_ID, &request, Some(genesis.id), forged),
+            Err(SyncError::InvalidCertificate(_))
+        ));
+
+        // A quorum of votes is not enough if one of them isn't signed
+        let mut unsigned = response.clone();
+        unsigned.certificates[1].votes[2].signature.clear();
+        assert!(matches!(
+            verify_response(&epochs, &params, &CHAIN_ID, &request, Some(genesis.id), unsigned),
+            Err(SyncError::InvalidCertificate(InvariantViolation::InvalidVoteSignature(
+                _,
+                ValidatorId::Index(2)
+            )))
+        ));
+
+        // Nor are votes signed for another chain
+        let other_chain = [1u8; 32];
+        assert!(matches!(
+            verify_response(&epochs, &params, &other_chain, &request, Some(genesis.id), response),
+            Err(SyncError::InvalidCertificate(InvariantViolation::InvalidVoteSignature(..)))
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+//! In-process multi-validator test harness
+//!
+//! A `Cluster` runs N engines in lockstep over an in-memory message bus, with
+//! no tokio and no wall clock: every `step` advances a shared `ManualClock`
+//! by one tick, delivers the messages that are due, fires deadlines and
+//! moves engines on to their next slot. Leaders propose empty blocks as soon
+//! as they may and send each shred to the root of its relay tree, from where
+//! engines relay it on. Messages take `latency` steps plus up to `jitter`
+//! more, and messages due in the same step arrive in the configured
+//! `DeliveryOrder`, all from a seeded RNG so runs are reproducible. The same
+//! seed draws the validators' stakes from the configured `StakeDistribution`.
+
+use crate::clock::ManualClock;
+use crate::consensus::{ConsensusConfig, ConsensusEngine};
+use crate::mempool::{Mempool, MempoolConfig};
+use crate::message::ConsensusMessage;
+use crate::producer::BlockProducer;
+use crate::stake::StakeDistribution;
+use crate::timer::Timeout;
+use crate::types::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Order in which messages due in the same step arrive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOrder {
+    /// In the order they were sent
+    Fifo,
+    /// Shuffled
+    Random,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    /// Configuration every engine runs with
+    pub consensus: ConsensusConfig,
+    /// Time each step advances the clock by
+    pub tick: Duration,
+    /// Steps every message takes at least; 0 delivers within the step
+    pub latency: u64,
+    /// Most extra steps a message may take, chosen at random
+    pub jitter: u64,
+    pub order: DeliveryOrder,
+    pub seed: u64,
+    /// Stakes of the validators; equal by default
+    pub stakes: StakeDistribution,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            consensus: ConsensusConfig::default(),
+            tick: Duration::from_millis(10),
+            latency: 1,
+            jitter: 0,
+            order: DeliveryOrder::Fifo,
+            seed: 0,
+            stakes: StakeDistribution::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    due: u64,
+    from: ValidatorId,
+    to: ValidatorId,
+    message: ConsensusMessage,
+}
+
+/// Engines connected by an in-memory bus, advanced together
+pub struct Cluster {
+    config: ClusterConfig,
+    clock: ManualClock,
+    engines: Vec<ConsensusEngine>,
+    producer: BlockProducer,
+    mempool: Mempool,
+    bus: Vec<InFlight>,
+    /// Validators whose messages are dropped both ways
+    isolated: HashSet<ValidatorId>,
+    /// Slots a block was proposed for
+    proposed: HashSet<Slot>,
+    step: u64,
+    rng: StdRng,
+}
+
+impl Cluster {
+    /// `validators` validators with stakes from `config.stakes`, all at slot 0
+    pub fn new(validators: u64, config: ClusterConfig) -> Self {
+        let vset = config.stakes.validator_set(validators, config.seed);
+        let clock = ManualClock::new();
+        let engines = (0..validators)
+            .map(|i| {
+                let id = ValidatorId::Index(i);
+                let mut engine = ConsensusEngine::new(id, vset.clone(), config.consensus.clone());
+                engine.set_clock(Arc::new(clock.clone()));
+                engine.start_round1_timer();
+                engine
+            })
+            .collect();
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            clock,
+            engines,
+            producer: BlockProducer::new(),
+            mempool: Mempool::new(MempoolConfig::default()),
+            bus: Vec::new(),
+            isolated: HashSet::new(),
+            proposed: HashSet::new(),
+            step: 0,
+        }
+    }
+
+    pub fn engines(&self) -> &[ConsensusEngine] {
+        &self.engines
+    }
+
+    pub fn engine(&self, id: ValidatorId) -> &ConsensusEngine {
+        &self.engines[position(id)]
+    }
+
+    pub fn engine_mut(&mut self, id: ValidatorId) -> &mut ConsensusEngine {
+        &mut self.engines[position(id)]
+    }
+
+    /// Cut `id` off the bus; it neither sends nor receives
+    pub fn isolate(&mut self, id: ValidatorId) {
+        self.isolated.insert(id);
+    }
+
+    pub fn reconnect(&mut self, id: ValidatorId) {
+        self.isolated.remove(&id);
+    }
+
+    /// Steps run so far
+    pub fn steps(&self) -> u64 {
+        self.step
+    }
+
+    /// Advance every engine by one tick
+    pub fn step(&mut self) {
+        self.step += 1;
+        self.clock.advance(self.config.tick);
+        self.propose();
+
+        let (mut due, later): (Vec<_>, Vec<_>) = std::mem::take(&mut self.bus)
+            .into_iter()
+            .partition(|msg| msg.due <= self.step);
+        self.bus = later;
+        if self.config.order == DeliveryOrder::Random {
+            due.shuffle(&mut self.rng);
+        }
+        for msg in due {
+            self.deliver(msg);
+        }
+
+        for i in 0..self.engines.len() {
+            let engine = &mut self.engines[i];
+            let slot = engine.current_slot();
+            let round2_over = engine.fire_timers().contains(&Timeout::Round2(slot));
+            if round2_over && engine.current_slot() == slot {
+                engine.next_slot();
+            } else {
+                engine.advance_pipeline();
+            }
+            // No-op unless the slot changed here or while handling messages
+            engine.start_round1_timer();
+            self.flush_votes(ValidatorId::Index(i as u64));
+        }
+    }
+
+    /// Step until `done` holds, at most `max_steps` times; whether it held
+    pub fn run_until(&mut self, max_steps: u64, mut done: impl FnMut(&Cluster) -> bool) -> bool {
+        for _ in 0..max_steps {
+            if done(self) {
+                return true;
+            }
+            self.step();
+        }
+        done(self)
+    }
+
+    /// Step until every connected engine finalized a block in `slot`
+    pub fn run_until_finalized(&mut self, slot: Slot, max_steps: u64) -> bool {
+        self.run_until(max_steps, |cluster| {
+            cluster.connected().all(|engine| finalized_in(engine, slot).is_some())
+        })
+    }
+
+    /// Panic unless every connected engine finalized the same block in `slot`
+    pub fn assert_finalized(&self, slot: Slot) -> BlockId {
+        let mut finalized = self.connected().map(|engine| finalized_in(engine, slot));
+        let first = finalized.next().flatten();
+        let Some(block_id) = first else {
+            panic!("slot {slot} not finalized by every connected validator");
+        };
+        assert!(
+            finalized.all(|other| other == Some(block_id)),
+            "validators finalized different blocks in slot {slot}"
+        );
+        block_id
+    }
+
+    fn connected(&self) -> impl Iterator<Item = &ConsensusEngine> {
+        let isolated = |i: usize| self.isolated.contains(&ValidatorId::Index(i as u64));
+        self.engines
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| !isolated(*i))
+            .map(|(_, engine)| engine)
+    }
+
+    /// Let leaders propose the first time they may
+    fn propose(&mut self) {
+        for i in 0..self.engines.len() {
+            let slot = self.engines[i].current_slot();
+            if self.proposed.contains(&slot) {
+                continue;
+            }
+            let Some(block) = self.producer.build_block(&self.engines[i], &mut self.mempool)
+            else {
+                continue;
+            };
+            let engine = &mut self.engines[i];
+            let Ok(shreds) = engine.propose_block(block) else {
+                continue;
+            };
+            self.proposed.insert(slot);
+            for (to, positions) in engine.broadcast_plan(&shreds) {
+                for shred in shreds.iter().filter(|shred| positions.contains(&shred.position())) {
+                    let message = ConsensusMessage::Shred(shred.clone());
+                    self.send(ValidatorId::Index(i as u64), to, message);
+                }
+            }
+        }
+    }
+
+    /// Put a message on the bus, unless either end is isolated
+    fn send(&mut self, from: ValidatorId, to: ValidatorId, message: ConsensusMessage) {
+        if from == to || self.isolated.contains(&from) || self.isolated.contains(&to) {
+            return;
+        }
+        let delay = self.config.latency + self.rng.gen_range(0..=self.config.jitter);
+        self.bus.push(InFlight { due: self.step + delay, from, to, message });
+    }
+
+    fn broadcast(&mut self, from: ValidatorId, message: ConsensusMessage) {
+        for i in 0..self.engines.len() as u64 {
+            self.send(from, ValidatorId::Index(i), message.clone());
+        }
+    }
+
+    fn deliver(&mut self, msg: InFlight) {
+        if self.isolated.contains(&msg.to) {
+            return;
+        }
+        let engine = &mut self.engines[position(msg.to)];
+        // Rejected messages (late votes, stale certificates) are dropped as
+        // a node would
+        if let Ok(dispatched) = engine.dispatch(msg.from, msg.message) {
+            for reply in dispatched.replies {
+                self.send(msg.to, msg.from, reply);
+            }
+            if let Some(cert) = dispatched.finalized.filter(|_| dispatched.assembled) {
+                self.broadcast(msg.to, ConsensusMessage::Certificate(cert));
+            }
+        }
+        for (to, shred) in self.engines[position(msg.to)].take_outgoing_shreds() {
+            self.send(msg.to, to, ConsensusMessage::Shred(shred));
+        }
+        self.flush_votes(msg.to);
+    }
+
+    /// Send the votes `id` cast to everyone
+    fn flush_votes(&mut self, id: ValidatorId) {
+        let engine = &mut self.engines[position(id)];
+        let mut votes = engine.take_outgoing_votes();
+        votes.extend(engine.rebroadcast_votes());
+        for vote in votes {
+            self.broadcast(id, ConsensusMessage::Vote(vote));
+        }
+    }
+}
+
+/// Position of a cluster validator's engine; they are numbered from 0
+fn position(id: ValidatorId) -> usize {
+    id.index().expect("cluster validators are numbered") as usize
+}
+
+/// Block `engine` finalized in `slot`
+fn finalized_in(engine: &ConsensusEngine, slot: Slot) -> Option<BlockId> {
+    engine
+        .finalized_blocks()
+        .iter()
+        .find(|cert| cert.slot == slot)
+        .map(|cert| cert.block_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_finalizes_with_shuffled_delivery_and_isolation() {
+        let config = ClusterConfig {
+            latency: 2,
+            jitter: 1,
+            order: DeliveryOrder::Random,
+            seed: 7,
+            ..ClusterConfig::default()
+        };
+        let mut cluster = Cluster::new(10, config);
+        // Leaders don't vote for their own blocks, so the other eight
+        // connected validators make up the 80% fast path quorum
+        cluster.isolate(ValidatorId::Index(9));
+        assert!(cluster.run_until_finalized(Slot(3), 500));
+        let block_ids: Vec<_> = (0..4).map(|slot| cluster.assert_finalized(Slot(slot))).collect();
+        assert_eq!(cluster.engine(ValidatorId::Index(0)).finalized_blocks().len(), 4);
+        assert!(block_ids.iter().all(|id| cluster.engine(ValidatorId::Index(2)).is_finalized(id)));
+        assert!(cluster.engine(ValidatorId::Index(9)).finalized_blocks().is_empty());
+    }
+
+    #[test]
+    fn test_shreds_reach_everyone_through_relays() {
+        let mut config = ClusterConfig::default();
+        config.consensus.rotor.fanout = 2;
+        let mut cluster = Cluster::new(10, config);
+        // Leaders send each shred to two validators; the rest relay it on
+        assert!(cluster.run_until_finalized(Slot(1), 200));
+        cluster.assert_finalized(Slot(0));
+        cluster.assert_finalized(Slot(1));
+    }
+
+    #[test]
+    fn test_cluster_finalizes_with_whales_and_a_small_validator_offline() {
+        let stakes = StakeDistribution::Whales { whales: 3, whale_pct: 70, total: 10_000 };
+        let vset = stakes.validator_set(10, 11);
+        // The last of the light validators, so it doesn't lead any slot up to 2
+        let lightest = vset.sorted_validators().into_iter().rev().min_by_key(|v| v.stake);
+        let lightest = lightest.unwrap().id;
+        let config = ClusterConfig { stakes, seed: 11, ..ClusterConfig::default() };
+        let mut cluster = Cluster::new(10, config);
+        // Losing the lightest validator barely dents the stake behind the quorum
+        cluster.isolate(lightest);
+        assert!(cluster.run_until_finalized(Slot(2), 500));
+        cluster.assert_finalized(Slot(2));
+    }
+}
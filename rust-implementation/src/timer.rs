@@ -0,0 +1,120 @@
+//! Round deadlines of the slots in flight
+//!
+//! When a slot starts, `TimerService` schedules its round 1 deadline and,
+//! the round 2 timeout later, its round 2 deadline, using the
+//! `RoundTimeouts` of the slot's epoch. The engine fires whatever
+//! fell due (`ConsensusEngine::fire_timers`) and the run loop sleeps until
+//! `next_deadline`, so nothing has to poll for timeouts.
+
+use crate::timing::RoundTimeouts;
+use crate::types::*;
+use std::time::Instant;
+
+/// A round deadline that passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Round 1 ended without finalization; round 2 begins
+    Round1(Slot),
+    /// Round 2 ended as well; the slot is given up on
+    Round2(Slot),
+}
+
+impl Timeout {
+    pub fn slot(&self) -> Slot {
+        match self {
+            Timeout::Round1(slot) | Timeout::Round2(slot) => *slot,
+        }
+    }
+}
+
+/// Pending round deadlines, earliest first
+#[derive(Debug, Clone, Default)]
+pub struct TimerService {
+    pending: Vec<(Instant, Timeout)>,
+}
+
+impl TimerService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule both deadlines of `slot` counted from `start`, replacing
+    /// any already scheduled for it
+    pub fn start_slot(&mut self, slot: Slot, start: Instant, timeouts: RoundTimeouts) {
+        self.cancel(slot);
+        let round1 = start + timeouts.round1;
+        self.pending.push((round1, Timeout::Round1(slot)));
+        self.pending.push((round1 + timeouts.round2, Timeout::Round2(slot)));
+        self.pending.sort_by_key(|(deadline, _)| *deadline);
+    }
+
+    /// Whether deadlines are scheduled for `slot`
+    pub fn is_running(&self, slot: Slot) -> bool {
+        self.pending.iter().any(|(_, timeout)| timeout.slot() == slot)
+    }
+
+    /// Drop the deadlines of `slot`
+    pub fn cancel(&mut self, slot: Slot) {
+        self.pending.retain(|(_, timeout)| timeout.slot() != slot);
+    }
+
+    /// Drop every deadline of slots before `slot`
+    pub fn cancel_before(&mut self, slot: Slot) {
+        self.pending.retain(|(_, timeout)| timeout.slot() >= slot);
+    }
+
+    /// Drop every deadline
+    pub fn cancel_all(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// Remove and return the deadlines due at `now`, earliest first
+    pub fn expired(&mut self, now: Instant) -> Vec<Timeout> {
+        let due = self.pending.partition_point(|(deadline, _)| *deadline <= now);
+        self.pending.drain(..due).map(|(_, timeout)| timeout).collect()
+    }
+
+    /// Whether the round 1 deadline of `slot` is scheduled and has passed
+    ///
+    /// False once it has been taken out by `expired`, and for unscheduled
+    /// slots.
+    pub fn round1_expired(&self, slot: Slot, now: Instant) -> bool {
+        self.pending
+            .iter()
+            .any(|(deadline, timeout)| *timeout == Timeout::Round1(slot) && *deadline <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_deadlines_fire_in_order() {
+        let mut timers = TimerService::new();
+        let timeouts = RoundTimeouts::default();
+        let start = Instant::now();
+        timers.start_slot(Slot(3), start, timeouts);
+        assert!(timers.is_running(Slot(3)));
+        assert_eq!(timers.next_deadline(), Some(start + Duration::from_millis(100)));
+
+        assert!(timers.expired(start + Duration::from_millis(99)).is_empty());
+        assert!(timers.round1_expired(Slot(3), start + Duration::from_millis(100)));
+        assert_eq!(
+            timers.expired(start + Duration::from_millis(300)),
+            vec![Timeout::Round1(Slot(3)), Timeout::Round2(Slot(3))]
+        );
+        assert_eq!(timers.next_deadline(), None);
+
+        timers.start_slot(Slot(4), start, timeouts);
+        timers.start_slot(Slot(5), start, timeouts);
+        timers.cancel_before(Slot(5));
+        assert!(!timers.is_running(Slot(4)));
+        assert!(timers.is_running(Slot(5)));
+    }
+}
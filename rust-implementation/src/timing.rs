@@ -0,0 +1,133 @@
+//! Slot timing configuration
+//!
+//! Slots have a fixed `SlotDuration` for the life of the chain, so every
+//! node derives the same current slot from the genesis time. The round
+//! timeouts may change at epoch boundaries: `TimingConfig` holds the ones in
+//! force from genesis and those taking over from later epochs, and both the
+//! `SlotClock` and the engine's round deadlines look them up per slot.
+
+use crate::params::ProtocolParams;
+use crate::types::Epoch;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Length of a slot; never zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SlotDuration(Duration);
+
+impl SlotDuration {
+    /// `None` for a zero duration
+    pub fn new(duration: Duration) -> Option<Self> {
+        (!duration.is_zero()).then_some(Self(duration))
+    }
+
+    pub const fn from_millis(millis: u64) -> Self {
+        assert!(millis > 0, "slots must have a positive length");
+        Self(Duration::from_millis(millis))
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Default for SlotDuration {
+    fn default() -> Self {
+        Self::from_millis(400)
+    }
+}
+
+/// How long each voting round of a slot waits before giving up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundTimeouts {
+    /// From the slot's start until round 2 begins
+    pub round1: Duration,
+    /// From the end of round 1 until the slot is given up on
+    pub round2: Duration,
+}
+
+impl RoundTimeouts {
+    pub fn new(round1: Duration, round2: Duration) -> Self {
+        Self { round1, round2 }
+    }
+}
+
+impl Default for RoundTimeouts {
+    fn default() -> Self {
+        ProtocolParams::DEFAULT.round_timeouts()
+    }
+}
+
+/// Slot length and the round timeouts of every epoch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimingConfig {
+    pub slot_duration: SlotDuration,
+    /// Timeouts from genesis on
+    pub timeouts: RoundTimeouts,
+    /// Timeouts taking over from each listed epoch on
+    pub epoch_timeouts: BTreeMap<Epoch, RoundTimeouts>,
+}
+
+impl TimingConfig {
+    /// Default slot length with `timeouts` in every epoch
+    pub fn with_timeouts(timeouts: RoundTimeouts) -> Self {
+        Self { timeouts, ..Self::default() }
+    }
+
+    /// Timeouts in force during `epoch`
+    pub fn timeouts_for(&self, epoch: Epoch) -> RoundTimeouts {
+        self.epoch_timeouts
+            .range(..=epoch)
+            .next_back()
+            .map_or(self.timeouts, |(_, timeouts)| *timeouts)
+    }
+
+    /// Every timeout schedule, from genesis on
+    pub fn all_timeouts(&self) -> impl Iterator<Item = &RoundTimeouts> {
+        std::iter::once(&self.timeouts).chain(self.epoch_timeouts.values())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use crate::consensus::{ConsensusConfig, ConsensusEngine};
+    use crate::epoch::EpochSchedule;
+    use crate::stake::StakeDistribution;
+    use crate::types::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_timeouts_change_at_epoch_boundaries() {
+        let slow = RoundTimeouts::new(Duration::from_millis(300), Duration::from_millis(450));
+        let mut timing = TimingConfig::default();
+        timing.epoch_timeouts.insert(Epoch(2), slow);
+        assert_eq!(timing.timeouts_for(Epoch(1)), RoundTimeouts::default());
+        assert_eq!(timing.timeouts_for(Epoch(2)), slow);
+        assert_eq!(timing.timeouts_for(Epoch(9)), slow);
+        assert_eq!(SlotDuration::new(Duration::ZERO), None);
+
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let config = ConsensusConfig {
+            timing,
+            epoch_schedule: EpochSchedule::new(4),
+            ..ConsensusConfig::default()
+        };
+        let clock = ManualClock::new();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config);
+        engine.set_clock(Arc::new(clock.clone()));
+
+        engine.start_round1_timer();
+        assert_eq!(engine.next_deadline(), Some(clock.now() + Duration::from_millis(100)));
+
+        // Slot 8 opens epoch 2 and its deadlines follow the slower schedule
+        clock.advance(Duration::from_secs(1));
+        engine.fire_timers();
+        while engine.current_slot() < Slot(8) {
+            engine.next_slot();
+        }
+        engine.start_round1_timer();
+        assert_eq!(engine.next_deadline(), Some(clock.now() + slow.round1));
+    }
+}
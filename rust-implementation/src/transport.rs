@@ -0,0 +1,272 @@
+//! Network transport used by the engine's run loop
+//!
+//! `ConsensusEngine::run` only talks to peers through `Transport`, so
+//! simulations, multi-node tests and real network backends run the same
+//! engine code. Transports carry `ConsensusMessage`s of every kind alike;
+//! backends that serialize them send each one in an `Envelope`.
+//! `LoopbackNetwork` connects transports in one process.
+//!
+//! Peers exchange `Handshake`s when they connect: a node whose protocol
+//! version a peer refuses cannot join, and each transport keeps the
+//! features negotiated with every peer.
+
+use crate::handshake::{
+    Capabilities, CapabilityRegistry, Handshake, HandshakeError, PeerCapabilities,
+};
+use crate::message::ConsensusMessage;
+use crate::types::*;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    #[error("Unknown peer {0}")]
+    UnknownPeer(ValidatorId),
+
+    #[error("Transport closed")]
+    Closed,
+
+    #[error("Handshake failed: {0}")]
+    Handshake(#[from] HandshakeError),
+}
+
+/// Point-to-point and broadcast delivery between validators
+pub trait Transport: Send {
+    fn send_to(&self, to: ValidatorId, message: ConsensusMessage) -> Result<(), TransportError>;
+
+    /// Send to every peer except ourselves
+    fn broadcast(&self, message: ConsensusMessage) -> Result<(), TransportError>;
+
+    /// Next incoming message with its sender; `None` once the transport closes
+    ///
+    /// Must be cancel-safe: the run loop polls it in `select!`.
+    fn recv(&mut self) -> impl Future<Output = Option<(ValidatorId, ConsensusMessage)>> + Send;
+
+    /// What the handshake with `peer` negotiated; `None` if it isn't
+    /// connected
+    fn peer_capabilities(&self, peer: &ValidatorId) -> Option<PeerCapabilities>;
+}
+
+type Inbox = mpsc::UnboundedSender<(ValidatorId, ConsensusMessage)>;
+
+/// A validator attached to a `LoopbackNetwork`
+#[derive(Debug)]
+struct Peer {
+    inbox: Inbox,
+    capabilities: Arc<Mutex<CapabilityRegistry>>,
+}
+
+/// In-process network connecting `LoopbackTransport`s
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackNetwork {
+    peers: Arc<Mutex<HashMap<ValidatorId, Peer>>>,
+}
+
+impl LoopbackNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a validator speaking our protocol version with every
+    /// supported feature, replacing an earlier transport with the same ID
+    pub fn join(&self, id: ValidatorId) -> Result<LoopbackTransport, TransportError> {
+        self.join_with(CapabilityRegistry::new(id, Capabilities::SUPPORTED))
+    }
+
+    /// Attach the validator `registry` speaks for, after exchanging
+    /// handshakes with every attached peer
+    ///
+    /// Fails, leaving the network unchanged, if either side of any pair
+    /// refuses the other's handshake.
+    pub fn join_with(
+        &self,
+        mut registry: CapabilityRegistry,
+    ) -> Result<LoopbackTransport, TransportError> {
+        let local = registry.local_handshake().clone();
+        let id = local.validator;
+        let mut peers = self.peers.lock().unwrap();
+        let others: Vec<&Peer> =
+            peers.iter().filter(|(other, _)| **other != id).map(|(_, peer)| peer).collect();
+        let remotes: Vec<Handshake> = others
+            .iter()
+            .map(|peer| peer.capabilities.lock().unwrap().local_handshake().clone())
+            .collect();
+        for (peer, remote) in others.iter().zip(&remotes) {
+            registry.check(remote)?;
+            peer.capabilities.lock().unwrap().check(&local)?;
+        }
+        for (peer, remote) in others.iter().zip(&remotes) {
+            registry.accept(remote)?;
+            peer.capabilities.lock().unwrap().accept(&local)?;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let capabilities = Arc::new(Mutex::new(registry));
+        peers.insert(
+            id,
+            Peer {
+                inbox: sender,
+                capabilities: capabilities.clone(),
+            },
+        );
+        Ok(LoopbackTransport {
+            id,
+            network: self.clone(),
+            receiver,
+            capabilities,
+        })
+    }
+
+    /// Detach a validator; messages to it fail with `UnknownPeer`
+    pub fn disconnect(&self, id: &ValidatorId) {
+        let mut peers = self.peers.lock().unwrap();
+        peers.remove(id);
+        for peer in peers.values() {
+            peer.capabilities.lock().unwrap().disconnect(id);
+        }
+    }
+}
+
+/// One validator's endpoint on a `LoopbackNetwork`
+#[derive(Debug)]
+pub struct LoopbackTransport {
+    id: ValidatorId,
+    network: LoopbackNetwork,
+    receiver: mpsc::UnboundedReceiver<(ValidatorId, ConsensusMessage)>,
+    capabilities: Arc<Mutex<CapabilityRegistry>>,
+}
+
+impl Transport for LoopbackTransport {
+    fn send_to(&self, to: ValidatorId, message: ConsensusMessage) -> Result<(), TransportError> {
+        let peers = self.network.peers.lock().unwrap();
+        let peer = peers.get(&to).ok_or(TransportError::UnknownPeer(to))?;
+        peer.inbox
+            .send((self.id, message))
+            .map_err(|_| TransportError::Closed)
+    }
+
+    fn broadcast(&self, message: ConsensusMessage) -> Result<(), TransportError> {
+        let peers = self.network.peers.lock().unwrap();
+        for (id, peer) in peers.iter() {
+            if *id != self.id {
+                // A peer that went away must not stop delivery to the rest
+                let _ = peer.inbox.send((self.id, message.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> impl Future<Output = Option<(ValidatorId, ConsensusMessage)>> + Send {
+        self.receiver.recv()
+    }
+
+    fn peer_capabilities(&self, peer: &ValidatorId) -> Option<PeerCapabilities> {
+        self.capabilities.lock().unwrap().peer(peer).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::Feature;
+
+    fn vote(validator: u64) -> Vote {
+        Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(0),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loopback_delivery() {
+        let network = LoopbackNetwork::new();
+        let a = network.join(ValidatorId::Index(0)).unwrap();
+        let mut b = network.join(ValidatorId::Index(1)).unwrap();
+        let mut c = network.join(ValidatorId::Index(2)).unwrap();
+
+        a.broadcast(ConsensusMessage::Vote(vote(0))).unwrap();
+        a.send_to(ValidatorId::Index(2), ConsensusMessage::Vote(vote(9))).unwrap();
+        assert!(matches!(b.recv().await, Some((ValidatorId::Index(0), ConsensusMessage::Vote(_)))));
+        for expected in [0, 9] {
+            let Some((_, ConsensusMessage::Vote(received))) = c.recv().await else {
+                panic!("vote expected");
+            };
+            assert_eq!(received.validator, ValidatorId::Index(expected));
+        }
+
+        network.disconnect(&ValidatorId::Index(2));
+        assert_eq!(
+            a.send_to(ValidatorId::Index(2), ConsensusMessage::Vote(vote(0))),
+            Err(TransportError::UnknownPeer(ValidatorId::Index(2)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loopback_preserves_send_order() {
+        let network = LoopbackNetwork::new();
+        let mut a = network.join(ValidatorId::Index(0)).unwrap();
+        let mut b = network.join(ValidatorId::Index(1)).unwrap();
+        let c = network.join(ValidatorId::Index(2)).unwrap();
+
+        // Interleaved senders: each one's messages arrive in the order they were sent
+        for seq in 0..50 {
+            a.send_to(ValidatorId::Index(1), ConsensusMessage::Vote(vote(seq))).unwrap();
+            c.send_to(ValidatorId::Index(1), ConsensusMessage::Vote(vote(100 + seq))).unwrap();
+        }
+        let mut next = [0, 100];
+        for _ in 0..100 {
+            let Some((from, ConsensusMessage::Vote(received))) = b.recv().await else {
+                panic!("vote expected");
+            };
+            let sender = usize::from(from == ValidatorId::Index(2));
+            assert_eq!(received.validator, ValidatorId::Index(next[sender]));
+            next[sender] += 1;
+        }
+        assert_eq!(next, [50, 150]);
+
+        // A broadcast never loops back to the sender
+        b.broadcast(ConsensusMessage::Vote(vote(7))).unwrap();
+        assert!(matches!(a.recv().await, Some((ValidatorId::Index(1), _))));
+        assert!(b.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_peers_handshake_on_join() {
+        let network = LoopbackNetwork::new();
+        let a = network.join(ValidatorId::Index(0)).unwrap();
+        let plain = CapabilityRegistry::new(ValidatorId::Index(1), Capabilities::NONE);
+        let b = network.join_with(plain).unwrap();
+
+        let negotiated = a.peer_capabilities(&ValidatorId::Index(1)).unwrap();
+        assert_eq!(negotiated.advertised, Capabilities::NONE);
+        assert!(!negotiated.negotiated.supports(Feature::RepairProtocol));
+        let negotiated = b.peer_capabilities(&ValidatorId::Index(0)).unwrap();
+        assert_eq!(negotiated.advertised, Capabilities::SUPPORTED);
+        assert_eq!(negotiated.negotiated, Capabilities::NONE);
+
+        // A node of a version we don't speak is turned away
+        let newer = CapabilityRegistry::with_handshake(Handshake {
+            validator: ValidatorId::Index(2),
+            protocol_version: crate::PROTOCOL_VERSION + 1,
+            capabilities: Capabilities::SUPPORTED,
+        });
+        assert!(matches!(
+            network.join_with(newer),
+            Err(TransportError::Handshake(HandshakeError::IncompatibleVersion { .. }))
+        ));
+        assert!(a.peer_capabilities(&ValidatorId::Index(2)).is_none());
+        assert_eq!(
+            a.send_to(ValidatorId::Index(2), ConsensusMessage::Vote(vote(0))),
+            Err(TransportError::UnknownPeer(ValidatorId::Index(2)))
+        );
+
+        network.disconnect(&ValidatorId::Index(1));
+        assert!(a.peer_capabilities(&ValidatorId::Index(1)).is_none());
+    }
+}
@@ -1,21 +1,130 @@
 //! Core data types for Alpenglow consensus
 
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use crate::params::ProtocolParams;
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier as _, VerifyingKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use thiserror::Error;
 
 /// Unique identifier for a validator
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct ValidatorId(pub u64);
+///
+/// IDs need not be dense: test networks number validators from 0, while
+/// deployments without pre-agreed numbers identify each validator by its
+/// 32-byte public key with `from_pubkey`. Indices order before keys.
+///
+/// Human-readable serde formats (JSON, TOML) carry an index as a number and
+/// a key as 64 hex digits; binary ones carry the variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub enum ValidatorId {
+    Index(u64),
+    Key([u8; 32]),
+}
+
+impl ValidatorId {
+    /// Index written in place of an index to mark a key ID in fixed-width
+    /// encodings; no validator may use it as its index
+    pub const KEY_MARKER: u64 = u64::MAX;
+
+    /// ID of the validator holding `key`: the key itself
+    pub fn from_pubkey(key: &VerifyingKey) -> Self {
+        ValidatorId::Key(key.to_bytes())
+    }
+
+    /// Append the encoding used in signed bytes and wire formats: an index
+    /// as 8 little-endian bytes, a key as `KEY_MARKER` followed by the key
+    pub fn write_bytes(&self, out: &mut Vec<u8>) {
+        match self {
+            ValidatorId::Index(index) => out.extend_from_slice(&index.to_le_bytes()),
+            ValidatorId::Key(key) => {
+                out.extend_from_slice(&Self::KEY_MARKER.to_le_bytes());
+                out.extend_from_slice(key);
+            }
+        }
+    }
+
+    /// The index, unless the validator is identified by key
+    pub fn index(&self) -> Option<u64> {
+        match self {
+            ValidatorId::Index(index) => Some(*index),
+            ValidatorId::Key(_) => None,
+        }
+    }
+}
 
 impl fmt::Display for ValidatorId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "V{}", self.0)
+        match self {
+            ValidatorId::Index(index) => write!(f, "V{}", index),
+            ValidatorId::Key(key) => write!(f, "V{}", hex::encode(key)),
+        }
+    }
+}
+
+/// Binary form of `ValidatorId`, as the derived impls would write it
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ValidatorId")]
+enum RawValidatorId {
+    Index(u64),
+    Key([u8; 32]),
+}
+
+/// Human-readable form of `ValidatorId`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ReadableValidatorId {
+    Index(u64),
+    Key(String),
+}
+
+impl Serialize for ValidatorId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            match *self {
+                ValidatorId::Index(index) => ReadableValidatorId::Index(index),
+                ValidatorId::Key(key) => ReadableValidatorId::Key(hex::encode(key)),
+            }
+            .serialize(serializer)
+        } else {
+            match *self {
+                ValidatorId::Index(index) => RawValidatorId::Index(index),
+                ValidatorId::Key(key) => RawValidatorId::Key(key),
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ValidatorId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            match ReadableValidatorId::deserialize(deserializer)? {
+                ReadableValidatorId::Index(index) => Ok(ValidatorId::Index(index)),
+                ReadableValidatorId::Key(hex) => {
+                    let mut key = [0u8; 32];
+                    hex::decode_to_slice(&hex, &mut key).map_err(serde::de::Error::custom)?;
+                    Ok(ValidatorId::Key(key))
+                }
+            }
+        } else {
+            Ok(match RawValidatorId::deserialize(deserializer)? {
+                RawValidatorId::Index(index) => ValidatorId::Index(index),
+                RawValidatorId::Key(key) => ValidatorId::Key(key),
+            })
+        }
     }
 }
 
 /// Stake weight for a validator
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+///
+/// `+` and `sum` saturate at `u64::MAX` rather than wrap; use
+/// `checked_add` where an overflow must be reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct StakeWeight(pub u64);
 
 impl StakeWeight {
@@ -26,19 +135,50 @@ impl StakeWeight {
     pub fn as_u64(&self) -> u64 {
         self.0
     }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self(self.0.saturating_add(other.0))
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+
+    /// `percent`% of this stake, rounded down, computed without overflow
+    pub fn percent(self, percent: u64) -> Self {
+        let scaled = u128::from(self.0) * u128::from(percent) / 100;
+        Self(u64::try_from(scaled).unwrap_or(u64::MAX))
+    }
+
+    /// `percent`% of this stake, rounded up, computed without overflow
+    ///
+    /// A stake reaches `percent`% of a total exactly when it is at least
+    /// `total.percent_ceil(percent)`.
+    pub fn percent_ceil(self, percent: u64) -> Self {
+        let scaled = (u128::from(self.0) * u128::from(percent)).div_ceil(100);
+        Self(u64::try_from(scaled).unwrap_or(u64::MAX))
+    }
 }
 
 impl std::ops::Add for StakeWeight {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        Self(self.0 + other.0)
+        self.saturating_add(other)
     }
 }
 
 impl std::ops::AddAssign for StakeWeight {
     fn add_assign(&mut self, other: Self) {
-        self.0 += other.0;
+        *self = self.saturating_add(other);
     }
 }
 
@@ -50,6 +190,7 @@ impl std::iter::Sum for StakeWeight {
 
 /// Slot number (height in the chain)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct Slot(pub u64);
 
 impl fmt::Display for Slot {
@@ -64,8 +205,29 @@ impl Slot {
     }
 }
 
+/// Epoch number; `epoch::EpochSchedule` maps it to its slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct Epoch(pub u64);
+
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Epoch{}", self.0)
+    }
+}
+
+impl Epoch {
+    pub fn next(&self) -> Self {
+        Epoch(self.0 + 1)
+    }
+}
+
 /// Block identifier (hash)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+///
+/// Displays and parses as 64 hex digits. Human-readable serde formats
+/// (JSON, TOML) carry the same string; binary ones carry the 32 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct BlockId([u8; 32]);
 
 impl BlockId {
@@ -80,59 +242,458 @@ impl BlockId {
 
 impl fmt::Display for BlockId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Block<{:x}{:x}{:x}{:x}>",
-               self.0[0], self.0[1], self.0[2], self.0[3])
+        f.write_str(&hex::encode(self.0))
     }
 }
 
-/// Block proposal
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Block {
-    pub id: BlockId,
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ParseBlockIdError {
+    #[error("Block ID must be 64 hex digits, got {0} characters")]
+    Length(usize),
+
+    #[error("Block ID is not hex: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
+}
+
+impl FromStr for BlockId {
+    type Err = ParseBlockIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 {
+            return Err(ParseBlockIdError::Length(s.len()));
+        }
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Binary form of `BlockId`, as the derived impls would write it
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "BlockId")]
+struct RawBlockId([u8; 32]);
+
+impl Serialize for BlockId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            RawBlockId(self.0).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(serde::de::Error::custom)
+        } else {
+            RawBlockId::deserialize(deserializer).map(|raw| Self(raw.0))
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TransactionError {
+    #[error("Transaction payer is not a valid public key")]
+    InvalidPayer,
+
+    #[error("Transaction signature does not verify")]
+    InvalidSignature,
+}
+
+const TRANSACTION_DOMAIN: &[u8] = b"alpenglow-transaction-v1";
+
+/// Transaction signed by the account paying for it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct Transaction {
+    /// Ed25519 public key of the payer
+    pub payer: [u8; 32],
+    /// Payer's sequence number, so repeated payloads stay distinct
+    pub nonce: u64,
+    /// Application data, opaque to consensus
+    pub payload: Vec<u8>,
+    /// Payer's signature over the other fields
+    pub signature: Vec<u8>,
+}
+
+impl Transaction {
+    /// Build a transaction signed by `payer`
+    pub fn new(payer: &SigningKey, nonce: u64, payload: Vec<u8>) -> Self {
+        let mut transaction = Self {
+            payer: payer.verifying_key().to_bytes(),
+            nonce,
+            payload,
+            signature: Vec::new(),
+        };
+        transaction.signature = payer.sign(&transaction.signing_bytes()).to_bytes().to_vec();
+        transaction
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = TRANSACTION_DOMAIN.to_vec();
+        bytes.extend_from_slice(&self.payer);
+        bytes.extend_from_slice(&self.nonce.to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Check the signature against the payer's key
+    pub fn verify(&self) -> Result<(), TransactionError> {
+        let payer =
+            VerifyingKey::from_bytes(&self.payer).map_err(|_| TransactionError::InvalidPayer)?;
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|_| TransactionError::InvalidSignature)?;
+        payer
+            .verify(&self.signing_bytes(), &signature)
+            .map_err(|_| TransactionError::InvalidSignature)
+    }
+
+    /// Hash of the signed fields; two transactions with the same payer,
+    /// nonce and payload share it
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.signing_bytes()).into()
+    }
+
+    /// Bytes the transaction takes in a bincode-serialized block
+    pub fn encoded_size(&self) -> usize {
+        // Fixed-size payer and nonce, then two length-prefixed byte strings
+        32 + 8 + (8 + self.payload.len()) + (8 + self.signature.len())
+    }
+}
+
+const BLOCK_HEADER_DOMAIN: &[u8] = b"alpenglow-block-header-v2";
+
+/// Block metadata, small enough to gossip and check without the body
+///
+/// The block ID is the hash of the header, and the header commits to the
+/// body through `transactions_root`. Slots may be skipped, so consumers
+/// wanting contiguous numbering use `height` instead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct BlockHeader {
     pub slot: Slot,
     pub parent: Option<BlockId>,
     pub leader: ValidatorId,
-    pub transactions: Vec<Vec<u8>>,  // Simplified transaction data
+    /// Number of ancestors: 0 without a parent, else the parent's plus one
+    pub height: u64,
+    /// Epoch of `slot`
+    pub epoch: Epoch,
+    /// Merkle root of the body's transactions
+    pub transactions_root: [u8; 32],
+    /// Block time in milliseconds; zero on chains whose `BlockTimePolicy`
+    /// omits it
     pub timestamp: u64,
+    /// Leader's signature over the block ID; empty if unsigned
+    pub signature: Vec<u8>,
+}
+
+impl BlockHeader {
+    /// Hash of every field but the signature
+    pub fn id(&self) -> BlockId {
+        self.id_with_root(&self.transactions_root)
+    }
+
+    /// Hash of the header as if it committed to `transactions_root`
+    fn id_with_root(&self, transactions_root: &[u8; 32]) -> BlockId {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(&self.slot).unwrap());
+        hasher.update(bincode::serialize(&self.parent).unwrap());
+        hasher.update(bincode::serialize(&self.leader).unwrap());
+        hasher.update(self.height.to_le_bytes());
+        hasher.update(self.epoch.0.to_le_bytes());
+        hasher.update(transactions_root);
+        hasher.update(bincode::serialize(&self.timestamp).unwrap());
+        BlockId(hasher.finalize().into())
+    }
+
+    /// Bytes covered by the leader signature: a domain tag, the chain ID
+    /// and the block ID
+    pub fn signing_bytes(&self, chain_id: &[u8; 32]) -> Vec<u8> {
+        let mut bytes = BLOCK_HEADER_DOMAIN.to_vec();
+        bytes.extend_from_slice(chain_id);
+        bytes.extend_from_slice(self.id().as_bytes());
+        bytes
+    }
+
+    pub fn sign(&mut self, key: &SigningKey, chain_id: &[u8; 32]) {
+        self.signature = key.sign(&self.signing_bytes(chain_id)).to_bytes().to_vec();
+    }
+
+    /// Whether the signature is the leader's on `chain_id`, given its key
+    pub fn verify(&self, key: &VerifyingKey, chain_id: &[u8; 32]) -> bool {
+        Signature::from_slice(&self.signature)
+            .is_ok_and(|signature| key.verify(&self.signing_bytes(chain_id), &signature).is_ok())
+    }
+}
+
+/// Transactions of a block, carried by Rotor
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct BlockBody {
+    pub transactions: Vec<Transaction>,
+}
+
+impl BlockBody {
+    /// Merkle root over the transactions in order; zero for an empty body
+    ///
+    /// Leaves and inner nodes are hashed with distinct prefixes, and a node
+    /// without a sibling is carried up unchanged.
+    pub fn transactions_root(&self) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = self
+            .transactions
+            .iter()
+            .map(|transaction| {
+                let mut hasher = Sha256::new();
+                hasher.update([0u8]);
+                hasher.update(bincode::serialize(transaction).unwrap());
+                hasher.finalize().into()
+            })
+            .collect();
+        if level.is_empty() {
+            return [0u8; 32];
+        }
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => {
+                        let mut hasher = Sha256::new();
+                        hasher.update([1u8]);
+                        hasher.update(left);
+                        hasher.update(right);
+                        hasher.finalize().into()
+                    }
+                    [single] => *single,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+        }
+        level[0]
+    }
+}
+
+/// Block proposal: header and body, under the header's ID
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct Block {
+    pub id: BlockId,
+    pub header: BlockHeader,
+    pub body: BlockBody,
 }
 
 impl Block {
+    /// Unsigned block committing to `transactions`, with its ID set
+    ///
+    /// The block sits at height 0 of epoch 0; see `at_position` for others.
+    pub fn new(
+        slot: Slot,
+        parent: Option<BlockId>,
+        leader: ValidatorId,
+        transactions: Vec<Transaction>,
+        timestamp: u64,
+    ) -> Self {
+        let body = BlockBody { transactions };
+        let header = BlockHeader {
+            slot,
+            parent,
+            leader,
+            height: 0,
+            epoch: Epoch(0),
+            transactions_root: body.transactions_root(),
+            timestamp,
+            signature: Vec::new(),
+        };
+        Self { id: header.id(), header, body }
+    }
+
+    /// The same block at `height` in `epoch`, with its ID updated
+    ///
+    /// Any header signature no longer matches and is dropped.
+    pub fn at_position(mut self, height: u64, epoch: Epoch) -> Self {
+        self.header.height = height;
+        self.header.epoch = epoch;
+        self.header.signature.clear();
+        self.id = self.header.id();
+        self
+    }
+
+    /// ID the block hashes to, committing to the body's own transactions
+    ///
+    /// The header is hashed with the root recomputed from the body rather
+    /// than the root it claims, so a block whose body was swapped under its
+    /// header never hashes to its ID.
     pub fn compute_id(&self) -> BlockId {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(&bincode::serialize(&self.slot).unwrap());
-        hasher.update(&bincode::serialize(&self.parent).unwrap());
-        hasher.update(&bincode::serialize(&self.leader).unwrap());
-        hasher.update(&bincode::serialize(&self.timestamp).unwrap());
-        let result = hasher.finalize();
-        let mut id = [0u8; 32];
-        id.copy_from_slice(&result);
-        BlockId(id)
+        self.header.id_with_root(&self.body.transactions_root())
     }
 }
 
-/// Voting round
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Voting round; `Round1` orders first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub enum VoteRound {
     Round1,  // Notarization vote (fast path)
     Round2,  // Finalization vote (fallback path)
 }
 
+/// Vote kinds defined by the Alpenglow paper, ordered as their tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub enum VoteKind {
+    Notar,          // Notarization vote (round 1, fast path)
+    NotarFallback,  // Counts toward notarization, cast after the first vote in a slot
+    Skip,           // Vote to skip the slot
+    SkipFallback,   // Counts toward skipping, cast after the first vote in a slot
+    Final,          // Finalization vote (round 2, fallback path)
+}
+
+impl VoteKind {
+    /// Voting round this kind of vote belongs to
+    pub fn round(&self) -> VoteRound {
+        match self {
+            VoteKind::Final => VoteRound::Round2,
+            _ => VoteRound::Round1,
+        }
+    }
+
+    /// Whether this vote targets the slot rather than a specific block
+    pub fn is_skip(&self) -> bool {
+        matches!(self, VoteKind::Skip | VoteKind::SkipFallback)
+    }
+
+    /// Byte identifying the kind in signing bytes and canonical encodings
+    pub fn tag(&self) -> u8 {
+        match self {
+            VoteKind::Notar => 0,
+            VoteKind::NotarFallback => 1,
+            VoteKind::Skip => 2,
+            VoteKind::SkipFallback => 3,
+            VoteKind::Final => 4,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(VoteKind::Notar),
+            1 => Some(VoteKind::NotarFallback),
+            2 => Some(VoteKind::Skip),
+            3 => Some(VoteKind::SkipFallback),
+            4 => Some(VoteKind::Final),
+            _ => None,
+        }
+    }
+}
+
 /// Vote on a block
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Votes order by their fields in declaration order, so model checker
+/// states holding them stay canonical; keep the field order fixed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct Vote {
     pub validator: ValidatorId,
-    pub block_id: BlockId,
+    pub block_id: BlockId,  // Ignored for skip votes
     pub slot: Slot,
-    pub round: VoteRound,
+    pub kind: VoteKind,
     pub signature: Vec<u8>,  // Simplified signature
 }
 
+const VOTE_DOMAIN: &[u8] = b"alpenglow-vote-v1";
+
+impl Vote {
+    /// Bytes covered by the voter's signature: a domain tag, the chain ID
+    /// and every field but the signature, at fixed widths
+    pub fn signing_bytes(&self, chain_id: &[u8; 32]) -> Vec<u8> {
+        let mut bytes = VOTE_DOMAIN.to_vec();
+        bytes.extend_from_slice(chain_id);
+        self.validator.write_bytes(&mut bytes);
+        bytes.extend_from_slice(self.block_id.as_bytes());
+        bytes.extend_from_slice(&self.slot.0.to_le_bytes());
+        bytes.push(self.kind.tag());
+        bytes
+    }
+
+    pub fn sign(&mut self, key: &SigningKey, chain_id: &[u8; 32]) {
+        self.signature = key.sign(&self.signing_bytes(chain_id)).to_bytes().to_vec();
+    }
+
+    /// Whether the signature is the voter's on `chain_id`, given its key
+    pub fn verify(&self, key: &VerifyingKey, chain_id: &[u8; 32]) -> bool {
+        Signature::from_slice(&self.signature)
+            .is_ok_and(|signature| key.verify(&self.signing_bytes(chain_id), &signature).is_ok())
+    }
+}
+
+/// Two conflicting votes from the same validator, kept for slashing proofs
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DoubleVoteEvidence {
+    pub first: Vote,
+    pub second: Vote,
+}
+
+impl DoubleVoteEvidence {
+    pub fn validator(&self) -> ValidatorId {
+        self.first.validator
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.first.slot
+    }
+
+    /// Check that the pair really is a conflicting double vote: notar or
+    /// final votes of one kind for two blocks of the same slot
+    ///
+    /// Signatures are not compared, so a re-signed copy of a vote is not a
+    /// conflict. Skip votes ignore the block and notar-fallback votes may
+    /// cover several blocks, so neither kind ever conflicts.
+    pub fn is_valid(&self) -> bool {
+        self.first.validator == self.second.validator
+            && self.first.slot == self.second.slot
+            && self.first.kind == self.second.kind
+            && matches!(self.first.kind, VoteKind::Notar | VoteKind::Final)
+            && self.first.block_id != self.second.block_id
+    }
+}
+
+/// Two different blocks proposed by one leader for the same slot, as the
+/// leader's signed headers
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EquivocationEvidence {
+    pub leader: ValidatorId,
+    pub slot: Slot,
+    pub first: BlockHeader,
+    pub second: BlockHeader,
+}
+
+impl EquivocationEvidence {
+    /// IDs of the two blocks
+    pub fn block_ids(&self) -> (BlockId, BlockId) {
+        (self.first.id(), self.second.id())
+    }
+
+    /// Check that both headers are the leader's, for the slot, and differ
+    pub fn is_valid(&self) -> bool {
+        [&self.first, &self.second]
+            .iter()
+            .all(|header| header.leader == self.leader && header.slot == self.slot)
+            && self.first.id() != self.second.id()
+    }
+
+    /// Check that the evidence is valid and the leader, holding `key`,
+    /// signed both headers for `chain_id`
+    pub fn verify(&self, key: &VerifyingKey, chain_id: &[u8; 32]) -> bool {
+        self.is_valid() && self.first.verify(key, chain_id) && self.second.verify(key, chain_id)
+    }
+}
+
 /// Vote collection for a specific block
 #[derive(Debug, Clone)]
 pub struct VoteSet {
     pub block_id: BlockId,
     pub round1_votes: HashMap<ValidatorId, Vote>,
+    pub notar_fallback_votes: HashMap<ValidatorId, Vote>,
     pub round2_votes: HashMap<ValidatorId, Vote>,
 }
 
@@ -141,21 +702,61 @@ impl VoteSet {
         Self {
             block_id,
             round1_votes: HashMap::new(),
+            notar_fallback_votes: HashMap::new(),
             round2_votes: HashMap::new(),
         }
     }
 
     pub fn add_vote(&mut self, vote: Vote) {
-        match vote.round {
-            VoteRound::Round1 => {
+        match vote.kind {
+            VoteKind::Notar => {
                 self.round1_votes.insert(vote.validator, vote);
             }
-            VoteRound::Round2 => {
+            VoteKind::NotarFallback => {
+                self.notar_fallback_votes.insert(vote.validator, vote);
+            }
+            VoteKind::Final => {
                 self.round2_votes.insert(vote.validator, vote);
             }
+            // Skip votes are tracked per slot in `SkipVoteSet`
+            VoteKind::Skip | VoteKind::SkipFallback => {}
+        }
+    }
+
+    /// Get the votes of the given kind, if this set tracks it
+    pub fn votes(&self, kind: VoteKind) -> Option<&HashMap<ValidatorId, Vote>> {
+        match kind {
+            VoteKind::Notar => Some(&self.round1_votes),
+            VoteKind::NotarFallback => Some(&self.notar_fallback_votes),
+            VoteKind::Final => Some(&self.round2_votes),
+            VoteKind::Skip | VoteKind::SkipFallback => None,
+        }
+    }
+
+    /// Mutable access to the votes of the given kind, if this set tracks it
+    pub fn votes_mut(&mut self, kind: VoteKind) -> Option<&mut HashMap<ValidatorId, Vote>> {
+        match kind {
+            VoteKind::Notar => Some(&mut self.round1_votes),
+            VoteKind::NotarFallback => Some(&mut self.notar_fallback_votes),
+            VoteKind::Final => Some(&mut self.round2_votes),
+            VoteKind::Skip | VoteKind::SkipFallback => None,
         }
     }
 
+    /// Validators that voted notar or notar-fallback (each counted once)
+    pub fn notarization_voters(&self) -> HashSet<ValidatorId> {
+        self.round1_votes
+            .keys()
+            .chain(self.notar_fallback_votes.keys())
+            .copied()
+            .collect()
+    }
+
+    /// Validators with a vote of `kind` on the block, in no particular order
+    pub fn voters(&self, kind: VoteKind) -> impl Iterator<Item = ValidatorId> + '_ {
+        self.votes(kind).into_iter().flat_map(|votes| votes.keys().copied())
+    }
+
     pub fn round1_count(&self) -> usize {
         self.round1_votes.len()
     }
@@ -163,10 +764,103 @@ impl VoteSet {
     pub fn round2_count(&self) -> usize {
         self.round2_votes.len()
     }
+
+    /// Stake behind the notar votes
+    pub fn round1_stake(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.stake_of(self.round1_votes.keys())
+    }
+
+    /// Stake behind the finalization votes
+    pub fn round2_stake(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.stake_of(self.round2_votes.keys())
+    }
+
+    /// Stake behind the notar and notar-fallback votes, each voter once
+    pub fn notarization_stake(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.calculate_stake(&self.notarization_voters())
+    }
+
+    /// Notar stake still needed to finalize on the fast path; zero once reached
+    pub fn stake_missing_for_fast_quorum(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set
+            .fast_quorum_threshold()
+            .saturating_sub(self.round1_stake(validator_set))
+    }
+
+    /// Finalization stake still needed on the fallback path; zero once reached
+    pub fn stake_missing_for_fallback_quorum(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set
+            .fallback_quorum_threshold()
+            .saturating_sub(self.round2_stake(validator_set))
+    }
 }
 
-/// Finalized block certificate
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Skip vote collection for a specific slot
+#[derive(Debug, Clone)]
+pub struct SkipVoteSet {
+    pub slot: Slot,
+    pub skip_votes: HashMap<ValidatorId, Vote>,
+    pub skip_fallback_votes: HashMap<ValidatorId, Vote>,
+}
+
+impl SkipVoteSet {
+    pub fn new(slot: Slot) -> Self {
+        Self {
+            slot,
+            skip_votes: HashMap::new(),
+            skip_fallback_votes: HashMap::new(),
+        }
+    }
+
+    pub fn add_vote(&mut self, vote: Vote) {
+        match vote.kind {
+            VoteKind::Skip => {
+                self.skip_votes.insert(vote.validator, vote);
+            }
+            VoteKind::SkipFallback => {
+                self.skip_fallback_votes.insert(vote.validator, vote);
+            }
+            // Block votes are tracked per block in `VoteSet`
+            _ => {}
+        }
+    }
+
+    /// Get the votes of the given kind, if this set tracks it
+    pub fn votes(&self, kind: VoteKind) -> Option<&HashMap<ValidatorId, Vote>> {
+        match kind {
+            VoteKind::Skip => Some(&self.skip_votes),
+            VoteKind::SkipFallback => Some(&self.skip_fallback_votes),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the votes of the given kind, if this set tracks it
+    pub fn votes_mut(&mut self, kind: VoteKind) -> Option<&mut HashMap<ValidatorId, Vote>> {
+        match kind {
+            VoteKind::Skip => Some(&mut self.skip_votes),
+            VoteKind::SkipFallback => Some(&mut self.skip_fallback_votes),
+            _ => None,
+        }
+    }
+
+    /// Validators that voted skip or skip-fallback (each counted once)
+    pub fn skip_voters(&self) -> HashSet<ValidatorId> {
+        self.skip_votes
+            .keys()
+            .chain(self.skip_fallback_votes.keys())
+            .copied()
+            .collect()
+    }
+
+    /// Stake behind the skip and skip-fallback votes, each voter once
+    pub fn skip_stake(&self, validator_set: &ValidatorSet) -> StakeWeight {
+        validator_set.calculate_stake(&self.skip_voters())
+    }
+}
+
+/// Finalized block certificate, ordered by block ID, slot and round first
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct FinalizationCertificate {
     pub block_id: BlockId,
     pub slot: Slot,
@@ -182,63 +876,363 @@ pub struct ValidatorConfig {
     pub stake: StakeWeight,
     pub is_byzantine: bool,
     pub is_offline: bool,
+    /// Ed25519 key the validator signs votes and blocks with, if known
+    pub pubkey: Option<VerifyingKey>,
+    /// Where to reach the validator, for transports that dial peers
+    pub address: Option<SocketAddr>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidatorSetError {
+    #[error("Validator {0} is already in the set")]
+    DuplicateValidator(ValidatorId),
+
+    #[error("Validator {0} is not in the set")]
+    UnknownValidator(ValidatorId),
+
+    #[error("Validator {0} is already active")]
+    AlreadyActive(ValidatorId),
+
+    #[error("Validator {0} is already inactive")]
+    AlreadyInactive(ValidatorId),
+
+    #[error("Stake of validator {0} would overflow the total stake")]
+    StakeOverflow(ValidatorId),
+
+    #[error("Public key of validator {0} is malformed")]
+    InvalidKey(ValidatorId),
+
+    #[error("Validator ID {0} is reserved by the wire format")]
+    ReservedId(ValidatorId),
+
+    #[error("Validator {0} is identified by a key other than its own")]
+    KeyMismatch(ValidatorId),
+}
+
+/// Reject the index reserved as the wire marker for key IDs, and a key ID
+/// naming a key other than the validator's own
+fn check_id(config: &ValidatorConfig) -> Result<(), ValidatorSetError> {
+    match (config.id, config.pubkey) {
+        (ValidatorId::Index(ValidatorId::KEY_MARKER), _) => {
+            Err(ValidatorSetError::ReservedId(config.id))
+        }
+        (ValidatorId::Key(bytes), Some(key)) if key.to_bytes() != bytes => {
+            Err(ValidatorSetError::KeyMismatch(config.id))
+        }
+        _ => Ok(()),
+    }
 }
 
+/// A validator's ID, stake and public key, if it has one, as carried by
+/// finality proofs and snapshots
+pub type ValidatorEntry = (ValidatorId, StakeWeight, Option<[u8; 32]>);
+
 /// Network of validators with stake distribution
+///
+/// Deactivated validators keep their entry and stake but are left out of
+/// everything else: lookups, the total stake, quorums and iteration.
+/// Iteration is always in ID order, so every node walks the set alike.
 #[derive(Debug, Clone)]
 pub struct ValidatorSet {
-    validators: HashMap<ValidatorId, ValidatorConfig>,
+    validators: BTreeMap<ValidatorId, ValidatorConfig>,
+    inactive: BTreeMap<ValidatorId, ValidatorConfig>,
     total_stake: StakeWeight,
 }
 
+impl Default for ValidatorSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ValidatorSet {
     pub fn new() -> Self {
         Self {
-            validators: HashMap::new(),
+            validators: BTreeMap::new(),
+            inactive: BTreeMap::new(),
             total_stake: StakeWeight(0),
         }
     }
 
-    pub fn add_validator(&mut self, config: ValidatorConfig) {
-        self.total_stake += config.stake;
-        self.validators.insert(config.id, config);
+    /// Add a new validator; fails, leaving the set unchanged, like
+    /// `try_add_validator`
+    ///
+    /// Use `update_stake` to change the stake of a validator in the set.
+    pub fn add_validator(&mut self, config: ValidatorConfig) -> Result<(), ValidatorSetError> {
+        self.try_add_validator(config)
+    }
+
+    /// Add a new validator; fails if its ID is taken, active or not, or its
+    /// stake would overflow the total
+    pub fn try_add_validator(&mut self, config: ValidatorConfig) -> Result<(), ValidatorSetError> {
+        let id = config.id;
+        if self.validators.contains_key(&id) || self.inactive.contains_key(&id) {
+            return Err(ValidatorSetError::DuplicateValidator(id));
+        }
+        check_id(&config)?;
+        self.total_stake = self.checked_total(id, config.stake)?;
+        self.validators.insert(id, config);
+        Ok(())
+    }
+
+    /// Validators `0..n` holding `stakes` in order, with no keys or addresses
+    ///
+    /// Panics if the stakes overflow in total; build sets from untrusted
+    /// stakes with `try_add_validator`.
+    pub fn with_stakes(stakes: impl IntoIterator<Item = StakeWeight>) -> Self {
+        let mut vset = Self::new();
+        for (i, stake) in stakes.into_iter().enumerate() {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(i as u64),
+                stake,
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: None,
+                address: None,
+            })
+            .expect("total stake overflows");
+        }
+        vset
+    }
+
+    /// Active validators `entries` describe; fails on a repeated ID, a
+    /// malformed key or stakes overflowing in total
+    pub fn from_entries(entries: &[ValidatorEntry]) -> Result<Self, ValidatorSetError> {
+        let mut vset = Self::new();
+        for (id, stake, pubkey) in entries {
+            let pubkey = pubkey
+                .map(|key| VerifyingKey::from_bytes(&key))
+                .transpose()
+                .map_err(|_| ValidatorSetError::InvalidKey(*id))?;
+            vset.try_add_validator(ValidatorConfig {
+                id: *id,
+                stake: *stake,
+                is_byzantine: false,
+                is_offline: false,
+                pubkey,
+                address: None,
+            })?;
+        }
+        Ok(vset)
+    }
+
+    /// ID, stake and key of every active validator, in ID order
+    pub fn entries(&self) -> Vec<ValidatorEntry> {
+        self.iter()
+            .map(|v| (v.id, v.stake, v.pubkey.map(|key| key.to_bytes())))
+            .collect()
+    }
+
+    /// Remove a validator, active or not
+    pub fn remove_validator(
+        &mut self,
+        id: &ValidatorId,
+    ) -> Result<ValidatorConfig, ValidatorSetError> {
+        if let Some(config) = self.validators.remove(id) {
+            self.total_stake = self.total_stake.saturating_sub(config.stake);
+            return Ok(config);
+        }
+        self.inactive.remove(id).ok_or(ValidatorSetError::UnknownValidator(*id))
+    }
+
+    /// Set a validator's stake, active or not; its previous stake
+    pub fn update_stake(
+        &mut self,
+        id: &ValidatorId,
+        stake: StakeWeight,
+    ) -> Result<StakeWeight, ValidatorSetError> {
+        if let Some(config) = self.validators.get_mut(id) {
+            let others = self.total_stake.saturating_sub(config.stake);
+            let total = others.checked_add(stake).ok_or(ValidatorSetError::StakeOverflow(*id))?;
+            self.total_stake = total;
+            return Ok(std::mem::replace(&mut config.stake, stake));
+        }
+        let config = self.inactive.get_mut(id).ok_or(ValidatorSetError::UnknownValidator(*id))?;
+        Ok(std::mem::replace(&mut config.stake, stake))
+    }
+
+    /// Take a validator out of the active set, keeping its stake on record
+    pub fn deactivate(&mut self, id: &ValidatorId) -> Result<(), ValidatorSetError> {
+        if self.inactive.contains_key(id) {
+            return Err(ValidatorSetError::AlreadyInactive(*id));
+        }
+        let config = self.validators.remove(id).ok_or(ValidatorSetError::UnknownValidator(*id))?;
+        self.total_stake = self.total_stake.saturating_sub(config.stake);
+        self.inactive.insert(*id, config);
+        Ok(())
+    }
+
+    /// Return a deactivated validator to the active set
+    pub fn activate(&mut self, id: &ValidatorId) -> Result<(), ValidatorSetError> {
+        if self.validators.contains_key(id) {
+            return Err(ValidatorSetError::AlreadyActive(*id));
+        }
+        let stake = self
+            .inactive
+            .get(id)
+            .ok_or(ValidatorSetError::UnknownValidator(*id))?
+            .stake;
+        self.total_stake = self.checked_total(*id, stake)?;
+        let config = self.inactive.remove(id).expect("looked up above");
+        self.validators.insert(*id, config);
+        Ok(())
+    }
+
+    pub fn is_active(&self, id: &ValidatorId) -> bool {
+        self.validators.contains_key(id)
+    }
+
+    /// Total stake with `stake` added for `id`
+    fn checked_total(
+        &self,
+        id: ValidatorId,
+        stake: StakeWeight,
+    ) -> Result<StakeWeight, ValidatorSetError> {
+        self.total_stake
+            .checked_add(stake)
+            .ok_or(ValidatorSetError::StakeOverflow(id))
     }
 
     pub fn get_validator(&self, id: &ValidatorId) -> Option<&ValidatorConfig> {
         self.validators.get(id)
     }
 
+    /// Active validator registered with `key`
+    pub fn find_by_pubkey(&self, key: &VerifyingKey) -> Option<&ValidatorConfig> {
+        self.iter().find(|v| v.pubkey.as_ref() == Some(key))
+    }
+
     pub fn total_stake(&self) -> StakeWeight {
         self.total_stake
     }
 
+    /// Validators in ID order
+    pub fn iter(&self) -> impl Iterator<Item = &ValidatorConfig> {
+        self.validators.values()
+    }
+
+    /// All validators ordered by ID
+    pub fn sorted_validators(&self) -> Vec<&ValidatorConfig> {
+        self.iter().collect()
+    }
+
+    /// Share of the total stake held by `id`; `None` if it isn't active
+    pub fn stake_fraction(&self, id: &ValidatorId) -> Option<f64> {
+        let stake = self.validators.get(id)?.stake;
+        if self.total_stake.0 == 0 {
+            return Some(0.0);
+        }
+        Some(stake.0 as f64 / self.total_stake.0 as f64)
+    }
+
+    /// The `k` validators with the most stake, ties broken by lower ID
+    pub fn top_by_stake(&self, k: usize) -> Vec<&ValidatorConfig> {
+        let mut validators = self.sorted_validators();
+        validators.sort_by_key(|v| std::cmp::Reverse(v.stake));
+        validators.truncate(k);
+        validators
+    }
+
+    /// Set of the validators matching `predicate`
+    pub fn subset(&self, mut predicate: impl FnMut(&ValidatorConfig) -> bool) -> ValidatorSet {
+        let mut subset = ValidatorSet::new();
+        for validator in self.iter().filter(|v| predicate(v)) {
+            subset
+                .add_validator(validator.clone())
+                .expect("a subset's stake is within the set's total");
+        }
+        subset
+    }
+
+    /// Up to `count` distinct validators drawn by stake, without replacement
+    ///
+    /// Deterministic in `seed`, so nodes sharing a seed draw the same
+    /// validators. Validators with zero stake are never drawn.
+    pub fn sample_by_stake(&self, seed: &[u8; 32], count: usize) -> Vec<ValidatorId> {
+        let mut candidates: Vec<(ValidatorId, u64)> = self
+            .iter()
+            .filter(|v| v.stake.as_u64() > 0)
+            .map(|v| (v.id, v.stake.as_u64()))
+            .collect();
+        let mut remaining: u128 = candidates.iter().map(|(_, stake)| *stake as u128).sum();
+
+        let mut drawn = Vec::with_capacity(count.min(candidates.len()));
+        for draw in 0..count as u64 {
+            if candidates.is_empty() {
+                break;
+            }
+
+            let mut target = sample_u128(seed, draw) % remaining;
+            let position = candidates
+                .iter()
+                .position(|(_, stake)| {
+                    if target < *stake as u128 {
+                        true
+                    } else {
+                        target -= *stake as u128;
+                        false
+                    }
+                })
+                .expect("target is below the remaining stake");
+
+            let (id, stake) = candidates.remove(position);
+            remaining -= stake as u128;
+            drawn.push(id);
+        }
+
+        drawn
+    }
+
     pub fn honest_validators(&self) -> impl Iterator<Item = &ValidatorConfig> {
-        self.validators
-            .values()
-            .filter(|v| !v.is_byzantine && !v.is_offline)
+        self.iter().filter(|v| !v.is_byzantine && !v.is_offline)
     }
 
     pub fn calculate_stake(&self, validator_ids: &HashSet<ValidatorId>) -> StakeWeight {
-        validator_ids
-            .iter()
+        self.stake_of(validator_ids)
+    }
+
+    /// Stake of the active validators among `ids`; unknown IDs count zero
+    pub fn stake_of<'a>(&self, ids: impl IntoIterator<Item = &'a ValidatorId>) -> StakeWeight {
+        ids.into_iter()
             .filter_map(|id| self.validators.get(id))
             .map(|v| v.stake)
             .sum()
     }
 
+    /// Least stake making up a fast path quorum under the default parameters
+    pub fn fast_quorum_threshold(&self) -> StakeWeight {
+        ProtocolParams::DEFAULT.fast_quorum_threshold(self)
+    }
+
+    /// Least stake making up a fallback quorum under the default parameters
+    pub fn fallback_quorum_threshold(&self) -> StakeWeight {
+        ProtocolParams::DEFAULT.fallback_quorum_threshold(self)
+    }
+
     pub fn check_fast_quorum(&self, stake: StakeWeight) -> bool {
-        let threshold = (self.total_stake.0 * 80) / 100;
-        stake.0 >= threshold
+        stake >= self.fast_quorum_threshold()
     }
 
     pub fn check_fallback_quorum(&self, stake: StakeWeight) -> bool {
-        let threshold = (self.total_stake.0 * 60) / 100;
-        stake.0 >= threshold
+        stake >= self.fallback_quorum_threshold()
     }
 
     pub fn len(&self) -> usize {
         self.validators.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+}
+
+/// Pseudo-random value for the given draw, derived from the seed
+fn sample_u128(seed: &[u8; 32], draw: u64) -> u128 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update(draw.to_le_bytes());
+    let digest = hasher.finalize();
+    u128::from_le_bytes(digest[..16].try_into().unwrap())
 }
 
 #[cfg(test)]
@@ -249,23 +1243,32 @@ mod tests {
     fn test_validator_set() {
         let mut vset = ValidatorSet::new();
         vset.add_validator(ValidatorConfig {
-            id: ValidatorId(1),
+            id: ValidatorId::Index(1),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
         vset.add_validator(ValidatorConfig {
-            id: ValidatorId(2),
+            id: ValidatorId::Index(2),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
         vset.add_validator(ValidatorConfig {
-            id: ValidatorId(3),
+            id: ValidatorId::Index(3),
             stake: StakeWeight(100),
             is_byzantine: false,
             is_offline: false,
-        });
+            pubkey: None,
+            address: None,
+        })
+        .unwrap();
 
         assert_eq!(vset.total_stake(), StakeWeight(300));
         assert!(vset.check_fast_quorum(StakeWeight(240)));
@@ -280,10 +1283,10 @@ mod tests {
         let mut vote_set = VoteSet::new(block_id);
 
         let vote1 = Vote {
-            validator: ValidatorId(1),
+            validator: ValidatorId::Index(1),
             block_id,
             slot: Slot(0),
-            round: VoteRound::Round1,
+            kind: VoteKind::Notar,
             signature: vec![],
         };
 
@@ -291,4 +1294,417 @@ mod tests {
         assert_eq!(vote_set.round1_count(), 1);
         assert_eq!(vote_set.round2_count(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_notarization_voters_counted_once() {
+        let block_id = BlockId::new([1u8; 32]);
+        let mut vote_set = VoteSet::new(block_id);
+
+        for kind in [VoteKind::Notar, VoteKind::NotarFallback] {
+            vote_set.add_vote(Vote {
+                validator: ValidatorId::Index(1),
+                block_id,
+                slot: Slot(0),
+                kind,
+                signature: vec![],
+            });
+        }
+        vote_set.add_vote(Vote {
+            validator: ValidatorId::Index(2),
+            block_id,
+            slot: Slot(0),
+            kind: VoteKind::NotarFallback,
+            signature: vec![],
+        });
+
+        assert_eq!(vote_set.notarization_voters().len(), 2);
+    }
+
+    #[test]
+    fn test_transaction_signature_and_size() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let transaction = Transaction::new(&key, 7, vec![1, 2, 3]);
+        assert_eq!(transaction.verify(), Ok(()));
+        let encoded = bincode::serialized_size(&transaction).unwrap() as usize;
+        assert_eq!(transaction.encoded_size(), encoded);
+
+        let mut replayed = transaction.clone();
+        replayed.nonce = 8;
+        assert_eq!(replayed.verify(), Err(TransactionError::InvalidSignature));
+        assert_ne!(replayed.hash(), transaction.hash());
+        let mut stolen = transaction;
+        stolen.payer = SigningKey::from_bytes(&[5u8; 32]).verifying_key().to_bytes();
+        assert_eq!(stolen.verify(), Err(TransactionError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_block_id_hex_round_trip() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0x0a;
+        bytes[31] = 0xff;
+        let id = BlockId::new(bytes);
+        let text = id.to_string();
+        assert_eq!(text.len(), 64);
+        assert!(text.starts_with("0a00") && text.ends_with("00ff"));
+        assert_eq!(text.parse::<BlockId>(), Ok(id));
+        assert_eq!(text.to_uppercase().parse::<BlockId>(), Ok(id));
+
+        assert_eq!("0a".parse::<BlockId>(), Err(ParseBlockIdError::Length(2)));
+        let bad = "z".repeat(64);
+        assert!(matches!(bad.parse::<BlockId>(), Err(ParseBlockIdError::InvalidHex(_))));
+
+        // JSON carries the hex string, bincode the raw 32 bytes
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{text}\""));
+        assert_eq!(serde_json::from_str::<BlockId>(&json).unwrap(), id);
+        let binary = bincode::serialize(&id).unwrap();
+        assert_eq!(binary, bytes);
+        assert_eq!(bincode::deserialize::<BlockId>(&binary).unwrap(), id);
+    }
+
+    #[test]
+    fn test_validator_set_mutation_keeps_total_stake() {
+        let config = |id, stake| ValidatorConfig {
+            id: ValidatorId::Index(id),
+            stake: StakeWeight(stake),
+            is_byzantine: false,
+            is_offline: false,
+            pubkey: None,
+            address: None,
+        };
+        let mut vset = ValidatorSet::new();
+        for id in 0..4 {
+            vset.try_add_validator(config(id, 100)).unwrap();
+        }
+        assert_eq!(
+            vset.try_add_validator(config(2, 50)),
+            Err(ValidatorSetError::DuplicateValidator(ValidatorId::Index(2)))
+        );
+        assert_eq!(
+            vset.try_add_validator(config(9, u64::MAX)),
+            Err(ValidatorSetError::StakeOverflow(ValidatorId::Index(9)))
+        );
+        assert_eq!(vset.total_stake(), StakeWeight(400));
+
+        let previous = vset.update_stake(&ValidatorId::Index(1), StakeWeight(300));
+        assert_eq!(previous, Ok(StakeWeight(100)));
+        assert_eq!(vset.total_stake(), StakeWeight(600));
+        assert!(vset.update_stake(&ValidatorId::Index(1), StakeWeight(u64::MAX)).is_err());
+        assert_eq!(vset.get_validator(&ValidatorId::Index(1)).unwrap().stake, StakeWeight(300));
+
+        // An inactive validator is kept aside and counts for nothing
+        vset.deactivate(&ValidatorId::Index(1)).unwrap();
+        assert_eq!(vset.total_stake(), StakeWeight(300));
+        assert!(vset.get_validator(&ValidatorId::Index(1)).is_none());
+        assert_eq!(vset.len(), 3);
+        assert!(vset.check_fast_quorum(StakeWeight(240)));
+        assert_eq!(
+            vset.deactivate(&ValidatorId::Index(1)),
+            Err(ValidatorSetError::AlreadyInactive(ValidatorId::Index(1)))
+        );
+        let previous = vset.update_stake(&ValidatorId::Index(1), StakeWeight(200));
+        assert_eq!(previous, Ok(StakeWeight(300)));
+        assert_eq!(vset.total_stake(), StakeWeight(300));
+        assert!(vset.try_add_validator(config(1, 10)).is_err());
+        vset.activate(&ValidatorId::Index(1)).unwrap();
+        assert_eq!(vset.total_stake(), StakeWeight(500));
+
+        assert_eq!(vset.remove_validator(&ValidatorId::Index(0)).unwrap().stake, StakeWeight(100));
+        assert_eq!(vset.total_stake(), StakeWeight(400));
+        assert_eq!(
+            vset.remove_validator(&ValidatorId::Index(0)).unwrap_err(),
+            ValidatorSetError::UnknownValidator(ValidatorId::Index(0))
+        );
+
+        // `add_validator` never replaces a validator, and a stake overflowing
+        // the total is refused rather than clamped
+        assert_eq!(
+            vset.add_validator(config(2, 50)),
+            Err(ValidatorSetError::DuplicateValidator(ValidatorId::Index(2)))
+        );
+        assert_eq!(
+            vset.add_validator(config(7, u64::MAX)),
+            Err(ValidatorSetError::StakeOverflow(ValidatorId::Index(7)))
+        );
+        assert_eq!(vset.total_stake(), StakeWeight(400));
+        assert!(vset.get_validator(&ValidatorId::Index(7)).is_none());
+        assert_eq!(vset.get_validator(&ValidatorId::Index(2)).unwrap().stake, StakeWeight(100));
+    }
+
+    #[test]
+    fn test_stake_arithmetic_never_wraps() {
+        let max = StakeWeight(u64::MAX);
+        assert_eq!(max.checked_add(StakeWeight(1)), None);
+        assert_eq!(StakeWeight(1).checked_sub(StakeWeight(2)), None);
+        assert_eq!(max + StakeWeight(1), max);
+        assert_eq!([max, max].into_iter().sum::<StakeWeight>(), max);
+        assert_eq!(StakeWeight(1).saturating_sub(StakeWeight(2)), StakeWeight(0));
+        assert_eq!(StakeWeight(1000).percent(80), StakeWeight(800));
+        assert_eq!(max.percent(100), max);
+
+        // Quorum thresholds hold for stakes whose product with 80 exceeds u64
+        let mut vset = ValidatorSet::new();
+        for id in 0..2 {
+            vset.try_add_validator(ValidatorConfig {
+                id: ValidatorId::Index(id),
+                stake: StakeWeight(u64::MAX / 2),
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
+        }
+        let total = vset.total_stake();
+        assert!(vset.check_fast_quorum(total.percent_ceil(80)));
+        assert!(!vset.check_fast_quorum(total.percent(80)));
+        assert!(vset.check_fallback_quorum(total.percent_ceil(60)));
+        assert!(!vset.check_fallback_quorum(StakeWeight(u64::MAX / 2)));
+    }
+
+    #[test]
+    fn test_quorum_thresholds_round_up() {
+        // 5 of 7 is 71%, short of 80% even though 80% of 7 rounds down to 5
+        let vset = ValidatorSet::with_stakes([StakeWeight(1); 7]);
+        assert_eq!(vset.fast_quorum_threshold(), StakeWeight(6));
+        assert!(!vset.check_fast_quorum(StakeWeight(5)));
+        assert!(vset.check_fast_quorum(StakeWeight(6)));
+        assert!(!vset.check_fallback_quorum(StakeWeight(4)));
+        assert!(vset.check_fallback_quorum(StakeWeight(5)));
+
+        // Exact multiples need no rounding
+        let vset = ValidatorSet::with_stakes([StakeWeight(1); 10]);
+        assert!(vset.check_fast_quorum(StakeWeight(8)));
+        assert!(!vset.check_fast_quorum(StakeWeight(7)));
+        assert_eq!(StakeWeight(7).percent_ceil(80), StakeWeight(6));
+        assert_eq!(StakeWeight(u64::MAX).percent_ceil(100), StakeWeight(u64::MAX));
+    }
+
+    #[test]
+    fn test_signatures_bound_to_message_type_and_chain() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let (ours, theirs) = ([1u8; 32], [2u8; 32]);
+        let mut header = Block::new(Slot(3), None, ValidatorId::Index(0), vec![], 1000).header;
+        header.sign(&key, &ours);
+        assert!(header.verify(&key.verifying_key(), &ours));
+        assert!(!header.verify(&key.verifying_key(), &theirs));
+
+        let mut vote = Vote {
+            validator: ValidatorId::Index(0),
+            block_id: header.id(),
+            slot: Slot(3),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+        vote.sign(&key, &ours);
+        assert!(vote.verify(&key.verifying_key(), &ours));
+        assert!(!vote.verify(&key.verifying_key(), &theirs));
+        let skip = Vote { kind: VoteKind::Skip, ..vote.clone() };
+        assert!(!skip.verify(&key.verifying_key(), &ours));
+
+        // Neither signature passes for the other message type
+        let replayed = Vote { signature: header.signature.clone(), ..vote.clone() };
+        assert!(!replayed.verify(&key.verifying_key(), &ours));
+        header.signature = vote.signature;
+        assert!(!header.verify(&key.verifying_key(), &ours));
+    }
+
+    #[test]
+    fn test_block_id_commits_to_transactions() {
+        let payer = SigningKey::from_bytes(&[9u8; 32]);
+        let transactions = vec![Transaction::new(&payer, 0, vec![1])];
+        let block = Block::new(Slot(2), None, ValidatorId::Index(0), transactions, 1000);
+        assert_eq!(block.compute_id(), block.id);
+        assert_eq!(block.compute_id(), block.header.id());
+
+        // Same header, other contents: the ID no longer matches
+        let mut swapped = block.clone();
+        swapped.body.transactions[0] = Transaction::new(&payer, 1, vec![1]);
+        assert_ne!(swapped.compute_id(), block.id);
+        swapped.body.transactions.clear();
+        assert_ne!(swapped.compute_id(), block.id);
+        let empty = Block::new(Slot(2), None, ValidatorId::Index(0), vec![], 1000);
+        assert_ne!(empty.id, block.id);
+    }
+
+    #[test]
+    fn test_validator_set_queries_are_deterministic() {
+        let mut vset = ValidatorSet::new();
+        for (id, stake) in [(4, 100), (1, 300), (3, 0), (0, 100), (2, 500)] {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(id),
+                stake: StakeWeight(stake),
+                is_byzantine: id == 1,
+                is_offline: false,
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
+        }
+        let ids = |validators: Vec<&ValidatorConfig>| -> Vec<u64> {
+            validators.iter().map(|v| v.id.index().unwrap()).collect()
+        };
+        assert_eq!(ids(vset.iter().collect()), vec![0, 1, 2, 3, 4]);
+        assert_eq!(ids(vset.top_by_stake(3)), vec![2, 1, 0]);
+        assert_eq!(vset.stake_fraction(&ValidatorId::Index(2)), Some(0.5));
+        assert_eq!(vset.stake_fraction(&ValidatorId::Index(9)), None);
+
+        let honest = vset.subset(|v| !v.is_byzantine);
+        assert_eq!(honest.len(), 4);
+        assert_eq!(honest.total_stake(), StakeWeight(700));
+
+        // Draws are distinct, repeatable and never pick zero stake
+        let seed = [6u8; 32];
+        let drawn = vset.sample_by_stake(&seed, 10);
+        assert_eq!(drawn.len(), 4);
+        assert!(!drawn.contains(&ValidatorId::Index(3)));
+        assert_eq!(vset.sample_by_stake(&seed, 10), drawn);
+        assert_eq!(vset.sample_by_stake(&seed, 2), drawn[..2]);
+    }
+
+    #[test]
+    fn test_vote_set_stake_accessors() {
+        let mut vset = ValidatorSet::new();
+        for (id, stake) in [(0, 400), (1, 300), (2, 200), (3, 100)] {
+            vset.add_validator(ValidatorConfig {
+                id: ValidatorId::Index(id),
+                stake: StakeWeight(stake),
+                is_byzantine: false,
+                is_offline: false,
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
+        }
+        let block_id = BlockId::new([1u8; 32]);
+        let vote = |validator, kind| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id,
+            slot: Slot(0),
+            kind,
+            signature: vec![],
+        };
+        let mut vote_set = VoteSet::new(block_id);
+        vote_set.add_vote(vote(1, VoteKind::Notar));
+        vote_set.add_vote(vote(2, VoteKind::Notar));
+        vote_set.add_vote(vote(2, VoteKind::NotarFallback));
+        vote_set.add_vote(vote(3, VoteKind::NotarFallback));
+
+        assert_eq!(vote_set.round1_stake(&vset), StakeWeight(500));
+        assert_eq!(vote_set.notarization_stake(&vset), StakeWeight(600));
+        assert_eq!(vote_set.stake_missing_for_fast_quorum(&vset), StakeWeight(300));
+        assert_eq!(vote_set.stake_missing_for_fallback_quorum(&vset), StakeWeight(600));
+        let mut voters: Vec<_> = vote_set.voters(VoteKind::NotarFallback).collect();
+        voters.sort();
+        assert_eq!(voters, vec![ValidatorId::Index(2), ValidatorId::Index(3)]);
+        assert_eq!(vote_set.voters(VoteKind::Skip).count(), 0);
+
+        vote_set.add_vote(vote(0, VoteKind::Notar));
+        assert_eq!(vote_set.stake_missing_for_fast_quorum(&vset), StakeWeight(0));
+        assert!(vset.check_fast_quorum(vote_set.round1_stake(&vset)));
+        for validator in [0, 1] {
+            vote_set.add_vote(vote(validator, VoteKind::Final));
+        }
+        assert_eq!(vote_set.round2_stake(&vset), StakeWeight(700));
+        assert_eq!(vote_set.stake_missing_for_fallback_quorum(&vset), StakeWeight(0));
+
+        let mut skips = SkipVoteSet::new(Slot(0));
+        skips.add_vote(vote(3, VoteKind::Skip));
+        skips.add_vote(vote(3, VoteKind::SkipFallback));
+        assert_eq!(skips.skip_stake(&vset), StakeWeight(100));
+    }
+
+    #[test]
+    fn test_core_types_hash_and_order_stably() {
+        let vote = |validator, slot, kind| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(slot),
+            kind,
+            signature: vec![],
+        };
+        let votes = vec![
+            vote(2, 0, VoteKind::Notar),
+            vote(1, 1, VoteKind::Final),
+            vote(1, 1, VoteKind::Notar),
+            vote(1, 0, VoteKind::Skip),
+        ];
+        let mut sorted = votes.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            vec![
+                vote(1, 0, VoteKind::Skip),
+                vote(1, 1, VoteKind::Notar),
+                vote(1, 1, VoteKind::Final),
+                vote(2, 0, VoteKind::Notar),
+            ]
+        );
+        assert!(VoteRound::Round1 < VoteRound::Round2);
+        assert!(VoteKind::Notar < VoteKind::Final);
+
+        // Certificates and blocks can make up hashed and ordered states
+        let certificate = |votes: Vec<Vote>| FinalizationCertificate {
+            block_id: BlockId::new([1u8; 32]),
+            slot: Slot(0),
+            round: VoteRound::Round1,
+            votes,
+            total_stake: StakeWeight(300),
+        };
+        let mut reversed = sorted.clone();
+        reversed.reverse();
+        let states = HashSet::from([certificate(sorted.clone()), certificate(sorted.clone())]);
+        assert_eq!(states.len(), 1);
+        assert!(!states.contains(&certificate(reversed)));
+
+        let block = Block::new(Slot(0), None, ValidatorId::Index(0), vec![], 1000);
+        let blocks = std::collections::BTreeSet::from([block.clone(), block]);
+        assert_eq!(blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_key_ids_are_whole_keys() {
+        let keys: Vec<_> =
+            (1..=3u8).map(|i| SigningKey::from_bytes(&[i; 32]).verifying_key()).collect();
+        let ids: HashSet<_> = keys.iter().map(ValidatorId::from_pubkey).collect();
+        assert_eq!(ids.len(), keys.len());
+        let id = ValidatorId::from_pubkey(&keys[0]);
+        assert_eq!(id, ValidatorId::Key(keys[0].to_bytes()));
+        assert_eq!(id.index(), None);
+        assert!(ValidatorId::Index(u64::MAX - 1) < id);
+
+        let mut bytes = Vec::new();
+        id.write_bytes(&mut bytes);
+        assert_eq!(&bytes[..8], &ValidatorId::KEY_MARKER.to_le_bytes());
+        assert_eq!(&bytes[8..], keys[0].as_bytes());
+
+        for id in [ValidatorId::Index(7), id] {
+            let json = serde_json::to_string(&id).unwrap();
+            assert_eq!(serde_json::from_str::<ValidatorId>(&json).unwrap(), id);
+            let binary = bincode::serialize(&id).unwrap();
+            assert_eq!(bincode::deserialize::<ValidatorId>(&binary).unwrap(), id);
+        }
+        assert_eq!(serde_json::to_string(&ValidatorId::Index(7)).unwrap(), "7");
+
+        let config = |id, pubkey| ValidatorConfig {
+            id,
+            stake: StakeWeight(100),
+            is_byzantine: false,
+            is_offline: false,
+            pubkey: Some(pubkey),
+            address: None,
+        };
+        let mut vset = ValidatorSet::new();
+        let reserved = ValidatorId::Index(ValidatorId::KEY_MARKER);
+        assert_eq!(
+            vset.try_add_validator(config(reserved, keys[0])),
+            Err(ValidatorSetError::ReservedId(reserved))
+        );
+        assert_eq!(
+            vset.add_validator(config(id, keys[1])),
+            Err(ValidatorSetError::KeyMismatch(id))
+        );
+        vset.try_add_validator(config(id, keys[0])).unwrap();
+        assert_eq!(vset.find_by_pubkey(&keys[0]).unwrap().id, id);
+    }
+}
@@ -0,0 +1,370 @@
+//! Block verification before voting
+//!
+//! Every block Rotor reconstructs passes through a `BlockVerifier` before the
+//! engine considers voting for it. The verifier checks that the block was
+//! proposed by the slot's scheduled leader for the slot its shreds claimed,
+//! that the header carries the leader's signature when we know its key, that
+//! it names the slot's epoch and sits one above its parent, that its
+//! timestamp follows the chain's `BlockTimePolicy` (after its parent's and
+//! not too far ahead of our wall clock, or zero where blocks carry no time),
+//! that the header commits to the body's transactions, that each
+//! transaction passes the `TransactionValidator` (by default, that its
+//! payer's signature verifies), and that the header hashes to the block ID.
+//! Blocks failing any check are never voted for.
+
+use crate::params::BlockTimePolicy;
+use crate::types::*;
+use ed25519_dalek::VerifyingKey;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("Block for slot {slot} is led by {got}, expected {expected}")]
+    WrongLeader { slot: Slot, expected: ValidatorId, got: ValidatorId },
+
+    #[error("Block claims slot {got}, its shreds slot {expected}")]
+    SlotMismatch { expected: Slot, got: Slot },
+
+    #[error("Block header is not signed by its leader {0}")]
+    InvalidHeaderSignature(ValidatorId),
+
+    #[error("Block claims epoch {got}, its slot is in epoch {expected}")]
+    EpochMismatch { expected: Epoch, got: Epoch },
+
+    #[error("Block claims height {got}, expected {expected} from its parent")]
+    HeightMismatch { expected: u64, got: u64 },
+
+    #[error("Block carries timestamp {0} on a chain without block times")]
+    UnexpectedTimestamp(u64),
+
+    #[error("Block timestamp {timestamp} is not after its parent's {parent}")]
+    TimestampNotAfterParent { timestamp: u64, parent: u64 },
+
+    #[error("Block timestamp {timestamp} is ahead of the local clock {now}")]
+    TimestampInFuture { timestamp: u64, now: u64 },
+
+    #[error("Block header does not commit to the block's transactions")]
+    TransactionsRootMismatch,
+
+    #[error("Transaction {index} of the block is invalid: {reason}")]
+    InvalidTransaction { index: usize, reason: TransactionError },
+
+    #[error("Block hashes to {computed}, not its ID {claimed}")]
+    IdMismatch { claimed: BlockId, computed: BlockId },
+}
+
+/// Decides whether a transaction may appear in a block
+pub trait TransactionValidator: Send + Sync {
+    fn validate(&self, transaction: &Transaction) -> Result<(), TransactionError>;
+}
+
+/// Accepts every transaction whose payer signature verifies
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignedTransactions;
+
+impl TransactionValidator for SignedTransactions {
+    fn validate(&self, transaction: &Transaction) -> Result<(), TransactionError> {
+        transaction.verify()
+    }
+}
+
+/// What a block is checked against, as seen by the verifying node
+#[derive(Debug, Clone, Copy)]
+pub struct BlockContext {
+    /// Slot of the shreds the block was reconstructed from
+    pub slot: Slot,
+    /// Scheduled leader of that slot
+    pub leader: ValidatorId,
+    /// Leader's public key, if registered; unsigned headers pass without it
+    pub leader_key: Option<VerifyingKey>,
+    /// Chain the header signature must be bound to
+    pub chain_id: [u8; 32],
+    /// Epoch of `slot`
+    pub epoch: Epoch,
+    /// Height of the parent block, if we have its body
+    pub parent_height: Option<u64>,
+    /// Timestamp of the parent block, if we have its body
+    pub parent_timestamp: Option<u64>,
+    pub block_time: BlockTimePolicy,
+    /// Local wall clock in milliseconds
+    pub now: u64,
+}
+
+/// Checks reconstructed blocks before they become eligible for votes
+pub struct BlockVerifier {
+    /// How far a block's timestamp may run ahead of our clock
+    max_clock_drift: Duration,
+    validator: Box<dyn TransactionValidator>,
+}
+
+impl Default for BlockVerifier {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl BlockVerifier {
+    pub fn new(max_clock_drift: Duration) -> Self {
+        Self {
+            max_clock_drift,
+            validator: Box::new(SignedTransactions),
+        }
+    }
+
+    pub fn set_transaction_validator(&mut self, validator: Box<dyn TransactionValidator>) {
+        self.validator = validator;
+    }
+
+    /// Run every check, cheapest first
+    pub fn verify(&self, block: &Block, context: &BlockContext) -> Result<(), VerifyError> {
+        let header = &block.header;
+        if header.slot != context.slot {
+            return Err(VerifyError::SlotMismatch { expected: context.slot, got: header.slot });
+        }
+        if header.leader != context.leader {
+            return Err(VerifyError::WrongLeader {
+                slot: header.slot,
+                expected: context.leader,
+                got: header.leader,
+            });
+        }
+        if context.leader_key.is_some_and(|key| !header.verify(&key, &context.chain_id)) {
+            return Err(VerifyError::InvalidHeaderSignature(header.leader));
+        }
+
+        if header.epoch != context.epoch {
+            return Err(VerifyError::EpochMismatch { expected: context.epoch, got: header.epoch });
+        }
+        let expected_height = match header.parent {
+            None => Some(0),
+            Some(_) => context.parent_height.map(|height| height + 1),
+        };
+        if let Some(expected) = expected_height {
+            if header.height != expected {
+                return Err(VerifyError::HeightMismatch { expected, got: header.height });
+            }
+        }
+
+        match context.block_time {
+            BlockTimePolicy::WallClock => self.check_timestamp(header, context)?,
+            BlockTimePolicy::Omitted if header.timestamp != 0 => {
+                return Err(VerifyError::UnexpectedTimestamp(header.timestamp));
+            }
+            BlockTimePolicy::Omitted => {}
+        }
+
+        if header.transactions_root != block.body.transactions_root() {
+            return Err(VerifyError::TransactionsRootMismatch);
+        }
+        for (index, transaction) in block.body.transactions.iter().enumerate() {
+            self.validator
+                .validate(transaction)
+                .map_err(|reason| VerifyError::InvalidTransaction { index, reason })?;
+        }
+
+        let computed = block.compute_id();
+        if computed != block.id {
+            return Err(VerifyError::IdMismatch { claimed: block.id, computed });
+        }
+        Ok(())
+    }
+
+    fn check_timestamp(
+        &self,
+        header: &BlockHeader,
+        context: &BlockContext,
+    ) -> Result<(), VerifyError> {
+        if let Some(parent) = context.parent_timestamp {
+            if header.timestamp <= parent {
+                return Err(VerifyError::TimestampNotAfterParent {
+                    timestamp: header.timestamp,
+                    parent,
+                });
+            }
+        }
+        let latest = context.now.saturating_add(self.max_clock_drift.as_millis() as u64);
+        if header.timestamp > latest {
+            return Err(VerifyError::TimestampInFuture {
+                timestamp: header.timestamp,
+                now: context.now,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for BlockVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockVerifier")
+            .field("max_clock_drift", &self.max_clock_drift)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusError};
+    use crate::rotor::Rotor;
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_only_verified_blocks_voted_for() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let payer = SigningKey::from_bytes(&[9u8; 32]);
+        let transaction = |nonce| Transaction::new(&payer, nonce, vec![1]);
+        let block = |transactions: Vec<Transaction>, timestamp: u64| {
+            Block::new(Slot(0), None, ValidatorId::Index(0), transactions, timestamp)
+        };
+        let new_engine = || {
+            ConsensusEngine::new(ValidatorId::Index(1), vset.clone(), ConsensusConfig::default())
+        };
+        let deliver = |engine: &mut ConsensusEngine, block: &Block| {
+            let shreds = Rotor::new(vset.clone()).encode_block(block).unwrap();
+            let mut result = Ok(());
+            for shred in shreds {
+                result = result.and(engine.receive_shred(shred));
+            }
+            result
+        };
+
+        // A transaction whose signature doesn't verify; nothing is voted for
+        let mut engine = new_engine();
+        let mut forged = transaction(1);
+        forged.nonce = 2;
+        let result = deliver(&mut engine, &block(vec![transaction(0), forged], 1000));
+        assert!(matches!(
+            result,
+            Err(ConsensusError::InvalidBlock(VerifyError::InvalidTransaction { index: 1, .. }))
+        ));
+        assert!(engine.take_outgoing_votes().is_empty());
+
+        // Nor for a block stamped far in the future
+        let mut engine = new_engine();
+        let result = deliver(&mut engine, &block(vec![transaction(0)], u64::MAX));
+        assert!(matches!(
+            result,
+            Err(ConsensusError::InvalidBlock(VerifyError::TimestampInFuture { .. }))
+        ));
+        assert!(engine.take_outgoing_votes().is_empty());
+
+        // A block passing every check gets our notar vote
+        let mut engine = new_engine();
+        deliver(&mut engine, &block(vec![transaction(0)], 1000)).unwrap();
+        assert_eq!(engine.take_outgoing_votes().len(), 1);
+
+        let context = BlockContext {
+            slot: Slot(0),
+            leader: ValidatorId::Index(0),
+            leader_key: None,
+            chain_id: [0u8; 32],
+            epoch: Epoch(0),
+            parent_height: None,
+            parent_timestamp: Some(1000),
+            block_time: BlockTimePolicy::WallClock,
+            now: 2000,
+        };
+        let verifier = BlockVerifier::default();
+        assert_eq!(
+            verifier.verify(&block(vec![], 1000), &context),
+            Err(VerifyError::TimestampNotAfterParent { timestamp: 1000, parent: 1000 })
+        );
+        let mut forged = block(vec![], 1001);
+        forged.header.leader = ValidatorId::Index(2);
+        assert!(matches!(verifier.verify(&forged, &context), Err(VerifyError::WrongLeader { .. })));
+        forged.header.leader = ValidatorId::Index(0);
+        forged.header.timestamp = 1002;
+        assert!(matches!(verifier.verify(&forged, &context), Err(VerifyError::IdMismatch { .. })));
+    }
+
+    #[test]
+    fn test_header_commits_to_body_and_carries_leader_signature() {
+        let leader = SigningKey::from_bytes(&[3u8; 32]);
+        let payer = SigningKey::from_bytes(&[9u8; 32]);
+        let transactions: Vec<_> = (0..3).map(|i| Transaction::new(&payer, i, vec![1])).collect();
+        let mut block = Block::new(Slot(0), None, ValidatorId::Index(0), transactions, 1000);
+        let context = BlockContext {
+            slot: Slot(0),
+            leader: ValidatorId::Index(0),
+            leader_key: Some(leader.verifying_key()),
+            chain_id: [7u8; 32],
+            epoch: Epoch(0),
+            parent_height: None,
+            parent_timestamp: None,
+            block_time: BlockTimePolicy::WallClock,
+            now: 2000,
+        };
+        let verifier = BlockVerifier::default();
+
+        // Once the leader's key is known its signature is required
+        assert_eq!(
+            verifier.verify(&block, &context),
+            Err(VerifyError::InvalidHeaderSignature(ValidatorId::Index(0)))
+        );
+        let id = block.id;
+        block.header.sign(&leader, &[8u8; 32]);
+        assert_eq!(
+            verifier.verify(&block, &context),
+            Err(VerifyError::InvalidHeaderSignature(ValidatorId::Index(0)))
+        );
+        block.header.sign(&leader, &context.chain_id);
+        assert_eq!(block.compute_id(), id);
+        assert_eq!(verifier.verify(&block, &context), Ok(()));
+
+        // A body swapped under a signed header is caught without rehashing the ID
+        let mut swapped = block.clone();
+        swapped.body.transactions.pop();
+        assert_eq!(verifier.verify(&swapped, &context), Err(VerifyError::TransactionsRootMismatch));
+
+        // Re-signing a changed header doesn't help; its hash no longer matches
+        let mut forged = block;
+        forged.header.timestamp += 1;
+        forged.header.sign(&leader, &context.chain_id);
+        assert!(matches!(verifier.verify(&forged, &context), Err(VerifyError::IdMismatch { .. })));
+    }
+
+    #[test]
+    fn test_height_epoch_and_block_time_checked() {
+        let parent = Block::new(Slot(4), None, ValidatorId::Index(0), vec![], 1000);
+        let child = |height, epoch, timestamp| {
+            Block::new(Slot(9), Some(parent.id), ValidatorId::Index(1), vec![], timestamp)
+                .at_position(height, Epoch(epoch))
+        };
+        let context = BlockContext {
+            slot: Slot(9),
+            leader: ValidatorId::Index(1),
+            leader_key: None,
+            chain_id: [0u8; 32],
+            epoch: Epoch(2),
+            parent_height: Some(parent.header.height),
+            parent_timestamp: Some(parent.header.timestamp),
+            block_time: BlockTimePolicy::WallClock,
+            now: 2000,
+        };
+        let verifier = BlockVerifier::default();
+
+        // Skipped slots 5 to 8 leave no gap in heights
+        assert_eq!(verifier.verify(&child(1, 2, 1001), &context), Ok(()));
+        assert_eq!(
+            verifier.verify(&child(5, 2, 1001), &context),
+            Err(VerifyError::HeightMismatch { expected: 1, got: 5 })
+        );
+        assert_eq!(
+            verifier.verify(&child(1, 1, 1001), &context),
+            Err(VerifyError::EpochMismatch { expected: Epoch(2), got: Epoch(1) })
+        );
+        // Without the parent's body the height is taken on trust
+        let orphaned = BlockContext { parent_height: None, ..context };
+        assert_eq!(verifier.verify(&child(5, 2, 1001), &orphaned), Ok(()));
+
+        // Chains without block times require a zero timestamp
+        let timeless = BlockContext { block_time: BlockTimePolicy::Omitted, ..context };
+        assert_eq!(
+            verifier.verify(&child(1, 2, 1001), &timeless),
+            Err(VerifyError::UnexpectedTimestamp(1001))
+        );
+        assert_eq!(verifier.verify(&child(1, 2, 0), &timeless), Ok(()));
+    }
+}
@@ -3,9 +3,15 @@
 //! Implements the dual-path concurrent voting strategy:
 //! - Round 1: Notarization votes targeting 80% quorum (fast path)
 //! - Round 2: Finalization votes targeting 60% quorum (fallback path)
+//!
+//! Notar-fallback votes count toward a 60% notarization certificate together
+//! with notar votes, and skip-fallback votes count toward a 60% skip
+//! certificate together with skip votes. The percentages are those of the
+//! default `ProtocolParams`; see `with_params`.
 
+use crate::params::ProtocolParams;
 use crate::types::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,6 +27,9 @@ pub enum VotorError {
 
     #[error("Block not found: {0}")]
     BlockNotFound(BlockId),
+
+    #[error("Vote for slot {slot} predates the current epoch starting at {epoch_start}")]
+    PreviousEpoch { slot: Slot, epoch_start: Slot },
 }
 
 /// Votor state machine for managing votes and finalization
@@ -28,27 +37,83 @@ pub struct Votor {
     /// Current slot
     current_slot: Slot,
 
-    /// Current round (1 or 2)
-    current_round: VoteRound,
+    /// Slots whose round 1 timed out; the rest are still in round 1
+    round2_slots: HashSet<Slot>,
 
     /// Vote sets per block
     vote_sets: HashMap<BlockId, VoteSet>,
 
+    /// Skip vote sets per slot
+    skip_vote_sets: HashMap<Slot, SkipVoteSet>,
+
+    /// Notarized blocks (60% notar + notar-fallback)
+    notarized: HashMap<BlockId, Slot>,
+
+    /// Skipped slots (60% skip + skip-fallback)
+    skipped: HashSet<Slot>,
+
+    /// First notar/final vote per (slot, kind, validator), for equivocation checks
+    slot_votes: HashMap<(Slot, VoteKind, ValidatorId), Vote>,
+
+    /// Double-vote evidence per offending validator
+    evidence: HashMap<ValidatorId, Vec<DoubleVoteEvidence>>,
+
     /// Finalized blocks
     finalized: Vec<FinalizationCertificate>,
 
     /// Validator set with stakes
     validator_set: ValidatorSet,
+
+    /// First slot `validator_set` applies to; older votes are rejected
+    epoch_start: Slot,
+
+    /// Quorum thresholds
+    params: ProtocolParams,
 }
 
 impl Votor {
     pub fn new(validator_set: ValidatorSet) -> Self {
         Self {
             current_slot: Slot(0),
-            current_round: VoteRound::Round1,
+            round2_slots: HashSet::new(),
             vote_sets: HashMap::new(),
+            skip_vote_sets: HashMap::new(),
+            notarized: HashMap::new(),
+            skipped: HashSet::new(),
+            slot_votes: HashMap::new(),
+            evidence: HashMap::new(),
             finalized: Vec::new(),
             validator_set,
+            epoch_start: Slot(0),
+            params: ProtocolParams::DEFAULT,
+        }
+    }
+
+    /// Use the quorum thresholds of `params`
+    pub fn with_params(mut self, params: ProtocolParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Rebuild a Votor from a checkpoint: the slot and finalized certificates
+    ///
+    /// In-flight vote sets are discarded and rebuilt as votes are re-received.
+    pub fn from_checkpoint(
+        validator_set: ValidatorSet,
+        slot: Slot,
+        finalized: Vec<FinalizationCertificate>,
+    ) -> Self {
+        let mut votor = Self::new(validator_set);
+        votor.current_slot = slot;
+        votor.finalized = finalized;
+        votor
+    }
+
+    /// Record a certificate verified outside of vote processing, e.g. by
+    /// catch-up sync; certificates for already finalized blocks are ignored
+    pub fn import_certificate(&mut self, cert: FinalizationCertificate) {
+        if !self.is_finalized(&cert.block_id) {
+            self.finalized.push(cert);
         }
     }
 
@@ -57,6 +122,23 @@ impl Votor {
         // Validate vote
         self.validate_vote(&vote)?;
 
+        if vote.kind.is_skip() {
+            self.process_skip_vote(vote)?;
+            return Ok(None);
+        }
+
+        // Notar and final votes may only go to one block per slot
+        // (notar-fallback votes may legitimately cover several blocks)
+        if matches!(vote.kind, VoteKind::Notar | VoteKind::Final) {
+            let key = (vote.slot, vote.kind, vote.validator);
+            if let Some(first) = self.slot_votes.get(&key) {
+                if first.block_id != vote.block_id {
+                    let first = first.clone();
+                    return Err(self.record_double_vote(first, vote));
+                }
+            }
+        }
+
         // Get or create vote set for this block
         let vote_set = self
             .vote_sets
@@ -64,40 +146,104 @@ impl Votor {
             .or_insert_with(|| VoteSet::new(vote.block_id));
 
         // Check for double voting
-        match vote.round {
-            VoteRound::Round1 => {
-                if vote_set.round1_votes.contains_key(&vote.validator) {
-                    return Err(VotorError::DoubleVote(vote.validator));
-                }
-            }
-            VoteRound::Round2 => {
-                if vote_set.round2_votes.contains_key(&vote.validator) {
-                    return Err(VotorError::DoubleVote(vote.validator));
-                }
-            }
+        if let Some(first) = vote_set
+            .votes(vote.kind)
+            .and_then(|votes| votes.get(&vote.validator))
+        {
+            let first = first.clone();
+            return Err(self.record_double_vote(first, vote));
+        }
+
+        if matches!(vote.kind, VoteKind::Notar | VoteKind::Final) {
+            self.slot_votes
+                .insert((vote.slot, vote.kind, vote.validator), vote.clone());
         }
 
         // Add vote
         vote_set.add_vote(vote.clone());
 
+        // Check if the block is notarized
+        self.check_notarization(vote.block_id, vote.slot);
+
         // Check if we can finalize
         self.check_finalization(vote.block_id, vote.slot)
     }
 
+    /// Process a skip or skip-fallback vote
+    fn process_skip_vote(&mut self, vote: Vote) -> Result<(), VotorError> {
+        let slot = vote.slot;
+        let skip_set = self
+            .skip_vote_sets
+            .entry(slot)
+            .or_insert_with(|| SkipVoteSet::new(slot));
+
+        if let Some(first) = skip_set
+            .votes(vote.kind)
+            .and_then(|votes| votes.get(&vote.validator))
+        {
+            let first = first.clone();
+            return Err(self.record_double_vote(first, vote));
+        }
+
+        skip_set.add_vote(vote);
+
+        // Check skip quorum (60% skip + skip-fallback)
+        let skip_stake = skip_set.skip_stake(&self.validator_set);
+        let quorum = self.params.is_fallback_quorum(&self.validator_set, skip_stake);
+        if quorum && self.skipped.insert(slot) {
+            tracing::info!("Slot {} skipped with {} stake", slot, skip_stake.as_u64());
+        }
+
+        Ok(())
+    }
+
+    /// Keep a conflicting vote pair as evidence and return the error to report
+    ///
+    /// Exact replays of the same vote are rejected but not kept, since they
+    /// are not misbehavior, and a pair already kept is not kept again.
+    fn record_double_vote(&mut self, first: Vote, second: Vote) -> VotorError {
+        let validator = second.validator;
+        let evidence = DoubleVoteEvidence { first, second };
+        let kept = self.evidence.entry(validator).or_default();
+        let offense = double_vote_offense(&evidence);
+        if evidence.is_valid() && !kept.iter().any(|known| double_vote_offense(known) == offense) {
+            tracing::warn!("Double vote by {} in slot {}", validator, evidence.slot());
+            kept.push(evidence);
+        }
+        VotorError::DoubleVote(validator)
+    }
+
+    /// Check if a block reached notarization (60% notar + notar-fallback)
+    fn check_notarization(&mut self, block_id: BlockId, slot: Slot) {
+        let Some(vote_set) = self.vote_sets.get(&block_id) else {
+            return;
+        };
+
+        let stake = vote_set.notarization_stake(&self.validator_set);
+        if self.params.is_fallback_quorum(&self.validator_set, stake) {
+            self.notarized.insert(block_id, slot);
+        }
+    }
+
     /// Check if a block can be finalized
     fn check_finalization(
         &mut self,
         block_id: BlockId,
         slot: Slot,
     ) -> Result<Option<FinalizationCertificate>, VotorError> {
+        // Votes arriving after the quorum (or after a certificate received
+        // from a peer) don't finalize the block again
+        if self.is_finalized(&block_id) {
+            return Ok(None);
+        }
         let vote_set = self
             .vote_sets
             .get(&block_id)
             .ok_or(VotorError::BlockNotFound(block_id))?;
 
         // Check fast path (80% in round 1)
-        let round1_stake = self.calculate_vote_stake(&vote_set.round1_votes);
-        if self.validator_set.check_fast_quorum(round1_stake) {
+        let round1_stake = vote_set.round1_stake(&self.validator_set);
+        if self.params.is_fast_quorum(&self.validator_set, round1_stake) {
             let cert = self.create_certificate(
                 block_id,
                 slot,
@@ -110,9 +256,9 @@ impl Votor {
         }
 
         // Check fallback path (60% in round 2)
-        if matches!(self.current_round, VoteRound::Round2) {
-            let round2_stake = self.calculate_vote_stake(&vote_set.round2_votes);
-            if self.validator_set.check_fallback_quorum(round2_stake) {
+        if self.round2_slots.contains(&slot) {
+            let round2_stake = vote_set.round2_stake(&self.validator_set);
+            if self.params.is_fallback_quorum(&self.validator_set, round2_stake) {
                 let cert = self.create_certificate(
                     block_id,
                     slot,
@@ -128,16 +274,10 @@ impl Votor {
         Ok(None)
     }
 
-    /// Calculate total stake from a set of votes
-    fn calculate_vote_stake(&self, votes: &HashMap<ValidatorId, Vote>) -> StakeWeight {
-        votes
-            .keys()
-            .filter_map(|id| self.validator_set.get_validator(id))
-            .map(|v| v.stake)
-            .sum()
-    }
-
     /// Create a finalization certificate
+    ///
+    /// Votes are sorted by validator ID so that every node produces a
+    /// byte-identical certificate for the same quorum.
     fn create_certificate(
         &self,
         block_id: BlockId,
@@ -146,17 +286,27 @@ impl Votor {
         votes: &HashMap<ValidatorId, Vote>,
         total_stake: StakeWeight,
     ) -> FinalizationCertificate {
+        let mut votes: Vec<Vote> = votes.values().cloned().collect();
+        votes.sort_by_key(|vote| vote.validator);
+
         FinalizationCertificate {
             block_id,
             slot,
             round,
-            votes: votes.values().cloned().collect(),
+            votes,
             total_stake,
         }
     }
 
     /// Validate a vote
     fn validate_vote(&self, vote: &Vote) -> Result<(), VotorError> {
+        if vote.slot < self.epoch_start {
+            return Err(VotorError::PreviousEpoch {
+                slot: vote.slot,
+                epoch_start: self.epoch_start,
+            });
+        }
+
         // Check validator exists
         if self.validator_set.get_validator(&vote.validator).is_none() {
             return Err(VotorError::UnknownValidator(vote.validator));
@@ -170,23 +320,147 @@ impl Votor {
         Ok(())
     }
 
-    /// Advance to round 2 (timeout on round 1)
+    /// Fill in the signature of a vote that was counted before it was
+    /// signed, so certificates holding it verify at peers
+    pub fn attach_signature(&mut self, signed: &Vote) {
+        let same = |vote: &&mut Vote| {
+            vote.validator == signed.validator
+                && vote.kind == signed.kind
+                && vote.block_id == signed.block_id
+                && vote.signature.is_empty()
+        };
+        let counted = match signed.kind {
+            VoteKind::Notar | VoteKind::NotarFallback | VoteKind::Final => self
+                .vote_sets
+                .get_mut(&signed.block_id)
+                .and_then(|set| set.votes_mut(signed.kind))
+                .and_then(|votes| votes.get_mut(&signed.validator)),
+            VoteKind::Skip | VoteKind::SkipFallback => self
+                .skip_vote_sets
+                .get_mut(&signed.slot)
+                .and_then(|set| set.votes_mut(signed.kind))
+                .and_then(|votes| votes.get_mut(&signed.validator)),
+        };
+        let first = self
+            .slot_votes
+            .get_mut(&(signed.slot, signed.kind, signed.validator));
+        let certified = self
+            .finalized
+            .iter_mut()
+            .filter(|cert| cert.block_id == signed.block_id)
+            .flat_map(|cert| cert.votes.iter_mut());
+        for vote in counted.into_iter().chain(first).chain(certified).filter(same) {
+            vote.signature = signed.signature.clone();
+        }
+    }
+
+    /// Advance the current slot to round 2 (timeout on round 1)
     pub fn advance_to_round2(&mut self) {
-        self.current_round = VoteRound::Round2;
+        self.start_round2(self.current_slot);
+    }
+
+    /// Advance `slot` to round 2, e.g. an earlier slot still in flight
+    pub fn start_round2(&mut self, slot: Slot) {
+        self.round2_slots.insert(slot);
     }
 
     /// Move to next slot
     pub fn next_slot(&mut self) {
         self.current_slot = self.current_slot.next();
-        self.current_round = VoteRound::Round1;
         // Keep vote sets for finalization verification
     }
 
+    /// Switch to a new epoch's validator set from `first_slot` on
+    ///
+    /// Votes for earlier slots are rejected from then on, so no quorum is
+    /// ever computed over one epoch's votes with another epoch's stakes.
+    pub fn begin_epoch(&mut self, validator_set: ValidatorSet, first_slot: Slot) {
+        self.validator_set = validator_set;
+        self.epoch_start = first_slot;
+    }
+
+    /// Validators that cast any vote in a slot
+    pub fn voters(&self, slot: Slot) -> HashSet<ValidatorId> {
+        self.slot_votes
+            .keys()
+            .filter(|(vote_slot, _, _)| *vote_slot == slot)
+            .map(|(_, _, validator)| *validator)
+            .collect()
+    }
+
+    /// Validators that voted to skip a slot
+    pub fn skip_voters(&self, slot: Slot) -> HashSet<ValidatorId> {
+        self.skip_vote_sets
+            .get(&slot)
+            .map(SkipVoteSet::skip_voters)
+            .unwrap_or_default()
+    }
+
     /// Check if a block is finalized
     pub fn is_finalized(&self, block_id: &BlockId) -> bool {
         self.finalized.iter().any(|cert| cert.block_id == *block_id)
     }
 
+    /// Check if a block is notarized
+    pub fn is_notarized(&self, block_id: &BlockId) -> bool {
+        self.notarized.contains_key(block_id)
+    }
+
+    /// Stake behind the notar and notar-fallback votes for a block
+    pub fn notarization_stake(&self, block_id: &BlockId) -> StakeWeight {
+        self.vote_sets
+            .get(block_id)
+            .map(|set| set.notarization_stake(&self.validator_set))
+            .unwrap_or(StakeWeight(0))
+    }
+
+    /// Notarized block of a slot; the lowest ID if several are
+    pub fn notarized_block(&self, slot: Slot) -> Option<BlockId> {
+        self.notarized
+            .iter()
+            .filter(|(_, notarized_slot)| **notarized_slot == slot)
+            .map(|(block_id, _)| *block_id)
+            .min()
+    }
+
+    /// Double-vote evidence recorded against a validator
+    pub fn evidence(&self, validator: &ValidatorId) -> &[DoubleVoteEvidence] {
+        self.evidence.get(validator).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// All recorded double-vote evidence, ordered by validator
+    pub fn all_evidence(&self) -> Vec<&DoubleVoteEvidence> {
+        let mut all: Vec<_> = self.evidence.values().flatten().collect();
+        all.sort_by_key(|e| (e.validator(), e.slot()));
+        all
+    }
+
+    /// Remove and return all evidence, e.g. to package it into slashing proofs
+    pub fn take_evidence(&mut self) -> Vec<DoubleVoteEvidence> {
+        let mut all: Vec<_> = self.evidence.drain().flat_map(|(_, e)| e).collect();
+        all.sort_by_key(|e| (e.validator(), e.slot()));
+        all
+    }
+
+    /// Whether exactly this vote was already counted
+    pub fn has_vote(&self, vote: &Vote) -> bool {
+        let votes = if vote.kind.is_skip() {
+            self.skip_vote_sets
+                .get(&vote.slot)
+                .and_then(|set| set.votes(vote.kind))
+        } else {
+            self.vote_sets
+                .get(&vote.block_id)
+                .and_then(|set| set.votes(vote.kind))
+        };
+        votes.and_then(|votes| votes.get(&vote.validator)) == Some(vote)
+    }
+
+    /// Check if a slot has been skipped
+    pub fn is_skipped(&self, slot: Slot) -> bool {
+        self.skipped.contains(&slot)
+    }
+
     /// Get current slot
     pub fn current_slot(&self) -> Slot {
         self.current_slot
@@ -194,28 +468,121 @@ impl Votor {
 
     /// Get current round
     pub fn current_round(&self) -> VoteRound {
-        self.current_round
+        self.round_of(self.current_slot)
+    }
+
+    /// Round `slot` is in
+    pub fn round_of(&self, slot: Slot) -> VoteRound {
+        if self.round2_slots.contains(&slot) {
+            VoteRound::Round2
+        } else {
+            VoteRound::Round1
+        }
     }
 
     /// Get finalized blocks
     pub fn finalized_blocks(&self) -> &[FinalizationCertificate] {
         &self.finalized
     }
+
+    /// Mutable access to finalized certificates, for corrupting state in tests
+    #[cfg(test)]
+    pub(crate) fn finalized_mut(&mut self) -> &mut Vec<FinalizationCertificate> {
+        &mut self.finalized
+    }
+}
+
+/// Adversarial vote injection hooks for testing Votor
+///
+/// Enabled for unit tests and by the `byzantine-testing` feature.
+#[cfg(any(test, feature = "byzantine-testing"))]
+pub mod byzantine {
+    use super::*;
+
+    /// Outcome of feeding a vote into Votor
+    pub type Reaction = Result<Option<FinalizationCertificate>, VotorError>;
+
+    /// Drives a Votor with malformed, equivocating, or replayed votes
+    pub struct ByzantineInjector<'a> {
+        votor: &'a mut Votor,
+    }
+
+    impl<'a> ByzantineInjector<'a> {
+        pub fn new(votor: &'a mut Votor) -> Self {
+            Self { votor }
+        }
+
+        /// Inject an arbitrary vote
+        pub fn inject(&mut self, vote: Vote) -> Reaction {
+            self.votor.process_vote(vote)
+        }
+
+        /// Inject a vote from a validator outside the validator set
+        pub fn inject_unknown_validator(&mut self, mut vote: Vote, id: ValidatorId) -> Reaction {
+            vote.validator = id;
+            self.inject(vote)
+        }
+
+        /// Inject a vote whose signature is garbage
+        pub fn inject_bad_signature(&mut self, mut vote: Vote) -> Reaction {
+            vote.signature = vec![0xff; 7];
+            self.inject(vote)
+        }
+
+        /// Vote the same kind for two different blocks in one slot
+        pub fn equivocate(&mut self, vote: Vote, other_block: BlockId) -> (Reaction, Reaction) {
+            let mut second = vote.clone();
+            second.block_id = other_block;
+            let first = self.inject(vote);
+            (first, self.inject(second))
+        }
+
+        /// Replay the same vote `times` times
+        pub fn replay(&mut self, vote: &Vote, times: usize) -> Vec<Reaction> {
+            (0..times).map(|_| self.inject(vote.clone())).collect()
+        }
+
+        /// Number of distinct validators Votor counted for a block and vote kind
+        pub fn tally(&self, block_id: &BlockId, kind: VoteKind) -> usize {
+            self.votor
+                .vote_sets
+                .get(block_id)
+                .and_then(|set| set.votes(kind))
+                .map_or(0, |votes| votes.len())
+        }
+
+        /// Access the underlying Votor for further assertions
+        pub fn votor(&self) -> &Votor {
+            self.votor
+        }
+    }
+}
+
+/// Slot and (kind, block) pairs of a double vote, in either order
+fn double_vote_offense(evidence: &DoubleVoteEvidence) -> (Slot, [(u8, BlockId); 2]) {
+    let vote = |vote: &Vote| (vote.kind.tag(), vote.block_id);
+    let mut pair = [vote(&evidence.first), vote(&evidence.second)];
+    pair.sort();
+    (evidence.slot(), pair)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stake::StakeDistribution;
 
     fn create_test_validator_set(count: usize) -> ValidatorSet {
         let mut vset = ValidatorSet::new();
         for i in 0..count {
             vset.add_validator(ValidatorConfig {
-                id: ValidatorId(i as u64),
+                id: ValidatorId::Index(i as u64),
                 stake: StakeWeight(100),
                 is_byzantine: false,
                 is_offline: false,
-            });
+                pubkey: None,
+                address: None,
+            })
+            .unwrap();
         }
         vset
     }
@@ -231,10 +598,10 @@ mod tests {
         // Cast 4 out of 5 votes (80%)
         for i in 0..4 {
             let vote = Vote {
-                validator: ValidatorId(i),
+                validator: ValidatorId::Index(i),
                 block_id,
                 slot,
-                round: VoteRound::Round1,
+                kind: VoteKind::Notar,
                 signature: vec![],
             };
 
@@ -260,10 +627,10 @@ mod tests {
         // Cast only 3 votes in round 1 (60%, not enough for fast path)
         for i in 0..3 {
             let vote = Vote {
-                validator: ValidatorId(i),
+                validator: ValidatorId::Index(i),
                 block_id,
                 slot,
-                round: VoteRound::Round1,
+                kind: VoteKind::Notar,
                 signature: vec![],
             };
             let result = votor.process_vote(vote);
@@ -276,10 +643,10 @@ mod tests {
         // Cast 3 votes in round 2 (60%, enough for fallback)
         for i in 0..3 {
             let vote = Vote {
-                validator: ValidatorId(i),
+                validator: ValidatorId::Index(i),
                 block_id,
                 slot,
-                round: VoteRound::Round2,
+                kind: VoteKind::Final,
                 signature: vec![],
             };
             let result = votor.process_vote(vote);
@@ -301,10 +668,10 @@ mod tests {
         let slot = Slot(0);
 
         let vote1 = Vote {
-            validator: ValidatorId(0),
+            validator: ValidatorId::Index(0),
             block_id,
             slot,
-            round: VoteRound::Round1,
+            kind: VoteKind::Notar,
             signature: vec![],
         };
 
@@ -312,7 +679,216 @@ mod tests {
         assert!(votor.process_vote(vote1.clone()).is_ok());
 
         // Second vote from same validator should fail
-        let result = votor.process_vote(vote1);
+        let result = votor.process_vote(vote1.clone());
         assert!(matches!(result, Err(VotorError::DoubleVote(_))));
+
+        // An exact replay is not evidence of misbehavior, nor is a copy
+        // re-signed or with its signature bits flipped
+        assert!(votor.evidence(&ValidatorId::Index(0)).is_empty());
+        let resigned = Vote { signature: vec![7u8; 64], ..vote1.clone() };
+        assert!(matches!(votor.process_vote(resigned), Err(VotorError::DoubleVote(_))));
+        assert!(votor.evidence(&ValidatorId::Index(0)).is_empty());
+
+        // Skip votes ignore the block, so two that differ only there don't conflict
+        let skip = Vote { kind: VoteKind::Skip, slot: Slot(1), ..vote1.clone() };
+        votor.process_vote(skip.clone()).unwrap();
+        let other_block = Vote { block_id: BlockId::new([9u8; 32]), ..skip };
+        assert!(votor.process_vote(other_block).is_err());
+        assert!(votor.evidence(&ValidatorId::Index(0)).is_empty());
+
+        // A vote for a different block in the same slot is kept as evidence
+        let mut vote2 = vote1.clone();
+        vote2.block_id = BlockId::new([2u8; 32]);
+        let result = votor.process_vote(vote2.clone());
+        assert!(matches!(result, Err(VotorError::DoubleVote(_))));
+
+        let evidence = votor.take_evidence();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].first, vote1);
+        assert_eq!(evidence[0].second, vote2);
+        assert!(evidence[0].is_valid());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_replayed_double_vote_kept_once() {
+        let vset = create_test_validator_set(3);
+        let mut votor = Votor::new(vset);
+        let vote = |block: u8| Vote {
+            validator: ValidatorId::Index(0),
+            block_id: BlockId::new([block; 32]),
+            slot: Slot(0),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+        votor.process_vote(vote(1)).unwrap();
+        for _ in 0..100 {
+            assert!(matches!(votor.process_vote(vote(2)), Err(VotorError::DoubleVote(_))));
+        }
+        assert_eq!(votor.evidence(&ValidatorId::Index(0)).len(), 1);
+
+        // A conflict with another block is a separate offense
+        assert!(votor.process_vote(vote(3)).is_err());
+        assert_eq!(votor.evidence(&ValidatorId::Index(0)).len(), 2);
+    }
+
+    #[test]
+    fn test_notar_fallback_notarization() {
+        let vset = create_test_validator_set(5);
+        let mut votor = Votor::new(vset);
+
+        let block_id = BlockId::new([1u8; 32]);
+        let slot = Slot(0);
+
+        // 2 notar + 1 notar-fallback = 60%, enough to notarize but not finalize
+        let kinds = [VoteKind::Notar, VoteKind::Notar, VoteKind::NotarFallback];
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let vote = Vote {
+                validator: ValidatorId::Index(i as u64),
+                block_id,
+                slot,
+                kind,
+                signature: vec![],
+            };
+            assert!(votor.process_vote(vote).unwrap().is_none());
+        }
+
+        assert!(votor.is_notarized(&block_id));
+        assert!(!votor.is_finalized(&block_id));
+    }
+
+    #[test]
+    fn test_skip_fallback_skip_certificate() {
+        let vset = create_test_validator_set(5);
+        let mut votor = Votor::new(vset);
+
+        let slot = Slot(0);
+        let kinds = [VoteKind::Skip, VoteKind::Skip, VoteKind::SkipFallback];
+        for (i, kind) in kinds.into_iter().enumerate() {
+            assert!(!votor.is_skipped(slot));
+            let vote = Vote {
+                validator: ValidatorId::Index(i as u64),
+                block_id: BlockId::new([0u8; 32]),
+                slot,
+                kind,
+                signature: vec![],
+            };
+            assert!(votor.process_vote(vote).unwrap().is_none());
+        }
+
+        assert!(votor.is_skipped(slot));
+    }
+
+    #[test]
+    fn test_certificate_vote_order_is_deterministic() {
+        let vset = create_test_validator_set(5);
+        let block_id = BlockId::new([1u8; 32]);
+
+        let votes: Vec<Vote> = (0..4)
+            .map(|i| Vote {
+                validator: ValidatorId::Index(i),
+                block_id,
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            })
+            .collect();
+
+        // Feed the same votes in two different orders
+        let mut forward = Votor::new(vset.clone());
+        let mut cert_a = None;
+        for vote in votes.iter().cloned() {
+            cert_a = forward.process_vote(vote).unwrap().or(cert_a);
+        }
+        let mut backward = Votor::new(vset);
+        let mut cert_b = None;
+        for vote in votes.iter().rev().cloned() {
+            cert_b = backward.process_vote(vote).unwrap().or(cert_b);
+        }
+
+        let cert_a = cert_a.unwrap();
+        let ids: Vec<_> = cert_a.votes.iter().map(|v| v.validator).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            bincode::serialize(&cert_a).unwrap(),
+            bincode::serialize(&cert_b.unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_attach_signature_reaches_certificate() {
+        let (vset, keys) = StakeDistribution::Equal(100).keyed_validator_set(5, 0);
+        let mut votor = Votor::new(vset);
+        let block_id = BlockId::new([1u8; 32]);
+
+        let mut cert = None;
+        let mut unsigned = None;
+        for i in 0..4 {
+            let mut vote = Vote {
+                validator: ValidatorId::Index(i),
+                block_id,
+                slot: Slot(0),
+                kind: VoteKind::Notar,
+                signature: vec![],
+            };
+            // Our own vote is counted before the signer returns
+            if i == 0 {
+                unsigned = Some(vote.clone());
+            } else {
+                vote.sign(&keys[i as usize], &[0u8; 32]);
+            }
+            cert = votor.process_vote(vote).unwrap().or(cert);
+        }
+        assert!(cert.unwrap().votes[0].signature.is_empty());
+
+        let mut signed = unsigned.unwrap();
+        signed.sign(&keys[0], &[0u8; 32]);
+        votor.attach_signature(&signed);
+        let cert = &votor.finalized_blocks()[0];
+        for vote in &cert.votes {
+            let key = keys[vote.validator.index().unwrap() as usize].verifying_key();
+            assert!(vote.verify(&key, &[0u8; 32]));
+        }
+        assert!(votor.has_vote(&signed));
+    }
+
+    #[test]
+    fn test_byzantine_replay_and_malformed_votes() {
+        use super::byzantine::ByzantineInjector;
+
+        let vset = create_test_validator_set(5);
+        let mut votor = Votor::new(vset);
+        let mut injector = ByzantineInjector::new(&mut votor);
+
+        let block_id = BlockId::new([1u8; 32]);
+        let vote = Vote {
+            validator: ValidatorId::Index(0),
+            block_id,
+            slot: Slot(0),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+
+        // Replays are rejected and counted once
+        let reactions = injector.replay(&vote, 3);
+        assert!(reactions[0].is_ok());
+        assert!(reactions[1..]
+            .iter()
+            .all(|r| matches!(r, Err(VotorError::DoubleVote(_)))));
+        assert_eq!(injector.tally(&block_id, VoteKind::Notar), 1);
+
+        // Votes from outside the validator set never count
+        let result = injector.inject_unknown_validator(vote.clone(), ValidatorId::Index(99));
+        assert!(matches!(result, Err(VotorError::UnknownValidator(_))));
+        assert_eq!(injector.tally(&block_id, VoteKind::Notar), 1);
+
+        // Equivocation is rejected and only the first vote counts
+        let mut equivocating = vote;
+        equivocating.validator = ValidatorId::Index(1);
+        let (first, second) = injector.equivocate(equivocating, BlockId::new([2u8; 32]));
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(VotorError::DoubleVote(_))));
+        assert_eq!(injector.tally(&block_id, VoteKind::Notar), 2);
+        assert_eq!(injector.tally(&BlockId::new([2u8; 32]), VoteKind::Notar), 0);
+        assert!(!injector.votor().is_finalized(&block_id));
+    }
+}
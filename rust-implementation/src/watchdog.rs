@@ -0,0 +1,134 @@
+//! Standstill detection
+//!
+//! A `Watchdog` counts round timeouts since a slot was last finalized or
+//! skipped. Once `standstill_timeouts` pass without progress the engine is in
+//! standstill: it emits `ConsensusEvent::Standstill` with what it has heard
+//! from the network, rebroadcasts its votes on every call instead of once per
+//! interval, and keeps repairing shreds after round 1. The next decided slot
+//! ends the standstill.
+
+use crate::types::*;
+
+/// What the engine saw when it entered standstill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandstillReport {
+    pub current_slot: Slot,
+    /// Slot of the latest finalized block
+    pub last_finalized: Option<Slot>,
+    /// Timeouts since a slot was last decided
+    pub timeouts: u32,
+    /// Votes counted in undecided slots, one per validator and slot
+    pub votes_seen: usize,
+    /// Stake of the validators we have votes from in undecided slots
+    pub stake_heard: StakeWeight,
+    pub total_stake: StakeWeight,
+}
+
+/// Counts timeouts without progress
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    threshold: u32,
+    timeouts: u32,
+    stalled: bool,
+}
+
+impl Watchdog {
+    /// Standstill after `threshold` timeouts without progress; 0 disables it
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            timeouts: 0,
+            stalled: false,
+        }
+    }
+
+    /// Use `threshold` from the next timeout on
+    pub fn set_threshold(&mut self, threshold: u32) {
+        self.threshold = threshold;
+    }
+
+    /// A slot was decided; whether that ended a standstill
+    pub fn progress(&mut self) -> bool {
+        self.timeouts = 0;
+        std::mem::take(&mut self.stalled)
+    }
+
+    /// A round deadline passed; whether this one starts a standstill
+    pub fn timed_out(&mut self) -> bool {
+        self.timeouts += 1;
+        if self.threshold == 0 || self.stalled || self.timeouts < self.threshold {
+            return false;
+        }
+        self.stalled = true;
+        true
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Timeouts since the last progress
+    pub fn timeouts(&self) -> u32 {
+        self.timeouts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusEvent};
+    use crate::stake::StakeDistribution;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_standstill_reported_and_rebroadcast_stepped_up() {
+        let vset = StakeDistribution::Equal(100).validator_set(5, 0);
+        let config = ConsensusConfig {
+            standstill_timeouts: 2,
+            vote_rebroadcast_interval: Duration::from_secs(3600),
+            ..ConsensusConfig::default()
+        };
+        let clock = ManualClock::new();
+        let mut engine = ConsensusEngine::new(ValidatorId::Index(1), vset, config.clone());
+        engine.set_clock(Arc::new(clock.clone()));
+        engine.start_round1_timer();
+        let events = engine.subscribe();
+        engine
+            .process_vote(Vote {
+                validator: ValidatorId::Index(3),
+                block_id: BlockId::new([0u8; 32]),
+                slot: Slot(0),
+                kind: VoteKind::Skip,
+                signature: vec![],
+            })
+            .unwrap();
+
+        // Nobody else votes: both deadlines of slot 0 pass without progress
+        clock.advance(config.timing.timeouts.round1);
+        engine.fire_timers();
+        assert!(!engine.is_stalled());
+        assert_eq!(engine.rebroadcast_votes().len(), 1);
+        assert!(engine.rebroadcast_votes().is_empty());
+        clock.advance(config.timing.timeouts.round2);
+        engine.fire_timers();
+        assert!(engine.is_stalled());
+
+        let report = events
+            .try_iter()
+            .find_map(|event| match event {
+                ConsensusEvent::Standstill(report) => Some(report),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(report.timeouts, 2);
+        assert_eq!(report.votes_seen, 2);
+        assert_eq!(report.stake_heard, StakeWeight(200));
+        assert_eq!(report.last_finalized, None);
+
+        // Our votes go out again without waiting for the interval
+        assert_eq!(engine.rebroadcast_votes().len(), 1);
+        assert_eq!(engine.rebroadcast_votes().len(), 1);
+    }
+}
@@ -0,0 +1,621 @@
+//! Compact wire formats for shreds and certificates
+//!
+//! # Shreds
+//!
+//! A shred travels in one UDP datagram of at most `MAX_SHRED_PACKET_SIZE`
+//! bytes: a fixed-layout header followed by the payload. All integers are
+//! little-endian.
+//!
+//! Layout, shared by versions 1 and 2:
+//!
+//! | Field           | Size |
+//! |-----------------|------|
+//! | version         | 1    |
+//! | flags           | 1    |
+//! | block_id        | 32   |
+//! | slot            | 8    |
+//! | leader          | 8, or 40 for a key ID |
+//! | fec_set         | 4    |
+//! | fec_set_count   | 4    |
+//! | index           | 2    |
+//! | total_shreds    | 2    |
+//! | data_shreds     | 2    |
+//! | payload length  | 2    |
+//! | signature       | 64 if flag `SIGNED` is set |
+//! | payload         | payload length |
+//!
+//! Validator IDs here and in certificates are written with
+//! `ValidatorId::write_bytes`: an index as 8 bytes, a key as the reserved
+//! index `ValidatorId::KEY_MARKER` followed by its 32 bytes. A shred from a
+//! leader identified by key has that much less room for payload.
+//!
+//! Flags: bit 0 marks a signed shred; bits 1 and 2 mark an lz4 or zstd
+//! compressed block payload.
+//!
+//! Version 2 adds the version to the bytes the leader signs (see
+//! `Shred::signing_bytes`). Decoding accepts every version from
+//! `MIN_SHRED_WIRE_VERSION` up to the current one, so nodes can upgrade
+//! one at a time; encoding writes the shred's own version.
+//!
+//! Decoding never panics on malformed input and rejects trailing bytes.
+//! `decode_shred_bytes` hands out the payload as a slice of the received
+//! buffer instead of copying it.
+//!
+//! # Certificates
+//!
+//! `encode_certificate` writes a `FinalizationCertificate` in a canonical
+//! layout other implementations (and the TLA+ tooling) can reproduce byte
+//! for byte; `tests/golden/finalization_certificate.hex` pins it. Integers
+//! are little-endian.
+//!
+//! | Field           | Size |
+//! |-----------------|------|
+//! | version         | 1    |
+//! | block_id        | 32   |
+//! | slot            | 8    |
+//! | round           | 1 (0 round 1, 1 round 2) |
+//! | total_stake     | 8    |
+//! | vote count      | 4    |
+//! | votes           | vote count times the vote layout |
+//!
+//! Each vote:
+//!
+//! | Field            | Size |
+//! |------------------|------|
+//! | validator        | 8, or 40 for a key ID |
+//! | block_id         | 32   |
+//! | slot             | 8    |
+//! | kind             | 1 (`VoteKind::tag`) |
+//! | signature length | 1 (0 or 64) |
+//! | signature        | signature length |
+//!
+//! Votes are written in ascending validator order, so a certificate has one
+//! encoding whatever order its votes arrived in; decoding rejects any other
+//! order, duplicate voters and trailing bytes.
+
+use crate::compression::Compression;
+use crate::rotor::Shred;
+use crate::types::*;
+use bytes::Bytes;
+use thiserror::Error;
+
+/// Current shred wire format version
+pub const SHRED_WIRE_VERSION: u8 = 2;
+
+/// Oldest shred wire format version still decoded
+pub const MIN_SHRED_WIRE_VERSION: u8 = 1;
+
+/// Largest datagram a shred may occupy (fits a 1280-byte IPv6 MTU)
+pub const MAX_SHRED_PACKET_SIZE: usize = 1232;
+
+/// Header size without the signature
+pub const SHRED_HEADER_SIZE: usize = 66;
+
+/// Current certificate encoding version
+pub const CERTIFICATE_WIRE_VERSION: u8 = 1;
+
+/// Ed25519 signature size
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Largest payload that fits a signed shred in one packet
+pub const MAX_SHRED_PAYLOAD: usize = MAX_SHRED_PACKET_SIZE - SHRED_HEADER_SIZE - SIGNATURE_SIZE;
+
+const FLAG_SIGNED: u8 = 1 << 0;
+const FLAG_LZ4: u8 = 1 << 1;
+const FLAG_ZSTD: u8 = 1 << 2;
+const KNOWN_FLAGS: u8 = FLAG_SIGNED | FLAG_LZ4 | FLAG_ZSTD;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    #[error("Packet truncated")]
+    Truncated,
+
+    #[error("Unsupported shred wire version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Unsupported certificate encoding version {0}")]
+    UnsupportedCertificateVersion(u8),
+
+    #[error("Unknown vote round {0}")]
+    UnknownRound(u8),
+
+    #[error("Unknown vote kind {0}")]
+    UnknownVoteKind(u8),
+
+    #[error("Certificate votes are not in ascending validator order")]
+    UnsortedVotes,
+
+    #[error("Validator {0} votes twice in the certificate")]
+    DuplicateVoter(ValidatorId),
+
+    #[error("Unknown shred flags {0:#04x}")]
+    UnknownFlags(u8),
+
+    #[error("Field {0} does not fit the wire format")]
+    FieldOverflow(&'static str),
+
+    #[error("Payload of {0} bytes exceeds the packet size")]
+    PayloadTooLarge(usize),
+
+    #[error("Signature must be empty or {SIGNATURE_SIZE} bytes")]
+    InvalidSignatureLength,
+
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+}
+
+/// Encode a shred into a single packet
+pub fn encode_shred(shred: &Shred) -> Result<Vec<u8>, WireError> {
+    check_version(shred.version)?;
+    let signed = match shred.signature.len() {
+        0 => false,
+        SIGNATURE_SIZE => true,
+        _ => return Err(WireError::InvalidSignatureLength),
+    };
+
+    let max_payload = if signed {
+        MAX_SHRED_PAYLOAD
+    } else {
+        MAX_SHRED_PAYLOAD + SIGNATURE_SIZE
+    };
+    let max_payload = match shred.leader {
+        ValidatorId::Index(_) => max_payload,
+        ValidatorId::Key(key) => max_payload - key.len(),
+    };
+    if shred.data.len() > max_payload {
+        return Err(WireError::PayloadTooLarge(shred.data.len()));
+    }
+
+    let mut out = Vec::with_capacity(SHRED_HEADER_SIZE + shred.signature.len() + shred.data.len());
+    out.push(shred.version);
+    let compression_flag = match shred.compression {
+        Compression::None => 0,
+        Compression::Lz4 => FLAG_LZ4,
+        Compression::Zstd => FLAG_ZSTD,
+    };
+    out.push(if signed { FLAG_SIGNED } else { 0 } | compression_flag);
+    out.extend_from_slice(shred.block_id.as_bytes());
+    out.extend_from_slice(&shred.slot.0.to_le_bytes());
+    shred.leader.write_bytes(&mut out);
+    out.extend_from_slice(&narrow::<u32>(shred.fec_set, "fec_set")?.to_le_bytes());
+    out.extend_from_slice(&narrow::<u32>(shred.fec_set_count, "fec_set_count")?.to_le_bytes());
+    out.extend_from_slice(&narrow::<u16>(shred.index, "index")?.to_le_bytes());
+    out.extend_from_slice(&narrow::<u16>(shred.total_shreds, "total_shreds")?.to_le_bytes());
+    out.extend_from_slice(&narrow::<u16>(shred.data_shreds, "data_shreds")?.to_le_bytes());
+    out.extend_from_slice(&(shred.data.len() as u16).to_le_bytes());
+    out.extend_from_slice(&shred.signature);
+    out.extend_from_slice(&shred.data);
+    Ok(out)
+}
+
+/// Decode a shred from a packet, copying its payload
+pub fn decode_shred(packet: &[u8]) -> Result<Shred, WireError> {
+    if packet.len() > MAX_SHRED_PACKET_SIZE {
+        return Err(WireError::PayloadTooLarge(packet.len()));
+    }
+    decode_shred_bytes(Bytes::copy_from_slice(packet))
+}
+
+/// Decode a shred whose payload shares `packet`'s buffer
+pub fn decode_shred_bytes(packet: Bytes) -> Result<Shred, WireError> {
+    if packet.len() > MAX_SHRED_PACKET_SIZE {
+        return Err(WireError::PayloadTooLarge(packet.len()));
+    }
+
+    let mut reader = Reader(&packet);
+    let version = reader.u8()?;
+    check_version(version)?;
+    let flags = reader.u8()?;
+    let compression = match flags & (FLAG_LZ4 | FLAG_ZSTD) {
+        0 => Compression::None,
+        FLAG_LZ4 => Compression::Lz4,
+        FLAG_ZSTD => Compression::Zstd,
+        _ => return Err(WireError::UnknownFlags(flags)),
+    };
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(WireError::UnknownFlags(flags));
+    }
+
+    let block_id = BlockId::new(reader.array()?);
+    let slot = Slot(u64::from_le_bytes(reader.array()?));
+    let leader = reader.validator_id()?;
+    let fec_set = u32::from_le_bytes(reader.array()?) as usize;
+    let fec_set_count = u32::from_le_bytes(reader.array()?) as usize;
+    let index = u16::from_le_bytes(reader.array()?) as usize;
+    let total_shreds = u16::from_le_bytes(reader.array()?) as usize;
+    let data_shreds = u16::from_le_bytes(reader.array()?) as usize;
+    let payload_len = u16::from_le_bytes(reader.array()?) as usize;
+
+    let signature = if flags & FLAG_SIGNED != 0 {
+        reader.take(SIGNATURE_SIZE)?.to_vec()
+    } else {
+        Vec::new()
+    };
+    let payload_start = packet.len() - reader.0.len();
+    reader.take(payload_len)?;
+
+    if !reader.0.is_empty() {
+        return Err(WireError::TrailingBytes(reader.0.len()));
+    }
+    let data = packet.slice(payload_start..payload_start + payload_len);
+
+    Ok(Shred {
+        version,
+        block_id,
+        slot,
+        leader,
+        fec_set,
+        fec_set_count,
+        index,
+        total_shreds,
+        data_shreds,
+        compression,
+        data,
+        signature,
+    })
+}
+
+/// Encode a certificate canonically, its votes sorted by validator
+pub fn encode_certificate(cert: &FinalizationCertificate) -> Result<Vec<u8>, WireError> {
+    let mut votes: Vec<&Vote> = cert.votes.iter().collect();
+    votes.sort_by_key(|vote| vote.validator);
+    if let Some(pair) = votes.windows(2).find(|pair| pair[0].validator == pair[1].validator) {
+        return Err(WireError::DuplicateVoter(pair[0].validator));
+    }
+
+    let mut out = vec![CERTIFICATE_WIRE_VERSION];
+    out.extend_from_slice(cert.block_id.as_bytes());
+    out.extend_from_slice(&cert.slot.0.to_le_bytes());
+    out.push(match cert.round {
+        VoteRound::Round1 => 0,
+        VoteRound::Round2 => 1,
+    });
+    out.extend_from_slice(&cert.total_stake.0.to_le_bytes());
+    out.extend_from_slice(&narrow::<u32>(votes.len(), "vote count")?.to_le_bytes());
+    for vote in votes {
+        if !matches!(vote.signature.len(), 0 | SIGNATURE_SIZE) {
+            return Err(WireError::InvalidSignatureLength);
+        }
+        vote.validator.write_bytes(&mut out);
+        out.extend_from_slice(vote.block_id.as_bytes());
+        out.extend_from_slice(&vote.slot.0.to_le_bytes());
+        out.push(vote.kind.tag());
+        out.push(vote.signature.len() as u8);
+        out.extend_from_slice(&vote.signature);
+    }
+    Ok(out)
+}
+
+/// Decode a canonically encoded certificate
+pub fn decode_certificate(bytes: &[u8]) -> Result<FinalizationCertificate, WireError> {
+    let mut reader = Reader(bytes);
+    let version = reader.u8()?;
+    if version != CERTIFICATE_WIRE_VERSION {
+        return Err(WireError::UnsupportedCertificateVersion(version));
+    }
+    let block_id = BlockId::new(reader.array()?);
+    let slot = Slot(u64::from_le_bytes(reader.array()?));
+    let round = match reader.u8()? {
+        0 => VoteRound::Round1,
+        1 => VoteRound::Round2,
+        other => return Err(WireError::UnknownRound(other)),
+    };
+    let total_stake = StakeWeight(u64::from_le_bytes(reader.array()?));
+    let count = u32::from_le_bytes(reader.array()?) as usize;
+
+    // Every vote takes at least 50 bytes; don't trust the count further
+    let mut votes = Vec::with_capacity(count.min(reader.0.len() / 50));
+    for _ in 0..count {
+        let validator = reader.validator_id()?;
+        match votes.last().map(|last: &Vote| last.validator.cmp(&validator)) {
+            Some(std::cmp::Ordering::Equal) => return Err(WireError::DuplicateVoter(validator)),
+            Some(std::cmp::Ordering::Greater) => return Err(WireError::UnsortedVotes),
+            _ => {}
+        }
+        let block_id = BlockId::new(reader.array()?);
+        let slot = Slot(u64::from_le_bytes(reader.array()?));
+        let tag = reader.u8()?;
+        let kind = VoteKind::from_tag(tag).ok_or(WireError::UnknownVoteKind(tag))?;
+        let signature = match reader.u8()? as usize {
+            len @ (0 | SIGNATURE_SIZE) => reader.take(len)?.to_vec(),
+            _ => return Err(WireError::InvalidSignatureLength),
+        };
+        votes.push(Vote { validator, block_id, slot, kind, signature });
+    }
+
+    if !reader.0.is_empty() {
+        return Err(WireError::TrailingBytes(reader.0.len()));
+    }
+    Ok(FinalizationCertificate { block_id, slot, round, votes, total_stake })
+}
+
+fn check_version(version: u8) -> Result<(), WireError> {
+    if (MIN_SHRED_WIRE_VERSION..=SHRED_WIRE_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(WireError::UnsupportedVersion(version))
+    }
+}
+
+fn narrow<T: TryFrom<usize>>(value: usize, field: &'static str) -> Result<T, WireError> {
+    T::try_from(value).map_err(|_| WireError::FieldOverflow(field))
+}
+
+/// Bounds-checked cursor over a packet
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        if self.0.len() < len {
+            return Err(WireError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], WireError> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    fn u8(&mut self) -> Result<u8, WireError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// ID written by `ValidatorId::write_bytes`
+    fn validator_id(&mut self) -> Result<ValidatorId, WireError> {
+        match u64::from_le_bytes(self.array()?) {
+            ValidatorId::KEY_MARKER => Ok(ValidatorId::Key(self.array()?)),
+            index => Ok(ValidatorId::Index(index)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotor::{Rotor, RotorConfig};
+    use crate::stake::StakeDistribution;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn test_shreds() -> Vec<Shred> {
+        let vset = StakeDistribution::Equal(100).validator_set(1, 0);
+        let transaction = Transaction::new(&SigningKey::from_bytes(&[5u8; 32]), 0, vec![5u8; 3000]);
+        let block = Block::new(Slot(9), None, ValidatorId::Index(0), vec![transaction], 1000);
+
+        let rotor = Rotor::with_config(vset, RotorConfig::default());
+        rotor
+            .encode_block_signed(&block, &SigningKey::from_bytes(&[3u8; 32]))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shred_roundtrip_fits_mtu() {
+        const { assert!(MAX_SHRED_PAYLOAD >= 1024) };
+
+        for shred in test_shreds() {
+            let packet = encode_shred(&shred).unwrap();
+            assert!(packet.len() <= MAX_SHRED_PACKET_SIZE);
+
+            let decoded = decode_shred(&packet).unwrap();
+            assert_eq!(decoded.position(), shred.position());
+            assert_eq!(decoded.signing_bytes(), shred.signing_bytes());
+            assert_eq!(decoded.signature, shred.signature);
+        }
+
+        let mut compressed = test_shreds().remove(0);
+        compressed.compression = Compression::Zstd;
+        let decoded = decode_shred(&encode_shred(&compressed).unwrap()).unwrap();
+        assert_eq!(decoded.compression, Compression::Zstd);
+    }
+
+    #[test]
+    fn test_malformed_packets_rejected() {
+        let shred = test_shreds().remove(0);
+        let packet = encode_shred(&shred).unwrap();
+
+        // Every truncation fails cleanly
+        for len in 0..packet.len() {
+            assert!(decode_shred(&packet[..len]).is_err());
+        }
+
+        let mut extra = packet.clone();
+        extra.push(0);
+        assert_eq!(decode_shred(&extra).unwrap_err(), WireError::TrailingBytes(1));
+
+        let mut bad_version = packet.clone();
+        bad_version[0] = 99;
+        assert_eq!(
+            decode_shred(&bad_version).unwrap_err(),
+            WireError::UnsupportedVersion(99)
+        );
+
+        let mut oversized = shred.clone();
+        oversized.data = vec![0; MAX_SHRED_PAYLOAD + 1].into();
+        assert!(matches!(encode_shred(&oversized), Err(WireError::PayloadTooLarge(_))));
+
+        // Arbitrary bytes never panic
+        let mut state = 1u64;
+        for _ in 0..1000 {
+            let len = (state % 200) as usize;
+            let bytes: Vec<u8> = (0..len)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (state >> 56) as u8
+                })
+                .collect();
+            let _ = decode_shred(&bytes);
+        }
+    }
+
+    #[test]
+    fn test_previous_version_still_decodes() {
+        let shred = test_shreds().remove(0);
+        assert_eq!(shred.version, SHRED_WIRE_VERSION);
+        let key = SigningKey::from_bytes(&[3u8; 32]).verifying_key();
+
+        // A version 1 sender signs without the version byte
+        let mut old = shred.clone();
+        old.version = MIN_SHRED_WIRE_VERSION;
+        old.signature = SigningKey::from_bytes(&[3u8; 32])
+            .sign(&old.signing_bytes())
+            .to_bytes()
+            .to_vec();
+        let decoded = decode_shred(&encode_shred(&old).unwrap()).unwrap();
+        assert_eq!(decoded.version, 1);
+        assert!(decoded.verify(&key));
+
+        // Relabeling a shred as another version breaks its signature
+        let mut packet = encode_shred(&shred).unwrap();
+        packet[0] = MIN_SHRED_WIRE_VERSION;
+        assert!(!decode_shred(&packet).unwrap().verify(&key));
+
+        let mut future = shred;
+        future.version = SHRED_WIRE_VERSION + 1;
+        assert_eq!(
+            encode_shred(&future).unwrap_err(),
+            WireError::UnsupportedVersion(SHRED_WIRE_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn test_decoded_payload_shares_packet() {
+        let shred = test_shreds().remove(0);
+        let packet = Bytes::from(encode_shred(&shred).unwrap());
+        let decoded = decode_shred_bytes(packet.clone()).unwrap();
+
+        assert_eq!(decoded.data, shred.data);
+        let payload_at = decoded.data.as_ptr() as usize - packet.as_ptr() as usize;
+        assert_eq!(payload_at, packet.len() - shred.data.len());
+    }
+
+    #[test]
+    fn test_certificate_encoding_matches_golden_file() {
+        const GOLDEN: &str = "tests/golden/finalization_certificate.hex";
+        let block_id = BlockId::new([0x11; 32]);
+        let vote = |validator, signature: Vec<u8>| Vote {
+            validator: ValidatorId::Index(validator),
+            block_id,
+            slot: Slot(7),
+            kind: if validator == 2 { VoteKind::NotarFallback } else { VoteKind::Notar },
+            signature,
+        };
+        let cert = FinalizationCertificate {
+            block_id,
+            slot: Slot(7),
+            round: VoteRound::Round1,
+            votes: vec![vote(3, vec![0xc3; 64]), vote(0, vec![0xc0; 64]), vote(2, vec![])],
+            total_stake: StakeWeight(400),
+        };
+
+        // The file is hex with one field per word and one line per vote;
+        // whitespace and comment lines are ignored. UPDATE_GOLDEN=1 rewrites it.
+        let bytes = encode_certificate(&cert).unwrap();
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let mut rest = &bytes[..];
+            let mut fields = |sizes: &[usize]| {
+                let words = sizes.iter().filter(|&&size| size > 0).map(|&size| {
+                    let (field, tail) = rest.split_at(size);
+                    rest = tail;
+                    hex::encode(field)
+                });
+                words.collect::<Vec<_>>().join(" ")
+            };
+            let mut lines = vec![
+                "# FinalizationCertificate, canonical encoding version 1 (see src/wire.rs)".into(),
+                fields(&[1, 32, 8, 1, 8, 4]),
+            ];
+            let mut votes = cert.votes.clone();
+            votes.sort_by_key(|vote| vote.validator);
+            for vote in &votes {
+                lines.push(fields(&[8, 32, 8, 1, 1, vote.signature.len()]));
+            }
+            std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+        }
+        let golden: String = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.split_whitespace())
+            .collect();
+        assert_eq!(hex::encode(&bytes), golden, "{GOLDEN} changed: the encoding is not stable");
+
+        let decoded = decode_certificate(&hex::decode(&golden).unwrap()).unwrap();
+        let voters: Vec<_> =
+            decoded.votes.iter().map(|vote| vote.validator.index().unwrap()).collect();
+        assert_eq!(voters, vec![0, 2, 3]);
+        assert_eq!((decoded.block_id, decoded.slot), (block_id, Slot(7)));
+        assert_eq!(decoded.total_stake, StakeWeight(400));
+        assert_eq!(decoded.votes[1].kind, VoteKind::NotarFallback);
+        assert!(decoded.votes[1].signature.is_empty());
+        assert_eq!(encode_certificate(&decoded).unwrap(), bytes);
+
+        // Any other vote order, a repeated voter or extra bytes are rejected
+        let mut swapped = bytes.clone();
+        swapped[54..54 + 8].copy_from_slice(&2u64.to_le_bytes());
+        let duplicate = WireError::DuplicateVoter(ValidatorId::Index(2));
+        assert_eq!(decode_certificate(&swapped).unwrap_err(), duplicate);
+        swapped[54..54 + 8].copy_from_slice(&5u64.to_le_bytes());
+        assert_eq!(decode_certificate(&swapped).unwrap_err(), WireError::UnsortedVotes);
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(decode_certificate(&padded).unwrap_err(), WireError::TrailingBytes(1));
+        for len in 0..bytes.len() {
+            assert!(decode_certificate(&bytes[..len]).is_err());
+        }
+        let mut twice = cert;
+        twice.votes.push(vote(0, vec![]));
+        let duplicate = WireError::DuplicateVoter(ValidatorId::Index(0));
+        assert_eq!(encode_certificate(&twice).unwrap_err(), duplicate);
+    }
+
+    #[test]
+    fn test_key_ids_round_trip() {
+        const { assert!(MAX_SHRED_PAYLOAD - 32 >= 1024) };
+
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let leader = ValidatorId::from_pubkey(&key.verifying_key());
+        let mut vset = ValidatorSet::new();
+        vset.try_add_validator(ValidatorConfig {
+            id: leader,
+            stake: StakeWeight(100),
+            is_byzantine: false,
+            is_offline: false,
+            pubkey: Some(key.verifying_key()),
+            address: None,
+        })
+        .unwrap();
+        let transaction = Transaction::new(&SigningKey::from_bytes(&[5u8; 32]), 0, vec![5u8; 3000]);
+        let block = Block::new(Slot(9), None, leader, vec![transaction], 1000);
+        for shred in Rotor::new(vset).encode_block_signed(&block, &key).unwrap() {
+            let packet = encode_shred(&shred).unwrap();
+            assert!(packet.len() <= MAX_SHRED_PACKET_SIZE);
+            let decoded = decode_shred(&packet).unwrap();
+            assert_eq!(decoded.leader, leader);
+            assert_eq!(decoded.signing_bytes(), shred.signing_bytes());
+        }
+
+        // A key voter sorts after the numbered ones
+        let block_id = BlockId::new([0x11; 32]);
+        let vote = |validator| Vote {
+            validator,
+            block_id,
+            slot: Slot(7),
+            kind: VoteKind::Notar,
+            signature: vec![],
+        };
+        let cert = FinalizationCertificate {
+            block_id,
+            slot: Slot(7),
+            round: VoteRound::Round1,
+            votes: vec![vote(leader), vote(ValidatorId::Index(4))],
+            total_stake: StakeWeight(200),
+        };
+        let decoded = decode_certificate(&encode_certificate(&cert).unwrap()).unwrap();
+        let voters: Vec<_> = decoded.votes.iter().map(|vote| vote.validator).collect();
+        assert_eq!(voters, vec![ValidatorId::Index(4), leader]);
+    }
+}
@@ -16,12 +16,6 @@ struct AlpenglowModel {
     offline: BTreeSet<ValidatorId>,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
-enum Round {
-    Round1,
-    Round2,
-}
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 struct State {
     /// Current slot
@@ -35,9 +29,9 @@ struct State {
     /// Votes in round 2
     votes_round2: BTreeMap<BlockId, BTreeSet<ValidatorId>>,
     /// Finalized blocks
-    finalized: Vec<(BlockId, u64, Round)>,
+    finalized: Vec<(BlockId, u64, VoteRound)>,
     /// Current round
-    round: Round,
+    round: VoteRound,
     /// Skip votes per slot
     skip_votes: BTreeMap<u64, BTreeSet<ValidatorId>>,
     /// Skipped slots
@@ -73,7 +67,7 @@ impl AlpenglowModel {
     }
 
     fn with_byzantine(mut self, byzantine_id: usize) -> Self {
-        self.byzantine.insert(ValidatorId(byzantine_id as u64));
+        self.byzantine.insert(ValidatorId::Index(byzantine_id as u64));
         self
     }
 
@@ -96,12 +90,12 @@ impl AlpenglowModel {
     fn initial_state(&self) -> State {
         State {
             slot: 0,
-            leader: ValidatorId(0),
+            leader: ValidatorId::Index(0),
             proposed: BTreeMap::new(),
             votes_round1: BTreeMap::new(),
             votes_round2: BTreeMap::new(),
             finalized: Vec::new(),
-            round: Round::Round1,
+            round: VoteRound::Round1,
             skip_votes: BTreeMap::new(),
             skipped: BTreeSet::new(),
             partitioned: None,
@@ -121,9 +115,9 @@ impl AlpenglowModel {
         // Validators can vote if block proposed
         if let Some((block_id, _)) = state.proposed.get(&state.slot) {
             // Round 1 votes
-            if matches!(state.round, Round::Round1) {
+            if matches!(state.round, VoteRound::Round1) {
                 for i in 0..self.validator_count {
-                    let v = ValidatorId(i as u64);
+                    let v = ValidatorId::Index(i as u64);
                     if self.is_honest(&v) {
                         let voted = state
                             .votes_round1
@@ -148,9 +142,9 @@ impl AlpenglowModel {
             }
 
             // Round 2 votes
-            if matches!(state.round, Round::Round2) {
+            if matches!(state.round, VoteRound::Round2) {
                 for i in 0..self.validator_count {
-                    let v = ValidatorId(i as u64);
+                    let v = ValidatorId::Index(i as u64);
                     if self.is_honest(&v) {
                         let voted = state
                             .votes_round2
@@ -175,7 +169,7 @@ impl AlpenglowModel {
         // Skip votes if no proposal
         if !state.proposed.contains_key(&state.slot) {
             for i in 0..self.validator_count {
-                let v = ValidatorId(i as u64);
+                let v = ValidatorId::Index(i as u64);
                 if self.is_honest(&v) {
                     let voted_skip = state
                         .skip_votes
@@ -211,7 +205,7 @@ impl AlpenglowModel {
             let mut p1 = BTreeSet::new();
             let mut p2 = BTreeSet::new();
             for i in 0..self.validator_count {
-                let v = ValidatorId(i as u64);
+                let v = ValidatorId::Index(i as u64);
                 if i < mid {
                     p1.insert(v);
                 } else {
@@ -242,35 +236,35 @@ impl AlpenglowModel {
             Action::VoteRound1(v, block_id) => {
                 next.votes_round1
                     .entry(*block_id)
-                    .or_insert_with(BTreeSet::new)
+                    .or_default()
                     .insert(*v);
             }
 
             Action::VoteRound2(v, block_id) => {
                 next.votes_round2
                     .entry(*block_id)
-                    .or_insert_with(BTreeSet::new)
+                    .or_default()
                     .insert(*v);
             }
 
             Action::CheckFastQuorum(block_id) => {
                 next.finalized
-                    .push((*block_id, state.slot, Round::Round1));
+                    .push((*block_id, state.slot, VoteRound::Round1));
             }
 
             Action::CheckFallbackQuorum(block_id) => {
                 next.finalized
-                    .push((*block_id, state.slot, Round::Round2));
+                    .push((*block_id, state.slot, VoteRound::Round2));
             }
 
             Action::AdvanceToRound2 => {
-                next.round = Round::Round2;
+                next.round = VoteRound::Round2;
             }
 
             Action::VoteSkip(v) => {
                 next.skip_votes
                     .entry(state.slot)
-                    .or_insert_with(BTreeSet::new)
+                    .or_default()
                     .insert(*v);
             }
 
@@ -280,8 +274,9 @@ impl AlpenglowModel {
 
             Action::NextSlot => {
                 next.slot += 1;
-                next.leader = ValidatorId((state.leader.0 + 1) % self.validator_count as u64);
-                next.round = Round::Round1;
+                let leader = state.leader.index().expect("model validators are numbered");
+                next.leader = ValidatorId::Index((leader + 1) % self.validator_count as u64);
+                next.round = VoteRound::Round1;
             }
 
             Action::NetworkPartition(p1, p2) => {
@@ -315,13 +310,13 @@ impl AlpenglowModel {
     fn check_quorum_validity(&self, state: &State) -> bool {
         for (block_id, _, round) in &state.finalized {
             match round {
-                Round::Round1 => {
+                VoteRound::Round1 => {
                     let votes = state.votes_round1.get(block_id).map(|v| v.len()).unwrap_or(0);
                     if (votes as u64) < self.fast_quorum() {
                         return false;
                     }
                 }
-                Round::Round2 => {
+                VoteRound::Round2 => {
                     let votes = state.votes_round2.get(block_id).map(|v| v.len()).unwrap_or(0);
                     if (votes as u64) < self.fallback_quorum() {
                         return false;
@@ -335,7 +330,7 @@ impl AlpenglowModel {
     /// Check voting integrity (no double voting)
     fn check_voting_integrity(&self, state: &State) -> bool {
         // Check round 1
-        for (_, voters) in &state.votes_round1 {
+        for voters in state.votes_round1.values() {
             let mut seen = HashSet::new();
             for v in voters {
                 if !seen.insert(v) {
@@ -345,7 +340,7 @@ impl AlpenglowModel {
         }
 
         // Check round 2
-        for (_, voters) in &state.votes_round2 {
+        for voters in state.votes_round2.values() {
             let mut seen = HashSet::new();
             for v in voters {
                 if !seen.insert(v) {
@@ -385,7 +380,7 @@ mod tests {
         let state = model.initial_state();
 
         assert_eq!(state.slot, 0);
-        assert_eq!(state.leader, ValidatorId(0));
+        assert_eq!(state.leader, ValidatorId::Index(0));
         assert!(state.finalized.is_empty());
     }
 
@@ -396,13 +391,13 @@ mod tests {
 
         // Propose block
         let block_id = BlockId::new([0u8; 32]);
-        state = model.step(&state, &Action::ProposeBlock(ValidatorId(0), block_id));
+        state = model.step(&state, &Action::ProposeBlock(ValidatorId::Index(0), block_id));
         assert!(state.proposed.contains_key(&0));
 
         // Vote round 1
-        state = model.step(&state, &Action::VoteRound1(ValidatorId(0), block_id));
-        state = model.step(&state, &Action::VoteRound1(ValidatorId(1), block_id));
-        state = model.step(&state, &Action::VoteRound1(ValidatorId(2), block_id));
+        state = model.step(&state, &Action::VoteRound1(ValidatorId::Index(0), block_id));
+        state = model.step(&state, &Action::VoteRound1(ValidatorId::Index(1), block_id));
+        state = model.step(&state, &Action::VoteRound1(ValidatorId::Index(2), block_id));
 
         // Check quorum (3/3 = 100% > 80%)
         assert!(model.check_quorum_validity(&state));
@@ -421,7 +416,7 @@ mod tests {
         let block1 = BlockId::new([1u8; 32]);
         state2
             .finalized
-            .push((block1, 0, Round::Round1));
+            .push((block1, 0, VoteRound::Round1));
 
         assert!(model.check_no_fork(&state2));
 
@@ -430,7 +425,7 @@ mod tests {
         let block2 = BlockId::new([2u8; 32]);
         state3
             .finalized
-            .push((block2, 0, Round::Round1));
+            .push((block2, 0, VoteRound::Round1));
 
         assert!(!model.check_no_fork(&state3)); // Fork detected!
     }
@@ -445,8 +440,8 @@ mod tests {
     #[test]
     fn test_byzantine_validator() {
         let model = AlpenglowModel::new(5).with_byzantine(4);
-        assert!(!model.is_honest(&ValidatorId(4)));
-        assert!(model.is_honest(&ValidatorId(0)));
+        assert!(!model.is_honest(&ValidatorId::Index(4)));
+        assert!(model.is_honest(&ValidatorId::Index(0)));
     }
 
     #[test]